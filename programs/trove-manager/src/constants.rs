@@ -10,21 +10,58 @@ pub const NICR_PRECISION: u64 = 100_000_000_000;
 /// Half-life of 12h. 12h = 720 min
 /// (1/2) = d^720 => d = (1/2)^(1/720)
 pub const MINUTE_DECAY_FACTOR: u64 = 999_037_759;
+/// Bounds of the per pool decay factor, half-lives of about 69 min and 4.8 days
+pub const MIN_MINUTE_DECAY_FACTOR: u64 = 990_000_000;
+pub const MAX_MINUTE_DECAY_FACTOR: u64 = 999_900_000;
+/// Cap on `dec_pow` exponent, 1000 years in minutes
+pub const MAX_DEC_POW_MINUTES: u64 = 525_600_000;
 pub const REDEMPTION_FEE_FLOOR: u64 = DECIMAL_PRECISION / 1000 * 5; // 0.5%
 pub const MAX_BORROWING_FEE: u64 = DECIMAL_PRECISION / 100 * 5; // 5%
 pub const BORROWING_FEE_FLOOR: u64 = DECIMAL_PRECISION / 1000 * 5; // 0.5%
+pub const MAX_BPS: u64 = 10_000; // 100%
 
 pub const SCALE_FACTOR: u64 = 100_000;
 
+// Maximum troves touched by one crank_apply_rewards call
+pub const MAX_CRANK_TROVES: usize = 20;
+// Maximum sorted list segment searched when re-insert hints are stale
+pub const MAX_NEIGHBOR_WALK: usize = 10;
+// Maximum troves returned by one list_troves_page call, kept within the return data limit
+pub const MAX_TROVES_PAGE: u8 = 25;
+// Compute units recovery_sweep keeps in reserve to settle the liquidated sequence
+pub const MIN_SWEEP_COMPUTE_UNITS: u64 = 80_000;
+
+// check_invariants bits, set when the accounting matches the vault
+pub const INVARIANT_COLL_VAULT: u8 = 1 << 0;
+pub const INVARIANT_STABLECOIN_SUPPLY: u8 = 1 << 1;
+pub const INVARIANT_SP_USV_VAULT: u8 = 1 << 2;
+pub const INVARIANT_SP_COLL_VAULT: u8 = 1 << 3;
+pub const ALL_INVARIANTS: u8 = INVARIANT_COLL_VAULT
+    | INVARIANT_STABLECOIN_SUPPLY
+    | INVARIANT_SP_USV_VAULT
+    | INVARIANT_SP_COLL_VAULT;
+
 // Price feed
 pub const TIMEOUT: i64 = 14400;
 pub const MAX_CONFIDENCE_RATE: u64 = 5_000_000; // 5%
 pub const FEED_DECIMAL_PRECISION: u64 = 100_000_000;
 pub const TARGET_DECIMAL_PRECISION: u64 = 1_000_000_000;
+// Mint decimals matching TARGET_DECIMAL_PRECISION
+pub const COLLATERAL_DECIMALS: u8 = 9;
+pub const STABLECOIN_DECIMALS: u8 = 9;
 pub const MAX_PRICE_DIFFERENCE_BETWEEN_ORACLES: u64 = 5_000_000; // 5%
+pub const MAX_JITOSOL_RATE_STALE_EPOCHS: u64 = 1;
+pub const MAX_JITOSOL_EPOCH_LAG: u64 = 2;
+
+// Price feed status transitions kept for debugging
+pub const STATUS_HISTORY_LEN: usize = 8;
 
 // Community Issuance
 pub const MAX_EMISSION_RATE: u64 = 10_000_000_000;
+// The vault must fund a new emission rate for at least 90 days
+pub const MIN_EMISSION_RUNWAY: u64 = 90 * 24 * 60 * 60;
+// Length of the periods `emission_decay_factor` applies to, 1 week
+pub const EMISSION_DECAY_PERIOD: u64 = 7 * 24 * 60 * 60;
 
 // Deployer
 pub const DEPLOYER: Pubkey = pubkey!("FeXpuNQFuEg8q5KdimHkogXiCuMKfa8PwbeYKJSbqiVo");
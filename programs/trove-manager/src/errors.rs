@@ -18,10 +18,10 @@ pub enum BorrowerOpsError {
     NewICRLowerThanOldICR,
     #[msg("Max fee percentage must less than or equal to 100%")]
     InvalidMaxFeeRecoveryMode,
-    #[msg("Max fee percentage must be between 0.5% and 100%")]
-    InvalidRedeemMaxFee,
     #[msg("Max redeem fee percentage must be between 0.5% and 100%")]
-    InvalidMaxFee,
+    InvalidRedeemMaxFee,
+    #[msg("Max fee percentage must be at least the 0.5% borrowing fee floor")]
+    MaxFeeBelowFloor,
     #[msg("Fee exceeded provided maximum")]
     FeeExceededMax,
     #[msg("Not support withdraw lamport")]
@@ -62,6 +62,52 @@ pub enum BorrowerOpsError {
     InvalidTroveNeighbor,
     #[msg("SortedTroves: NICR must be positive")]
     NICRZero,
+    #[msg("TroveManager: Too many troves provided")]
+    TooManyTroves,
+    #[msg("SortedTroves: Same account passed for several neighbors")]
+    DuplicateTroveNeighbor,
+    #[msg("BorrowerOps: Stake pool accounts are required to deposit SOL")]
+    MissingStakeAccount,
+    #[msg("BorrowerOps: Pre-launch fee treasury account is required")]
+    MissingFeeTreasury,
+    #[msg("BorrowerOps: Staked collateral is below the minimum expected")]
+    CollOutBelowMin,
+    #[msg("BorrowerOps: Trove collateral exceeds the per-trove cap")]
+    TroveCollAboveMax,
+    #[msg("TroveManager: Redeemed collateral exceeds the trove's collateral")]
+    RedemptionCollAboveTroveColl,
+    #[msg("BorrowerOps: Cannot increase debt of a trove under MCR after pending rewards")]
+    BaselineICRLowerThanMCR,
+    #[msg("BorrowerOps: System debt exceeds the debt cap")]
+    SystemDebtAboveMax,
+    #[msg("BorrowerOps: Operation only permitted during Recovery Mode")]
+    NotInRecoveryMode,
+    #[msg("Max fee percentage must not exceed 100%")]
+    MaxFeeAboveMax,
+    #[msg("TroveManager: Borrowers can't liquidate their own trove")]
+    SelfLiquidation,
+    #[msg("TroveManager: Redemption exceeds the maximum share of the total supply")]
+    RedemptionAboveMaxFraction,
+    #[msg("TroveManager: Accounting does not match the vault balances")]
+    InvariantViolated,
+    #[msg("TroveManager: Debt was increased within the cooldown")]
+    DebtIncreaseCooldownNotElapsed,
+    #[msg("TroveManager: Token account must be emptied before closing")]
+    TokenAccountNotEmpty,
+    #[msg("BorrowerOps: No active trove left to redistribute to")]
+    NoTrovesToRedistribute,
+    #[msg("TroveManager: Trove is still within its liquidation grace period")]
+    TroveInLiquidationGracePeriod,
+    #[msg("BorrowerOps: In Recovery Mode a collateral top-up must improve the ICR")]
+    RecoveryCollTopUpNoImprovement,
+    #[msg("BorrowerOps: Number of troves is at the cap")]
+    TroveCountAtMax,
+    #[msg("BorrowerOps: Trove account still holds a balance or is linked in the sorted list")]
+    TroveAccountNotClosable,
+    #[msg("BorrowerOps: Gas compensation from the redemption close must be repaid first")]
+    GasCompensationOwed,
+    #[msg("TroveManager: Redemption fee treasury account is required")]
+    MissingRedemptionFeeTreasury,
 }
 
 #[error_code]
@@ -76,6 +122,20 @@ pub enum StabilityPoolError {
     InvalidLowestTrove,
     #[msg("StabilityPool: Amount must be non-zero")]
     ZeroAmount,
+    #[msg("StabilityPool: Deposit must be empty")]
+    DepositNotEmpty,
+    #[msg("StabilityPool: New deposits are disabled")]
+    DepositsDisabled,
+    #[msg("StabilityPool: Debt to offset exceeds deposits")]
+    OffsetAboveDeposits,
+    #[msg("StabilityPool: Reward sum or product out of range")]
+    RewardMathOverflow,
+    #[msg("StabilityPool: Epoch scale is not behind the current one")]
+    EpochScaleNotSuperseded,
+    #[msg("StabilityPool: Amount to provide exceeds the minted USV")]
+    ProvideAboveMinted,
+    #[msg("StabilityPool: SP deposit fee reserve account is required")]
+    MissingFeeReserve,
 }
 
 #[error_code]
@@ -94,6 +154,24 @@ pub enum PriceFeedError {
     OnlyDevMode,
     #[msg("PriceFeed: JitoSol stake list and pool out of date")]
     PoolNotUpdated,
+    #[msg("PriceFeed: switchboard wrong feed")]
+    SwitchboardWrongFeed,
+    #[msg("PriceFeed: invalid signer")]
+    InvalidSigner,
+    #[msg("PriceFeed: dev price must be non-zero")]
+    InvalidDevPrice,
+    #[msg("PriceFeed: price too stale for this operation")]
+    PriceTooStale,
+    #[msg("PriceFeed: no valid price fetched yet")]
+    ZeroPrice,
+    #[msg("PriceFeed: jitosol epoch lag above maximum")]
+    InvalidEpochLag,
+    #[msg("PriceFeed: Confidence rate must be above 0 and at most 100%")]
+    InvalidConfidenceRate,
+    #[msg("PriceFeed: pyth update is for another feed id")]
+    PythWrongFeedId,
+    #[msg("PriceFeed: no new debt while both oracles are untrusted")]
+    BothOraclesUntrusted,
 }
 
 #[error_code]
@@ -102,6 +180,18 @@ pub enum CommunityIssuanceError {
     InvalidSigner,
     #[msg("Exceed maximum emission rate")]
     ExceedMax,
+    #[msg("Community issuance vault cannot cover outstanding CVGT")]
+    IssuanceVaultUnderfunded,
+    #[msg("Emission rate would drain the issuance vault before the minimum runway")]
+    EmissionRunwayTooShort,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("Emission decay factor must be below 1")]
+    InvalidDecayFactor,
+    #[msg("CVGT sent exceeds CVGT issued")]
+    SentAboveIssued,
+    #[msg("Community issuance vault is required while the issuance guard is enabled")]
+    MissingIssuanceVault,
 }
 
 #[error_code]
@@ -112,4 +202,32 @@ pub enum CVGTStakingError {
     UserNotHasStake,
     #[msg("CVGTStaking: Invalid signer")]
     InvalidSigner,
+    #[msg("CVGTStaking: Stake is still within the minimum stake duration")]
+    StakeCooldownNotElapsed,
+}
+
+#[error_code]
+pub enum PoolConfigError {
+    #[msg("PoolConfig: Basis points must not exceed 100%")]
+    InvalidBps,
+    #[msg("PoolConfig: Collateral mint has unexpected decimals")]
+    CollateralDecimalsMismatch,
+    #[msg("PoolConfig: Stablecoin mint has unexpected decimals")]
+    StablecoinDecimalsMismatch,
+    #[msg("PoolConfig: Invalid signer")]
+    InvalidSigner,
+    #[msg("PoolConfig: MCR must be above 100%")]
+    InvalidMcr,
+    #[msg("PoolConfig: CCR must be above MCR")]
+    InvalidCcr,
+    #[msg("PoolConfig: Min net debt must be above gas compensation")]
+    InvalidMinNetDebt,
+    #[msg("PoolConfig: Staking pool doesn't pay out this pool's mints")]
+    StakingPoolMismatch,
+    #[msg("PoolConfig: Minute decay factor is out of bounds")]
+    InvalidMinuteDecayFactor,
+    #[msg("PoolConfig: Min coll gas compensation must not exceed the max")]
+    InvalidCollGasCompBounds,
+    #[msg("PoolConfig: Gas compensation can only change while no trove is open")]
+    TrovesOutstanding,
 }
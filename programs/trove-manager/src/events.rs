@@ -3,6 +3,8 @@ use anchor_lang::prelude::{
     *,
 };
 
+use crate::state::Status;
+
 #[event]
 pub struct TroveCreated {
     pub borrower: Pubkey,
@@ -15,6 +17,8 @@ pub struct TroveUpdated {
     pub coll: u64,
     pub stake: u64,
     pub operation: Operation,
+    // Strictly increasing per pool, orders events within the same slot
+    pub event_nonce: u64,
 }
 
 #[event]
@@ -23,6 +27,8 @@ pub struct Liquidation {
     pub coll: u64,
     pub total_usv_compensation: u64,
     pub total_coll_compensation: u64,
+    // Strictly increasing per pool, orders events within the same slot
+    pub event_nonce: u64,
 }
 
 #[event]
@@ -31,6 +37,8 @@ pub struct Redemption {
     pub actual_usv_amount: u64,
     pub coll_sent: u64,
     pub coll_fee: u64,
+    // Strictly increasing per pool, orders events within the same slot
+    pub event_nonce: u64,
 }
 
 #[event]
@@ -62,12 +70,6 @@ pub struct SystemSnapshotsUpdated {
     pub total_coll_snapshot: u64,
 }
 
-#[event]
-pub struct LTermsUpdated {
-    pub l_coll: u64,
-    pub l_usv_debt: u64,
-}
-
 #[event]
 pub struct TroveSnapshotsUpdated {
     pub l_coll: u128,
@@ -91,6 +93,20 @@ pub struct USVBorrowingFeePaid {
     pub usv_fee: u64,
 }
 
+#[event]
+pub struct TroveReferral {
+    pub borrower: Pubkey,
+    pub referral: Pubkey,
+}
+
+#[event]
+pub struct RiskParamsUpdated {
+    pub pool_state: Pubkey,
+    pub mcr: u64,
+    pub ccr: u64,
+    pub min_net_debt: u64,
+}
+
 // SurplusPool
 #[event]
 pub struct SurplusPoolCollBalanceUpdated {
@@ -128,17 +144,19 @@ pub struct StabilityPoolUSVBalanceUpdated {
     pub new_balance: u64,
 }
 
-#[event]
-pub struct StabilityPoolCollBalanceUpdated {
-    pub new_balance: u64,
-}
-
 #[event]
 pub struct UserDepositChanged {
     pub depositor: Pubkey,
     pub new_deposit: u64,
 }
 
+#[event]
+pub struct SPDepositTransferred {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub initial_value: u64,
+}
+
 #[event]
 pub struct CollGainWithdrawn {
     pub depositor: Pubkey,
@@ -146,6 +164,14 @@ pub struct CollGainWithdrawn {
     pub usv_loss: u64,
 }
 
+#[event]
+pub struct SpClaimed {
+    pub depositor: Pubkey,
+    pub coll_claimed: u64,
+    pub cvgt_claimed: u64,
+    pub remaining_deposit: u64,
+}
+
 #[event]
 pub struct DepositSnapshotUpdated {
     pub depositor: Pubkey,
@@ -201,6 +227,29 @@ pub struct EmissionRateChanged {
     pub new_rate: u64,
 }
 
+#[event]
+pub struct IssuanceGuardChanged {
+    pub token: Pubkey,
+    pub enabled: bool,
+    pub max_shortfall: u64,
+}
+
+#[event]
+pub struct EmissionDecayChanged {
+    pub token: Pubkey,
+    pub decay_factor: u64,
+    pub decay_start: u64,
+}
+
+#[event]
+pub struct CVGTEmissionStateRead {
+    pub token: Pubkey,
+    pub emission_rate: u64,
+    pub total_issued: u64,
+    pub enable_emission: bool,
+    pub current_timestamp: u64,
+}
+
 #[event]
 pub struct AuthorityChanged {
     pub token: Pubkey,
@@ -242,3 +291,23 @@ pub struct FCollUpdated {
 pub struct FUSVUpdated {
     pub f_usv: u64,
 }
+
+#[event]
+pub struct DevPriceChanged {
+    pub new_price: u64,
+}
+
+#[event]
+pub struct TroveICRUpdated {
+    pub borrower: Pubkey,
+    // 0 once the trove is closed
+    pub icr: u64,
+    pub operation: Operation,
+}
+
+#[event]
+pub struct PriceUsed {
+    pub price: u64,
+    // Feed status after the fetch, tells which oracle the price came from
+    pub source: Status,
+}
@@ -1,12 +1,18 @@
 use crate::{
+    constants::MAX_NEIGHBOR_WALK,
     errors::{BorrowerOpsError, PriceFeedError},
     events::{Operation, TroveUpdated, USVBorrowingFeePaid},
-    state::{CVGTStakingPoolState, CommunityIssuanceConfig, PoolState, PriceFeedState, Trove},
+    state::{
+        find_insert_position, CVGTStakingPoolState, CommunityIssuanceConfig, NeighborNode,
+        PoolState, PriceFeedState, Trove,
+    },
     utils::{
+        get_current_timestamp, require_distinct_trove_neighbors, require_min_coll_out,
         require_new_icr_is_above_old_icr, require_no_coll_withdrawal, require_non_zero_adjustment,
-        require_non_zero_debt_change, require_sufficient_usv_balance, require_user_accepts_fee,
-        require_valid_borrow_max_fee_percentage,
+        require_non_zero_debt_change, require_stake_account, require_sufficient_usv_balance,
+        require_user_accepts_fee, require_valid_borrow_max_fee_percentage,
     },
+    ID,
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -52,6 +58,13 @@ pub struct AdjustTrove<'info> {
     )]
     user_coll_ata: Box<Account<'info, TokenAccount>>,
 
+    // Receives withdrawn collateral instead of `user_coll_ata` when provided
+    #[account(
+        mut,
+        token::mint = collateral,
+    )]
+    pub coll_recipient: Option<Box<Account<'info, TokenAccount>>>,
+
     /// CHECK: This account is not read or written
     #[account(
         seeds = [
@@ -83,6 +96,13 @@ pub struct AdjustTrove<'info> {
     )]
     pub stablecoin_cvgt_staking_vault: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = pool_state.pre_launch_fee_treasury
+    )]
+    pub pre_launch_fee_treasury_ata: Option<Box<Account<'info, TokenAccount>>>,
+
     #[account(
         mut,
         constraint = stablecoin.key() == pool_state.stablecoin
@@ -142,24 +162,25 @@ pub struct AdjustTrove<'info> {
         constraint = jitosol_stake_pool.key() == price_feed_state.jitosol_stake_pool @ PriceFeedError::JitoSolStakePoolWrong
     )]
     pub jitosol_stake_pool: UncheckedAccount<'info>,
+    // Stake pool accounts below are only needed to deposit SOL (is_lamport)
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub jitosol_stake_withdraw_authority: UncheckedAccount<'info>,
+    pub jitosol_stake_withdraw_authority: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub reserve_stake_account: UncheckedAccount<'info>,
+    pub reserve_stake_account: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub manager_fee: UncheckedAccount<'info>,
+    pub manager_fee: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub referrer_fee: UncheckedAccount<'info>,
+    pub referrer_fee: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(
         mut,
         constraint = stake_program.key() == spl_stake_pool::ID
     )]
-    pub stake_program: UncheckedAccount<'info>,
+    pub stake_program: Option<UncheckedAccount<'info>>,
     #[account(
         constraint = chainlink_program.key() == chainlink_solana::ID
     )]
@@ -186,7 +207,7 @@ impl<'info> AdjustTrove<'info> {
     pub fn transfer_coll_out_ctx(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
         let cpi_accounts = TransferChecked {
             from: self.collateral_vault.to_account_info(),
-            to: self.user_coll_ata.to_account_info(),
+            to: get_coll_out_account(&self.user_coll_ata, self.coll_recipient.as_deref()),
             authority: self.token_authority.to_account_info(),
             mint: self.collateral.to_account_info(),
         };
@@ -218,18 +239,28 @@ impl<'info> AdjustTrove<'info> {
         CpiContext::new(cpi_program, cpi_accounts)
     }
 
-    pub fn mint_stablecoin_to_staking_pool_ctx(
+    /// The USV fee goes to the pre-launch treasury when `to_treasury`, otherwise to the staking vault
+    pub fn mint_stablecoin_fee_ctx(
         &self,
-    ) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
+        to_treasury: bool,
+    ) -> Result<CpiContext<'_, '_, '_, 'info, MintTo<'info>>> {
+        let to = if to_treasury {
+            self.pre_launch_fee_treasury_ata
+                .as_ref()
+                .ok_or(BorrowerOpsError::MissingFeeTreasury)?
+                .to_account_info()
+        } else {
+            self.stablecoin_cvgt_staking_vault.to_account_info()
+        };
         let cpi_accounts = MintTo {
             mint: self.stablecoin.to_account_info(),
-            to: self.stablecoin_cvgt_staking_vault.to_account_info(),
+            to,
             authority: self.token_authority.to_account_info(),
         };
 
         let cpi_program = self.token_program.to_account_info();
 
-        CpiContext::new(cpi_program, cpi_accounts)
+        Ok(CpiContext::new(cpi_program, cpi_accounts))
     }
 
     pub fn increase_f_usv(&self, usv_fee: u64) -> Result<()> {
@@ -248,12 +279,13 @@ impl<'info> AdjustTrove<'info> {
         let collateral_vault = &mut self.collateral_vault;
         let borrower = &self.borrower;
         let collateral = &self.collateral;
-        let stake_program = &self.stake_program;
+        let stake_program = require_stake_account(&self.stake_program)?;
         let jitosol_stake_pool = &self.jitosol_stake_pool;
-        let reserve_stake_account = &self.reserve_stake_account;
-        let jitosol_stake_withdraw_authority = &self.jitosol_stake_withdraw_authority;
-        let manager_fee = &self.manager_fee;
-        let referrer_fee = &self.referrer_fee;
+        let reserve_stake_account = require_stake_account(&self.reserve_stake_account)?;
+        let jitosol_stake_withdraw_authority =
+            require_stake_account(&self.jitosol_stake_withdraw_authority)?;
+        let manager_fee = require_stake_account(&self.manager_fee)?;
+        let referrer_fee = require_stake_account(&self.referrer_fee)?;
 
         let balance_before = collateral_vault.amount;
         let ix = spl_stake_pool::instruction::deposit_sol(
@@ -292,18 +324,49 @@ impl<'info> AdjustTrove<'info> {
     }
 }
 
-pub fn adjust_trove_handler(
-    ctx: Context<AdjustTrove>,
+/// Loads the sorted list segment used to search a new position, ordered from head to tail
+fn load_neighbor_segment<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    pool_state: &PoolState,
+    trove_key: Pubkey,
+) -> Result<Vec<Account<'info, Trove>>> {
+    require!(
+        remaining_accounts.len() <= MAX_NEIGHBOR_WALK,
+        BorrowerOpsError::TooManyTroves
+    );
+    remaining_accounts
+        .iter()
+        .map(|account| {
+            require!(
+                account.key() != trove_key,
+                BorrowerOpsError::DuplicateTroveNeighbor
+            );
+            let trove = Account::<Trove>::try_from(account)?;
+            require!(
+                trove.pool_state == pool_state.key(),
+                BorrowerOpsError::InvalidAccount
+            );
+            trove.require_trove_active()?;
+            Ok(trove)
+        })
+        .collect()
+}
+
+pub fn adjust_trove_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, AdjustTrove<'info>>,
     max_fee_percentage: u64,
     is_lamport: bool,
     coll_change: u64,
     is_coll_increase: bool,
     usv_change: u64,
     is_debt_increase: bool,
+    min_coll_out: u64,
 ) -> Result<()> {
     let coll_change = if is_lamport {
         require!(is_coll_increase, BorrowerOpsError::IsLamportNotSupported);
-        ctx.accounts.stake_sol_for_jitosol(coll_change)?
+        let coll_out = ctx.accounts.stake_sol_for_jitosol(coll_change)?;
+        require_min_coll_out(coll_out, min_coll_out)?;
+        coll_out
     } else {
         coll_change
     };
@@ -316,6 +379,12 @@ pub fn adjust_trove_handler(
         &ctx.accounts.jitosol_stake_pool,
         &ctx.accounts.pyth_feed_account,
     )?;
+    emit!(ctx.accounts.price_feed_state.price_used(price));
+    if is_debt_increase && usv_change > 0 {
+        ctx.accounts
+            .price_feed_state
+            .require_trusted_price_for_borrow()?;
+    }
 
     let cur_next_trove = &mut ctx.accounts.cur_next_trove;
     let cur_prev_trove = &mut ctx.accounts.cur_prev_trove;
@@ -326,6 +395,14 @@ pub fn adjust_trove_handler(
     let pool_state = &mut ctx.accounts.pool_state;
     let trove_key = trove.key();
 
+    require_distinct_trove_neighbors(
+        trove_key,
+        cur_prev_trove.as_ref().map(|t| t.key()),
+        cur_next_trove.as_ref().map(|t| t.key()),
+        new_prev_trove.as_ref().map(|t| t.key()),
+        new_next_trove.as_ref().map(|t| t.key()),
+    )?;
+
     // Check is recovery
     let is_recovery_mode = pool_state.check_recovery_mode(price);
 
@@ -335,25 +412,49 @@ pub fn adjust_trove_handler(
     }
     require_non_zero_adjustment(coll_change, usv_change)?;
     trove.require_trove_active()?;
+    let current_timestamp = get_current_timestamp();
+    if is_debt_increase {
+        trove.require_debt_increase_cooldown_elapsed(pool_state, current_timestamp)?;
+    }
 
     pool_state.apply_pending_reward(trove)?;
 
+    // ICR after redistribution rewards and before the user's change
+    let old_icr = trove.get_icr(price);
+    msg!("ICR after pending rewards {}", old_icr);
+    if is_debt_increase {
+        pool_state.require_baseline_icr_for_debt_increase(old_icr)?;
+    }
+
     let mut net_debt_change = usv_change;
     let mut usv_fee = 0;
 
     if is_debt_increase && !is_recovery_mode {
-        usv_fee = trigger_borrowing_fee(pool_state, usv_change, max_fee_percentage)?;
+        usv_fee = trigger_borrowing_fee(pool_state, usv_change, max_fee_percentage, price)?;
         net_debt_change = net_debt_change.checked_add(usv_fee).unwrap();
     }
 
+    // A collateral-only trove takes on the gas compensation with its first debt. It is minted
+    // to the borrower, as when opened with `gas_comp_to_borrower`.
+    let gas_compensation =
+        trove.get_first_debt_gas_compensation(is_debt_increase, pool_state.gas_compensation);
+    if trove.coll_only && is_debt_increase {
+        pool_state.require_at_least_min_net_debt(net_debt_change)?;
+    } else if trove.coll_only {
+        require!(usv_change == 0, BorrowerOpsError::InvalidUSVRepayment);
+    }
+    net_debt_change = net_debt_change.checked_add(gas_compensation).unwrap();
+    if is_debt_increase {
+        pool_state.require_system_debt_within_cap(net_debt_change)?;
+    }
+
     let debt = trove.debt;
     let coll = trove.coll;
 
-    let old_icr = trove.get_icr(price);
     let new_icr = trove.get_new_icr_from_trove_change(
         coll_change,
         is_coll_increase,
-        usv_change,
+        usv_change.checked_add(gas_compensation).unwrap(),
         is_debt_increase,
         price,
     );
@@ -397,31 +498,110 @@ pub fn adjust_trove_handler(
         net_debt_change,
         is_debt_increase,
     );
-    let (new_coll, new_debt) = trove.update_from_adjustment(
+    let (new_coll, _) = trove.update_from_adjustment(
         coll_change,
         is_coll_increase,
         net_debt_change,
         is_debt_increase,
     );
-    let stake = trove.update_stake_and_total_stakes(pool_state);
+    if is_coll_increase {
+        pool_state.require_trove_coll_within_cap(new_coll)?;
+    }
+    if trove.coll_only && is_debt_increase {
+        trove.coll_only = false;
+        trove.gas_comp_held_by_borrower = true;
+    }
+    if is_debt_increase {
+        trove.last_debt_increase_time = current_timestamp;
+    }
+    trove.update_stake_and_total_stakes(pool_state);
+
+    // Hints must be computed from NICRs including pending rewards, as `get_nominal_icr` does.
+    // Stale hints are corrected from the sorted list segment passed in `remaining_accounts`.
+    let hints_valid = trove
+        .validate_head_tail(
+            &trove_key,
+            new_nicr,
+            new_prev_trove,
+            new_next_trove,
+            pool_state,
+        )
+        .is_ok();
+    if hints_valid || ctx.remaining_accounts.is_empty() {
+        trove.re_insert(
+            trove_key,
+            new_nicr,
+            cur_prev_trove,
+            cur_next_trove,
+            new_prev_trove,
+            new_next_trove,
+            pool_state,
+        )?;
+    } else {
+        let segment = load_neighbor_segment(ctx.remaining_accounts, pool_state, trove_key)?;
+        let nodes: Vec<NeighborNode> = segment
+            .iter()
+            .map(|t| NeighborNode {
+                key: t.key(),
+                prev: t.prev,
+                next: t.next,
+                nicr: t.get_nominal_icr(pool_state),
+            })
+            .collect();
+
+        require!(new_nicr > 0, BorrowerOpsError::NICRZero);
+        trove.remove_sorted(trove_key, cur_prev_trove, cur_next_trove, pool_state)?;
+        for cur in [&*cur_prev_trove, &*cur_next_trove].into_iter().flatten() {
+            cur.exit(&ID)?;
+        }
+        // Head and tail are read after the removal in case the trove was one of them
+        let (prev_index, next_index) = find_insert_position(
+            &trove_key,
+            new_nicr,
+            &nodes,
+            pool_state.trove_head,
+            pool_state.trove_tail,
+        )
+        .ok_or(BorrowerOpsError::InvalidTroveNeighbor)?;
+        // Neighbors are loaded after the removal so they see its pointer updates
+        let load = |index: Option<usize>| -> Result<Option<Box<Account<'info, Trove>>>> {
+            index
+                .map(|i| Ok(Box::new(Account::try_from(&ctx.remaining_accounts[i])?)))
+                .transpose()
+        };
+        let mut found_prev = load(prev_index)?;
+        let mut found_next = load(next_index)?;
+        trove.insert_sorted(
+            trove_key,
+            new_nicr,
+            &mut found_prev,
+            &mut found_next,
+            pool_state,
+        )?;
 
-    trove.re_insert(
-        trove_key,
-        new_nicr,
-        cur_prev_trove,
-        cur_next_trove,
-        new_prev_trove,
-        new_next_trove,
-        pool_state,
-    )?;
+        // Found neighbors are not exited by Anchor, write them back, then refresh every
+        // hint account so the exit at the end of the instruction can't overwrite them
+        for found in [&found_prev, &found_next].into_iter().flatten() {
+            found.exit(&ID)?;
+        }
+        for hint in [
+            cur_prev_trove,
+            cur_next_trove,
+            new_prev_trove,
+            new_next_trove,
+        ] {
+            if let Some(hint) = hint.as_mut() {
+                hint.reload()?;
+            }
+        }
+    }
 
-    emit!(TroveUpdated {
-        borrower: borrower.key(),
-        debt: new_debt,
-        coll: new_coll,
-        stake,
-        operation: Operation::AdjustTrove
-    });
+    emit!(get_adjust_trove_event(
+        borrower.key(),
+        trove,
+        pool_state.next_event_nonce()
+    ));
+    emit!(trove.icr_updated(pool_state, price, Operation::AdjustTrove));
 
     emit!(USVBorrowingFeePaid {
         borrower: borrower.key(),
@@ -445,9 +625,11 @@ pub fn adjust_trove_handler(
         token_auth_bump.as_ref(),
     ];
 
+    let fee_to_treasury = usv_fee > 0
+        && pool_state.routes_usv_fee_to_treasury(community_issuance_config.enable_emission);
     mint_to(
         ctx.accounts
-            .mint_stablecoin_to_staking_pool_ctx()
+            .mint_stablecoin_fee_ctx(fee_to_treasury)?
             .with_signer(&[&authority_seed[..]]),
         usv_fee,
     )?;
@@ -460,7 +642,7 @@ pub fn adjust_trove_handler(
         coll_change,
         is_lamport,
         is_coll_increase,
-        usv_change,
+        usv_change.checked_add(gas_compensation).unwrap(),
         is_debt_increase,
     )
 }
@@ -529,13 +711,34 @@ fn move_tokens_from_adjust(
     Ok(())
 }
 
+/// Unlike opening, an adjustment doesn't report `TroveCreated`
+fn get_adjust_trove_event(borrower: Pubkey, trove: &Trove, event_nonce: u64) -> TroveUpdated {
+    TroveUpdated {
+        borrower,
+        debt: trove.debt,
+        coll: trove.coll,
+        stake: trove.stake,
+        operation: Operation::AdjustTrove,
+        event_nonce,
+    }
+}
+
+/// Withdrawn collateral goes to `coll_recipient` when provided, else to the borrower's ATA
+fn get_coll_out_account<'info>(
+    user_coll_ata: &Account<'info, TokenAccount>,
+    coll_recipient: Option<&Account<'info, TokenAccount>>,
+) -> AccountInfo<'info> {
+    coll_recipient.unwrap_or(user_coll_ata).to_account_info()
+}
+
 fn trigger_borrowing_fee(
     pool_state: &mut PoolState,
     usv_amt: u64,
     max_fee_percentage: u64,
+    price: u64,
 ) -> Result<u64> {
     pool_state.decay_base_rate_from_borrowing()?;
-    let usv_fee = pool_state.get_borrowing_fee(usv_amt);
+    let usv_fee = pool_state.get_borrowing_fee(usv_amt, price);
 
     require_user_accepts_fee(usv_fee, usv_amt, max_fee_percentage)?;
     Ok(usv_fee)
@@ -555,8 +758,16 @@ fn require_valid_adjustment_in_current_mode(
     if is_recovery_mode {
         require_no_coll_withdrawal(coll_change, is_coll_increase)?;
         if is_debt_increase {
+            // A sole trove has no other trove to absorb its liquidation
+            pool_state.require_more_than_one_trove_in_system()?;
             pool_state.require_icr_is_above_ccr(new_icr)?;
             require_new_icr_is_above_old_icr(new_icr, old_icr)?;
+        } else if net_debt_change == 0 {
+            // A debt-free trove has no ICR to improve, its collateral must still grow
+            require!(
+                coll_change > 0 && (new_icr > old_icr || old_icr == u64::MAX),
+                BorrowerOpsError::RecoveryCollTopUpNoImprovement
+            );
         }
     } else {
         pool_state.require_icr_is_above_mcr(new_icr)?;
@@ -571,3 +782,410 @@ fn require_valid_adjustment_in_current_mode(
     }
     Ok(())
 }
+
+#[cfg(test)]
+pub mod adjust_trove_test {
+    use super::*;
+    use crate::{
+        constants::DECIMAL_PRECISION,
+        state::{
+            price_feed_info_test::load_price_feed_info, trove_test::load_trove_account, Status,
+            TroveStatus,
+        },
+        utils::utils_test::{
+            load_account, load_anchor_account, load_mint, load_program, load_token_account,
+            read_token_amount, set_program_test_stubs,
+        },
+    };
+    use anchor_lang::{solana_program::program_pack::Pack, Discriminator};
+    use anchor_spl::{
+        associated_token::get_associated_token_address,
+        token::spl_token::{self, state::Mint as SplMint},
+    };
+    use std::collections::BTreeSet;
+
+    /// Index of `coll_recipient` in the accounts of `load_adjust_trove_accounts`
+    const COLL_RECIPIENT_INDEX: usize = 7;
+
+    const PRICE: u64 = 100_000_000_000;
+
+    /// Accounts of `adjust_trove` in declaration order, with every optional account left out
+    fn load_adjust_trove_accounts() -> Vec<AccountInfo<'static>> {
+        load_adjust_trove_accounts_with(PoolState::default(), &Trove::default())
+    }
+
+    /// As `load_adjust_trove_accounts`, with `trove` as the only trove of the list when active.
+    /// Prices are read in dev mode.
+    fn load_adjust_trove_accounts_with(
+        pool_state: PoolState,
+        trove: &Trove,
+    ) -> Vec<AccountInfo<'static>> {
+        let absent = || load_program(ID);
+        let borrower = Pubkey::new_unique();
+        let cvgt = Pubkey::new_unique();
+        let collateral = Pubkey::new_unique();
+        let stablecoin = Pubkey::new_unique();
+        let cvgt_staking_state = Pubkey::new_unique();
+        let pool_state_key = Pubkey::new_unique();
+        let (trove_key, _) = Pubkey::find_program_address(
+            &[b"trove", pool_state_key.as_ref(), borrower.as_ref()],
+            &ID,
+        );
+        let trove_list = if trove.status == TroveStatus::Active {
+            PoolState {
+                trove_head: trove_key,
+                trove_tail: trove_key,
+                trove_size: 1,
+                ..pool_state
+            }
+        } else {
+            pool_state
+        };
+        let (token_authority, token_auth_bump) =
+            Pubkey::find_program_address(&[b"token-authority", pool_state_key.as_ref()], &ID);
+        let pool_state = PoolState {
+            stablecoin,
+            collateral,
+            cvgt,
+            cvgt_staking_state,
+            token_auth_bump: [token_auth_bump],
+            ..trove_list
+        };
+        let (community_issuance_config, _) =
+            Pubkey::find_program_address(&[b"community-issuance", cvgt.as_ref()], &ID);
+        let (price_feed_key, price_feed_bump) =
+            Pubkey::find_program_address(&[b"price_feed", cvgt.as_ref()], &ID);
+        let price_feed_state = PriceFeedState {
+            bump: price_feed_bump,
+            _is_dev: true,
+            _dev_price: PRICE,
+            ..load_price_feed_info(DECIMAL_PRECISION, Status::PythWorking)
+        };
+        let token_account = |mint: Pubkey, owner: Pubkey| {
+            load_account(
+                get_associated_token_address(&owner, &mint),
+                spl_token::ID,
+                load_token_account(mint, owner, 1_000),
+            )
+        };
+        let mut pyth_feed_account = PriceUpdateV2::DISCRIMINATOR.to_vec();
+        pyth_feed_account.resize(PriceUpdateV2::LEN, 0);
+        let mut borrower = load_account(borrower, Pubkey::default(), vec![]);
+        borrower.is_signer = true;
+
+        vec![
+            load_anchor_account(pool_state_key, &pool_state),
+            load_trove_account(trove_key, trove),
+            absent(),
+            absent(),
+            absent(),
+            absent(),
+            token_account(collateral, borrower.key()),
+            absent(),
+            load_account(token_authority, Pubkey::default(), vec![]),
+            token_account(collateral, token_authority),
+            token_account(stablecoin, borrower.key()),
+            token_account(stablecoin, cvgt_staking_state),
+            absent(),
+            load_account(stablecoin, spl_token::ID, load_mint(token_authority)),
+            load_account(collateral, spl_token::ID, load_mint(token_authority)),
+            borrower,
+            load_anchor_account(
+                community_issuance_config,
+                &CommunityIssuanceConfig::default(),
+            ),
+            load_account(cvgt_staking_state, ID, vec![]),
+            load_anchor_account(price_feed_key, &price_feed_state),
+            load_account(
+                price_feed_state.pyth_feed_account,
+                pyth_solana_receiver_sdk::ID,
+                pyth_feed_account,
+            ),
+            load_account(price_feed_state.chainlink_feed, Pubkey::default(), vec![]),
+            load_account(
+                price_feed_state.jitosol_stake_pool,
+                Pubkey::default(),
+                vec![],
+            ),
+            absent(),
+            absent(),
+            absent(),
+            absent(),
+            absent(),
+            load_program(chainlink_solana::ID),
+            load_program(spl_token::ID),
+            load_program(anchor_spl::associated_token::ID),
+            load_program(System::id()),
+        ]
+    }
+
+    fn try_adjust_trove_accounts(
+        accounts: Vec<AccountInfo<'static>>,
+    ) -> Result<AdjustTrove<'static>> {
+        let mut accounts: &[AccountInfo<'static>] = Box::leak(accounts.into_boxed_slice());
+        AdjustTrove::try_accounts(
+            &ID,
+            &mut accounts,
+            &[],
+            &mut AdjustTroveBumps::default(),
+            &mut BTreeSet::new(),
+        )
+    }
+
+    /// Runs `adjust_trove` as `add_coll` does
+    fn add_coll(accounts: &[AccountInfo<'static>], coll_change: u64) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = AdjustTroveBumps::default();
+        let mut adjust_trove =
+            AdjustTrove::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        adjust_trove_handler(
+            Context::new(&ID, &mut adjust_trove, &[], bumps),
+            0,
+            false,
+            coll_change,
+            true,
+            0,
+            false,
+            0,
+        )?;
+        adjust_trove.exit(&ID)
+    }
+
+    /// Runs `adjust_trove` as `repay_usv` does
+    fn repay_usv(accounts: &[AccountInfo<'static>], usv_amt: u64) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = AdjustTroveBumps::default();
+        let mut adjust_trove =
+            AdjustTrove::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        adjust_trove_handler(
+            Context::new(&ID, &mut adjust_trove, &[], bumps),
+            0,
+            false,
+            0,
+            false,
+            usv_amt,
+            false,
+            0,
+        )?;
+        adjust_trove.exit(&ID)
+    }
+
+    #[test]
+    fn add_coll_crossing_cap() {
+        set_program_test_stubs();
+        let trove = Trove {
+            coll: 900,
+            debt: 2_000,
+            stake: 900,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            max_trove_coll: 1_000,
+            active_coll: 900,
+            active_debt: 2_000,
+            total_stakes: 900,
+            ..Default::default()
+        };
+        let accounts = load_adjust_trove_accounts_with(pool_state, &trove);
+
+        assert_eq!(
+            add_coll(&accounts, 101).unwrap_err(),
+            BorrowerOpsError::TroveCollAboveMax.into()
+        );
+        assert_eq!(read_token_amount(&accounts[6]), 1_000);
+
+        // Up to the cap is allowed
+        add_coll(&accounts, 100).unwrap();
+        let trove =
+            Trove::try_deserialize(&mut &accounts[1].try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!(trove.coll, 1_000);
+        assert_eq!(read_token_amount(&accounts[6]), 900);
+        assert_eq!(read_token_amount(&accounts[9]), 1_100);
+    }
+
+    #[test]
+    fn repay_without_stake_pool_accounts() {
+        set_program_test_stubs();
+        let trove = Trove {
+            coll: 900,
+            debt: 2_000,
+            stake: 900,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            active_coll: 900,
+            active_debt: 2_000,
+            total_stakes: 900,
+            ..Default::default()
+        };
+        let accounts = load_adjust_trove_accounts_with(pool_state, &trove);
+        // The borrower's USV is the whole supply
+        {
+            let mut data = accounts[13].try_borrow_mut_data().unwrap();
+            let mut mint = SplMint::unpack(&data).unwrap();
+            mint.supply = 1_000;
+            mint.pack_into_slice(&mut data);
+        }
+
+        // Only depositing SOL (is_lamport) reaches the stake pool accounts
+        let adjust_trove = try_adjust_trove_accounts(accounts.clone()).unwrap();
+        assert!(adjust_trove.jitosol_stake_withdraw_authority.is_none());
+        assert!(adjust_trove.reserve_stake_account.is_none());
+        assert!(adjust_trove.manager_fee.is_none());
+        assert!(adjust_trove.referrer_fee.is_none());
+        assert!(adjust_trove.stake_program.is_none());
+
+        repay_usv(&accounts, 600).unwrap();
+        let trove =
+            Trove::try_deserialize(&mut &accounts[1].try_borrow_data().unwrap()[..]).unwrap();
+        let pool_state =
+            PoolState::try_deserialize(&mut &accounts[0].try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!(trove.debt, 1_400);
+        assert_eq!(trove.coll, 900);
+        assert_eq!(pool_state.active_debt, 1_400);
+        assert_eq!(read_token_amount(&accounts[10]), 400);
+        assert_eq!(
+            SplMint::unpack(&accounts[13].try_borrow_data().unwrap())
+                .unwrap()
+                .supply,
+            400
+        );
+        // The collateral and the staking vault are untouched, no fee is charged
+        assert_eq!(read_token_amount(&accounts[6]), 1_000);
+        assert_eq!(read_token_amount(&accounts[9]), 1_000);
+        assert_eq!(read_token_amount(&accounts[11]), 1_000);
+    }
+
+    #[test]
+    fn coll_withdrawal_to_recipient() {
+        let accounts = load_adjust_trove_accounts();
+        let user_coll_ata = accounts[6].key();
+        let collateral = accounts[14].key();
+
+        let adjust_trove = try_adjust_trove_accounts(accounts.clone()).unwrap();
+        assert_eq!(
+            adjust_trove.transfer_coll_out_ctx().accounts.to.key(),
+            user_coll_ata
+        );
+
+        // The borrower's ATA is left out of a withdrawal to a third party
+        let recipient = Pubkey::new_unique();
+        let mut with_recipient = accounts.clone();
+        with_recipient[COLL_RECIPIENT_INDEX] = load_account(
+            recipient,
+            spl_token::ID,
+            load_token_account(collateral, Pubkey::new_unique(), 1_000),
+        );
+        let adjust_trove = try_adjust_trove_accounts(with_recipient).unwrap();
+        assert_eq!(
+            adjust_trove.transfer_coll_out_ctx().accounts.to.key(),
+            recipient
+        );
+
+        // A recipient must hold the pool's collateral
+        let mut wrong_mint = accounts;
+        wrong_mint[COLL_RECIPIENT_INDEX] = load_account(
+            recipient,
+            spl_token::ID,
+            load_token_account(Pubkey::new_unique(), Pubkey::new_unique(), 1_000),
+        );
+        assert_eq!(
+            try_adjust_trove_accounts(wrong_mint).err().unwrap(),
+            ErrorCode::ConstraintTokenMint.into()
+        );
+    }
+
+    #[test]
+    fn adjust_reports_updated_only() {
+        let borrower = Pubkey::new_unique();
+        let mut trove = Trove {
+            coll: 10_000_000_000,
+            debt: 510_000_000_000,
+            stake: 10_000_000_000,
+            ..Default::default()
+        };
+        trove.update_from_adjustment(1_000_000_000, true, 100_000_000_000, false);
+
+        let trove_updated = get_adjust_trove_event(borrower, &trove, 5);
+        assert_eq!(trove_updated.borrower, borrower);
+        assert_eq!(trove_updated.debt, 410_000_000_000);
+        assert_eq!(trove_updated.coll, 11_000_000_000);
+        assert!(matches!(trove_updated.operation, Operation::AdjustTrove));
+        assert_eq!(trove_updated.event_nonce, 5);
+    }
+
+    #[test]
+    fn recovery_mode_coll_top_up_improves_icr() {
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            trove_size: 2,
+            ..Default::default()
+        };
+        let top_up = |coll_change: u64, old_icr: u64, new_icr: u64| {
+            require_valid_adjustment_in_current_mode(
+                &pool_state,
+                true,
+                coll_change,
+                true,
+                0,
+                false,
+                DECIMAL_PRECISION,
+                old_icr,
+                new_icr,
+            )
+        };
+
+        assert!(top_up(1_000_000_000, 1_200_000_000, 1_300_000_000).is_ok());
+        // Staking slippage left nothing to add
+        assert_eq!(
+            top_up(0, 1_200_000_000, 1_200_000_000).unwrap_err(),
+            BorrowerOpsError::RecoveryCollTopUpNoImprovement.into()
+        );
+        assert_eq!(
+            top_up(1, 1_200_000_000, 1_200_000_000).unwrap_err(),
+            BorrowerOpsError::RecoveryCollTopUpNoImprovement.into()
+        );
+        assert!(top_up(1_000_000_000, u64::MAX, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn sole_trove_debt_increase_in_recovery_mode() {
+        let mut pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            trove_size: 1,
+            ..Default::default()
+        };
+        let adjust = |pool_state: &PoolState| {
+            require_valid_adjustment_in_current_mode(
+                pool_state,
+                true,
+                10_000_000_000,
+                true,
+                1_000_000_000,
+                true,
+                DECIMAL_PRECISION,
+                1_550_000_000,
+                1_600_000_000,
+            )
+        };
+
+        assert_eq!(
+            adjust(&pool_state).unwrap_err(),
+            BorrowerOpsError::OnlyOneTrove.into()
+        );
+
+        // A second healthy trove is there as a backstop
+        pool_state.trove_size = 2;
+        assert!(adjust(&pool_state).is_ok());
+    }
+}
@@ -1,4 +1,4 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::compute_units::sol_remaining_compute_units};
 use anchor_spl::{
     associated_token::AssociatedToken,
     token::{Mint, Token, TokenAccount},
@@ -7,7 +7,7 @@ use anchor_spl::{
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 use crate::{
-    constants::ONE_HUNDERED_PERCENT,
+    constants::{MIN_SWEEP_COMPUTE_UNITS, ONE_HUNDERED_PERCENT},
     errors::{BorrowerOpsError, PriceFeedError},
     events::{Liquidation, Operation, TroveLiquidated},
     math::compute_cr,
@@ -16,6 +16,7 @@ use crate::{
         LiquidationValues, LocalVariablesLiquidationSequence, PoolState, PriceFeedState,
         StabilityPoolState, Trove, TroveStatus,
     },
+    utils::get_current_timestamp,
     ID,
 };
 
@@ -243,21 +244,67 @@ impl<'info> BatchLiquidateTroves<'info> {
 
         // Move token to Stability Pool
         self.transfer_coll_from_active_pool_to_sp(totals.total_coll_to_send_to_sp)?;
-        self.burn_usv_from_stability_pool(totals.total_debt_to_offset)?;
+        self.burn_usv_from_stability_pool(totals.get_sp_usv_loss())?;
 
         Ok(())
     }
+
+    /// Offsets and redistributes the liquidated sequence, then moves the tokens
+    pub fn settle(&mut self, mut totals: LiquidationTotals, usv_in_stab_pool: u64) -> Result<()> {
+        let pool_state = &mut self.pool_state;
+        let sp_state = &mut self.stability_pool_state;
+
+        // StabilityPool offset
+        if totals.total_debt_to_offset > 0 && usv_in_stab_pool > 0 {
+            totals.retain_gas_comp_in_sp(pool_state.get_liquidator_gas_comp_bps());
+
+            // _triggerCVGTIssuance
+            let config = &mut self.community_issuance_config;
+            let cvgt_issuance = config.issue_token()?;
+
+            sp_state.offset(&mut self.current_epoch_scale, &totals, cvgt_issuance)?;
+        }
+
+        pool_state.redistribute_debt_and_coll(
+            totals.total_debt_to_redistribute,
+            totals.total_coll_to_redistribute,
+        )?;
+
+        if totals.total_coll_surplus > 0 {
+            pool_state.decrease_active_coll(totals.total_coll_surplus);
+            pool_state.increase_total_surplus(totals.total_coll_surplus);
+        }
+
+        pool_state
+            .update_system_snapshots_exclude_coll_remainder(totals.total_coll_gas_compensation);
+
+        let liquidated_debt = totals.total_debt_in_sequence;
+        let liquidated_coll = totals.total_coll_in_sequence
+            - totals.total_coll_surplus
+            - totals.total_coll_gas_compensation;
+
+        emit!(Liquidation {
+            debt: liquidated_debt,
+            coll: liquidated_coll,
+            total_usv_compensation: totals.total_coll_gas_compensation,
+            total_coll_compensation: totals.total_usv_gas_compensation,
+            event_nonce: pool_state.next_event_nonce(),
+        });
+
+        pool_state.move_coll_debt_from_liquidate(sp_state, &totals);
+
+        self.move_tokens(totals)
+    }
 }
 
 pub fn batch_liquidate_troves_handler(ctx: Context<BatchLiquidateTroves>) -> Result<()> {
     // TODO: Require trove length
     let pool_state = &mut ctx.accounts.pool_state;
-    let sp_state = &mut ctx.accounts.stability_pool_state;
-    let current_epoch_scale = &mut ctx.accounts.current_epoch_scale;
+    let liquidator = ctx.accounts.liquidator.key();
 
     let mut vars = LocalVariables::default();
 
-    vars.usv_in_stab_pool = sp_state.total_usv_deposits;
+    vars.usv_in_stab_pool = ctx.accounts.stability_pool_state.total_usv_deposits;
     vars.price = ctx.accounts.price_feed_state.fetch_price(
         &ctx.accounts.chainlink_program,
         &ctx.accounts.chainlink_feed,
@@ -270,6 +317,7 @@ pub fn batch_liquidate_troves_handler(ctx: Context<BatchLiquidateTroves>) -> Res
         get_totals_from_batch_liquidate_recovery_mode(
             pool_state,
             ctx.remaining_accounts,
+            &liquidator,
             vars.price,
             vars.usv_in_stab_pool,
         )?
@@ -277,8 +325,10 @@ pub fn batch_liquidate_troves_handler(ctx: Context<BatchLiquidateTroves>) -> Res
         get_totals_from_batch_liqidate_normal_mode(
             pool_state,
             ctx.remaining_accounts,
+            &liquidator,
             vars.price,
             vars.usv_in_stab_pool,
+            get_current_timestamp(),
         )?
     };
 
@@ -287,49 +337,43 @@ pub fn batch_liquidate_troves_handler(ctx: Context<BatchLiquidateTroves>) -> Res
         BorrowerOpsError::LiquidateZeroDebt
     );
 
-    // StabilityPool offset
-    if totals.total_debt_to_offset > 0 && vars.usv_in_stab_pool > 0 {
-        // _triggerCVGTIssuance
-        let config = &mut ctx.accounts.community_issuance_config;
-        let cvgt_issuance = config.issue_token()?;
-
-        sp_state.offset(current_epoch_scale, &totals, cvgt_issuance);
-    }
-
-    pool_state.redistribute_debt_and_coll(
-        totals.total_debt_to_redistribute,
-        totals.total_coll_to_redistribute,
-    );
-
-    if totals.total_coll_surplus > 0 {
-        pool_state.decrease_active_coll(totals.total_coll_surplus);
-        pool_state.increase_total_surplus(totals.total_coll_surplus);
-    }
-
-    pool_state.update_system_snapshots_exclude_coll_remainder(totals.total_coll_gas_compensation);
-
-    vars.liquidated_debt = totals.total_debt_in_sequence;
-    vars.liquidated_coll = totals.total_coll_in_sequence
-        - totals.total_coll_surplus
-        - totals.total_coll_gas_compensation;
+    ctx.accounts.settle(totals, vars.usv_in_stab_pool)
+}
 
-    emit!(Liquidation {
-        debt: vars.liquidated_debt,
-        coll: vars.liquidated_coll,
-        total_usv_compensation: totals.total_coll_gas_compensation,
-        total_coll_compensation: totals.total_usv_gas_compensation,
-    });
+/// Liquidates troves from the tail of the sorted list while the system is in recovery
+/// mode, stopping at the first trove with ICR >= CCR or when the compute budget runs low.
+pub fn recovery_sweep_handler(ctx: Context<BatchLiquidateTroves>) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    let liquidator = ctx.accounts.liquidator.key();
+    let usv_in_stab_pool = ctx.accounts.stability_pool_state.total_usv_deposits;
+    let price = ctx.accounts.price_feed_state.fetch_price(
+        &ctx.accounts.chainlink_program,
+        &ctx.accounts.chainlink_feed,
+        &ctx.accounts.jitosol_stake_pool,
+        &ctx.accounts.pyth_feed_account,
+    )?;
 
-    drop(vars);
+    let totals = get_totals_from_recovery_sweep(
+        pool_state,
+        ctx.remaining_accounts,
+        &liquidator,
+        price,
+        usv_in_stab_pool,
+        || sol_remaining_compute_units() >= MIN_SWEEP_COMPUTE_UNITS,
+    )?;
 
-    pool_state.move_coll_debt_from_liquidate(sp_state, &totals);
+    require!(
+        totals.total_debt_in_sequence > 0,
+        BorrowerOpsError::LiquidateZeroDebt
+    );
 
-    ctx.accounts.move_tokens(totals)
+    ctx.accounts.settle(totals, usv_in_stab_pool)
 }
 
 fn get_totals_from_batch_liquidate_recovery_mode(
     pool_state: &mut PoolState,
     remaining_accounts: &[AccountInfo<'_>],
+    liquidator: &Pubkey,
     price: u64,
     usv_in_stab_pool: u64,
 ) -> Result<LiquidationTotals> {
@@ -372,6 +416,7 @@ fn get_totals_from_batch_liquidate_recovery_mode(
             if vars.icr >= pool_state.mcr && vars.remaining_usv_in_stab_pool == 0 {
                 continue;
             }
+            pool_state.require_not_self_liquidation(liquidator, &trove.creator)?;
             let tcr = compute_cr(vars.entire_system_coll, vars.entire_system_debt, price).unwrap();
             single_liquidation = liquidate_recovery_mode(
                 pool_state,
@@ -395,12 +440,14 @@ fn get_totals_from_batch_liquidate_recovery_mode(
                 price,
             );
         } else if vars.back_to_normal_mode && vars.icr < pool_state.mcr {
+            pool_state.require_not_self_liquidation(liquidator, &trove.creator)?;
             single_liquidation = liquidate_normal_mode(
                 pool_state,
                 &mut trove,
                 account.key(),
                 remaining_accounts,
                 vars.remaining_usv_in_stab_pool,
+                price,
             )?;
             vars.remaining_usv_in_stab_pool = vars
                 .remaining_usv_in_stab_pool
@@ -415,7 +462,6 @@ fn get_totals_from_batch_liquidate_recovery_mode(
         // store trove data
         trove.try_serialize(&mut data.as_mut())?;
         drop(data);
-        drop(trove);
     }
     Ok(totals)
 }
@@ -423,8 +469,10 @@ fn get_totals_from_batch_liquidate_recovery_mode(
 fn get_totals_from_batch_liqidate_normal_mode(
     pool_state: &mut PoolState,
     remaining_accounts: &[AccountInfo<'_>],
+    liquidator: &Pubkey,
     price: u64,
     usv_in_stab_pool: u64,
+    current_timestamp: u64,
 ) -> Result<LiquidationTotals> {
     let mut totals = LiquidationTotals::default();
     let mut vars = LocalVariablesLiquidationSequence::default();
@@ -457,13 +505,17 @@ fn get_totals_from_batch_liqidate_normal_mode(
         }
         vars.icr = trove.get_current_icr(pool_state, price);
 
-        if vars.icr < pool_state.mcr {
+        if vars.icr < pool_state.mcr
+            && !trove.is_in_liquidation_grace_period(pool_state, vars.icr, current_timestamp)
+        {
+            pool_state.require_not_self_liquidation(liquidator, &trove.creator)?;
             single_liquidation = liquidate_normal_mode(
                 pool_state,
                 &mut trove,
                 account.key(),
                 remaining_accounts,
                 vars.remaining_usv_in_stab_pool,
+                price,
             )?;
             vars.remaining_usv_in_stab_pool = vars
                 .remaining_usv_in_stab_pool
@@ -476,7 +528,88 @@ fn get_totals_from_batch_liqidate_normal_mode(
         // store trove data
         trove.try_serialize(&mut data.as_mut())?;
         drop(data);
-        drop(trove);
+    }
+    Ok(totals)
+}
+
+fn get_totals_from_recovery_sweep(
+    pool_state: &mut PoolState,
+    remaining_accounts: &[AccountInfo<'_>],
+    liquidator: &Pubkey,
+    price: u64,
+    usv_in_stab_pool: u64,
+    has_compute_budget: impl Fn() -> bool,
+) -> Result<LiquidationTotals> {
+    require!(
+        pool_state.check_recovery_mode(price),
+        BorrowerOpsError::NotInRecoveryMode
+    );
+
+    let mut totals = LiquidationTotals::default();
+    let mut vars = LocalVariablesLiquidationSequence {
+        remaining_usv_in_stab_pool: usv_in_stab_pool,
+        entire_system_debt: pool_state.get_entire_debt(),
+        entire_system_coll: pool_state.get_entire_coll(),
+        ..Default::default()
+    };
+
+    let mut trove_id = pool_state.trove_tail;
+    while trove_id != Pubkey::default() && has_compute_budget() {
+        // Stop where the caller's segment of the sorted list ends
+        let index = match find_trove_index(remaining_accounts, trove_id) {
+            Some(index) => index,
+            None => break,
+        };
+        let account = &remaining_accounts[index];
+        require!(account.owner == &ID, BorrowerOpsError::InvalidAccount);
+
+        let mut data = account.try_borrow_mut_data()?;
+        let mut trove =
+            Trove::try_deserialize(&mut data.as_ref()).expect("Error Deserializing Data");
+        require!(
+            trove.pool_state == pool_state.key(),
+            BorrowerOpsError::InvalidAccount
+        );
+        if trove.status != TroveStatus::Active
+            || find_trove_index(remaining_accounts, trove.prev).is_none()
+        {
+            break;
+        }
+
+        vars.icr = trove.get_current_icr(pool_state, price);
+        if vars.icr >= pool_state.ccr {
+            break;
+        }
+
+        pool_state.require_not_self_liquidation(liquidator, &trove.creator)?;
+        let prev = trove.prev;
+        let tcr = compute_cr(vars.entire_system_coll, vars.entire_system_debt, price).unwrap();
+        let single_liquidation = liquidate_recovery_mode(
+            pool_state,
+            &mut trove,
+            trove_id,
+            remaining_accounts,
+            vars.remaining_usv_in_stab_pool,
+            tcr,
+            vars.icr,
+            price,
+        )?;
+        vars.update(&single_liquidation);
+        totals.add_liquidation_values(&single_liquidation);
+
+        // store trove data
+        trove.try_serialize(&mut data.as_mut())?;
+        drop(data);
+
+        // The recovery mode rules no longer apply once the sequence restores TCR >= CCR
+        if !pool_state.check_potential_recovery_mode(
+            vars.entire_system_coll,
+            vars.entire_system_debt,
+            price,
+        ) {
+            break;
+        }
+        trove_id = prev;
     }
     Ok(totals)
 }
@@ -487,6 +620,7 @@ fn liquidate_normal_mode(
     trove_id: Pubkey,
     remaining_accounts: &[AccountInfo<'_>],
     usv_in_stab_pool: u64,
+    price: u64,
 ) -> Result<LiquidationValues> {
     let mut single_liquidation: LiquidationValues = Default::default();
 
@@ -501,9 +635,13 @@ fn liquidate_normal_mode(
     // Remove stake
     trove.remove_stake(pool_state);
 
-    single_liquidation.coll_gas_compensation =
-        pool_state.get_coll_gas_compensation(single_liquidation.entire_trove_coll);
-    single_liquidation.usv_gas_compensation = pool_state.gas_compensation;
+    single_liquidation.coll_gas_compensation = pool_state.get_coll_liquidation_compensation(
+        single_liquidation.entire_trove_coll,
+        single_liquidation.entire_trove_debt,
+        price,
+    );
+    single_liquidation.usv_gas_compensation =
+        trove.get_liquidation_usv_gas_compensation(pool_state.gas_compensation);
 
     let coll_to_liquidate = single_liquidation
         .entire_trove_coll
@@ -545,9 +683,13 @@ fn liquidate_recovery_mode(
     let pending_debt_reward = entire_debt_and_coll.2;
     let pending_coll_reward = entire_debt_and_coll.3;
 
-    single_liquidation.coll_gas_compensation =
-        pool_state.get_coll_gas_compensation(single_liquidation.entire_trove_coll);
-    single_liquidation.usv_gas_compensation = pool_state.gas_compensation;
+    single_liquidation.coll_gas_compensation = pool_state.get_coll_liquidation_compensation(
+        single_liquidation.entire_trove_coll,
+        single_liquidation.entire_trove_debt,
+        price,
+    );
+    single_liquidation.usv_gas_compensation =
+        trove.get_liquidation_usv_gas_compensation(pool_state.gas_compensation);
     let coll_to_liquidate = single_liquidation
         .entire_trove_coll
         .checked_sub(single_liquidation.coll_gas_compensation)
@@ -628,6 +770,180 @@ struct LocalVariables {
     pub price: u64,
     pub usv_in_stab_pool: u64,
     pub recovery_mode_at_start: bool,
-    pub liquidated_debt: u64,
-    pub liquidated_coll: u64,
+}
+
+#[cfg(test)]
+pub mod batch_liquidate_troves_test {
+    use super::*;
+    use crate::{
+        constants::DECIMAL_PRECISION,
+        state::trove_test::{load_trove_account, read_trove_account},
+    };
+
+    const UNIT: u64 = 1_000_000_000;
+
+    /// Sorted troves from tail to head as (coll, debt)
+    fn load_system(troves: &[(u64, u64)]) -> (PoolState, Vec<Pubkey>, Vec<AccountInfo<'static>>) {
+        let cvgt = Pubkey::new_unique();
+        let (_, bump) = Pubkey::find_program_address(&[b"state", cvgt.as_ref()], &ID);
+        let keys: Vec<Pubkey> = troves.iter().map(|_| Pubkey::new_unique()).collect();
+        let mut pool_state = PoolState {
+            cvgt,
+            bump: [bump],
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            coll_gas_comp_percent_divisor: 200,
+            trove_size: troves.len() as u64,
+            trove_tail: keys[0],
+            trove_head: keys[keys.len() - 1],
+            ..Default::default()
+        };
+
+        let accounts = troves
+            .iter()
+            .enumerate()
+            .map(|(i, &(coll, debt))| {
+                pool_state.active_coll += coll;
+                pool_state.active_debt += debt;
+                pool_state.total_stakes += coll;
+                let trove = Trove {
+                    pool_state: pool_state.key(),
+                    creator: keys[i],
+                    coll,
+                    debt,
+                    stake: coll,
+                    status: TroveStatus::Active,
+                    // prev points towards the head
+                    prev: keys.get(i + 1).copied().unwrap_or_default(),
+                    next: if i == 0 {
+                        Pubkey::default()
+                    } else {
+                        keys[i - 1]
+                    },
+                    ..Default::default()
+                };
+                load_trove_account(keys[i], &trove)
+            })
+            .collect();
+        (pool_state, keys, accounts)
+    }
+
+    #[test]
+    fn recovery_sweep_closes_troves_below_ccr() {
+        let (mut pool_state, keys, accounts) = load_system(&[
+            (95 * UNIT, 100 * UNIT),
+            (120 * UNIT, 100 * UNIT),
+            (160 * UNIT, 100 * UNIT),
+            (170 * UNIT, 100 * UNIT),
+        ]);
+
+        let totals = get_totals_from_recovery_sweep(
+            &mut pool_state,
+            &accounts,
+            &Pubkey::new_unique(),
+            DECIMAL_PRECISION,
+            1_000 * UNIT,
+            || true,
+        )
+        .unwrap();
+
+        // ICR 95% is redistributed, ICR 120% is offset at the capped rate
+        assert_eq!(totals.total_debt_in_sequence, 200 * UNIT);
+        assert_eq!(totals.total_debt_to_redistribute, 100 * UNIT);
+        assert_eq!(totals.total_debt_to_offset, 100 * UNIT);
+        assert_eq!(totals.total_coll_surplus, 10 * UNIT);
+
+        let statuses: Vec<TroveStatus> = accounts
+            .iter()
+            .map(|a| read_trove_account(a).status)
+            .collect();
+        assert!(
+            statuses
+                == [
+                    TroveStatus::ClosedByLiquidation,
+                    TroveStatus::ClosedByLiquidation,
+                    TroveStatus::Active,
+                    TroveStatus::Active,
+                ]
+        );
+        assert_eq!(pool_state.trove_size, 2);
+        assert_eq!(pool_state.trove_tail, keys[2]);
+        assert_eq!(read_trove_account(&accounts[2]).next, Pubkey::default());
+    }
+
+    #[test]
+    fn recovery_sweep_requires_recovery_mode() {
+        let (mut pool_state, _, accounts) =
+            load_system(&[(120 * UNIT, 100 * UNIT), (200 * UNIT, 100 * UNIT)]);
+
+        // TCR 160%
+        assert_eq!(
+            get_totals_from_recovery_sweep(
+                &mut pool_state,
+                &accounts,
+                &Pubkey::new_unique(),
+                DECIMAL_PRECISION,
+                1_000 * UNIT,
+                || true,
+            )
+            .err()
+            .unwrap(),
+            BorrowerOpsError::NotInRecoveryMode.into()
+        );
+
+        // TCR 128%, but no compute budget left to liquidate anything
+        let totals = get_totals_from_recovery_sweep(
+            &mut pool_state,
+            &accounts,
+            &Pubkey::new_unique(),
+            DECIMAL_PRECISION * 8 / 10,
+            1_000 * UNIT,
+            || false,
+        )
+        .unwrap();
+        assert_eq!(totals.total_debt_in_sequence, 0);
+        assert_eq!(pool_state.trove_size, 2);
+    }
+
+    #[test]
+    fn recovery_sweep_rejects_self_liquidation() {
+        let (mut pool_state, keys, accounts) = load_system(&[
+            (95 * UNIT, 100 * UNIT),
+            (120 * UNIT, 100 * UNIT),
+            (170 * UNIT, 100 * UNIT),
+        ]);
+
+        // The liquidator owns the second trove of the sequence
+        assert_eq!(
+            get_totals_from_recovery_sweep(
+                &mut pool_state,
+                &accounts,
+                &keys[1],
+                DECIMAL_PRECISION,
+                1_000 * UNIT,
+                || true,
+            )
+            .err()
+            .unwrap(),
+            BorrowerOpsError::SelfLiquidation.into()
+        );
+
+        // Unless the pool allows it
+        let (mut pool_state, keys, accounts) = load_system(&[
+            (95 * UNIT, 100 * UNIT),
+            (120 * UNIT, 100 * UNIT),
+            (170 * UNIT, 100 * UNIT),
+        ]);
+        pool_state.self_liquidation_allowed = true;
+        let totals = get_totals_from_recovery_sweep(
+            &mut pool_state,
+            &accounts,
+            &keys[1],
+            DECIMAL_PRECISION,
+            1_000 * UNIT,
+            || true,
+        )
+        .unwrap();
+        assert_eq!(totals.total_debt_in_sequence, 200 * UNIT);
+    }
 }
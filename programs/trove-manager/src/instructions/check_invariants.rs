@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, TokenAccount};
+
+use crate::{
+    constants::ALL_INVARIANTS,
+    errors::BorrowerOpsError,
+    state::{PoolState, StabilityPoolState, VaultBalances},
+};
+
+#[derive(Accounts)]
+pub struct CheckInvariants<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        constraint = stablecoin.key() == pool_state.stablecoin
+    )]
+    pub stablecoin: Box<Account<'info, Mint>>,
+
+    #[account(
+        constraint = collateral.key() == pool_state.collateral
+    )]
+    pub collateral: Box<Account<'info, Mint>>,
+
+    /// CHECK: This account is not read or written
+    #[account(
+        seeds = [
+            b"token-authority",
+            pool_state.key().as_ref()
+        ],
+        bump
+    )]
+    pub token_authority: UncheckedAccount<'info>,
+
+    #[account(
+        associated_token::mint = collateral,
+        associated_token::authority = token_authority
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        associated_token::mint = stablecoin,
+        associated_token::authority = token_authority
+    )]
+    pub gas_compensation_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        associated_token::mint = stablecoin,
+        associated_token::authority = stability_pool_state
+    )]
+    pub sp_usv_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        associated_token::mint = collateral,
+        associated_token::authority = stability_pool_state
+    )]
+    pub sp_coll_vault: Box<Account<'info, TokenAccount>>,
+}
+
+/// Read-only: returns the `INVARIANT_*` bits that hold between the accounting and the vaults.
+/// With `require_all`, reverts unless every check holds.
+pub fn check_invariants_handler(ctx: Context<CheckInvariants>, require_all: bool) -> Result<u8> {
+    let accounts = &ctx.accounts;
+    let balances = VaultBalances {
+        collateral_vault: accounts.collateral_vault.amount,
+        stablecoin_supply: accounts.stablecoin.supply,
+        gas_compensation_vault: accounts.gas_compensation_vault.amount,
+        sp_usv_vault: accounts.sp_usv_vault.amount,
+        sp_coll_vault: accounts.sp_coll_vault.amount,
+    };
+    let invariants = accounts
+        .pool_state
+        .get_invariants(&accounts.stability_pool_state, &balances);
+    msg!("Invariants: {:#06b}", invariants);
+
+    if require_all {
+        require!(
+            invariants == ALL_INVARIANTS,
+            BorrowerOpsError::InvariantViolated
+        );
+    }
+    Ok(invariants)
+}
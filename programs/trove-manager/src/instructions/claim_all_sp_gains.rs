@@ -0,0 +1,238 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Mint, Token, TokenAccount},
+    token_2022::{transfer_checked, TransferChecked},
+};
+
+use crate::{
+    events::{CVGTPaidToDepositor, CollGainWithdrawn, UserDepositChanged},
+    state::{
+        get_epoch_scale_range, CommunityIssuanceConfig, EpochScale, PoolState,
+        StabilityPoolDeposit, StabilityPoolState,
+    },
+};
+
+#[derive(Accounts)]
+pub struct ClaimAllSPGains<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            depositor.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"epoch-scale",
+            stability_pool_state.key().as_ref(),
+            stability_pool_state.current_epoch.to_le_bytes().as_ref(),
+            stability_pool_state.current_scale.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub current_epoch_scale: Box<Account<'info, EpochScale>>,
+
+    #[account(
+        constraint = collateral.key() == pool_state.collateral
+    )]
+    pub collateral: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = collateral,
+        associated_token::authority = stability_pool_state
+    )]
+    pub sp_coll_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = collateral,
+        associated_token::authority = depositor,
+    )]
+    pub depositor_coll_ata: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = cvgt,
+        associated_token::authority = depositor,
+    )]
+    pub depositor_cvgt_ata: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = cvgt.key() == pool_state.cvgt
+    )]
+    pub cvgt: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = community_issuance_config.cvgt,
+        associated_token::authority = community_issuance_config
+    )]
+    pub community_issuance_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            pool_state.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub community_issuance_config: Box<Account<'info, CommunityIssuanceConfig>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClaimAllSPGains<'info> {
+    pub fn issue_cvgt(&mut self) -> Result<u64> {
+        let config = &mut self.community_issuance_config;
+
+        let issued = config.issue_token()?;
+        Ok(issued)
+    }
+
+    pub fn send_cvgt_to_depositor(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let cpi_accounts = TransferChecked {
+            from: self.community_issuance_vault.to_account_info(),
+            to: self.depositor_cvgt_ata.to_account_info(),
+            authority: self.community_issuance_config.to_account_info(),
+            mint: self.cvgt.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
+        let auth_seed = &self.community_issuance_config.seeds();
+
+        transfer_checked(
+            cpi_context.with_signer(&[auth_seed]),
+            amount,
+            self.cvgt.decimals,
+        )
+    }
+
+    pub fn transfer_coll_out(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let pool_state_key = self.pool_state.key();
+        let auth_seed = &self.pool_state.stability_pool_seeds(&pool_state_key);
+
+        let cpi_accounts = TransferChecked {
+            from: self.sp_coll_vault.to_account_info(),
+            to: self.depositor_coll_ata.to_account_info(),
+            authority: self.stability_pool_state.to_account_info(),
+            mint: self.collateral.to_account_info(),
+        };
+
+        let cpi_program = self.token_program.to_account_info();
+
+        transfer_checked(
+            CpiContext::new(cpi_program, cpi_accounts).with_signer(&[auth_seed]),
+            amount,
+            self.collateral.decimals,
+        )
+    }
+}
+
+/// Realizes and pays out the gains of a deposit that has been idle across several scale changes.
+/// `remaining_accounts` holds the ordered epoch-scale accounts from the deposit's snapshot onwards.
+pub fn claim_all_sp_gains_handler(ctx: Context<ClaimAllSPGains>) -> Result<()> {
+    let cvgt_issuance = ctx.accounts.issue_cvgt()?;
+
+    let current_epoch_scale_key = &ctx.accounts.current_epoch_scale.key();
+    let depositor = ctx.accounts.depositor.key;
+    let sp_deposit = &mut ctx.accounts.stability_pool_deposit;
+    let sp_state = &mut ctx.accounts.stability_pool_state;
+    let current_epoch_scale = &mut ctx.accounts.current_epoch_scale;
+
+    current_epoch_scale.update_g(sp_state, cvgt_issuance);
+
+    sp_deposit.require_user_has_deposit()?;
+
+    let epoch_scales = get_epoch_scale_range(
+        ctx.remaining_accounts,
+        current_epoch_scale_key,
+        current_epoch_scale,
+        sp_state,
+        sp_deposit,
+    )?;
+
+    let (depositor_coll_gain, cvgt_gain) = sp_deposit
+        .get_gains_from_epoch_scales(&epoch_scales)
+        .unwrap();
+    let compounded_usv_deposit = sp_deposit.get_compounded_usv_deposit(sp_state).unwrap();
+    let usv_loss = sp_deposit
+        .initial_value
+        .checked_sub(compounded_usv_deposit)
+        .unwrap();
+
+    emit!(CVGTPaidToDepositor {
+        depositor: *depositor,
+        cvgt_gain
+    });
+
+    // Update deposit
+    sp_deposit.update_deposit_and_snapshot(
+        sp_state,
+        current_epoch_scale,
+        *depositor,
+        compounded_usv_deposit,
+    );
+    sp_state.decrease_coll(depositor_coll_gain);
+
+    let claimable_coll = sp_deposit
+        .claimable_coll
+        .checked_add(depositor_coll_gain)
+        .unwrap();
+    let claimable_cvgt = sp_deposit.claimable_cvgt.checked_add(cvgt_gain).unwrap();
+    sp_deposit.claimable_coll = 0;
+    sp_deposit.claimable_cvgt = 0;
+    ctx.accounts
+        .community_issuance_config
+        .record_cvgt_sent(claimable_cvgt);
+
+    // Transfer CVGT to user
+    ctx.accounts.send_cvgt_to_depositor(claimable_cvgt)?;
+    // Transfer Coll to user
+    ctx.accounts.transfer_coll_out(claimable_coll)?;
+
+    emit!(UserDepositChanged {
+        depositor: *depositor,
+        new_deposit: compounded_usv_deposit
+    });
+
+    emit!(CollGainWithdrawn {
+        depositor: *depositor,
+        coll: depositor_coll_gain,
+        usv_loss
+    });
+
+    Ok(())
+}
@@ -2,10 +2,13 @@ use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
     token::{Mint, Token, TokenAccount},
-    token_2022::{transfer_checked, TransferChecked},
+    token_2022::{burn, transfer_checked, Burn, TransferChecked},
 };
 
-use crate::state::{PoolState, Trove};
+use crate::{
+    state::{PoolState, Trove},
+    utils::require_sufficient_usv_balance,
+};
 
 #[derive(Accounts)]
 pub struct ClaimCollSurplus<'info> {
@@ -28,6 +31,19 @@ pub struct ClaimCollSurplus<'info> {
     )]
     pub collateral: Box<Account<'info, Mint>>,
 
+    #[account(
+        mut,
+        constraint = stablecoin.key() == pool_state.stablecoin
+    )]
+    pub stablecoin: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = borrower
+    )]
+    pub borrower_stablecoin_ata: Box<Account<'info, TokenAccount>>,
+
     /// CHECK: This account is not read or written
     #[account(
         seeds = [
@@ -71,25 +87,47 @@ impl<'info> ClaimCollSurplus<'info> {
         let cpi_program = self.token_program.to_account_info();
         CpiContext::new(cpi_program, cpi_accounts)
     }
+
+    pub fn burn_stablecoin_from_user_ctx(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        let cpi_accounts = Burn {
+            mint: self.stablecoin.to_account_info(),
+            from: self.borrower_stablecoin_ata.to_account_info(),
+            authority: self.borrower.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
 }
 
 pub fn claim_coll_surplus_handler(ctx: Context<ClaimCollSurplus>) -> Result<()> {
     let pool_state = &mut ctx.accounts.pool_state;
     let trove = &mut ctx.accounts.trove;
 
+    // Gas compensation the borrower kept through a redemption close is burned first
+    let usv_to_burn = trove.clear_gas_comp_owed();
+    require_sufficient_usv_balance(ctx.accounts.borrower_stablecoin_ata.amount, usv_to_burn)?;
+    if usv_to_burn > 0 {
+        pool_state.decrease_active_debt(usv_to_burn);
+    }
+
     let amount_to_send = trove.clear_surplus();
     if amount_to_send > 0 {
         pool_state.decrease_total_surplus(amount_to_send);
-        move_token(ctx, amount_to_send)?;
     }
-    Ok(())
+    move_token(ctx, usv_to_burn, amount_to_send)
 }
 
-pub fn move_token(ctx: Context<ClaimCollSurplus>, coll: u64) -> Result<()> {
+pub fn move_token(ctx: Context<ClaimCollSurplus>, usv_to_burn: u64, coll: u64) -> Result<()> {
     let pool_state = &ctx.accounts.pool_state;
     let pool_state_key = pool_state.key();
     let authority_seed = &pool_state.token_auth_seeds(&pool_state_key);
 
+    if usv_to_burn > 0 {
+        burn(ctx.accounts.burn_stablecoin_from_user_ctx(), usv_to_burn)?;
+    }
+    if coll == 0 {
+        return Ok(());
+    }
     transfer_checked(
         ctx.accounts
             .transfer_coll_out_ctx()
@@ -99,3 +137,122 @@ pub fn move_token(ctx: Context<ClaimCollSurplus>, coll: u64) -> Result<()> {
     )?;
     Ok(())
 }
+
+#[cfg(test)]
+pub mod claim_coll_surplus_test {
+    use super::*;
+    use crate::{
+        errors::BorrowerOpsError,
+        state::{trove_test::load_trove_account, TroveStatus},
+        utils::utils_test::{
+            load_account, load_anchor_account, load_mint, load_program, load_signer,
+            load_token_account, read_token_amount, set_program_test_stubs,
+        },
+        ID,
+    };
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::{
+        associated_token::get_associated_token_address,
+        token::spl_token::{self, state::Mint as SplMint},
+    };
+    use std::collections::BTreeSet;
+
+    const SURPLUS: u64 = 7_600_000_000;
+    const GAS_COMPENSATION: u64 = 200_000_000;
+
+    /// Accounts of `claim_coll_surplus` in declaration order
+    fn load_claim_accounts(borrower_usv: u64) -> Vec<AccountInfo<'static>> {
+        let borrower = Pubkey::new_unique();
+        let pool_state_key = Pubkey::new_unique();
+        let pool_state = PoolState {
+            stablecoin: Pubkey::new_unique(),
+            collateral: Pubkey::new_unique(),
+            gas_compensation: GAS_COMPENSATION,
+            active_debt: 5_000_000_000,
+            total_surplus: SURPLUS,
+            ..Default::default()
+        };
+        let (trove_key, _) = Pubkey::find_program_address(
+            &[b"trove", pool_state_key.as_ref(), borrower.as_ref()],
+            &ID,
+        );
+        // Closed by a redemption while the borrower held the gas compensation
+        let trove = Trove {
+            creator: borrower,
+            status: TroveStatus::ClosedByRedemption,
+            surplus_balance: SURPLUS,
+            gas_comp_held_by_borrower: true,
+            gas_comp_owed: GAS_COMPENSATION,
+            ..Default::default()
+        };
+        let (token_authority, _) =
+            Pubkey::find_program_address(&[b"token-authority", pool_state_key.as_ref()], &ID);
+        let token_account = |mint: Pubkey, owner: Pubkey, amount: u64| {
+            load_account(
+                get_associated_token_address(&owner, &mint),
+                spl_token::ID,
+                load_token_account(mint, owner, amount),
+            )
+        };
+        // The borrower's USV is the whole supply
+        let mut stablecoin_mint = load_mint(token_authority);
+        let mut mint = SplMint::unpack(&stablecoin_mint).unwrap();
+        mint.supply = borrower_usv;
+        mint.pack_into_slice(&mut stablecoin_mint);
+
+        vec![
+            load_anchor_account(pool_state_key, &pool_state),
+            load_trove_account(trove_key, &trove),
+            load_account(
+                pool_state.collateral,
+                spl_token::ID,
+                load_mint(token_authority),
+            ),
+            load_account(pool_state.stablecoin, spl_token::ID, stablecoin_mint),
+            token_account(pool_state.stablecoin, borrower, borrower_usv),
+            load_account(token_authority, Pubkey::default(), vec![]),
+            token_account(pool_state.collateral, borrower, 0),
+            token_account(pool_state.collateral, token_authority, SURPLUS),
+            load_signer(borrower, 0),
+            load_program(spl_token::ID),
+            load_program(anchor_spl::associated_token::ID),
+            load_program(System::id()),
+        ]
+    }
+
+    fn claim_coll_surplus(accounts: &[AccountInfo<'static>]) -> Result<ClaimCollSurplus<'static>> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = ClaimCollSurplusBumps::default();
+        let mut claim_accounts =
+            ClaimCollSurplus::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        claim_coll_surplus_handler(Context::new(&ID, &mut claim_accounts, &[], bumps))?;
+        Ok(claim_accounts)
+    }
+
+    #[test]
+    fn claim_burns_owed_gas_comp_and_its_debt() {
+        set_program_test_stubs();
+        let accounts = load_claim_accounts(GAS_COMPENSATION);
+
+        let claimed = claim_coll_surplus(&accounts).unwrap();
+        assert_eq!(read_token_amount(&accounts[4]), 0);
+        assert_eq!(read_token_amount(&accounts[6]), SURPLUS);
+        assert_eq!(read_token_amount(&accounts[7]), 0);
+        assert_eq!(claimed.pool_state.active_debt, 4_800_000_000);
+        assert_eq!(claimed.pool_state.total_surplus, 0);
+        assert_eq!(claimed.trove.gas_comp_owed, 0);
+        assert_eq!(claimed.trove.surplus_balance, 0);
+    }
+
+    #[test]
+    fn claim_needs_usv_for_owed_gas_comp() {
+        set_program_test_stubs();
+        let accounts = load_claim_accounts(GAS_COMPENSATION - 1);
+
+        assert_eq!(
+            claim_coll_surplus(&accounts).err().unwrap(),
+            BorrowerOpsError::InsufficientUSVBalance.into()
+        );
+    }
+}
@@ -77,6 +77,7 @@ pub struct ClaimFromSP<'info> {
     pub community_issuance_vault: Box<Account<'info, TokenAccount>>,
 
     #[account(
+        mut,
         seeds = [
             b"community-issuance",
             pool_state.cvgt.as_ref()
@@ -139,16 +140,20 @@ impl<'info> ClaimFromSP<'info> {
 }
 
 pub fn claim_from_sp_handler(ctx: Context<ClaimFromSP>) -> Result<()> {
-    let sp_deposit = &mut ctx.accounts.stability_pool_deposit;
-    let claimable_coll = sp_deposit.claimable_coll;
-    let claimable_cvgt = sp_deposit.claimable_cvgt;
-    sp_deposit.claimable_coll = 0;
-    sp_deposit.claimable_cvgt = 0;
+    let depositor = ctx.accounts.depositor.key();
+    let claimed = ctx.accounts.stability_pool_deposit.claim(depositor);
+    let claimable_coll = claimed.coll_claimed;
+    let claimable_cvgt = claimed.cvgt_claimed;
+    ctx.accounts
+        .community_issuance_config
+        .record_cvgt_sent(claimable_cvgt);
 
     // Transfer CVGT to user
     ctx.accounts.send_cvgt_to_depositor(claimable_cvgt)?;
     // Transfer Coll to user
     ctx.accounts.transfer_coll_out(claimable_coll)?;
 
+    emit!(claimed);
+
     Ok(())
 }
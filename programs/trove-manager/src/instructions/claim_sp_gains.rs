@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    instructions::realize_sp_deposit_gains,
+    state::{
+        CommunityIssuanceConfig, EpochScale, PoolState, StabilityPoolDeposit, StabilityPoolState,
+    },
+};
+
+#[derive(Accounts)]
+pub struct ClaimSPGains<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            depositor.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"epoch-scale",
+            stability_pool_state.key().as_ref(),
+            stability_pool_state.current_epoch.to_le_bytes().as_ref(),
+            stability_pool_state.current_scale.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub current_epoch_scale: Box<Account<'info, EpochScale>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            pool_state.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub community_issuance_config: Box<Account<'info, CommunityIssuanceConfig>>,
+
+    pub depositor: Signer<'info>,
+}
+
+/// Realizes the depositor's coll and CVGT gains into the claimable balances, paid out by
+/// `claim_from_sp`. No USV leaves the pool, so neither the price nor the lowest trove is needed.
+/// `remaining_accounts` holds the ordered epoch-scale accounts from the deposit's snapshot onwards.
+pub fn claim_sp_gains_handler(ctx: Context<ClaimSPGains>) -> Result<()> {
+    let depositor = ctx.accounts.depositor.key();
+    let accounts = ctx.accounts;
+    realize_sp_deposit_gains(
+        ctx.remaining_accounts,
+        &mut accounts.community_issuance_config,
+        &mut accounts.stability_pool_state,
+        &mut accounts.stability_pool_deposit,
+        &mut accounts.current_epoch_scale,
+        depositor,
+    )
+}
@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::{PoolConfigError, StabilityPoolError},
+    state::{EpochScale, PoolState, StabilityPoolState},
+};
+
+#[derive(Accounts)]
+#[instruction(epoch: u128, scale: u128)]
+pub struct CloseEpochScale<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        mut,
+        close = creator,
+        seeds = [
+            b"epoch-scale",
+            stability_pool_state.key().as_ref(),
+            epoch.to_le_bytes().as_ref(),
+            scale.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub epoch_scale: Box<Account<'info, EpochScale>>,
+
+    #[account(
+        mut,
+        constraint = creator.key() == pool_state.creator @ PoolConfigError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+}
+
+/// Closes an epoch-scale behind the current one and returns its rent to the creator.
+/// The creator attests no deposit still snapshots it, which is checked off-chain
+/// (`force_realize_sp_deposit` moves abandoned deposits off old epoch-scales).
+pub fn close_epoch_scale_handler(
+    ctx: Context<CloseEpochScale>,
+    epoch: u128,
+    scale: u128,
+) -> Result<()> {
+    require!(
+        ctx.accounts
+            .stability_pool_state
+            .is_epoch_scale_superseded(epoch, scale),
+        StabilityPoolError::EpochScaleNotSuperseded
+    );
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{PoolState, StabilityPoolDeposit, StabilityPoolState};
+
+#[derive(Accounts)]
+pub struct CloseSPDeposit<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        mut,
+        close = depositor,
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            depositor.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+}
+
+/// Closes a fully withdrawn and claimed deposit, returning its rent to the depositor
+pub fn close_sp_deposit_handler(ctx: Context<CloseSPDeposit>) -> Result<()> {
+    ctx.accounts.stability_pool_deposit.require_empty()
+}
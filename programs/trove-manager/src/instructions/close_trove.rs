@@ -165,12 +165,22 @@ impl<'info> CloseTrove<'info> {
 }
 
 pub fn close_trove_handler(ctx: Context<CloseTrove>) -> Result<()> {
+    close(ctx, false)
+}
+
+/// Closes the trove and pays out any collateral surplus left from an earlier redemption
+pub fn close_trove_and_claim_handler(ctx: Context<CloseTrove>) -> Result<()> {
+    close(ctx, true)
+}
+
+fn close(ctx: Context<CloseTrove>, claim_surplus: bool) -> Result<()> {
     let price = ctx.accounts.price_feed_state.fetch_price(
         &ctx.accounts.chainlink_program,
         &ctx.accounts.chainlink_feed,
         &ctx.accounts.jitosol_stake_pool,
         &ctx.accounts.pyth_feed_account,
     )?;
+    emit!(ctx.accounts.price_feed_state.price_used(price));
     let trove = &mut ctx.accounts.trove;
     let trove_id = trove.key();
     let next_trove = &mut ctx.accounts.next_trove;
@@ -184,10 +194,12 @@ pub fn close_trove_handler(ctx: Context<CloseTrove>) -> Result<()> {
 
     let coll = trove.coll;
     let debt = trove.debt;
+    let (usv_from_borrower, usv_from_gas_compensation) =
+        trove.get_close_burn_amounts(debt, pool_state.gas_compensation);
 
     require_sufficient_usv_balance(
         ctx.accounts.borrower_stablecoin_ata.amount,
-        debt.checked_sub(pool_state.gas_compensation).unwrap(),
+        usv_from_borrower,
     )?;
 
     let new_tcr = pool_state.get_new_tcr_from_trove_change(coll, false, debt, false, price);
@@ -206,27 +218,49 @@ pub fn close_trove_handler(ctx: Context<CloseTrove>) -> Result<()> {
     // Remove trove from sorted troves
     trove.remove_sorted(trove_id, prev_trove, next_trove, pool_state)?;
 
+    // Claim surplus, it is held in the same collateral vault
+    let surplus = if claim_surplus {
+        trove.clear_surplus()
+    } else {
+        0
+    };
+    if surplus > 0 {
+        pool_state.decrease_total_surplus(surplus);
+    }
+
     // Move tokens
-    move_tokens_from_close(ctx, coll, debt)?;
+    move_tokens_from_close(
+        ctx,
+        coll.checked_add(surplus).unwrap(),
+        usv_from_borrower,
+        usv_from_gas_compensation,
+    )?;
 
     Ok(())
 }
 
-pub fn move_tokens_from_close(ctx: Context<CloseTrove>, coll: u64, debt: u64) -> Result<()> {
+pub fn move_tokens_from_close(
+    ctx: Context<CloseTrove>,
+    coll: u64,
+    usv_from_borrower: u64,
+    usv_from_gas_compensation: u64,
+) -> Result<()> {
     let pool_state = &ctx.accounts.pool_state;
     let pool_state_key = pool_state.key();
     let authority_seed = &pool_state.token_auth_seeds(&pool_state_key);
 
     burn(
         ctx.accounts.burn_stablecoin_from_user_ctx(),
-        debt.checked_sub(pool_state.gas_compensation).unwrap(),
-    )?;
-    burn(
-        ctx.accounts
-            .burn_stablecoin_from_gas_compensation_ctx()
-            .with_signer(&[&authority_seed[..]]),
-        pool_state.gas_compensation,
+        usv_from_borrower,
     )?;
+    if usv_from_gas_compensation > 0 {
+        burn(
+            ctx.accounts
+                .burn_stablecoin_from_gas_compensation_ctx()
+                .with_signer(&[&authority_seed[..]]),
+            usv_from_gas_compensation,
+        )?;
+    }
     transfer_checked(
         ctx.accounts
             .transfer_coll_out_ctx()
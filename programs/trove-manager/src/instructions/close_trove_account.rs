@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{PoolState, Trove};
+
+#[derive(Accounts)]
+pub struct CloseTroveAccount<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        close = borrower,
+        seeds = [
+            b"trove",
+            pool_state.key().as_ref(),
+            borrower.key().as_ref(),
+        ],
+        bump
+    )]
+    pub trove: Box<Account<'info, Trove>>,
+
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+}
+
+/// Closes a closed trove's account once its surplus is claimed, returning its rent to the borrower
+pub fn close_trove_account_handler(ctx: Context<CloseTroveAccount>) -> Result<()> {
+    ctx.accounts.trove.require_account_closable()
+}
+
+#[cfg(test)]
+pub mod close_trove_account_test {
+    use super::*;
+    use crate::{
+        errors::BorrowerOpsError,
+        state::TroveStatus,
+        utils::utils_test::{load_anchor_account, load_reallocable_account, load_signer},
+        ID,
+    };
+    use std::collections::BTreeSet;
+
+    const TROVE_RENT: u64 = 2_000_000;
+
+    fn load_close_accounts(trove: &Trove) -> Vec<AccountInfo<'static>> {
+        let borrower = Pubkey::new_unique();
+        let pool_state_key = Pubkey::new_unique();
+        let (trove_key, _) = Pubkey::find_program_address(
+            &[b"trove", pool_state_key.as_ref(), borrower.as_ref()],
+            &ID,
+        );
+        let mut data = Vec::new();
+        trove.try_serialize(&mut data).unwrap();
+        data.resize(8 + Trove::INIT_SPACE, 0);
+
+        vec![
+            load_anchor_account(pool_state_key, &PoolState::default()),
+            load_reallocable_account(trove_key, TROVE_RENT, &data),
+            load_signer(borrower, 0),
+        ]
+    }
+
+    /// Runs the instruction with its exit, where the `close` constraint applies
+    fn close(accounts: &[AccountInfo<'static>]) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = CloseTroveAccountBumps::default();
+        let mut close_accounts = CloseTroveAccount::try_accounts(
+            &ID,
+            &mut infos,
+            &[],
+            &mut bumps,
+            &mut BTreeSet::new(),
+        )?;
+        close_trove_account_handler(Context::new(&ID, &mut close_accounts, &[], bumps))?;
+        close_accounts.exit(&ID)
+    }
+
+    #[test]
+    fn closed_trove_returns_rent() {
+        let accounts = load_close_accounts(&Trove {
+            status: TroveStatus::ClosedByOwner,
+            ..Default::default()
+        });
+
+        close(&accounts).unwrap();
+        assert_eq!(accounts[2].lamports(), TROVE_RENT);
+        assert_eq!(accounts[1].lamports(), 0);
+        assert_eq!(accounts[1].data_len(), 0);
+        assert_eq!(*accounts[1].owner, System::id());
+    }
+
+    #[test]
+    fn trove_with_surplus_keeps_rent() {
+        let accounts = load_close_accounts(&Trove {
+            status: TroveStatus::ClosedByLiquidation,
+            surplus_balance: 1,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            close(&accounts).unwrap_err(),
+            BorrowerOpsError::TroveAccountNotClosable.into()
+        );
+        assert_eq!(accounts[2].lamports(), 0);
+        assert_eq!(accounts[1].lamports(), TROVE_RENT);
+    }
+}
@@ -0,0 +1,156 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, Token, TokenAccount},
+    token_2022::{close_account, CloseAccount},
+};
+
+use crate::{errors::BorrowerOpsError, state::PoolState};
+
+#[derive(Accounts)]
+pub struct CloseUserAtas<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        constraint = collateral.key() == pool_state.collateral
+    )]
+    pub collateral: Box<Account<'info, Mint>>,
+
+    #[account(
+        constraint = stablecoin.key() == pool_state.stablecoin
+    )]
+    pub stablecoin: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = collateral,
+        associated_token::authority = user,
+    )]
+    pub user_coll_ata: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = user,
+    )]
+    pub user_stablecoin_ata: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CloseUserAtas<'info> {
+    pub fn close_ata_ctx(
+        &self,
+        ata: &Account<'info, TokenAccount>,
+    ) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: ata.to_account_info(),
+            destination: self.user.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+/// Closes the user's emptied collateral and stablecoin ATAs, such as those created by
+/// `redeem_collateral` and `close_trove` with `init_if_needed`, returning their rent
+pub fn close_user_atas_handler(ctx: Context<CloseUserAtas>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    for ata in [&accounts.user_coll_ata, &accounts.user_stablecoin_ata]
+        .into_iter()
+        .flatten()
+    {
+        require!(ata.amount == 0, BorrowerOpsError::TokenAccountNotEmpty);
+        close_account(accounts.close_ata_ctx(ata))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod close_user_atas_test {
+    use super::*;
+    use crate::{
+        utils::utils_test::{
+            load_account, load_anchor_account, load_mint, load_program, load_signer,
+            load_token_account, set_program_test_stubs,
+        },
+        ID,
+    };
+    use anchor_spl::{associated_token::get_associated_token_address, token::spl_token};
+    use std::collections::BTreeSet;
+
+    const ATA_RENT: u64 = 2_039_280;
+
+    /// Accounts of `close_user_atas` in declaration order, holding `coll_amount` and `usv_amount`
+    fn load_close_user_atas_accounts(
+        coll_amount: u64,
+        usv_amount: u64,
+    ) -> Vec<AccountInfo<'static>> {
+        let user = Pubkey::new_unique();
+        let collateral = Pubkey::new_unique();
+        let stablecoin = Pubkey::new_unique();
+        let pool_state = PoolState {
+            collateral,
+            stablecoin,
+            ..Default::default()
+        };
+        let token_account = |mint: Pubkey, amount: u64| {
+            let info = load_account(
+                get_associated_token_address(&user, &mint),
+                spl_token::ID,
+                load_token_account(mint, user, amount),
+            );
+            **info.lamports.borrow_mut() = ATA_RENT;
+            info
+        };
+
+        vec![
+            load_anchor_account(Pubkey::new_unique(), &pool_state),
+            load_account(collateral, spl_token::ID, load_mint(Pubkey::new_unique())),
+            load_account(stablecoin, spl_token::ID, load_mint(Pubkey::new_unique())),
+            token_account(collateral, coll_amount),
+            token_account(stablecoin, usv_amount),
+            load_signer(user, 0),
+            load_program(spl_token::ID),
+        ]
+    }
+
+    fn close_user_atas(accounts: &[AccountInfo<'static>]) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = CloseUserAtasBumps::default();
+        let mut close_accounts =
+            CloseUserAtas::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        close_user_atas_handler(Context::new(&ID, &mut close_accounts, &[], bumps))
+    }
+
+    #[test]
+    fn emptied_atas_return_rent() {
+        set_program_test_stubs();
+        let accounts = load_close_user_atas_accounts(0, 0);
+
+        close_user_atas(&accounts).unwrap();
+        assert_eq!(accounts[5].lamports(), 2 * ATA_RENT);
+        for ata in &accounts[3..5] {
+            assert_eq!(ata.lamports(), 0);
+            assert_eq!(ata.owner, &System::id());
+        }
+    }
+
+    #[test]
+    fn ata_holding_tokens_is_kept() {
+        set_program_test_stubs();
+        let accounts = load_close_user_atas_accounts(0, 1);
+
+        assert_eq!(
+            close_user_atas(&accounts).unwrap_err(),
+            BorrowerOpsError::TokenAccountNotEmpty.into()
+        );
+        assert_eq!(accounts[4].lamports(), ATA_RENT);
+        assert_eq!(accounts[4].owner, &spl_token::ID);
+    }
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{events::AuthorityChanged, state::CommunityIssuanceConfig};
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            config.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub config: Account<'info, CommunityIssuanceConfig>,
+
+    pub pending_authority: Signer<'info>,
+}
+
+pub fn accept_authority_handler(ctx: Context<AcceptAuthority>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let new_authority = ctx.accounts.pending_authority.key();
+
+    config.accept_authority(new_authority)?;
+
+    emit!(AuthorityChanged {
+        token: config.cvgt,
+        new_authority,
+    });
+    Ok(())
+}
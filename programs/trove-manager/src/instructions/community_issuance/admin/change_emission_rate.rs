@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
 
 use crate::{
     constants::MAX_EMISSION_RATE, errors::CommunityIssuanceError, events::EmissionRateChanged,
@@ -22,6 +23,12 @@ pub struct ChangeEmissionRate<'info> {
         constraint = authority.key() == config.authority @ CommunityIssuanceError::InvalidSigner
     )]
     pub authority: Signer<'info>,
+
+    #[account(
+        associated_token::mint = config.cvgt,
+        associated_token::authority = config
+    )]
+    pub community_issuance_vault: Account<'info, TokenAccount>,
 }
 
 pub fn change_emission_rate_handler(ctx: Context<ChangeEmissionRate>, new_rate: u64) -> Result<()> {
@@ -31,6 +38,7 @@ pub fn change_emission_rate_handler(ctx: Context<ChangeEmissionRate>, new_rate:
         new_rate < MAX_EMISSION_RATE,
         CommunityIssuanceError::ExceedMax
     );
+    config.require_emission_runway(new_rate, ctx.accounts.community_issuance_vault.amount)?;
 
     config.emission_rate = new_rate;
 
@@ -26,7 +26,7 @@ pub struct EnableEmission<'info> {
 
 pub fn enable_emission_handler(ctx: Context<EnableEmission>) -> Result<()> {
     let config = &mut ctx.accounts.config;
-    config.last_reward_timestamp = get_current_timestamp_with_config(&config)?;
+    config.last_reward_timestamp = get_current_timestamp_with_config(config)?;
     config.enable_emission = true;
     emit!(EmissionEnabled { token: config.cvgt });
     Ok(())
@@ -0,0 +1,191 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{
+    errors::CommunityIssuanceError, state::CommunityIssuanceConfig, utils::realloc_zeroed, ID,
+};
+
+// Layout before `total_cvgt_sent`, ending with `bump`
+const V1_LEN: usize = 8 + 163;
+
+#[derive(Accounts)]
+pub struct MigrateCommunityIssuanceConfig<'info> {
+    /// CHECK: An old layout can't be deserialized as CommunityIssuanceConfig, discriminator and authority are checked in the handler
+    #[account(
+        mut,
+        owner = ID
+    )]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a CommunityIssuanceConfig created with an older layout to the current `INIT_SPACE`.
+/// The guard, pending authority and decay read as disabled. A config older than
+/// `total_cvgt_sent` can't tell what was paid out of the vault, so it's set from
+/// `total_cvgt_sent`, otherwise ignored. Calling it again is a no-op.
+pub fn migrate_community_issuance_config_handler(
+    ctx: Context<MigrateCommunityIssuanceConfig>,
+    total_cvgt_sent: u64,
+) -> Result<()> {
+    let config = &ctx.accounts.config;
+    {
+        let data = config.try_borrow_data()?;
+        require!(
+            data.len() >= 8 + 96 && data[..8] == CommunityIssuanceConfig::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        // `authority` follows `creator` and `cvgt` in every layout
+        let authority = Pubkey::try_from(&data[72..104]).unwrap();
+        require!(
+            authority == ctx.accounts.authority.key(),
+            CommunityIssuanceError::InvalidSigner
+        );
+    }
+    let tracks_sent = config.data_len() > V1_LEN;
+
+    realloc_zeroed(
+        config,
+        &ctx.accounts.authority,
+        &ctx.accounts.system_program,
+        8 + CommunityIssuanceConfig::INIT_SPACE,
+    )?;
+
+    if !tracks_sent {
+        let mut data = config.try_borrow_mut_data()?;
+        let mut migrated = CommunityIssuanceConfig::try_deserialize(&mut &data[..])?;
+        require!(
+            total_cvgt_sent <= migrated.total_cvgt_issued,
+            CommunityIssuanceError::SentAboveIssued
+        );
+        migrated.total_cvgt_sent = total_cvgt_sent;
+        migrated.try_serialize(&mut &mut data[..])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod migrate_community_issuance_config_test {
+    use super::*;
+    use crate::utils::utils_test::{
+        load_program, load_reallocable_account, load_signer, set_program_test_stubs,
+    };
+    use std::collections::BTreeSet;
+
+    fn load_migrate_accounts(config: &CommunityIssuanceConfig) -> Vec<AccountInfo<'static>> {
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        data.truncate(V1_LEN);
+
+        vec![
+            load_reallocable_account(
+                Pubkey::new_unique(),
+                Rent::default().minimum_balance(V1_LEN),
+                &data,
+            ),
+            load_signer(config.authority, 1_000_000_000),
+            load_program(System::id()),
+        ]
+    }
+
+    fn migrate(accounts: &[AccountInfo<'static>], total_cvgt_sent: u64) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = MigrateCommunityIssuanceConfigBumps::default();
+        let mut migrate_accounts = MigrateCommunityIssuanceConfig::try_accounts(
+            &ID,
+            &mut infos,
+            &[],
+            &mut bumps,
+            &mut BTreeSet::new(),
+        )?;
+        migrate_community_issuance_config_handler(
+            Context::new(&ID, &mut migrate_accounts, &[], bumps),
+            total_cvgt_sent,
+        )
+    }
+
+    fn read_config(account: &AccountInfo) -> CommunityIssuanceConfig {
+        CommunityIssuanceConfig::try_deserialize(&mut &account.try_borrow_data().unwrap()[..])
+            .unwrap()
+    }
+
+    #[test]
+    fn old_layout_grows_with_sent_cvgt() {
+        set_program_test_stubs();
+        let config = CommunityIssuanceConfig {
+            creator: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            enable_emission: true,
+            total_cvgt_issued: 5_000,
+            emission_rate: 10,
+            bump: [253],
+            total_cvgt_sent: 1,
+            issuance_guard_enabled: true,
+            max_issuance_shortfall: 1,
+            pending_authority: Pubkey::new_unique(),
+            emission_decay_factor: 1,
+            emission_decay_start: 1,
+            ..Default::default()
+        };
+        let accounts = load_migrate_accounts(&config);
+        let new_len = 8 + CommunityIssuanceConfig::INIT_SPACE;
+        let top_up = Rent::default().minimum_balance(new_len) - accounts[0].lamports();
+
+        migrate(&accounts, 4_000).unwrap();
+        assert_eq!(accounts[0].data_len(), new_len);
+        assert_eq!(accounts[1].lamports(), 1_000_000_000 - top_up);
+        let migrated = read_config(&accounts[0]);
+        assert_eq!(migrated.authority, config.authority);
+        assert!(migrated.enable_emission);
+        assert_eq!(migrated.total_cvgt_issued, 5_000);
+        assert_eq!(migrated.emission_rate, 10);
+        assert_eq!(migrated.bump, [253]);
+        assert_eq!(migrated.total_cvgt_sent, 4_000);
+        assert_eq!(migrated.get_outstanding_cvgt(), 1_000);
+        assert!(!migrated.issuance_guard_enabled);
+        assert_eq!(migrated.max_issuance_shortfall, 0);
+        assert_eq!(migrated.pending_authority, Pubkey::default());
+        assert_eq!(migrated.emission_decay_factor, 0);
+        assert_eq!(migrated.emission_decay_start, 0);
+
+        // Already migrated, what was sent since is kept
+        migrate(&accounts, 0).unwrap();
+        assert_eq!(accounts[1].lamports(), 1_000_000_000 - top_up);
+        assert_eq!(read_config(&accounts[0]).total_cvgt_sent, 4_000);
+    }
+
+    #[test]
+    fn sent_cvgt_above_issued_is_rejected() {
+        set_program_test_stubs();
+        let accounts = load_migrate_accounts(&CommunityIssuanceConfig {
+            authority: Pubkey::new_unique(),
+            total_cvgt_issued: 5_000,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            migrate(&accounts, 5_001).unwrap_err(),
+            CommunityIssuanceError::SentAboveIssued.into()
+        );
+    }
+
+    #[test]
+    fn only_authority_migrates() {
+        set_program_test_stubs();
+        let mut accounts = load_migrate_accounts(&CommunityIssuanceConfig {
+            creator: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            ..Default::default()
+        });
+        accounts[1] = load_signer(Pubkey::new_unique(), 1_000_000_000);
+
+        assert_eq!(
+            migrate(&accounts, 0).unwrap_err(),
+            CommunityIssuanceError::InvalidSigner.into()
+        );
+        assert_eq!(accounts[0].data_len(), V1_LEN);
+    }
+}
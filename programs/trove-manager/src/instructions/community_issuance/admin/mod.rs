@@ -1,8 +1,20 @@
-pub mod change_authority;
-pub use change_authority::*;
+pub mod propose_authority;
+pub use propose_authority::*;
+
+pub mod accept_authority;
+pub use accept_authority::*;
 
 pub mod change_emission_rate;
 pub use change_emission_rate::*;
 
 pub mod enable_emission;
 pub use enable_emission::*;
+
+pub mod set_issuance_guard;
+pub use set_issuance_guard::*;
+
+pub mod set_emission_decay_factor;
+pub use set_emission_decay_factor::*;
+
+pub mod migrate_community_issuance_config;
+pub use migrate_community_issuance_config::*;
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::CommunityIssuanceError, state::CommunityIssuanceConfig};
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            config.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub config: Account<'info, CommunityIssuanceConfig>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == config.authority @ CommunityIssuanceError::InvalidSigner
+    )]
+    pub authority: Signer<'info>,
+}
+
+/// The authority only changes once `new_authority` signs `accept_authority`
+pub fn propose_authority_handler(
+    ctx: Context<ProposeAuthority>,
+    new_authority: Pubkey,
+) -> Result<()> {
+    ctx.accounts.config.propose_authority(new_authority);
+    Ok(())
+}
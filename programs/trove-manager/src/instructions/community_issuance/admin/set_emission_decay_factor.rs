@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::CommunityIssuanceError,
+    events::{EmissionDecayChanged, EmissionRateChanged},
+    state::CommunityIssuanceConfig,
+};
+
+#[derive(Accounts)]
+pub struct SetEmissionDecayFactor<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            config.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub config: Account<'info, CommunityIssuanceConfig>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == config.authority @ CommunityIssuanceError::InvalidSigner
+    )]
+    pub authority: Signer<'info>,
+}
+
+pub fn set_emission_decay_factor_handler(
+    ctx: Context<SetEmissionDecayFactor>,
+    decay_factor: u64,
+) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let old_rate = config.emission_rate;
+
+    config.set_emission_decay_factor(decay_factor)?;
+
+    if config.emission_rate != old_rate {
+        emit!(EmissionRateChanged {
+            token: config.cvgt,
+            new_rate: config.emission_rate,
+        });
+    }
+
+    emit!(EmissionDecayChanged {
+        token: config.cvgt,
+        decay_factor,
+        decay_start: config.emission_decay_start,
+    });
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::CommunityIssuanceError, events::IssuanceGuardChanged, state::CommunityIssuanceConfig,
+};
+
+#[derive(Accounts)]
+pub struct SetIssuanceGuard<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            config.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub config: Account<'info, CommunityIssuanceConfig>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == config.authority @ CommunityIssuanceError::InvalidSigner
+    )]
+    pub authority: Signer<'info>,
+}
+
+pub fn set_issuance_guard_handler(
+    ctx: Context<SetIssuanceGuard>,
+    enabled: bool,
+    max_shortfall: u64,
+) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+
+    config.issuance_guard_enabled = enabled;
+    config.max_issuance_shortfall = max_shortfall;
+
+    emit!(IssuanceGuardChanged {
+        token: config.cvgt,
+        enabled,
+        max_shortfall,
+    });
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::CVGTEmissionStateRead,
+    state::{CVGTEmissionState, CommunityIssuanceConfig},
+    utils::get_current_timestamp_with_config,
+};
+
+#[derive(Accounts)]
+pub struct GetCVGTEmissionState<'info> {
+    pub config: Account<'info, CommunityIssuanceConfig>,
+}
+
+/// Read-only: CVGT emission figures for front-ends, CVGT not issued yet isn't counted
+pub fn cvgt_emission_state_handler(
+    ctx: Context<GetCVGTEmissionState>,
+) -> Result<CVGTEmissionState> {
+    let config = &ctx.accounts.config;
+    let current_timestamp = get_current_timestamp_with_config(config)?;
+    let emission_state = config.get_emission_state(current_timestamp);
+
+    emit!(CVGTEmissionStateRead {
+        token: config.cvgt,
+        emission_rate: emission_state.emission_rate,
+        total_issued: emission_state.total_issued,
+        enable_emission: emission_state.enable_emission,
+        current_timestamp,
+    });
+    Ok(emission_state)
+}
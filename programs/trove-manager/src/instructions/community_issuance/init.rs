@@ -73,6 +73,12 @@ pub fn initialize_community_issuance_handler(
         bump: [bump],
         _is_dev: is_dev,
         _timestamp: 0,
+        total_cvgt_sent: 0,
+        issuance_guard_enabled: false,
+        max_issuance_shortfall: 0,
+        pending_authority: Pubkey::default(),
+        emission_decay_factor: 0,
+        emission_decay_start: 0,
     };
 
     Ok(())
@@ -4,5 +4,8 @@ pub use set_timestamp::*;
 pub mod init;
 pub use init::*;
 
+pub mod cvgt_emission_state;
+pub use cvgt_emission_state::*;
+
 pub mod admin;
 pub use admin::*;
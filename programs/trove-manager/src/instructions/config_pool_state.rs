@@ -26,7 +26,6 @@ pub struct ConfigPoolState<'info> {
 
 pub fn config_pool_state_handler(ctx: Context<ConfigPoolState>) -> Result<()> {
     let pool_state = &mut ctx.accounts.pool_state;
-    let cvgt_staking_state_key = ctx.accounts.cvgt_staking_state.key();
-    pool_state.cvgt_staking_state = cvgt_staking_state_key;
-    Ok(())
+    let cvgt_staking_state = &ctx.accounts.cvgt_staking_state;
+    pool_state.set_cvgt_staking_state(cvgt_staking_state.key(), cvgt_staking_state)
 }
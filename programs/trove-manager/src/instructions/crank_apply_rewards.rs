@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::MAX_CRANK_TROVES,
+    errors::BorrowerOpsError,
+    state::{PoolState, Trove},
+    ID,
+};
+
+#[derive(Accounts)]
+pub struct CrankApplyRewards<'info> {
+    #[account(mut)]
+    pub pool_state: Box<Account<'info, PoolState>>,
+}
+
+/// Applies pending redistribution rewards to the troves in `remaining_accounts`.
+/// NICR already includes pending rewards, so applying them never moves a trove in the sorted list.
+pub fn crank_apply_rewards_handler(ctx: Context<CrankApplyRewards>) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    require!(
+        ctx.remaining_accounts.len() <= MAX_CRANK_TROVES,
+        BorrowerOpsError::TooManyTroves
+    );
+
+    for account in ctx.remaining_accounts.iter() {
+        require!(
+            account.to_account_info().owner == &ID,
+            BorrowerOpsError::InvalidAccount
+        );
+
+        let mut data = account.try_borrow_mut_data()?;
+        let mut trove =
+            Trove::try_deserialize(&mut data.as_ref()).expect("Error Deserializing Data");
+        require!(
+            trove.pool_state == pool_state.key(),
+            BorrowerOpsError::InvalidAccount
+        );
+
+        if !trove.has_pending_rewards(pool_state) {
+            continue;
+        }
+
+        // Emits TroveUpdated with the ApplyPendingRewards operation
+        pool_state.apply_pending_reward(&mut trove)?;
+        trove.try_serialize(&mut data.as_mut())?;
+    }
+
+    Ok(())
+}
@@ -52,6 +52,7 @@ pub fn initialize_cvgt_staking_handler(ctx: Context<InitializeCVGTStaking>) -> R
         f_coll: 0,
         f_usv: 0,
         bump: [bump],
+        min_stake_duration: 0,
     };
     Ok(())
 }
@@ -0,0 +1,157 @@
+use anchor_lang::{prelude::*, Discriminator};
+use anchor_spl::token::Mint;
+
+use crate::{state::CVGTStakingInfo, utils::realloc_zeroed, ID};
+
+#[derive(Accounts)]
+pub struct MigrateCVGTStakingInfo<'info> {
+    /// CHECK: Only its address is used, to derive `staking_info`
+    #[account(
+        seeds = [
+            b"staking-state",
+            cvgt.key().as_ref()
+        ],
+        bump
+    )]
+    pub pool_state: UncheckedAccount<'info>,
+
+    /// CHECK: An old layout can't be deserialized as CVGTStakingInfo, the discriminator is checked in the handler
+    #[account(
+        mut,
+        owner = ID,
+        seeds = [
+            b"info",
+            pool_state.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub staking_info: UncheckedAccount<'info>,
+
+    #[account()]
+    pub cvgt: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a CVGTStakingInfo created with an older layout to the current `INIT_SPACE`.
+/// The stake reads as past any minimum stake duration. Calling it again is a no-op.
+pub fn migrate_cvgt_staking_info_handler(ctx: Context<MigrateCVGTStakingInfo>) -> Result<()> {
+    let staking_info = &ctx.accounts.staking_info;
+    {
+        let data = staking_info.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == CVGTStakingInfo::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+    }
+
+    realloc_zeroed(
+        staking_info,
+        &ctx.accounts.user,
+        &ctx.accounts.system_program,
+        8 + CVGTStakingInfo::INIT_SPACE,
+    )
+}
+
+#[cfg(test)]
+pub mod migrate_cvgt_staking_info_test {
+    use super::*;
+    use crate::utils::utils_test::{
+        load_account, load_mint, load_program, load_reallocable_account, load_signer,
+        set_program_test_stubs,
+    };
+    use anchor_spl::token::spl_token;
+    use std::collections::BTreeSet;
+
+    // Layout before `last_stake_time`
+    const V1_LEN: usize = 8 + CVGTStakingInfo::INIT_SPACE - 8;
+
+    fn load_migrate_accounts(
+        user: Pubkey,
+        staking_info: &CVGTStakingInfo,
+    ) -> Vec<AccountInfo<'static>> {
+        let cvgt = Pubkey::new_unique();
+        let (pool_state_key, _) =
+            Pubkey::find_program_address(&[b"staking-state", cvgt.as_ref()], &ID);
+        let (staking_info_key, _) =
+            Pubkey::find_program_address(&[b"info", pool_state_key.as_ref(), user.as_ref()], &ID);
+        let mut data = Vec::new();
+        staking_info.try_serialize(&mut data).unwrap();
+        data.truncate(V1_LEN);
+
+        vec![
+            load_account(pool_state_key, ID, vec![]),
+            load_reallocable_account(
+                staking_info_key,
+                Rent::default().minimum_balance(V1_LEN),
+                &data,
+            ),
+            load_account(cvgt, spl_token::ID, load_mint(Pubkey::new_unique())),
+            load_signer(user, 1_000_000_000),
+            load_program(System::id()),
+        ]
+    }
+
+    fn migrate(accounts: &[AccountInfo<'static>]) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = MigrateCVGTStakingInfoBumps::default();
+        let mut migrate_accounts = MigrateCVGTStakingInfo::try_accounts(
+            &ID,
+            &mut infos,
+            &[],
+            &mut bumps,
+            &mut BTreeSet::new(),
+        )?;
+        migrate_cvgt_staking_info_handler(Context::new(&ID, &mut migrate_accounts, &[], bumps))
+    }
+
+    fn load_staking_info() -> CVGTStakingInfo {
+        CVGTStakingInfo {
+            balance: 1_000,
+            f_coll_snapshot: 1,
+            f_usv_snapshot: 2,
+            last_stake_time: 60,
+        }
+    }
+
+    #[test]
+    fn old_layout_grows_without_stake_time() {
+        set_program_test_stubs();
+        let accounts = load_migrate_accounts(Pubkey::new_unique(), &load_staking_info());
+        let new_len = 8 + CVGTStakingInfo::INIT_SPACE;
+        let top_up = Rent::default().minimum_balance(new_len) - accounts[1].lamports();
+
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[1].data_len(), new_len);
+        assert_eq!(accounts[3].lamports(), 1_000_000_000 - top_up);
+        let migrated =
+            CVGTStakingInfo::try_deserialize(&mut &accounts[1].try_borrow_data().unwrap()[..])
+                .unwrap();
+        assert_eq!(migrated.balance, 1_000);
+        assert_eq!(migrated.f_coll_snapshot, 1);
+        assert_eq!(migrated.f_usv_snapshot, 2);
+        assert_eq!(migrated.last_stake_time, 0);
+
+        // Already migrated
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[3].lamports(), 1_000_000_000 - top_up);
+    }
+
+    #[test]
+    fn only_staker_migrates() {
+        set_program_test_stubs();
+        let mut accounts = load_migrate_accounts(Pubkey::new_unique(), &load_staking_info());
+        accounts[3] = load_signer(Pubkey::new_unique(), 1_000_000_000);
+
+        assert_eq!(
+            migrate(&accounts).unwrap_err(),
+            ErrorCode::ConstraintSeeds.into()
+        );
+        assert_eq!(accounts[1].data_len(), V1_LEN);
+    }
+}
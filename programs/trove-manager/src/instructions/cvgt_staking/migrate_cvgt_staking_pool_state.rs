@@ -0,0 +1,163 @@
+use anchor_lang::{prelude::*, Discriminator};
+use anchor_spl::token::Mint;
+
+use crate::{
+    constants::DEPLOYER, errors::CVGTStakingError, state::CVGTStakingPoolState,
+    utils::realloc_zeroed, ID,
+};
+
+#[derive(Accounts)]
+pub struct MigrateCVGTStakingPoolState<'info> {
+    /// CHECK: An old layout can't be deserialized as CVGTStakingPoolState, the discriminator is checked in the handler
+    #[account(
+        mut,
+        owner = ID,
+        seeds = [
+            b"staking-state",
+            cvgt.key().as_ref()
+        ],
+        bump
+    )]
+    pub pool_state: UncheckedAccount<'info>,
+
+    #[account()]
+    pub cvgt: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = creator.key() == DEPLOYER @ CVGTStakingError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a CVGTStakingPoolState created with an older layout to the current `INIT_SPACE`.
+/// Stakes read as withdrawable at once. Calling it again is a no-op.
+pub fn migrate_cvgt_staking_pool_state_handler(
+    ctx: Context<MigrateCVGTStakingPoolState>,
+) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    {
+        let data = pool_state.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == CVGTStakingPoolState::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+    }
+
+    realloc_zeroed(
+        pool_state,
+        &ctx.accounts.creator,
+        &ctx.accounts.system_program,
+        8 + CVGTStakingPoolState::INIT_SPACE,
+    )
+}
+
+#[cfg(test)]
+pub mod migrate_cvgt_staking_pool_state_test {
+    use super::*;
+    use crate::utils::utils_test::{
+        load_account, load_mint, load_program, load_reallocable_account, load_signer,
+        set_program_test_stubs,
+    };
+    use anchor_spl::token::spl_token;
+    use std::collections::BTreeSet;
+
+    // Layout before `min_stake_duration`
+    const V1_LEN: usize = 8 + CVGTStakingPoolState::INIT_SPACE - 8;
+
+    fn load_migrate_accounts(pool_state: &CVGTStakingPoolState) -> Vec<AccountInfo<'static>> {
+        let (pool_state_key, _) =
+            Pubkey::find_program_address(&[b"staking-state", pool_state.cvgt.as_ref()], &ID);
+        let mut data = Vec::new();
+        pool_state.try_serialize(&mut data).unwrap();
+        data.truncate(V1_LEN);
+
+        vec![
+            load_reallocable_account(
+                pool_state_key,
+                Rent::default().minimum_balance(V1_LEN),
+                &data,
+            ),
+            load_account(
+                pool_state.cvgt,
+                spl_token::ID,
+                load_mint(Pubkey::new_unique()),
+            ),
+            load_signer(DEPLOYER, 1_000_000_000),
+            load_program(System::id()),
+        ]
+    }
+
+    fn migrate(accounts: &[AccountInfo<'static>]) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = MigrateCVGTStakingPoolStateBumps::default();
+        let mut migrate_accounts = MigrateCVGTStakingPoolState::try_accounts(
+            &ID,
+            &mut infos,
+            &[],
+            &mut bumps,
+            &mut BTreeSet::new(),
+        )?;
+        migrate_cvgt_staking_pool_state_handler(Context::new(
+            &ID,
+            &mut migrate_accounts,
+            &[],
+            bumps,
+        ))
+    }
+
+    fn load_pool_state() -> CVGTStakingPoolState {
+        CVGTStakingPoolState {
+            usv: Pubkey::new_unique(),
+            collateral: Pubkey::new_unique(),
+            cvgt: Pubkey::new_unique(),
+            f_usv: 1,
+            f_coll: 2,
+            total_cvgt_staked: 3,
+            bump: [252],
+            min_stake_duration: 60,
+        }
+    }
+
+    #[test]
+    fn old_layout_grows_without_min_stake_duration() {
+        set_program_test_stubs();
+        let pool_state = load_pool_state();
+        let accounts = load_migrate_accounts(&pool_state);
+        let new_len = 8 + CVGTStakingPoolState::INIT_SPACE;
+        let top_up = Rent::default().minimum_balance(new_len) - accounts[0].lamports();
+
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[0].data_len(), new_len);
+        assert_eq!(accounts[2].lamports(), 1_000_000_000 - top_up);
+        let migrated =
+            CVGTStakingPoolState::try_deserialize(&mut &accounts[0].try_borrow_data().unwrap()[..])
+                .unwrap();
+        assert_eq!(migrated.cvgt, pool_state.cvgt);
+        assert_eq!(migrated.f_usv, 1);
+        assert_eq!(migrated.f_coll, 2);
+        assert_eq!(migrated.total_cvgt_staked, 3);
+        assert_eq!(migrated.bump, [252]);
+        assert_eq!(migrated.min_stake_duration, 0);
+
+        // Already migrated
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[2].lamports(), 1_000_000_000 - top_up);
+    }
+
+    #[test]
+    fn only_deployer_migrates() {
+        set_program_test_stubs();
+        let mut accounts = load_migrate_accounts(&load_pool_state());
+        accounts[2] = load_signer(Pubkey::new_unique(), 1_000_000_000);
+
+        assert_eq!(
+            migrate(&accounts).unwrap_err(),
+            CVGTStakingError::InvalidSigner.into()
+        );
+        assert_eq!(accounts[0].data_len(), V1_LEN);
+    }
+}
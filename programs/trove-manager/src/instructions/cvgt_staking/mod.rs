@@ -6,3 +6,15 @@ pub use stake::*;
 
 pub mod unstake;
 pub use unstake::*;
+
+pub mod set_min_stake_duration;
+pub use set_min_stake_duration::*;
+
+pub mod staking_gains_preview;
+pub use staking_gains_preview::*;
+
+pub mod migrate_cvgt_staking_pool_state;
+pub use migrate_cvgt_staking_pool_state::*;
+
+pub mod migrate_cvgt_staking_info;
+pub use migrate_cvgt_staking_info::*;
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::{constants::DEPLOYER, errors::CVGTStakingError, state::CVGTStakingPoolState};
+
+#[derive(Accounts)]
+pub struct SetMinStakeDuration<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"staking-state",
+            cvgt.key().as_ref()
+        ],
+        bump
+    )]
+    pub pool_state: Account<'info, CVGTStakingPoolState>,
+
+    #[account()]
+    pub cvgt: Account<'info, Mint>,
+
+    #[account(
+        constraint = creator.key() == DEPLOYER @ CVGTStakingError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+}
+
+pub fn set_min_stake_duration_handler(
+    ctx: Context<SetMinStakeDuration>,
+    min_stake_duration: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.min_stake_duration = min_stake_duration;
+    Ok(())
+}
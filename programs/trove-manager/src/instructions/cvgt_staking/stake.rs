@@ -9,6 +9,7 @@ use crate::{
     errors::CVGTStakingError,
     events::{StakeChanged, StakingGainsWithdrawn, TotalCVGTStakedUpdated},
     state::{CVGTStakingInfo, CVGTStakingPoolState},
+    utils::get_current_timestamp,
 };
 
 #[derive(Accounts)]
@@ -184,6 +185,7 @@ pub fn stake_handler(ctx: Context<Stake>, cvgt_amt: u64) -> Result<()> {
 
     // Increase user’s stake and total CVGT staked
     staking_info.balance = new_stake;
+    staking_info.last_stake_time = get_current_timestamp();
     pool_state.total_cvgt_staked = pool_state.total_cvgt_staked.checked_add(cvgt_amt).unwrap();
     emit!(TotalCVGTStakedUpdated {
         total_cvgt_staked: pool_state.total_cvgt_staked
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::state::{CVGTStakingInfo, CVGTStakingPoolState, StakingGainsPreview};
+
+#[derive(Accounts)]
+pub struct PreviewStakingGains<'info> {
+    #[account(
+        seeds = [
+            b"staking-state",
+            cvgt.key().as_ref()
+        ],
+        bump
+    )]
+    pub pool_state: Box<Account<'info, CVGTStakingPoolState>>,
+
+    #[account(
+        seeds = [
+            b"info",
+            pool_state.key().as_ref(),
+            user.key().as_ref()
+        ],
+        bump
+    )]
+    pub staking_info: Box<Account<'info, CVGTStakingInfo>>,
+
+    #[account(
+        constraint = cvgt.key() == pool_state.cvgt
+    )]
+    pub cvgt: Box<Account<'info, Mint>>,
+
+    /// CHECK: Only used to derive the staking info address
+    pub user: UncheckedAccount<'info>,
+}
+
+/// Read-only: returns the staker's claimable gains without updating the snapshots
+pub fn staking_gains_preview_handler(
+    ctx: Context<PreviewStakingGains>,
+) -> Result<StakingGainsPreview> {
+    let accounts = &ctx.accounts;
+    Ok(accounts
+        .staking_info
+        .get_gains_preview(&accounts.pool_state))
+}
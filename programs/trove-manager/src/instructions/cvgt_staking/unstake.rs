@@ -11,6 +11,7 @@ use crate::{
     errors::CVGTStakingError,
     events::{StakeChanged, StakingGainsWithdrawn, TotalCVGTStakedUpdated},
     state::{CVGTStakingInfo, CVGTStakingPoolState},
+    utils::get_current_timestamp,
 };
 
 #[derive(Accounts)]
@@ -184,6 +185,8 @@ pub fn unstake_handler(ctx: Context<Unstake>, cvgt_amt: u64) -> Result<()> {
     staking_info.update_snapshot(user_key, pool_state);
 
     if cvgt_amt > 0 {
+        staking_info.require_stake_cooldown_elapsed(pool_state, get_current_timestamp())?;
+
         let cvgt_to_withdraw = min(cvgt_amt, current_stake);
         let new_stake = current_stake.checked_sub(cvgt_to_withdraw).unwrap();
 
@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 
-use crate::{errors::PriceFeedError, state::PriceFeedState};
+use crate::state::PriceFeedState;
 
 #[derive(Accounts)]
 pub struct DevChangePrice<'info> {
@@ -9,8 +9,7 @@ pub struct DevChangePrice<'info> {
 }
 
 pub fn dev_change_price_handler(ctx: Context<DevChangePrice>, new_price: u64) -> Result<()> {
-    let price_feed_state = &mut ctx.accounts.price_feed_state;
-    require!(price_feed_state._is_dev, PriceFeedError::OnlyDevMode);
-    price_feed_state._dev_price = new_price;
+    let event = ctx.accounts.price_feed_state.set_dev_price(new_price)?;
+    emit!(event);
     Ok(())
 }
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::PoolConfigError,
+    events::{CVGTPaidToDepositor, CollGainWithdrawn, UserDepositChanged},
+    state::{
+        get_epoch_scale_range, CommunityIssuanceConfig, EpochScale, PoolState,
+        StabilityPoolDeposit, StabilityPoolState,
+    },
+};
+
+#[derive(Accounts)]
+#[instruction(depositor: Pubkey)]
+pub struct ForceRealizeSPDeposit<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        constraint = creator.key() == pool_state.creator @ PoolConfigError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            depositor.as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"epoch-scale",
+            stability_pool_state.key().as_ref(),
+            stability_pool_state.current_epoch.to_le_bytes().as_ref(),
+            stability_pool_state.current_scale.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub current_epoch_scale: Box<Account<'info, EpochScale>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            pool_state.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub community_issuance_config: Box<Account<'info, CommunityIssuanceConfig>>,
+}
+
+/// Realizes an abandoned deposit's gains into its claimable balances without moving tokens,
+/// so the epoch-scales it snapshots are no longer needed. The deposit's value is unchanged.
+pub fn force_realize_sp_deposit_handler(
+    ctx: Context<ForceRealizeSPDeposit>,
+    depositor: Pubkey,
+) -> Result<()> {
+    let accounts = ctx.accounts;
+    realize_sp_deposit_gains(
+        ctx.remaining_accounts,
+        &mut accounts.community_issuance_config,
+        &mut accounts.stability_pool_state,
+        &mut accounts.stability_pool_deposit,
+        &mut accounts.current_epoch_scale,
+        depositor,
+    )
+}
+
+/// Issues CVGT, then realizes the deposit's gains over the epoch-scales in `remaining_accounts`
+/// into its claimable balances, as a withdrawal of zero does.
+pub fn realize_sp_deposit_gains(
+    remaining_accounts: &[AccountInfo<'_>],
+    community_issuance_config: &mut CommunityIssuanceConfig,
+    sp_state: &mut Account<'_, StabilityPoolState>,
+    sp_deposit: &mut StabilityPoolDeposit,
+    current_epoch_scale: &mut Account<'_, EpochScale>,
+    depositor: Pubkey,
+) -> Result<()> {
+    let cvgt_issuance = community_issuance_config.issue_token()?;
+
+    let current_epoch_scale_key = &current_epoch_scale.key();
+
+    current_epoch_scale.update_g(sp_state, cvgt_issuance);
+
+    sp_deposit.require_user_has_deposit()?;
+
+    let epoch_scales = get_epoch_scale_range(
+        remaining_accounts,
+        current_epoch_scale_key,
+        current_epoch_scale,
+        sp_state,
+        sp_deposit,
+    )?;
+
+    let initial_value = sp_deposit.initial_value;
+    let (depositor_coll_gain, cvgt_gain, compounded_usv_deposit) = sp_deposit
+        .realize_gains(sp_state, &epoch_scales, current_epoch_scale, depositor)
+        .unwrap();
+    let usv_loss = initial_value.checked_sub(compounded_usv_deposit).unwrap();
+    sp_state.decrease_coll(depositor_coll_gain);
+
+    emit!(CVGTPaidToDepositor {
+        depositor,
+        cvgt_gain
+    });
+
+    emit!(UserDepositChanged {
+        depositor,
+        new_deposit: compounded_usv_deposit
+    });
+
+    emit!(CollGainWithdrawn {
+        depositor,
+        coll: depositor_coll_gain,
+        usv_loss
+    });
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::{
+    errors::PriceFeedError,
+    math::compute_value,
+    state::{PoolState, PriceFeedState, StabilityPoolState},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TvlBreakdown {
+    pub active_coll_value: u64,
+    // Redistributed collateral not yet applied to troves
+    pub liquidated_coll_value: u64,
+    pub sp_usv_deposits: u64,
+    pub total_value: u64,
+}
+
+#[derive(Accounts)]
+pub struct GetTvl<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"price_feed",
+            pool_state.cvgt.as_ref()
+        ],
+        bump = price_feed_state.bump
+    )]
+    pub price_feed_state: Box<Account<'info, PriceFeedState>>,
+
+    #[account(
+        constraint = pyth_feed_account.key() == price_feed_state.pyth_feed_account @ PriceFeedError::PythWrongFeed
+    )]
+    pub pyth_feed_account: Box<Account<'info, PriceUpdateV2>>,
+
+    #[account(
+        constraint = chainlink_feed.key == &price_feed_state.chainlink_feed @ PriceFeedError::ChainlinkWrongFeed
+    )]
+    /// CHECK: This is the Chainlink feed account
+    pub chainlink_feed: AccountInfo<'info>,
+
+    #[account(
+        constraint = jitosol_stake_pool.key() == price_feed_state.jitosol_stake_pool @ PriceFeedError::JitoSolStakePoolWrong
+    )]
+    /// CHECK: This is the Jito staking pool
+    pub jitosol_stake_pool: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = chainlink_program.key() == chainlink_solana::ID
+    )]
+    /// CHECK: This is the Chainlink program library
+    pub chainlink_program: AccountInfo<'info>,
+}
+
+/// Read-only: USD value locked in the pool at the current price, collateral and SP deposits
+pub fn get_tvl_handler(ctx: Context<GetTvl>) -> Result<TvlBreakdown> {
+    let price = ctx.accounts.price_feed_state.fetch_price(
+        &ctx.accounts.chainlink_program,
+        &ctx.accounts.chainlink_feed,
+        &ctx.accounts.jitosol_stake_pool,
+        &ctx.accounts.pyth_feed_account,
+    )?;
+
+    Ok(get_tvl(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.stability_pool_state,
+        price,
+    ))
+}
+
+pub fn get_tvl(pool_state: &PoolState, sp_state: &StabilityPoolState, price: u64) -> TvlBreakdown {
+    let active_coll_value = compute_value(pool_state.active_coll, price).unwrap();
+    let liquidated_coll_value = compute_value(pool_state.liquidated_coll, price).unwrap();
+    let sp_usv_deposits = sp_state.total_usv_deposits;
+
+    TvlBreakdown {
+        active_coll_value,
+        liquidated_coll_value,
+        sp_usv_deposits,
+        total_value: active_coll_value
+            .checked_add(liquidated_coll_value)
+            .unwrap()
+            .checked_add(sp_usv_deposits)
+            .unwrap(),
+    }
+}
+
+#[cfg(test)]
+pub mod get_tvl_test {
+    use super::*;
+
+    #[test]
+    fn coll_valued_at_price() {
+        // 12.5 collateral at $150.25
+        let pool_state = PoolState {
+            active_coll: 12_500_000_000,
+            liquidated_coll: 500_000_000,
+            ..Default::default()
+        };
+        let sp_state = StabilityPoolState {
+            total_usv_deposits: 1_000_000_000_000,
+            ..Default::default()
+        };
+
+        let tvl = get_tvl(&pool_state, &sp_state, 150_250_000_000);
+        assert_eq!(
+            tvl,
+            TvlBreakdown {
+                active_coll_value: 1_878_125_000_000,
+                liquidated_coll_value: 75_125_000_000,
+                sp_usv_deposits: 1_000_000_000_000,
+                total_value: 2_953_250_000_000,
+            }
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     constants::{DEPLOYER, TREASURY_VAULT},
     state::{PoolState, StabilityPoolState},
+    utils::require_valid_mint_decimals,
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -90,6 +91,11 @@ pub fn initialize_handler(
     gas_compensation: u64,
     coll_gas_comp_percent_divisor: u64,
 ) -> Result<()> {
+    require_valid_mint_decimals(
+        ctx.accounts.collateral.decimals,
+        ctx.accounts.stablecoin.decimals,
+    )?;
+
     let creator = ctx.accounts.creator.key();
     let stablecoin = ctx.accounts.stablecoin.key();
     let collateral = ctx.accounts.collateral.key();
@@ -2,9 +2,9 @@ use anchor_lang::prelude::*;
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 use crate::{
-    constants::DEPLOYER,
+    constants::{DEPLOYER, MAX_CONFIDENCE_RATE},
     errors::PriceFeedError,
-    state::{PriceFeedState, Status},
+    state::{PriceFeedState, SecondarySource, Status},
     utils::{is_pyth_broken, is_pyth_frozen},
 };
 
@@ -69,6 +69,15 @@ pub fn initialize_price_feed_handler(
         last_good_price: price_info.price_message.price.try_into().unwrap(),
         _is_dev: is_dev,
         _dev_price: 130_000_000_000,
+        jitosol_rate: 0,
+        jitosol_rate_epoch: 0,
+        secondary_source: SecondarySource::Chainlink,
+        last_good_price_time: price_info.price_message.publish_time,
+        status_history: Default::default(),
+        status_transitions: 0,
+        jitosol_max_epoch_lag: 0,
+        max_confidence_rate: MAX_CONFIDENCE_RATE,
+        pyth_feed_id: price_info.price_message.feed_id,
     };
 
     Ok(())
@@ -0,0 +1,170 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::{
+    errors::{BorrowerOpsError, PriceFeedError},
+    math::compute_cr,
+    state::{LiquidationTotals, PoolState, PriceFeedState, StabilityPoolState, Trove},
+    utils::get_current_timestamp,
+};
+
+use super::get_liquidation_values;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LiquidationPreview {
+    pub is_recovery_mode: bool,
+    pub total_debt_in_sequence: u64,
+    pub total_coll_in_sequence: u64,
+    pub total_coll_gas_compensation: u64,
+    pub total_usv_gas_compensation: u64,
+    pub total_debt_to_offset: u64,
+    pub total_coll_to_send_to_sp: u64,
+    pub total_debt_to_redistribute: u64,
+    pub total_coll_to_redistribute: u64,
+    pub total_coll_surplus: u64,
+    pub total_usv_gas_comp_to_sp: u64,
+}
+
+impl LiquidationPreview {
+    fn new(is_recovery_mode: bool, totals: &LiquidationTotals) -> Self {
+        Self {
+            is_recovery_mode,
+            total_debt_in_sequence: totals.total_debt_in_sequence,
+            total_coll_in_sequence: totals.total_coll_in_sequence,
+            total_coll_gas_compensation: totals.total_coll_gas_compensation,
+            total_usv_gas_compensation: totals.total_usv_gas_compensation,
+            total_debt_to_offset: totals.total_debt_to_offset,
+            total_coll_to_send_to_sp: totals.total_coll_to_send_to_sp,
+            total_debt_to_redistribute: totals.total_debt_to_redistribute,
+            total_coll_to_redistribute: totals.total_coll_to_redistribute,
+            total_coll_surplus: totals.total_coll_surplus,
+            total_usv_gas_comp_to_sp: totals.total_usv_gas_comp_to_sp,
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct PreviewLiquidation<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        seeds = [
+            b"trove",
+            pool_state.key().as_ref(),
+            borrower.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub trove: Box<Account<'info, Trove>>,
+
+    /// CHECK: Only used to derive the trove address
+    pub borrower: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"price_feed",
+            pool_state.cvgt.as_ref()
+        ],
+        bump = price_feed_state.bump
+    )]
+    pub price_feed_state: Box<Account<'info, PriceFeedState>>,
+
+    #[account(
+        constraint = pyth_feed_account.key() == price_feed_state.pyth_feed_account @ PriceFeedError::PythWrongFeed
+    )]
+    pub pyth_feed_account: Box<Account<'info, PriceUpdateV2>>,
+
+    #[account(
+        constraint = chainlink_feed.key == &price_feed_state.chainlink_feed @ PriceFeedError::ChainlinkWrongFeed
+    )]
+    /// CHECK: This is the Chainlink feed account
+    pub chainlink_feed: AccountInfo<'info>,
+
+    #[account(
+        constraint = jitosol_stake_pool.key() == price_feed_state.jitosol_stake_pool @ PriceFeedError::JitoSolStakePoolWrong
+    )]
+    /// CHECK: This is the Jito staking pool
+    pub jitosol_stake_pool: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = chainlink_program.key() == chainlink_solana::ID
+    )]
+    /// CHECK: This is the Chainlink program library
+    pub chainlink_program: AccountInfo<'info>,
+}
+
+/// Read-only: how `liquidate_trove` would split this trove between the SP and redistribution
+/// at the current price and SP balance. A trove it would not liquidate previews as all zeros.
+pub fn liquidate_preview_handler(ctx: Context<PreviewLiquidation>) -> Result<LiquidationPreview> {
+    let price = ctx.accounts.price_feed_state.fetch_price(
+        &ctx.accounts.chainlink_program,
+        &ctx.accounts.chainlink_feed,
+        &ctx.accounts.jitosol_stake_pool,
+        &ctx.accounts.pyth_feed_account,
+    )?;
+
+    get_liquidation_preview(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.trove,
+        ctx.accounts.stability_pool_state.total_usv_deposits,
+        price,
+        get_current_timestamp(),
+    )
+}
+
+/// Mirrors the checks of `liquidate_trove` on a single trove without changing any state
+pub fn get_liquidation_preview(
+    pool_state: &PoolState,
+    trove: &Trove,
+    usv_in_stab_pool: u64,
+    price: u64,
+    current_timestamp: u64,
+) -> Result<LiquidationPreview> {
+    trove.require_trove_active()?;
+
+    let is_recovery_mode = pool_state.check_recovery_mode(price);
+    let icr = trove.get_current_icr(pool_state, price);
+
+    let single_liquidation = if is_recovery_mode {
+        if pool_state.trove_size <= 1 || (icr >= pool_state.mcr && usv_in_stab_pool == 0) {
+            None
+        } else {
+            let tcr = compute_cr(
+                pool_state.get_entire_coll(),
+                pool_state.get_entire_debt(),
+                price,
+            )
+            .unwrap();
+            get_liquidation_values(pool_state, trove, usv_in_stab_pool, price, Some(tcr))?
+        }
+    } else if icr < pool_state.mcr {
+        require!(
+            !trove.is_in_liquidation_grace_period(pool_state, icr, current_timestamp),
+            BorrowerOpsError::TroveInLiquidationGracePeriod
+        );
+        get_liquidation_values(pool_state, trove, usv_in_stab_pool, price, None)?
+    } else {
+        None
+    };
+
+    let mut totals = LiquidationTotals::default();
+    if let Some(single_liquidation) = single_liquidation {
+        totals.add_liquidation_values(&single_liquidation);
+    }
+    if totals.total_debt_to_offset > 0 && usv_in_stab_pool > 0 {
+        totals.retain_gas_comp_in_sp(pool_state.get_liquidator_gas_comp_bps());
+    }
+
+    Ok(LiquidationPreview::new(is_recovery_mode, &totals))
+}
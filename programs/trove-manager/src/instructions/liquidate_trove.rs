@@ -7,6 +7,7 @@ use crate::{
         CommunityIssuanceConfig, EpochScale, LiquidationTotals, LiquidationValues, PoolState,
         PriceFeedState, StabilityPoolState, Trove, TroveStatus,
     },
+    utils::get_current_timestamp,
 };
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -267,34 +268,51 @@ impl<'info> LiquidateTrove<'info> {
 
         // Move token to Stability Pool
         self.transfer_coll_from_active_pool_to_sp(totals.total_coll_to_send_to_sp)?;
-        self.burn_usv_from_stability_pool(totals.total_debt_to_offset)?;
+        self.burn_usv_from_stability_pool(totals.get_sp_usv_loss())?;
 
         Ok(())
     }
 }
 
 pub fn liquidate_trove_handler(ctx: Context<LiquidateTrove>) -> Result<()> {
+    liquidate(ctx, false)
+}
+
+/// Same as `liquidate_trove`, the coll gas compensation is paid in USV at the current price
+pub fn liquidate_trove_usv_comp_handler(ctx: Context<LiquidateTrove>) -> Result<()> {
+    liquidate(ctx, true)
+}
+
+fn liquidate(ctx: Context<LiquidateTrove>, coll_comp_in_usv: bool) -> Result<()> {
+    // A borrower that never opened a trove has a zeroed account
+    ctx.accounts.trove.require_trove_active()?;
+
     let price = ctx.accounts.price_feed_state.fetch_price(
         &ctx.accounts.chainlink_program,
         &ctx.accounts.chainlink_feed,
         &ctx.accounts.jitosol_stake_pool,
         &ctx.accounts.pyth_feed_account,
     )?;
+    emit!(ctx.accounts.price_feed_state.price_used(price));
 
     let trove = &mut ctx.accounts.trove;
     let pool_state = &mut ctx.accounts.pool_state;
+    pool_state.require_not_self_liquidation(
+        &ctx.accounts.liquidator.key(),
+        &ctx.accounts.borrower.key(),
+    )?;
     let sp_state = &mut ctx.accounts.stability_pool_state;
     let current_epoch_scale = &mut ctx.accounts.current_epoch_scale;
     let prev_trove_option = &mut ctx.accounts.prev_trove;
     let next_trove_option = &mut ctx.accounts.next_trove;
 
-    // now we will not handle the case that Stability pool has non-zero USV
+    // Debt above the SP balance is redistributed rather than offset
     let usv_in_stab_pool = sp_state.total_usv_deposits;
 
     // Check is recovery
     let is_recovery_mode_at_start = pool_state.check_recovery_mode(price);
 
-    let totals = if is_recovery_mode_at_start {
+    let mut totals = if is_recovery_mode_at_start {
         get_totals_from_liquidate_recovery_mode(
             price,
             usv_in_stab_pool,
@@ -312,8 +330,8 @@ pub fn liquidate_trove_handler(ctx: Context<LiquidateTrove>) -> Result<()> {
             prev_trove_option,
             next_trove_option,
             pool_state,
-        )
-        .unwrap()
+            get_current_timestamp(),
+        )?
     };
 
     require!(
@@ -321,18 +339,30 @@ pub fn liquidate_trove_handler(ctx: Context<LiquidateTrove>) -> Result<()> {
         BorrowerOpsError::LiquidateZeroDebt
     );
 
+    if coll_comp_in_usv {
+        // The retained coll and the USV minted for it go to the remaining troves
+        require!(
+            pool_state.total_stakes > 0,
+            BorrowerOpsError::NoTrovesToRedistribute
+        );
+        let usv_comp = totals.convert_coll_gas_comp_to_usv(price);
+        pool_state.increase_active_debt(usv_comp);
+    }
+
     // StabilityPool offset
     if totals.total_debt_to_offset > 0 && usv_in_stab_pool > 0 {
+        totals.retain_gas_comp_in_sp(pool_state.get_liquidator_gas_comp_bps());
+
         // _triggerCVGTIssuance
         let config = &mut ctx.accounts.community_issuance_config;
         let cvgt_issuance = config.issue_token()?;
 
-        sp_state.offset(current_epoch_scale, &totals, cvgt_issuance);
+        sp_state.offset(current_epoch_scale, &totals, cvgt_issuance)?;
     }
     pool_state.redistribute_debt_and_coll(
         totals.total_debt_to_redistribute,
         totals.total_coll_to_redistribute,
-    );
+    )?;
     if totals.total_coll_surplus > 0 {
         pool_state.decrease_active_coll(totals.total_coll_surplus);
         pool_state.increase_total_surplus(totals.total_coll_surplus);
@@ -350,9 +380,19 @@ pub fn liquidate_trove_handler(ctx: Context<LiquidateTrove>) -> Result<()> {
         coll: liquidated_coll,
         total_usv_compensation: totals.total_coll_gas_compensation,
         total_coll_compensation: totals.total_usv_gas_compensation,
+        event_nonce: pool_state.next_event_nonce(),
     });
 
     pool_state.move_coll_debt_from_liquidate(sp_state, &totals);
+    emit!(trove.icr_updated(
+        pool_state,
+        price,
+        if is_recovery_mode_at_start {
+            Operation::LiquidateInRecoveryMode
+        } else {
+            Operation::LiquidateInNormalMode
+        }
+    ));
 
     ctx.accounts.move_tokens(totals)
 }
@@ -364,18 +404,24 @@ fn get_totals_from_liquidate_normal_mode(
     prev_trove: &mut Option<Box<Account<'_, Trove>>>,
     next_trove: &mut Option<Box<Account<'_, Trove>>>,
     pool_state: &mut PoolState,
+    current_timestamp: u64,
 ) -> Result<LiquidationTotals> {
     let mut totals: LiquidationTotals = Default::default();
     let remaining_usv_in_stab_pool: u64 = usv_in_stab_pool;
 
     let icr: u64 = trove.get_current_icr(pool_state, price);
     if icr < pool_state.mcr {
+        require!(
+            !trove.is_in_liquidation_grace_period(pool_state, icr, current_timestamp),
+            BorrowerOpsError::TroveInLiquidationGracePeriod
+        );
         let single_liquidation = liquidate_normal_mode(
             pool_state,
             trove,
             prev_trove,
             next_trove,
             remaining_usv_in_stab_pool,
+            price,
         )
         .unwrap();
         // Add liquidation values to their respective running totals
@@ -430,32 +476,19 @@ fn liquidate_normal_mode(
     prev_trove: &mut Option<Box<Account<'_, Trove>>>,
     next_trove: &mut Option<Box<Account<'_, Trove>>>,
     usv_in_stab_pool: u64,
+    price: u64,
 ) -> Result<LiquidationValues> {
-    let mut single_liquidation: LiquidationValues = Default::default();
     let trove_id = trove.key();
 
-    let entire_debt_and_coll = trove.get_entire_debt_coll(pool_state);
-    single_liquidation.entire_trove_debt = entire_debt_and_coll.0;
-    single_liquidation.entire_trove_coll = entire_debt_and_coll.1;
-    let pending_debt_reward = entire_debt_and_coll.2;
-    let pending_coll_reward = entire_debt_and_coll.3;
+    let (_, _, pending_debt_reward, pending_coll_reward) = trove.get_entire_debt_coll(pool_state);
+    let single_liquidation =
+        get_liquidation_values(pool_state, trove, usv_in_stab_pool, price, None)?.unwrap();
 
     // moving pending debt/coll to active pool
     pool_state.move_pending_trove_rewards_to_active(pending_debt_reward, pending_coll_reward);
     // Remove stake
     trove.remove_stake(pool_state);
 
-    single_liquidation.coll_gas_compensation =
-        pool_state.get_coll_gas_compensation(single_liquidation.entire_trove_coll);
-    single_liquidation.usv_gas_compensation = pool_state.gas_compensation;
-
-    let coll_to_liquidate = single_liquidation
-        .entire_trove_coll
-        .checked_sub(single_liquidation.coll_gas_compensation)
-        .unwrap();
-
-    single_liquidation.offset_and_redistribute(coll_to_liquidate, usv_in_stab_pool);
-
     trove.close_trove(pool_state, TroveStatus::ClosedByLiquidation)?;
     trove.remove_sorted(trove_id, prev_trove, next_trove, pool_state)?;
 
@@ -472,6 +505,7 @@ fn liquidate_normal_mode(
         coll: 0,
         stake: 0,
         operation: Operation::LiquidateInNormalMode,
+        event_nonce: pool_state.next_event_nonce(),
     });
 
     Ok(single_liquidation)
@@ -487,112 +521,365 @@ fn liquidate_recovery_mode(
     icr: u64,
     price: u64,
 ) -> Result<LiquidationValues> {
-    let mut single_liquidation: LiquidationValues = Default::default();
     let trove_id = trove.key();
     if pool_state.trove_size <= 1 {
-        return Ok(single_liquidation);
+        return Ok(LiquidationValues::default());
     }
-    let entire_debt_and_coll = trove.get_entire_debt_coll(pool_state);
-    single_liquidation.entire_trove_debt = entire_debt_and_coll.0;
-    single_liquidation.entire_trove_coll = entire_debt_and_coll.1;
-    let pending_debt_reward = entire_debt_and_coll.2;
-    let pending_coll_reward = entire_debt_and_coll.3;
-
-    single_liquidation.coll_gas_compensation =
-        pool_state.get_coll_gas_compensation(single_liquidation.entire_trove_coll);
-    single_liquidation.usv_gas_compensation = pool_state.gas_compensation;
-    let coll_to_liquidate = single_liquidation
-        .entire_trove_coll
+    let (_, _, pending_debt_reward, pending_coll_reward) = trove.get_entire_debt_coll(pool_state);
+    let single_liquidation =
+        match get_liquidation_values(pool_state, trove, usv_in_stab_pool, price, Some(tcr))? {
+            Some(single_liquidation) => single_liquidation,
+            // if (ICR >= MCR && ( ICR >= TCR || singleLiquidation.entireTroveDebt > USVInStabPool))
+            None => return Ok(LiquidationValues::default()),
+        };
+
+    pool_state.move_pending_trove_rewards_to_active(pending_debt_reward, pending_coll_reward);
+    trove.remove_stake(pool_state);
+
+    trove.close_trove(pool_state, TroveStatus::ClosedByLiquidation)?;
+    trove.remove_sorted(trove_id, prev_trove, next_trove, pool_state)?;
+    if single_liquidation.coll_surplus > 0 {
+        trove.account_surplus(single_liquidation.coll_surplus);
+    }
+    emit!(TroveLiquidated {
+        borrower: trove.key(),
+        debt: single_liquidation.entire_trove_debt,
+        // Only the capped offset liquidates a trove with ICR >= MCR
+        coll: if icr >= pool_state.mcr {
+            single_liquidation.coll_to_send_to_sp
+        } else {
+            single_liquidation.entire_trove_coll
+        },
+        operation: Operation::LiquidateInRecoveryMode,
+    });
+    emit!(TroveUpdated {
+        borrower: trove.key(),
+        debt: 0,
+        coll: 0,
+        stake: 0,
+        operation: Operation::LiquidateInRecoveryMode,
+        event_nonce: pool_state.next_event_nonce()
+    });
+    Ok(single_liquidation)
+}
+
+/// Split of a trove with `entire_trove_debt` and `entire_trove_coll` between the SP and the
+/// other troves. `recovery_mode_tcr` is the TCR in recovery mode, where `None` leaves the trove
+/// untouched. Shared by `liquidate_trove` and `liquidate_preview`, it changes no state.
+pub fn get_liquidation_values(
+    pool_state: &PoolState,
+    trove: &Trove,
+    usv_in_stab_pool: u64,
+    price: u64,
+    recovery_mode_tcr: Option<u64>,
+) -> Result<Option<LiquidationValues>> {
+    let (entire_trove_debt, entire_trove_coll, _, _) = trove.get_entire_debt_coll(pool_state);
+    let mut single_liquidation = LiquidationValues {
+        entire_trove_debt,
+        entire_trove_coll,
+        coll_gas_compensation: pool_state.get_coll_liquidation_compensation(
+            entire_trove_coll,
+            entire_trove_debt,
+            price,
+        ),
+        usv_gas_compensation: trove
+            .get_liquidation_usv_gas_compensation(pool_state.gas_compensation),
+        ..Default::default()
+    };
+    let coll_to_liquidate = entire_trove_coll
         .checked_sub(single_liquidation.coll_gas_compensation)
         .unwrap();
 
+    let tcr = match recovery_mode_tcr {
+        Some(tcr) => tcr,
+        None => {
+            single_liquidation.offset_and_redistribute(coll_to_liquidate, usv_in_stab_pool);
+            return Ok(Some(single_liquidation));
+        }
+    };
+    let icr = compute_cr(entire_trove_coll, entire_trove_debt, price).unwrap();
+
     // If ICR <= 100%, purely redistribute the Trove across all active Troves
     if icr <= ONE_HUNDERED_PERCENT {
-        pool_state.move_pending_trove_rewards_to_active(pending_debt_reward, pending_coll_reward);
-        trove.remove_stake(pool_state);
-        single_liquidation.debt_to_offset = 0;
-        single_liquidation.coll_to_send_to_sp = 0;
-        single_liquidation.debt_to_redistribute = single_liquidation.entire_trove_debt;
+        single_liquidation.debt_to_redistribute = entire_trove_debt;
         single_liquidation.coll_to_redistribute = coll_to_liquidate;
-        trove.close_trove(pool_state, TroveStatus::ClosedByLiquidation)?;
-        trove.remove_sorted(trove_id, prev_trove, next_trove, pool_state)?;
-        emit!(TroveLiquidated {
-            borrower: trove.key(),
-            debt: single_liquidation.entire_trove_debt,
-            coll: single_liquidation.entire_trove_coll,
-            operation: Operation::LiquidateInRecoveryMode,
-        });
-        emit!(TroveUpdated {
-            borrower: trove.key(),
-            debt: 0,
-            coll: 0,
-            stake: 0,
-            operation: Operation::LiquidateInRecoveryMode
-        });
 
     // If 100% < ICR < MCR, offset as much as possible, and redistribute the remainder
-    } else if (icr > ONE_HUNDERED_PERCENT) && (icr < pool_state.mcr) {
-        pool_state.move_pending_trove_rewards_to_active(pending_debt_reward, pending_coll_reward);
-        trove.remove_stake(pool_state);
+    } else if icr < pool_state.mcr {
         single_liquidation.offset_and_redistribute(coll_to_liquidate, usv_in_stab_pool);
 
-        trove.close_trove(pool_state, TroveStatus::ClosedByLiquidation)?;
-        trove.remove_sorted(trove_id, prev_trove, next_trove, pool_state)?;
-        emit!(TroveLiquidated {
-            borrower: trove.key(),
-            debt: single_liquidation.entire_trove_debt,
-            coll: single_liquidation.entire_trove_coll,
-            operation: Operation::LiquidateInRecoveryMode,
-        });
-        emit!(TroveUpdated {
-            borrower: trove.key(),
-            debt: 0,
-            coll: 0,
-            stake: 0,
-            operation: Operation::LiquidateInRecoveryMode
-        });
-
     /*
      * If 110% <= ICR < current TCR (accounting for the preceding liquidations in the current sequence)
      * and there is USV in the Stability Pool, only offset, with no redistribution,
      * but at a capped rate of 1.1 and only if the whole debt can be liquidated.
      * The remainder due to the capped rate will be claimable as collateral surplus.
      */
-    } else if (icr >= pool_state.mcr)
-        && (icr < tcr)
-        && (single_liquidation.entire_trove_debt <= usv_in_stab_pool)
-    {
-        pool_state.move_pending_trove_rewards_to_active(pending_debt_reward, pending_coll_reward);
-        assert!(usv_in_stab_pool != 0);
-
-        trove.remove_stake(pool_state);
-        single_liquidation = pool_state.get_capped_offset_vals(
-            single_liquidation.entire_trove_debt,
-            single_liquidation.entire_trove_coll,
+    } else if icr < tcr && entire_trove_debt <= usv_in_stab_pool {
+        single_liquidation =
+            pool_state.get_capped_offset_vals(entire_trove_debt, entire_trove_coll, price)?;
+    } else {
+        return Ok(None);
+    }
+    Ok(Some(single_liquidation))
+}
+
+#[cfg(test)]
+pub mod liquidate_trove_test {
+    use super::*;
+    use crate::{
+        constants::DECIMAL_PRECISION,
+        errors::BorrowerOpsError,
+        instructions::get_liquidation_preview,
+        state::{
+            price_feed_info_test::load_price_feed_info, trove_test::load_trove_account, Status,
+        },
+        utils::utils_test::{
+            load_account, load_anchor_account, load_mint, load_program, load_token_account,
+            set_program_test_stubs,
+        },
+        ID,
+    };
+    use anchor_lang::Discriminator;
+    use anchor_spl::{associated_token::get_associated_token_address, token::spl_token};
+    use std::collections::BTreeSet;
+
+    const UNIT: u64 = 1_000_000_000;
+
+    /// Accounts of `liquidate_trove` in declaration order, without trove neighbors
+    fn load_liquidate_trove_accounts(trove: &Trove) -> Vec<AccountInfo<'static>> {
+        let absent = || load_program(ID);
+        let borrower = Pubkey::new_unique();
+        let liquidator = Pubkey::new_unique();
+        let cvgt = Pubkey::new_unique();
+        let collateral = Pubkey::new_unique();
+        let stablecoin = Pubkey::new_unique();
+        let pool_state_key = Pubkey::new_unique();
+        let pool_state = PoolState {
+            stablecoin,
+            collateral,
+            cvgt,
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            ..Default::default()
+        };
+        let (sp_state_key, _) =
+            Pubkey::find_program_address(&[b"stability", pool_state_key.as_ref()], &ID);
+        let (trove_key, _) = Pubkey::find_program_address(
+            &[b"trove", pool_state_key.as_ref(), borrower.as_ref()],
+            &ID,
+        );
+        let (epoch_scale_key, _) = Pubkey::find_program_address(
+            &[
+                b"epoch-scale",
+                sp_state_key.as_ref(),
+                0u128.to_le_bytes().as_ref(),
+                0u128.to_le_bytes().as_ref(),
+            ],
+            &ID,
+        );
+        let (token_authority, _) =
+            Pubkey::find_program_address(&[b"token-authority", pool_state_key.as_ref()], &ID);
+        let (community_issuance_config, _) =
+            Pubkey::find_program_address(&[b"community-issuance", cvgt.as_ref()], &ID);
+        let (price_feed_key, price_feed_bump) =
+            Pubkey::find_program_address(&[b"price_feed", cvgt.as_ref()], &ID);
+        let price_feed_state = PriceFeedState {
+            bump: price_feed_bump,
+            _is_dev: true,
+            _dev_price: 100 * UNIT,
+            ..load_price_feed_info(DECIMAL_PRECISION, Status::PythWorking)
+        };
+        let token_account = |mint: Pubkey, owner: Pubkey| {
+            load_account(
+                get_associated_token_address(&owner, &mint),
+                spl_token::ID,
+                load_token_account(mint, owner, 0),
+            )
+        };
+        let mut pyth_feed_account = PriceUpdateV2::DISCRIMINATOR.to_vec();
+        pyth_feed_account.resize(PriceUpdateV2::LEN, 0);
+        let mut liquidator_account = load_account(liquidator, Pubkey::default(), vec![]);
+        liquidator_account.is_signer = true;
+
+        vec![
+            load_anchor_account(pool_state_key, &pool_state),
+            load_anchor_account(sp_state_key, &StabilityPoolState::default()),
+            load_trove_account(trove_key, trove),
+            absent(),
+            absent(),
+            load_anchor_account(epoch_scale_key, &EpochScale::default()),
+            token_account(collateral, liquidator),
+            token_account(stablecoin, liquidator),
+            token_account(collateral, token_authority),
+            token_account(stablecoin, sp_state_key),
+            token_account(collateral, sp_state_key),
+            load_account(stablecoin, spl_token::ID, load_mint(token_authority)),
+            load_account(collateral, spl_token::ID, load_mint(token_authority)),
+            load_account(token_authority, Pubkey::default(), vec![]),
+            load_account(borrower, Pubkey::default(), vec![]),
+            liquidator_account,
+            load_anchor_account(
+                community_issuance_config,
+                &CommunityIssuanceConfig::default(),
+            ),
+            load_anchor_account(price_feed_key, &price_feed_state),
+            load_account(
+                price_feed_state.pyth_feed_account,
+                pyth_solana_receiver_sdk::ID,
+                pyth_feed_account,
+            ),
+            load_account(price_feed_state.chainlink_feed, Pubkey::default(), vec![]),
+            load_account(
+                price_feed_state.jitosol_stake_pool,
+                Pubkey::default(),
+                vec![],
+            ),
+            load_program(chainlink_solana::ID),
+            load_program(spl_token::ID),
+            load_program(anchor_spl::associated_token::ID),
+            load_program(System::id()),
+        ]
+    }
+
+    fn liquidate_trove(accounts: Vec<AccountInfo<'static>>) -> Result<()> {
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.into_boxed_slice());
+        let mut bumps = LiquidateTroveBumps::default();
+        let mut liquidate_accounts =
+            LiquidateTrove::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        liquidate_trove_handler(Context::new(&ID, &mut liquidate_accounts, &[], bumps))
+    }
+
+    #[test]
+    fn inactive_trove_cannot_be_liquidated() {
+        set_program_test_stubs();
+        // Never opened, its zeroed debt reads as an infinite ICR
+        let trove = Trove::default();
+        assert_eq!(
+            liquidate_trove(load_liquidate_trove_accounts(&trove)).unwrap_err(),
+            BorrowerOpsError::TroveIsNotActive.into()
+        );
+
+        let trove = Trove {
+            coll: 100 * UNIT,
+            debt: 100 * UNIT,
+            status: TroveStatus::ClosedByOwner,
+            ..Default::default()
+        };
+        assert_eq!(
+            liquidate_trove(load_liquidate_trove_accounts(&trove)).unwrap_err(),
+            BorrowerOpsError::TroveIsNotActive.into()
+        );
+    }
+
+    #[test]
+    fn preview_matches_liquidation() {
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            coll_gas_comp_percent_divisor: 200,
+            trove_size: 2,
+            trove_tail: keys[0],
+            trove_head: keys[1],
+            active_coll: 400 * UNIT,
+            active_debt: 200 * UNIT,
+            total_stakes: 400 * UNIT,
+            ..Default::default()
+        };
+        // Tail at ICR 105%, head at ICR 315%
+        let troves = [
+            Trove {
+                coll: 100 * UNIT,
+                debt: 100 * UNIT,
+                stake: 100 * UNIT,
+                status: TroveStatus::Active,
+                prev: keys[1],
+                ..Default::default()
+            },
+            Trove {
+                coll: 300 * UNIT,
+                debt: 100 * UNIT,
+                stake: 300 * UNIT,
+                status: TroveStatus::Active,
+                next: keys[0],
+                ..Default::default()
+            },
+        ];
+        let accounts: Vec<AccountInfo> = keys
+            .iter()
+            .zip(&troves)
+            .map(|(key, trove)| load_trove_account(*key, trove))
+            .collect();
+        let price = 1_050_000_000;
+        let usv_in_stab_pool = 50 * UNIT;
+
+        let preview =
+            get_liquidation_preview(&pool_state, &troves[0], usv_in_stab_pool, price, 0).unwrap();
+        assert!(!preview.is_recovery_mode);
+
+        let mut trove = Account::<Trove>::try_from(&accounts[0]).unwrap();
+        let mut prev_trove = Some(Box::new(Account::<Trove>::try_from(&accounts[1]).unwrap()));
+        let mut totals = get_totals_from_liquidate_normal_mode(
             price,
-        )?;
+            usv_in_stab_pool,
+            &mut trove,
+            &mut prev_trove,
+            &mut None,
+            &mut pool_state,
+            0,
+        )
+        .unwrap();
+        totals.retain_gas_comp_in_sp(pool_state.get_liquidator_gas_comp_bps());
+        assert!(trove.status == TroveStatus::ClosedByLiquidation);
+
+        assert_eq!(preview.total_debt_to_offset, totals.total_debt_to_offset);
+        assert_eq!(
+            preview.total_coll_to_send_to_sp,
+            totals.total_coll_to_send_to_sp
+        );
+        assert_eq!(
+            preview.total_debt_to_redistribute,
+            totals.total_debt_to_redistribute
+        );
+        assert_eq!(
+            preview.total_coll_to_redistribute,
+            totals.total_coll_to_redistribute
+        );
+        assert_eq!(preview.total_debt_to_offset, 50 * UNIT);
+        assert_eq!(preview.total_debt_to_redistribute, 50 * UNIT);
+    }
 
-        trove.close_trove(pool_state, TroveStatus::ClosedByLiquidation)?;
-        trove.remove_sorted(trove_id, prev_trove, next_trove, pool_state)?;
-        if single_liquidation.coll_surplus > 0 {
-            trove.account_surplus(single_liquidation.coll_surplus);
-        }
-        emit!(TroveLiquidated {
-            borrower: trove.key(),
-            debt: single_liquidation.entire_trove_debt,
-            coll: single_liquidation.coll_to_send_to_sp,
-            operation: Operation::LiquidateInRecoveryMode,
-        });
-        emit!(TroveUpdated {
-            borrower: trove.key(),
-            debt: 0,
-            coll: 0,
-            stake: 0,
-            operation: Operation::LiquidateInRecoveryMode
-        });
-    } else {
-        // if (ICR >= MCR && ( ICR >= TCR || singleLiquidation.entireTroveDebt > USVInStabPool))
-        return Ok(LiquidationValues::default());
+    #[test]
+    fn borrower_held_gas_comp_not_minted_to_liquidator() {
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            coll_gas_comp_percent_divisor: 200,
+            gas_compensation: 2 * UNIT,
+            trove_size: 2,
+            ..Default::default()
+        };
+        let mut trove = Trove {
+            coll: 100 * UNIT,
+            debt: 100 * UNIT,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+        let price = 1_050_000_000;
+
+        let single_liquidation =
+            get_liquidation_values(&pool_state, &trove, 50 * UNIT, price, None)
+                .unwrap()
+                .unwrap();
+        assert_eq!(single_liquidation.usv_gas_compensation, 2 * UNIT);
+
+        // The borrower kept the reserve, the liquidator only gets the coll compensation
+        trove.gas_comp_held_by_borrower = true;
+        let single_liquidation =
+            get_liquidation_values(&pool_state, &trove, 50 * UNIT, price, None)
+                .unwrap()
+                .unwrap();
+        assert_eq!(single_liquidation.usv_gas_compensation, 0);
+        assert_eq!(single_liquidation.coll_gas_compensation, UNIT / 2);
+        assert_eq!(single_liquidation.entire_trove_debt, 100 * UNIT);
     }
-    Ok(single_liquidation)
 }
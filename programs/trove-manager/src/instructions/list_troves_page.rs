@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    constants::MAX_TROVES_PAGE,
+    errors::BorrowerOpsError,
+    state::{get_trove_page, NeighborNode, PoolState, Trove, TrovePageEntry},
+};
+
+#[derive(Accounts)]
+pub struct ListTrovesPage<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+}
+
+/// Read-only: returns `remaining_accounts` as a page of the sorted list starting at `start`,
+/// after checking their `prev`/`next` linkage. A page shorter than `limit` must end at the tail.
+pub fn list_troves_page_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ListTrovesPage<'info>>,
+    start: Pubkey,
+    limit: u8,
+) -> Result<Vec<TrovePageEntry>> {
+    require!(limit <= MAX_TROVES_PAGE, BorrowerOpsError::TooManyTroves);
+    let pool_state = &ctx.accounts.pool_state;
+
+    let nodes = ctx
+        .remaining_accounts
+        .iter()
+        .map(|account| {
+            let trove = Account::<Trove>::try_from(account)?;
+            require!(
+                trove.pool_state == pool_state.key(),
+                BorrowerOpsError::InvalidAccount
+            );
+            trove.require_trove_active()?;
+            Ok(NeighborNode {
+                key: trove.key(),
+                prev: trove.prev,
+                next: trove.next,
+                nicr: trove.get_nominal_icr(pool_state),
+            })
+        })
+        .collect::<Result<Vec<NeighborNode>>>()?;
+
+    get_trove_page(start, limit.into(), &nodes)
+}
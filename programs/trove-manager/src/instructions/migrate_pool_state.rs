@@ -0,0 +1,180 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{errors::PoolConfigError, state::PoolState, utils::realloc_zeroed, ID};
+
+#[derive(Accounts)]
+pub struct MigratePoolState<'info> {
+    /// CHECK: An old layout can't be deserialized as PoolState, discriminator and creator are checked in the handler
+    #[account(
+        mut,
+        owner = ID
+    )]
+    pub pool_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a PoolState created with an older layout to the current `INIT_SPACE`.
+/// Fields appended after `bump` read as zero, which is their default. Calling it again is a no-op.
+pub fn migrate_pool_state_handler(ctx: Context<MigratePoolState>) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    {
+        let data = pool_state.try_borrow_data()?;
+        require!(
+            data.len() >= 8 + 32 && data[..8] == PoolState::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        // `creator` is the first field of every layout
+        let creator = Pubkey::try_from(&data[8..40]).unwrap();
+        require!(
+            creator == ctx.accounts.creator.key(),
+            PoolConfigError::InvalidSigner
+        );
+    }
+
+    realloc_zeroed(
+        pool_state,
+        &ctx.accounts.creator,
+        &ctx.accounts.system_program,
+        8 + PoolState::INIT_SPACE,
+    )
+}
+
+#[cfg(test)]
+pub mod migrate_pool_state_test {
+    use super::*;
+    use crate::{
+        constants::{MAX_BPS, MINUTE_DECAY_FACTOR},
+        utils::utils_test::{
+            load_program, load_reallocable_account, load_signer, set_program_test_stubs,
+        },
+    };
+    use std::collections::BTreeSet;
+
+    // Layout before the fields appended after `bump`
+    const V1_LEN: usize = 8 + 419;
+
+    fn load_migrate_accounts(pool_state: &PoolState) -> Vec<AccountInfo<'static>> {
+        let mut data = Vec::new();
+        pool_state.try_serialize(&mut data).unwrap();
+        data.truncate(V1_LEN);
+
+        vec![
+            load_reallocable_account(
+                Pubkey::new_unique(),
+                Rent::default().minimum_balance(V1_LEN),
+                &data,
+            ),
+            load_signer(pool_state.creator, 1_000_000_000),
+            load_program(System::id()),
+        ]
+    }
+
+    fn migrate(accounts: &[AccountInfo<'static>]) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = MigratePoolStateBumps::default();
+        let mut migrate_accounts =
+            MigratePoolState::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        migrate_pool_state_handler(Context::new(&ID, &mut migrate_accounts, &[], bumps))
+    }
+
+    #[test]
+    fn old_layout_reads_new_fields_as_defaults() {
+        set_program_test_stubs();
+        let pool_state = PoolState {
+            creator: Pubkey::new_unique(),
+            mcr: 1_100_000_000,
+            gas_compensation: 200_000_000,
+            trove_tail: Pubkey::new_unique(),
+            bump: [254],
+            redemption_fee_treasury_bps: 3_000,
+            gas_comp_to_borrower: true,
+            liquidation_bonus_bps: 100,
+            sp_deposits_disabled: true,
+            event_nonce: 7,
+            max_trove_coll: 1_000_000_000,
+            max_system_debt: 1_000_000_000,
+            self_liquidation_allowed: true,
+            minute_decay_factor: MINUTE_DECAY_FACTOR,
+            max_redemption_fraction_bps: 500,
+            debt_increase_cooldown: 60,
+            min_coll_gas_comp: 1_000,
+            max_coll_gas_comp: 1_000_000,
+            redemption_hint_tolerance_bps: 50,
+            pre_launch_fee_treasury: Pubkey::new_unique(),
+            redemption_max_price_age: 60,
+            liquidation_grace_period: 300,
+            liquidator_gas_comp_bps: 5_000,
+            recovery_proximity_surcharge_bps: 100,
+            recovery_proximity_band: 100_000_000,
+            max_troves: 100,
+            nicr_tie_break_enforced: true,
+            liquidator_gas_comp_bps_set: true,
+            ..Default::default()
+        };
+        let accounts = load_migrate_accounts(&pool_state);
+        let new_len = 8 + PoolState::INIT_SPACE;
+        let top_up = Rent::default().minimum_balance(new_len) - accounts[0].lamports();
+
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[0].data_len(), new_len);
+        assert_eq!(accounts[1].lamports(), 1_000_000_000 - top_up);
+        let migrated =
+            PoolState::try_deserialize(&mut &accounts[0].try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!(migrated.creator, pool_state.creator);
+        assert_eq!(migrated.mcr, pool_state.mcr);
+        assert_eq!(migrated.gas_compensation, pool_state.gas_compensation);
+        assert_eq!(migrated.trove_tail, pool_state.trove_tail);
+        assert_eq!(migrated.bump, pool_state.bump);
+        assert_eq!(migrated.redemption_fee_treasury_bps, 0);
+        assert!(!migrated.gas_comp_to_borrower);
+        assert_eq!(migrated.liquidation_bonus_bps, 0);
+        assert!(!migrated.sp_deposits_disabled);
+        assert_eq!(migrated.event_nonce, 0);
+        assert_eq!(migrated.max_trove_coll, 0);
+        assert_eq!(migrated.max_system_debt, 0);
+        assert!(!migrated.self_liquidation_allowed);
+        assert_eq!(migrated.minute_decay_factor, 0);
+        assert_eq!(migrated.get_minute_decay_factor(), MINUTE_DECAY_FACTOR);
+        assert_eq!(migrated.max_redemption_fraction_bps, 0);
+        assert_eq!(migrated.debt_increase_cooldown, 0);
+        assert_eq!(migrated.min_coll_gas_comp, 0);
+        assert_eq!(migrated.max_coll_gas_comp, 0);
+        assert_eq!(migrated.redemption_hint_tolerance_bps, 0);
+        assert_eq!(migrated.pre_launch_fee_treasury, Pubkey::default());
+        assert_eq!(migrated.redemption_max_price_age, 0);
+        assert_eq!(migrated.liquidation_grace_period, 0);
+        assert_eq!(migrated.liquidator_gas_comp_bps, 0);
+        assert_eq!(migrated.get_liquidator_gas_comp_bps(), MAX_BPS);
+        assert_eq!(migrated.recovery_proximity_surcharge_bps, 0);
+        assert_eq!(migrated.recovery_proximity_band, 0);
+        assert_eq!(migrated.max_troves, 0);
+        assert!(!migrated.nicr_tie_break_enforced);
+        assert!(!migrated.liquidator_gas_comp_bps_set);
+
+        // Already migrated
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[0].data_len(), new_len);
+        assert_eq!(accounts[1].lamports(), 1_000_000_000 - top_up);
+    }
+
+    #[test]
+    fn only_pool_creator_migrates() {
+        set_program_test_stubs();
+        let mut accounts = load_migrate_accounts(&PoolState {
+            creator: Pubkey::new_unique(),
+            ..Default::default()
+        });
+        accounts[1] = load_signer(Pubkey::new_unique(), 1_000_000_000);
+
+        assert_eq!(
+            migrate(&accounts).unwrap_err(),
+            PoolConfigError::InvalidSigner.into()
+        );
+        assert_eq!(accounts[0].data_len(), V1_LEN);
+    }
+}
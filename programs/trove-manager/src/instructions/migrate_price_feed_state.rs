@@ -0,0 +1,44 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{errors::PriceFeedError, state::PriceFeedState, utils::realloc_zeroed, ID};
+
+#[derive(Accounts)]
+pub struct MigratePriceFeedState<'info> {
+    /// CHECK: An old layout can't be deserialized as PriceFeedState, discriminator and creator are checked in the handler
+    #[account(
+        mut,
+        owner = ID
+    )]
+    pub price_feed_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a PriceFeedState created with an older layout to the current `INIT_SPACE`.
+/// The status history starts empty. Calling it again is a no-op.
+pub fn migrate_price_feed_state_handler(ctx: Context<MigratePriceFeedState>) -> Result<()> {
+    let price_feed_state = &ctx.accounts.price_feed_state;
+    {
+        let data = price_feed_state.try_borrow_data()?;
+        require!(
+            data.len() >= 8 + 32 && data[..8] == PriceFeedState::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        // `creator` is the first field of every layout
+        let creator = Pubkey::try_from(&data[8..40]).unwrap();
+        require!(
+            creator == ctx.accounts.creator.key(),
+            PriceFeedError::InvalidSigner
+        );
+    }
+
+    realloc_zeroed(
+        price_feed_state,
+        &ctx.accounts.creator,
+        &ctx.accounts.system_program,
+        8 + PriceFeedState::INIT_SPACE,
+    )
+}
@@ -0,0 +1,161 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{
+    errors::PoolConfigError,
+    state::{PoolState, StabilityPoolState},
+    utils::realloc_zeroed,
+    ID,
+};
+
+#[derive(Accounts)]
+pub struct MigrateStabilityPoolState<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    /// CHECK: An old layout can't be deserialized as StabilityPoolState, the discriminator is checked in the handler
+    #[account(
+        mut,
+        owner = ID,
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = creator.key() == pool_state.creator @ PoolConfigError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a StabilityPoolState created with an older layout to the current `INIT_SPACE`.
+/// The deposit fee reads as disabled. Calling it again is a no-op.
+pub fn migrate_stability_pool_state_handler(ctx: Context<MigrateStabilityPoolState>) -> Result<()> {
+    let sp_state = &ctx.accounts.stability_pool_state;
+    {
+        let data = sp_state.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == StabilityPoolState::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+    }
+
+    realloc_zeroed(
+        sp_state,
+        &ctx.accounts.creator,
+        &ctx.accounts.system_program,
+        8 + StabilityPoolState::INIT_SPACE,
+    )
+}
+
+#[cfg(test)]
+pub mod migrate_stability_pool_state_test {
+    use super::*;
+    use crate::{
+        constants::DECIMAL_PRECISION,
+        utils::utils_test::{
+            load_anchor_account, load_program, load_reallocable_account, load_signer,
+            set_program_test_stubs,
+        },
+    };
+    use std::collections::BTreeSet;
+
+    // Layout before the deposit fee
+    const V1_LEN: usize = 8 + StabilityPoolState::INIT_SPACE - 8 - 32;
+
+    fn load_migrate_accounts(
+        creator: Pubkey,
+        sp_state: &StabilityPoolState,
+    ) -> Vec<AccountInfo<'static>> {
+        let pool_state_key = Pubkey::new_unique();
+        let pool_state = PoolState {
+            creator,
+            ..Default::default()
+        };
+        let (sp_state_key, _) =
+            Pubkey::find_program_address(&[b"stability", pool_state_key.as_ref()], &ID);
+        let mut data = Vec::new();
+        sp_state.try_serialize(&mut data).unwrap();
+        data.truncate(V1_LEN);
+
+        vec![
+            load_anchor_account(pool_state_key, &pool_state),
+            load_reallocable_account(sp_state_key, Rent::default().minimum_balance(V1_LEN), &data),
+            load_signer(creator, 1_000_000_000),
+            load_program(System::id()),
+        ]
+    }
+
+    fn migrate(accounts: &[AccountInfo<'static>]) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = MigrateStabilityPoolStateBumps::default();
+        let mut migrate_accounts = MigrateStabilityPoolState::try_accounts(
+            &ID,
+            &mut infos,
+            &[],
+            &mut bumps,
+            &mut BTreeSet::new(),
+        )?;
+        migrate_stability_pool_state_handler(Context::new(&ID, &mut migrate_accounts, &[], bumps))
+    }
+
+    #[test]
+    fn old_layout_grows_with_fee_disabled() {
+        set_program_test_stubs();
+        let creator = Pubkey::new_unique();
+        let sp_state = StabilityPoolState {
+            cvgt: Pubkey::new_unique(),
+            total_usv_deposits: 1_000_000_000,
+            p: DECIMAL_PRECISION.into(),
+            current_scale: 2,
+            last_usv_error_offset: 7,
+            ..Default::default()
+        };
+        let accounts = load_migrate_accounts(creator, &sp_state);
+        let new_len = 8 + StabilityPoolState::INIT_SPACE;
+        let top_up = Rent::default().minimum_balance(new_len) - accounts[1].lamports();
+
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[1].data_len(), new_len);
+        assert_eq!(accounts[2].lamports(), 1_000_000_000 - top_up);
+        assert_eq!(
+            accounts[1].lamports(),
+            Rent::default().minimum_balance(new_len)
+        );
+        let migrated =
+            StabilityPoolState::try_deserialize(&mut &accounts[1].try_borrow_data().unwrap()[..])
+                .unwrap();
+        assert_eq!(migrated.cvgt, sp_state.cvgt);
+        assert_eq!(migrated.total_usv_deposits, sp_state.total_usv_deposits);
+        assert_eq!(migrated.p, sp_state.p);
+        assert_eq!(migrated.current_scale, 2);
+        assert_eq!(migrated.last_usv_error_offset, 7);
+        assert_eq!(migrated.deposit_fee_bps, 0);
+        assert_eq!(migrated.deposit_fee_reserve, Pubkey::default());
+
+        // Already migrated
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[1].data_len(), new_len);
+        assert_eq!(accounts[2].lamports(), 1_000_000_000 - top_up);
+    }
+
+    #[test]
+    fn only_pool_creator_migrates() {
+        set_program_test_stubs();
+        let mut accounts =
+            load_migrate_accounts(Pubkey::new_unique(), &StabilityPoolState::default());
+        accounts[2] = load_signer(Pubkey::new_unique(), 1_000_000_000);
+
+        assert_eq!(
+            migrate(&accounts).unwrap_err(),
+            PoolConfigError::InvalidSigner.into()
+        );
+        assert_eq!(accounts[1].data_len(), V1_LEN);
+    }
+}
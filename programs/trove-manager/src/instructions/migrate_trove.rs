@@ -0,0 +1,183 @@
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{
+    errors::PoolConfigError,
+    state::{PoolState, Trove},
+    utils::realloc_zeroed,
+    ID,
+};
+
+#[derive(Accounts)]
+pub struct MigrateTrove<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    /// CHECK: Only its address is used, to derive `trove`
+    #[account()]
+    pub borrower: UncheckedAccount<'info>,
+
+    /// CHECK: An old layout can't be deserialized as Trove, the discriminator is checked in the handler
+    #[account(
+        mut,
+        owner = ID,
+        seeds = [
+            b"trove",
+            pool_state.key().as_ref(),
+            borrower.key().as_ref(),
+        ],
+        bump
+    )]
+    pub trove: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == borrower.key()
+            || authority.key() == pool_state.creator @ PoolConfigError::InvalidSigner
+    )]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Grows a Trove created with an older layout to the current `INIT_SPACE`, by its borrower or
+/// the pool creator. Its gas compensation reads as held by the pool, and debt increase cooldown
+/// and liquidation grace period as elapsed. Calling it again is a no-op.
+pub fn migrate_trove_handler(ctx: Context<MigrateTrove>) -> Result<()> {
+    let trove = &ctx.accounts.trove;
+    {
+        let data = trove.try_borrow_data()?;
+        require!(
+            data.len() >= 8 && data[..8] == Trove::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+    }
+
+    realloc_zeroed(
+        trove,
+        &ctx.accounts.authority,
+        &ctx.accounts.system_program,
+        8 + Trove::INIT_SPACE,
+    )
+}
+
+#[cfg(test)]
+pub mod migrate_trove_test {
+    use super::*;
+    use crate::{
+        state::TroveStatus,
+        utils::utils_test::{
+            load_account, load_anchor_account, load_program, load_reallocable_account, load_signer,
+            set_program_test_stubs,
+        },
+    };
+    use std::collections::BTreeSet;
+
+    // Layout before the fields appended after `next`
+    const V1_LEN: usize = 8 + 193;
+
+    /// The pool creator, then the accounts of `migrate_trove` signed by the borrower
+    fn load_migrate_accounts(trove: &Trove) -> (Pubkey, Vec<AccountInfo<'static>>) {
+        let creator = Pubkey::new_unique();
+        let borrower = Pubkey::new_unique();
+        let pool_state_key = Pubkey::new_unique();
+        let (trove_key, _) = Pubkey::find_program_address(
+            &[b"trove", pool_state_key.as_ref(), borrower.as_ref()],
+            &ID,
+        );
+        let mut data = Vec::new();
+        trove.try_serialize(&mut data).unwrap();
+        data.truncate(V1_LEN);
+
+        let accounts = vec![
+            load_anchor_account(
+                pool_state_key,
+                &PoolState {
+                    creator,
+                    ..Default::default()
+                },
+            ),
+            load_account(borrower, Pubkey::default(), vec![]),
+            load_reallocable_account(trove_key, Rent::default().minimum_balance(V1_LEN), &data),
+            load_signer(borrower, 1_000_000_000),
+            load_program(System::id()),
+        ];
+        (creator, accounts)
+    }
+
+    fn migrate(accounts: &[AccountInfo<'static>]) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = MigrateTroveBumps::default();
+        let mut migrate_accounts =
+            MigrateTrove::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        migrate_trove_handler(Context::new(&ID, &mut migrate_accounts, &[], bumps))
+    }
+
+    fn load_trove() -> Trove {
+        Trove {
+            pool_state: Pubkey::new_unique(),
+            debt: 2_000,
+            coll: 1_000,
+            stake: 1_000,
+            snapshot_coll_reward: 1,
+            snapshot_debt_reward: 2,
+            status: TroveStatus::Active,
+            next: Pubkey::new_unique(),
+            gas_comp_held_by_borrower: true,
+            coll_only: true,
+            last_debt_increase_time: 60,
+            created_at: 60,
+            gas_comp_owed: 200,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn old_layout_grows_with_pool_held_gas_comp() {
+        set_program_test_stubs();
+        let trove = load_trove();
+        let (_, accounts) = load_migrate_accounts(&trove);
+        let new_len = 8 + Trove::INIT_SPACE;
+        let top_up = Rent::default().minimum_balance(new_len) - accounts[2].lamports();
+
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[2].data_len(), new_len);
+        assert_eq!(accounts[3].lamports(), 1_000_000_000 - top_up);
+        let migrated =
+            Trove::try_deserialize(&mut &accounts[2].try_borrow_data().unwrap()[..]).unwrap();
+        assert_eq!(migrated.pool_state, trove.pool_state);
+        assert_eq!(migrated.debt, 2_000);
+        assert_eq!(migrated.coll, 1_000);
+        assert_eq!(migrated.stake, 1_000);
+        assert_eq!(migrated.snapshot_coll_reward, 1);
+        assert_eq!(migrated.snapshot_debt_reward, 2);
+        assert!(migrated.status == TroveStatus::Active);
+        assert_eq!(migrated.next, trove.next);
+        assert!(!migrated.gas_comp_held_by_borrower);
+        assert!(!migrated.coll_only);
+        assert_eq!(migrated.last_debt_increase_time, 0);
+        assert_eq!(migrated.created_at, 0);
+        assert_eq!(migrated.gas_comp_owed, 0);
+
+        // Already migrated
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[3].lamports(), 1_000_000_000 - top_up);
+    }
+
+    #[test]
+    fn borrower_or_pool_creator_migrates() {
+        set_program_test_stubs();
+        let (creator, mut accounts) = load_migrate_accounts(&load_trove());
+        accounts[3] = load_signer(creator, 1_000_000_000);
+        migrate(&accounts).unwrap();
+        assert_eq!(accounts[2].data_len(), 8 + Trove::INIT_SPACE);
+
+        let (_, mut accounts) = load_migrate_accounts(&load_trove());
+        accounts[3] = load_signer(Pubkey::new_unique(), 1_000_000_000);
+        assert_eq!(
+            migrate(&accounts).unwrap_err(),
+            PoolConfigError::InvalidSigner.into()
+        );
+        assert_eq!(accounts[2].data_len(), V1_LEN);
+    }
+}
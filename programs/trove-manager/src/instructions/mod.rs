@@ -10,12 +10,30 @@ pub use init_trove::*;
 pub mod init_epoch_scale;
 pub use init_epoch_scale::*;
 
+pub mod close_epoch_scale;
+pub use close_epoch_scale::*;
+
 pub mod dev_change_price;
 pub use dev_change_price::*;
 
+pub mod set_secondary_source;
+pub use set_secondary_source::*;
+
+pub mod set_jitosol_max_epoch_lag;
+pub use set_jitosol_max_epoch_lag::*;
+
+pub mod set_max_confidence_rate;
+pub use set_max_confidence_rate::*;
+
 pub mod open_trove;
 pub use open_trove::*;
 
+pub mod open_trove_and_provide_sp;
+pub use open_trove_and_provide_sp::*;
+
+pub mod simulate_open_trove;
+pub use simulate_open_trove::*;
+
 pub mod adjust_trove;
 pub use adjust_trove::*;
 
@@ -25,12 +43,18 @@ pub use close_trove::*;
 pub mod liquidate_trove;
 pub use liquidate_trove::*;
 
+pub mod liquidate_preview;
+pub use liquidate_preview::*;
+
 pub mod batch_liquidate_troves;
 pub use batch_liquidate_troves::*;
 
 pub mod redeem_collateral;
 pub use redeem_collateral::*;
 
+pub mod redeem_from_trove_preview;
+pub use redeem_from_trove_preview::*;
+
 pub mod provide_to_sp;
 pub use provide_to_sp::*;
 
@@ -40,15 +64,78 @@ pub use withdraw_from_sp::*;
 pub mod claim_from_sp;
 pub use claim_from_sp::*;
 
+pub mod provide_and_claim_sp;
+pub use provide_and_claim_sp::*;
+
+pub mod claim_all_sp_gains;
+pub use claim_all_sp_gains::*;
+
+pub mod claim_sp_gains;
+pub use claim_sp_gains::*;
+
+pub mod force_realize_sp_deposit;
+pub use force_realize_sp_deposit::*;
+
+pub mod transfer_sp_deposit;
+pub use transfer_sp_deposit::*;
+
+pub mod close_sp_deposit;
+pub use close_sp_deposit::*;
+
+pub mod sweep_gas_comp_residual;
+pub use sweep_gas_comp_residual::*;
+
+pub mod sp_deposit_preview;
+pub use sp_deposit_preview::*;
+
 pub mod claim_coll_surplus;
 pub use claim_coll_surplus::*;
 
+pub mod close_trove_account;
+pub use close_trove_account::*;
+
+pub mod crank_apply_rewards;
+pub use crank_apply_rewards::*;
+
+pub mod list_troves_page;
+pub use list_troves_page::*;
+
+pub mod check_invariants;
+pub use check_invariants::*;
+
+pub mod reconcile_surplus;
+pub use reconcile_surplus::*;
+
+pub mod close_user_atas;
+pub use close_user_atas::*;
+
 pub mod config_pool_state;
 pub use config_pool_state::*;
 
+pub mod pool_config;
+pub use pool_config::*;
+
+pub mod migrate_pool_state;
+pub use migrate_pool_state::*;
+
 pub mod fetch_price;
 pub use fetch_price::*;
 
+pub mod migrate_price_feed_state;
+pub use migrate_price_feed_state::*;
+
+pub mod migrate_stability_pool_state;
+pub use migrate_stability_pool_state::*;
+
+pub mod migrate_trove;
+pub use migrate_trove::*;
+
+pub mod price_feed_history;
+pub use price_feed_history::*;
+
+pub mod get_tvl;
+pub use get_tvl::*;
+
 pub mod community_issuance;
 pub use community_issuance::*;
 
@@ -9,10 +9,13 @@ use solana_program::program::invoke;
 
 use crate::{
     errors::{BorrowerOpsError, PriceFeedError},
-    events::{Operation, TroveUpdated, USVBorrowingFeePaid},
+    events::{Operation, TroveCreated, TroveReferral, TroveUpdated, USVBorrowingFeePaid},
     math::{compute_cr, compute_nominal_cr},
     state::{CVGTStakingPoolState, CommunityIssuanceConfig, PoolState, PriceFeedState, Trove},
-    utils::{require_user_accepts_fee, require_valid_borrow_max_fee_percentage},
+    utils::{
+        get_current_timestamp, require_min_coll_out, require_stake_account,
+        require_user_accepts_fee, require_valid_borrow_max_fee_percentage,
+    },
 };
 
 #[derive(Accounts)]
@@ -82,6 +85,13 @@ pub struct OpenTrove<'info> {
     )]
     pub stablecoin_cvgt_staking_vault: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = pool_state.pre_launch_fee_treasury
+    )]
+    pub pre_launch_fee_treasury_ata: Option<Box<Account<'info, TokenAccount>>>,
+
     #[account(
         mut,
         constraint = stablecoin.key() == pool_state.stablecoin
@@ -141,25 +151,26 @@ pub struct OpenTrove<'info> {
         constraint = jitosol_stake_pool.key() == price_feed_state.jitosol_stake_pool @ PriceFeedError::JitoSolStakePoolWrong
     )]
     pub jitosol_stake_pool: UncheckedAccount<'info>,
+    // Stake pool accounts below are only needed to deposit SOL (is_lamport)
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub jitosol_stake_withdraw_authority: UncheckedAccount<'info>,
+    pub jitosol_stake_withdraw_authority: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub reserve_stake_account: UncheckedAccount<'info>,
+    pub reserve_stake_account: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub manager_fee: UncheckedAccount<'info>,
+    pub manager_fee: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(mut)]
-    pub referrer_fee: UncheckedAccount<'info>,
+    pub referrer_fee: Option<UncheckedAccount<'info>>,
     /// CHECK: This account will check by StakeProgram
     #[account(
         mut,
         constraint = stake_program.key() == spl_stake_pool::ID
 
     )]
-    pub stake_program: UncheckedAccount<'info>,
+    pub stake_program: Option<UncheckedAccount<'info>>,
 
     #[account(
         constraint = chainlink_program.key() == chainlink_solana::ID
@@ -212,12 +223,12 @@ impl<'info> OpenTrove<'info> {
         CpiContext::new(cpi_program, cpi_accounts)
     }
 
-    pub fn mint_stablecoin_to_staking_pool_ctx(
+    pub fn mint_stablecoin_to_borrower_gas_compensation_ctx(
         &self,
     ) -> CpiContext<'_, '_, '_, 'info, MintTo<'info>> {
         let cpi_accounts = MintTo {
             mint: self.stablecoin.to_account_info(),
-            to: self.stablecoin_cvgt_staking_vault.to_account_info(),
+            to: self.stablecoin_receive_account.to_account_info(),
             authority: self.token_authority.to_account_info(),
         };
 
@@ -226,6 +237,30 @@ impl<'info> OpenTrove<'info> {
         CpiContext::new(cpi_program, cpi_accounts)
     }
 
+    /// The USV fee goes to the pre-launch treasury when `to_treasury`, otherwise to the staking vault
+    pub fn mint_stablecoin_fee_ctx(
+        &self,
+        to_treasury: bool,
+    ) -> Result<CpiContext<'_, '_, '_, 'info, MintTo<'info>>> {
+        let to = if to_treasury {
+            self.pre_launch_fee_treasury_ata
+                .as_ref()
+                .ok_or(BorrowerOpsError::MissingFeeTreasury)?
+                .to_account_info()
+        } else {
+            self.stablecoin_cvgt_staking_vault.to_account_info()
+        };
+        let cpi_accounts = MintTo {
+            mint: self.stablecoin.to_account_info(),
+            to,
+            authority: self.token_authority.to_account_info(),
+        };
+
+        let cpi_program = self.token_program.to_account_info();
+
+        Ok(CpiContext::new(cpi_program, cpi_accounts))
+    }
+
     pub fn increase_f_usv(&self, usv_fee: u64) -> Result<()> {
         let cvgt_staking_account = &self.cvgt_staking_state;
         let mut data = cvgt_staking_account.try_borrow_mut_data()?;
@@ -242,12 +277,13 @@ impl<'info> OpenTrove<'info> {
         let collateral_vault = &mut self.collateral_vault;
         let creator = &self.creator;
         let collateral = &self.collateral;
-        let stake_program = &self.stake_program;
+        let stake_program = require_stake_account(&self.stake_program)?;
         let jitosol_stake_pool = &self.jitosol_stake_pool;
-        let reserve_stake_account = &self.reserve_stake_account;
-        let jitosol_stake_withdraw_authority = &self.jitosol_stake_withdraw_authority;
-        let manager_fee = &self.manager_fee;
-        let referrer_fee = &self.referrer_fee;
+        let reserve_stake_account = require_stake_account(&self.reserve_stake_account)?;
+        let jitosol_stake_withdraw_authority =
+            require_stake_account(&self.jitosol_stake_withdraw_authority)?;
+        let manager_fee = require_stake_account(&self.manager_fee)?;
+        let referrer_fee = require_stake_account(&self.referrer_fee)?;
 
         let balance_before = collateral_vault.amount;
         let ix = spl_stake_pool::instruction::deposit_sol(
@@ -292,9 +328,13 @@ pub fn open_trove_handler(
     is_lamport: bool,
     amt: u64,
     usv_amt: u64,
+    min_coll_out: u64,
+    referral: Option<Pubkey>,
 ) -> Result<()> {
     let coll_amt = if is_lamport {
-        ctx.accounts.stake_sol_for_jitosol(amt)?
+        let coll_out = ctx.accounts.stake_sol_for_jitosol(amt)?;
+        require_min_coll_out(coll_out, min_coll_out)?;
+        coll_out
     } else {
         amt
     };
@@ -313,10 +353,22 @@ pub fn open_trove_handler(
         &ctx.accounts.jitosol_stake_pool,
         &ctx.accounts.pyth_feed_account,
     )?;
+    emit!(ctx.accounts.price_feed_state.price_used(price));
+    require_can_open_trove(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.price_feed_state,
+        usv_amt,
+    )?;
 
     let trove = &mut ctx.accounts.trove;
     let pool_state = &mut ctx.accounts.pool_state;
-    let gas_compensation = pool_state.gas_compensation;
+    // Collateral-only troves mint no USV and hold no gas compensation
+    let coll_only = usv_amt == 0;
+    let gas_compensation = if coll_only {
+        0
+    } else {
+        pool_state.gas_compensation
+    };
 
     // Check is recovery
     let is_recovery_mode = pool_state.check_recovery_mode(price);
@@ -324,39 +376,31 @@ pub fn open_trove_handler(
     require_valid_borrow_max_fee_percentage(max_fee_percentage, is_recovery_mode)?;
     // Require trove is not active
     trove.require_trove_not_active()?;
+    trove.require_no_gas_comp_owed()?;
 
     // Calculate debt
     let mut usv_fee = 0u64;
     let mut net_debt = usv_amt;
-    if !is_recovery_mode {
-        usv_fee = trigger_borrowing_fee(pool_state, usv_amt, max_fee_percentage)?;
+    if !is_recovery_mode && !coll_only {
+        usv_fee = trigger_borrowing_fee(pool_state, usv_amt, max_fee_percentage, price)?;
         net_debt = net_debt.checked_add(usv_fee).unwrap();
     }
 
-    // Require min debt
-    pool_state.require_at_least_min_net_debt(net_debt)?;
-
-    // ICR is based on the composite debt, i.e. the requested USV amount + USV borrowing fee + USV gas comp.
-    let composite_debt = pool_state.get_composit_debt(net_debt);
-    require!(composite_debt > 0, BorrowerOpsError::Calculation);
-
-    let icr = compute_cr(coll_amt, composite_debt, price).unwrap();
-
-    let nicr = compute_nominal_cr(coll_amt, composite_debt).unwrap();
-
-    if is_recovery_mode {
-        pool_state.require_icr_is_above_ccr(icr)?;
-    } else {
-        pool_state.require_icr_is_above_mcr(icr)?;
-        let new_tcr =
-            pool_state.get_new_tcr_from_trove_change(coll_amt, true, composite_debt, true, price); // bools: coll increase, debt increase
-        pool_state.require_new_tcr_is_above_ccr(new_tcr)?;
-    }
+    let OpenTroveValues {
+        composite_debt,
+        nicr,
+        ..
+    } = get_open_trove_values(pool_state, coll_amt, net_debt, price, is_recovery_mode)?;
 
     // Set the trove struct's properties
     trove.init(pool_state.key(), *creator, coll_amt, composite_debt);
+    trove.gas_comp_held_by_borrower = pool_state.gas_comp_to_borrower;
+    trove.coll_only = coll_only;
+    let current_timestamp = get_current_timestamp();
+    trove.last_debt_increase_time = if coll_only { 0 } else { current_timestamp };
+    trove.created_at = current_timestamp;
     trove.update_reward_snapshot(pool_state);
-    let stake = trove.update_stake_and_total_stakes(pool_state);
+    trove.update_stake_and_total_stakes(pool_state);
 
     trove.insert_sorted(trove_key, nicr, prev_trove, next_trove, pool_state)?;
 
@@ -374,9 +418,11 @@ pub fn open_trove_handler(
     pool_state.increase_active_debt(net_debt);
     pool_state.increase_active_debt(gas_compensation);
 
+    let fee_to_treasury = usv_fee > 0
+        && pool_state.routes_usv_fee_to_treasury(community_issuance_config.enable_emission);
     mint_to(
         ctx.accounts
-            .mint_stablecoin_to_staking_pool_ctx()
+            .mint_stablecoin_fee_ctx(fee_to_treasury)?
             .with_signer(&[&authority_seed[..]]),
         usv_fee,
     )?;
@@ -400,24 +446,37 @@ pub fn open_trove_handler(
         )?;
     }
 
-    mint_to(
+    let gas_compensation_ctx = if ctx.accounts.trove.gas_comp_held_by_borrower {
         ctx.accounts
-            .mint_stablecoin_to_gas_compensation_ctx()
-            .with_signer(&[&authority_seed[..]]),
-        gas_compensation,
-    )?;
+            .mint_stablecoin_to_borrower_gas_compensation_ctx()
+    } else {
+        ctx.accounts.mint_stablecoin_to_gas_compensation_ctx()
+    };
+    if gas_compensation > 0 {
+        mint_to(
+            gas_compensation_ctx.with_signer(&[&authority_seed[..]]),
+            gas_compensation,
+        )?;
+    }
 
-    emit!(TroveUpdated {
-        borrower: creator.key(),
-        debt: composite_debt,
-        coll: coll_amt,
-        stake,
-        operation: Operation::OpenTrove
-    });
+    let (trove_created, trove_updated) = get_open_trove_events(
+        *creator,
+        &ctx.accounts.trove,
+        ctx.accounts.pool_state.next_event_nonce(),
+    );
+    emit!(trove_created);
+    emit!(trove_updated);
+    emit!(ctx
+        .accounts
+        .trove
+        .icr_updated(&ctx.accounts.pool_state, price, Operation::OpenTrove));
     emit!(USVBorrowingFeePaid {
         borrower: creator.key(),
         usv_fee
     });
+    if let Some(event) = get_referral_event(*creator, referral) {
+        emit!(event);
+    }
 
     Ok(())
 }
@@ -426,11 +485,263 @@ fn trigger_borrowing_fee(
     pool_state: &mut PoolState,
     usv_amt: u64,
     max_fee_percentage: u64,
+    price: u64,
 ) -> Result<u64> {
     pool_state.decay_base_rate_from_borrowing()?;
-    let usv_fee = pool_state.get_borrowing_fee(usv_amt);
+    let usv_fee = pool_state.get_borrowing_fee(usv_amt, price);
 
     require_user_accepts_fee(usv_fee, usv_amt, max_fee_percentage)?;
 
     Ok(usv_fee)
 }
+
+/// Checks of `open_trove` that don't depend on the trove account, shared with `simulate_open_trove`
+pub fn require_can_open_trove(
+    pool_state: &PoolState,
+    price_feed_state: &PriceFeedState,
+    usv_amt: u64,
+) -> Result<()> {
+    if usv_amt > 0 {
+        price_feed_state.require_trusted_price_for_borrow()?;
+    }
+    pool_state.require_trove_count_below_cap()
+}
+
+/// Values of a trove opened with `coll_amt` and `net_debt`, fee included
+pub struct OpenTroveValues {
+    pub composite_debt: u64,
+    pub icr: u64,
+    pub nicr: u64,
+    pub new_tcr: u64,
+}
+
+/// Debt and collateral ratio checks shared by `open_trove` and `simulate_open_trove`
+pub fn get_open_trove_values(
+    pool_state: &PoolState,
+    coll_amt: u64,
+    net_debt: u64,
+    price: u64,
+    is_recovery_mode: bool,
+) -> Result<OpenTroveValues> {
+    pool_state.require_trove_coll_within_cap(coll_amt)?;
+
+    // Collateral-only trove, its ICR is infinite and it sits at the head of the sorted list
+    if net_debt == 0 {
+        require!(coll_amt > 0, BorrowerOpsError::ZeroAdjustment);
+        return Ok(OpenTroveValues {
+            composite_debt: 0,
+            icr: u64::MAX,
+            nicr: u64::MAX,
+            new_tcr: pool_state.get_new_tcr_from_trove_change(coll_amt, true, 0, true, price),
+        });
+    }
+
+    // Require min debt
+    pool_state.require_at_least_min_net_debt(net_debt)?;
+
+    // ICR is based on the composite debt, i.e. the requested USV amount + USV borrowing fee + USV gas comp.
+    let composite_debt = pool_state.get_composit_debt(net_debt);
+    require!(composite_debt > 0, BorrowerOpsError::Calculation);
+    pool_state.require_system_debt_within_cap(composite_debt)?;
+
+    let icr = compute_cr(coll_amt, composite_debt, price).ok_or(BorrowerOpsError::Calculation)?;
+
+    // Saturated for a huge collateral to debt ratio, zero would put the trove behind the tail
+    let nicr = compute_nominal_cr(coll_amt, composite_debt).unwrap();
+    require!(nicr > 0, BorrowerOpsError::NICRZero);
+
+    let new_tcr =
+        pool_state.get_new_tcr_from_trove_change(coll_amt, true, composite_debt, true, price); // bools: coll increase, debt increase
+    if is_recovery_mode {
+        pool_state.require_icr_is_above_ccr(icr)?;
+    } else {
+        pool_state.require_icr_is_above_mcr(icr)?;
+        pool_state.require_new_tcr_is_above_ccr(new_tcr)?;
+    }
+
+    Ok(OpenTroveValues {
+        composite_debt,
+        icr,
+        nicr,
+        new_tcr,
+    })
+}
+
+/// An opened trove is reported by both events, its adjustments by `TroveUpdated` alone
+fn get_open_trove_events(
+    borrower: Pubkey,
+    trove: &Trove,
+    event_nonce: u64,
+) -> (TroveCreated, TroveUpdated) {
+    (
+        TroveCreated { borrower },
+        TroveUpdated {
+            borrower,
+            debt: trove.debt,
+            coll: trove.coll,
+            stake: trove.stake,
+            operation: Operation::OpenTrove,
+            event_nonce,
+        },
+    )
+}
+
+/// Referrals are only attributed off-chain, no fee is split on-chain
+fn get_referral_event(borrower: Pubkey, referral: Option<Pubkey>) -> Option<TroveReferral> {
+    referral.map(|referral| TroveReferral { borrower, referral })
+}
+
+#[cfg(test)]
+pub mod open_trove_test {
+    use super::*;
+
+    #[test]
+    fn referral_event_only_when_provided() {
+        let borrower = Pubkey::new_unique();
+        let referral = Pubkey::new_unique();
+
+        let event = get_referral_event(borrower, Some(referral)).unwrap();
+        assert_eq!(event.borrower, borrower);
+        assert_eq!(event.referral, referral);
+
+        assert!(get_referral_event(borrower, None).is_none());
+    }
+
+    #[test]
+    fn open_reports_created_and_updated() {
+        let borrower = Pubkey::new_unique();
+        let mut trove = Trove::default();
+        trove.init(
+            Pubkey::new_unique(),
+            borrower,
+            10_000_000_000,
+            510_000_000_000,
+        );
+        trove.stake = 10_000_000_000;
+
+        let (trove_created, trove_updated) = get_open_trove_events(borrower, &trove, 4);
+        assert_eq!(trove_created.borrower, borrower);
+        assert_eq!(trove_updated.borrower, borrower);
+        assert_eq!(trove_updated.debt, 510_000_000_000);
+        assert_eq!(trove_updated.coll, 10_000_000_000);
+        assert_eq!(trove_updated.stake, 10_000_000_000);
+        assert!(matches!(trove_updated.operation, Operation::OpenTrove));
+        assert_eq!(trove_updated.event_nonce, 4);
+    }
+
+    #[test]
+    fn open_trove_coll_capped() {
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 100_000_000_000,
+            gas_compensation: 10_000_000_000,
+            max_trove_coll: 10_000_000_000,
+            ..Default::default()
+        };
+        let price = 100_000_000_000;
+
+        assert!(
+            get_open_trove_values(&pool_state, 10_000_000_000, 500_000_000_000, price, false)
+                .is_ok()
+        );
+        assert_eq!(
+            get_open_trove_values(&pool_state, 10_000_000_001, 500_000_000_000, price, false)
+                .err()
+                .unwrap(),
+            BorrowerOpsError::TroveCollAboveMax.into()
+        );
+    }
+
+    #[test]
+    fn extreme_coll_tiny_debt_trove() {
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 1_000_000_000,
+            ..Default::default()
+        };
+        let coll_amt = 300_000_000_000_000_000;
+        let net_debt = 1_000_000_000;
+
+        // The NICR overflows u64 and saturates, the ICR at 50 still fits
+        let values =
+            get_open_trove_values(&pool_state, coll_amt, net_debt, 50_000_000_000, false).unwrap();
+        assert_eq!(values.nicr, u64::MAX);
+        assert_eq!(values.icr, 15_000_000_000_000_000_000);
+
+        // At 100 the ICR overflows too
+        assert_eq!(
+            get_open_trove_values(&pool_state, coll_amt, net_debt, 100_000_000_000, false)
+                .err()
+                .unwrap(),
+            BorrowerOpsError::Calculation.into()
+        );
+
+        // Debt without collateral
+        assert_eq!(
+            get_open_trove_values(&pool_state, 0, net_debt, 50_000_000_000, false)
+                .err()
+                .unwrap(),
+            BorrowerOpsError::NICRZero.into()
+        );
+    }
+
+    #[test]
+    fn coll_only_trove_skips_debt_checks() {
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 100_000_000_000,
+            gas_compensation: 10_000_000_000,
+            active_coll: 10_000_000_000,
+            active_debt: 900_000_000_000,
+            max_system_debt: 900_000_000_000,
+            ..Default::default()
+        };
+        let price = 100_000_000_000;
+
+        // Min net debt, gas compensation and the system debt cap don't apply
+        let values = get_open_trove_values(&pool_state, 5_000_000_000, 0, price, false).unwrap();
+        assert_eq!(values.composite_debt, 0);
+        assert_eq!(values.icr, u64::MAX);
+        assert_eq!(values.nicr, u64::MAX);
+        assert_eq!(values.new_tcr, 1_666_666_666);
+
+        assert_eq!(
+            get_open_trove_values(&pool_state, 0, 0, price, false)
+                .err()
+                .unwrap(),
+            BorrowerOpsError::ZeroAdjustment.into()
+        );
+    }
+
+    #[test]
+    fn open_trove_system_debt_capped() {
+        let mut pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 100_000_000_000,
+            gas_compensation: 10_000_000_000,
+            active_debt: 400_000_000_000,
+            active_coll: 10_000_000_000,
+            max_system_debt: 900_000_000_000,
+            ..Default::default()
+        };
+        let price = 100_000_000_000;
+
+        // 400 + 500 + 10 of gas compensation crosses the cap
+        assert_eq!(
+            get_open_trove_values(&pool_state, 10_000_000_000, 500_000_000_000, price, false)
+                .err()
+                .unwrap(),
+            BorrowerOpsError::SystemDebtAboveMax.into()
+        );
+
+        pool_state.max_system_debt = 910_000_000_000;
+        assert!(
+            get_open_trove_values(&pool_state, 10_000_000_000, 500_000_000_000, price, false)
+                .is_ok()
+        );
+    }
+}
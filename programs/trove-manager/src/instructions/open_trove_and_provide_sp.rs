@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+
+// The nested accounts need the modules and bumps generated next to `OpenTrove` and `ProvideToSP`
+use crate::{
+    errors::StabilityPoolError,
+    instructions::{open_trove::*, provide_to_sp::*},
+};
+
+#[derive(Accounts)]
+pub struct OpenTroveAndProvideSP<'info> {
+    pub open_trove: OpenTrove<'info>,
+    pub provide_to_sp: ProvideToSP<'info>,
+}
+
+/// Opens a trove and provides `sp_usv_amt` of the minted USV to the stability pool.
+/// The rest of the minted USV stays in the borrower's ATA.
+/// `remaining_accounts` holds the epoch-scales `provide_to_sp` needs.
+pub fn open_trove_and_provide_sp_handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, OpenTroveAndProvideSP<'info>>,
+    max_fee_percentage: u64,
+    is_lamport: bool,
+    amt: u64,
+    usv_amt: u64,
+    min_coll_out: u64,
+    referral: Option<Pubkey>,
+    sp_usv_amt: u64,
+) -> Result<()> {
+    let Context {
+        program_id,
+        accounts,
+        remaining_accounts,
+        bumps,
+    } = ctx;
+
+    require_keys_eq!(
+        accounts.open_trove.creator.key(),
+        accounts.provide_to_sp.depositor.key()
+    );
+    get_usv_left_after_provide(usv_amt, sp_usv_amt)?;
+
+    open_trove_handler(
+        Context::new(
+            program_id,
+            &mut accounts.open_trove,
+            remaining_accounts,
+            bumps.open_trove,
+        ),
+        max_fee_percentage,
+        is_lamport,
+        amt,
+        usv_amt,
+        min_coll_out,
+        referral,
+    )?;
+
+    provide_to_sp_handler(
+        Context::new(
+            program_id,
+            &mut accounts.provide_to_sp,
+            remaining_accounts,
+            bumps.provide_to_sp,
+        ),
+        sp_usv_amt,
+    )
+}
+
+/// USV left in the borrower's ATA once `sp_usv_amt` of the minted `usv_amt` is provided
+pub fn get_usv_left_after_provide(usv_amt: u64, sp_usv_amt: u64) -> Result<u64> {
+    require!(sp_usv_amt > 0, StabilityPoolError::ZeroAmount);
+    require!(
+        sp_usv_amt <= usv_amt,
+        StabilityPoolError::ProvideAboveMinted
+    );
+    Ok(usv_amt - sp_usv_amt)
+}
+
+#[cfg(test)]
+pub mod open_trove_and_provide_sp_test {
+    use super::*;
+    use crate::{
+        constants::DECIMAL_PRECISION,
+        instructions::get_open_trove_values,
+        state::{
+            EpochScale, PoolState, StabilityPoolDeposit, StabilityPoolState, Trove, TroveStatus,
+        },
+    };
+
+    #[test]
+    fn opened_trove_and_sp_deposit_in_one_call() {
+        let pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 100_000_000_000,
+            gas_compensation: 10_000_000_000,
+            ..Default::default()
+        };
+        let mut sp_state = StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            ..Default::default()
+        };
+        let price = 100_000_000_000;
+        let borrower = Pubkey::new_unique();
+        let usv_amt = 500_000_000_000;
+        let sp_usv_amt = 300_000_000_000;
+
+        let values =
+            get_open_trove_values(&pool_state, 10_000_000_000, usv_amt, price, false).unwrap();
+        let mut trove = Trove::default();
+        trove.init(
+            Pubkey::new_unique(),
+            borrower,
+            10_000_000_000,
+            values.composite_debt,
+        );
+        assert!(trove.status == TroveStatus::Active);
+
+        let left = get_usv_left_after_provide(usv_amt, sp_usv_amt).unwrap();
+        let mut deposit = StabilityPoolDeposit::default();
+        deposit
+            .provide(
+                &sp_state,
+                &[EpochScale::default(), EpochScale::default()],
+                &EpochScale::default(),
+                borrower,
+                sp_usv_amt,
+            )
+            .unwrap();
+        sp_state.increase_usv(sp_usv_amt);
+
+        assert_eq!(deposit.initial_value, sp_usv_amt);
+        assert_eq!(
+            deposit.get_compounded_usv_deposit(&sp_state).unwrap(),
+            sp_usv_amt
+        );
+        assert_eq!(sp_state.total_usv_deposits, sp_usv_amt);
+        assert_eq!(left, 200_000_000_000);
+
+        assert_eq!(
+            get_usv_left_after_provide(usv_amt, usv_amt + 1).unwrap_err(),
+            StabilityPoolError::ProvideAboveMinted.into()
+        );
+        assert_eq!(
+            get_usv_left_after_provide(usv_amt, 0).unwrap_err(),
+            StabilityPoolError::ZeroAmount.into()
+        );
+    }
+}
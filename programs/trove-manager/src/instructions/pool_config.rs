@@ -0,0 +1,236 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::RiskParamsUpdated,
+    state::{PoolState, StabilityPoolState},
+};
+
+#[derive(Accounts)]
+pub struct UpdatePoolConfig<'info> {
+    #[account(mut)]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        constraint = creator.key() == pool_state.creator
+    )]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSPConfig<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        constraint = creator.key() == pool_state.creator
+    )]
+    pub creator: Signer<'info>,
+}
+
+pub fn set_redemption_fee_treasury_bps_handler(
+    ctx: Context<UpdatePoolConfig>,
+    bps: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.set_redemption_fee_treasury_bps(bps)
+}
+
+pub fn update_risk_params_handler(
+    ctx: Context<UpdatePoolConfig>,
+    mcr: u64,
+    ccr: u64,
+    min_net_debt: u64,
+) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state;
+    pool_state.update_risk_params(mcr, ccr, min_net_debt)?;
+
+    emit!(RiskParamsUpdated {
+        pool_state: pool_state.key(),
+        mcr,
+        ccr,
+        min_net_debt,
+    });
+    Ok(())
+}
+
+/// `band` is the TCR range above the CCR the surcharge applies in
+pub fn set_recovery_proximity_surcharge_handler(
+    ctx: Context<UpdatePoolConfig>,
+    bps: u64,
+    band: u64,
+) -> Result<()> {
+    ctx.accounts
+        .pool_state
+        .set_recovery_proximity_surcharge(bps, band)
+}
+
+pub fn set_gas_compensation_handler(
+    ctx: Context<UpdatePoolConfig>,
+    gas_compensation: u64,
+) -> Result<()> {
+    ctx.accounts
+        .pool_state
+        .set_gas_compensation(gas_compensation)
+}
+
+/// Zero `bps` disables the deposit fee, a non-zero one needs the `reserve` owning the fee ATA
+pub fn set_sp_deposit_fee_handler(
+    ctx: Context<UpdateSPConfig>,
+    bps: u64,
+    reserve: Pubkey,
+) -> Result<()> {
+    ctx.accounts
+        .stability_pool_state
+        .set_deposit_fee(bps, reserve)
+}
+
+/// Zero disables a bound
+pub fn set_coll_gas_comp_bounds_handler(
+    ctx: Context<UpdatePoolConfig>,
+    min_coll_gas_comp: u64,
+    max_coll_gas_comp: u64,
+) -> Result<()> {
+    ctx.accounts
+        .pool_state
+        .set_coll_gas_comp_bounds(min_coll_gas_comp, max_coll_gas_comp)
+}
+
+pub fn set_liquidation_bonus_bps_handler(ctx: Context<UpdatePoolConfig>, bps: u64) -> Result<()> {
+    ctx.accounts.pool_state.set_liquidation_bonus_bps(bps)
+}
+
+/// The SP only keeps the remainder when it offsets debt, otherwise the liquidator gets everything
+pub fn set_liquidator_gas_comp_bps_handler(ctx: Context<UpdatePoolConfig>, bps: u64) -> Result<()> {
+    ctx.accounts.pool_state.set_liquidator_gas_comp_bps(bps)
+}
+
+/// Only affects troves opened afterwards, existing troves keep the mode they were opened with
+pub fn set_gas_comp_to_borrower_handler(
+    ctx: Context<UpdatePoolConfig>,
+    gas_comp_to_borrower: bool,
+) -> Result<()> {
+    ctx.accounts.pool_state.gas_comp_to_borrower = gas_comp_to_borrower;
+    Ok(())
+}
+
+/// Withdrawals and liquidation offsets keep working while deposits are disabled
+pub fn set_sp_deposits_disabled_handler(
+    ctx: Context<UpdatePoolConfig>,
+    sp_deposits_disabled: bool,
+) -> Result<()> {
+    ctx.accounts.pool_state.sp_deposits_disabled = sp_deposits_disabled;
+    Ok(())
+}
+
+/// Existing troves above the cap are kept, they can only stop adding collateral
+pub fn set_max_trove_coll_handler(
+    ctx: Context<UpdatePoolConfig>,
+    max_trove_coll: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.max_trove_coll = max_trove_coll;
+    Ok(())
+}
+
+/// Only limits new debt, repayments and redemptions are allowed above the cap
+pub fn set_max_system_debt_handler(
+    ctx: Context<UpdatePoolConfig>,
+    max_system_debt: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.max_system_debt = max_system_debt;
+    Ok(())
+}
+
+/// Enable once no equal NICR pair of the sorted list is out of pubkey order, redemptions
+/// through such a pair are rejected afterwards
+pub fn set_nicr_tie_break_enforced_handler(
+    ctx: Context<UpdatePoolConfig>,
+    nicr_tie_break_enforced: bool,
+) -> Result<()> {
+    ctx.accounts.pool_state.nicr_tie_break_enforced = nicr_tie_break_enforced;
+    Ok(())
+}
+
+/// Only limits new troves, a cap below the current count keeps the existing ones
+pub fn set_max_troves_handler(ctx: Context<UpdatePoolConfig>, max_troves: u64) -> Result<()> {
+    ctx.accounts.pool_state.max_troves = max_troves;
+    Ok(())
+}
+
+/// Borrowing fees are minted to this owner's USV ATA while CVGT emission is disabled,
+/// `Pubkey::default()` keeps them in the staking vault
+pub fn set_pre_launch_fee_treasury_handler(
+    ctx: Context<UpdatePoolConfig>,
+    pre_launch_fee_treasury: Pubkey,
+) -> Result<()> {
+    ctx.accounts.pool_state.pre_launch_fee_treasury = pre_launch_fee_treasury;
+    Ok(())
+}
+
+pub fn set_redemption_hint_tolerance_bps_handler(
+    ctx: Context<UpdatePoolConfig>,
+    bps: u64,
+) -> Result<()> {
+    ctx.accounts
+        .pool_state
+        .set_redemption_hint_tolerance_bps(bps)
+}
+
+pub fn set_max_redemption_fraction_bps_handler(
+    ctx: Context<UpdatePoolConfig>,
+    bps: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.set_max_redemption_fraction_bps(bps)
+}
+
+pub fn set_minute_decay_factor_handler(
+    ctx: Context<UpdatePoolConfig>,
+    minute_decay_factor: u64,
+) -> Result<()> {
+    ctx.accounts
+        .pool_state
+        .set_minute_decay_factor(minute_decay_factor)
+}
+
+/// Opening a trove starts the cooldown, repayments and collateral changes are never limited
+pub fn set_debt_increase_cooldown_handler(
+    ctx: Context<UpdatePoolConfig>,
+    debt_increase_cooldown: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.debt_increase_cooldown = debt_increase_cooldown;
+    Ok(())
+}
+
+/// Tighter bound than the oracle timeouts on the price age redemptions accept
+pub fn set_redemption_max_price_age_handler(
+    ctx: Context<UpdatePoolConfig>,
+    redemption_max_price_age: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.redemption_max_price_age = redemption_max_price_age;
+    Ok(())
+}
+
+/// Only applies in normal mode, troves under 100% ICR stay liquidatable
+pub fn set_liquidation_grace_period_handler(
+    ctx: Context<UpdatePoolConfig>,
+    liquidation_grace_period: u64,
+) -> Result<()> {
+    ctx.accounts.pool_state.liquidation_grace_period = liquidation_grace_period;
+    Ok(())
+}
+
+pub fn set_self_liquidation_allowed_handler(
+    ctx: Context<UpdatePoolConfig>,
+    self_liquidation_allowed: bool,
+) -> Result<()> {
+    ctx.accounts.pool_state.self_liquidation_allowed = self_liquidation_allowed;
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{PriceFeedState, StatusTransition};
+
+#[derive(Accounts)]
+pub struct GetPriceFeedHistory<'info> {
+    pub price_feed_state: Account<'info, PriceFeedState>,
+}
+
+/// Read-only: last status transitions of the price feed, oldest first
+pub fn price_feed_history_handler(
+    ctx: Context<GetPriceFeedHistory>,
+) -> Result<Vec<StatusTransition>> {
+    Ok(ctx.accounts.price_feed_state.get_status_history())
+}
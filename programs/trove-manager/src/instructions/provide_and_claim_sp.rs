@@ -0,0 +1,245 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::{Mint, Token, TokenAccount},
+    token_2022::{transfer_checked, TransferChecked},
+};
+
+use crate::{
+    instructions::{provide_usv_to_sp, transfer_sp_deposit_fee},
+    state::{
+        CommunityIssuanceConfig, EpochScale, PoolState, StabilityPoolDeposit, StabilityPoolState,
+    },
+};
+
+#[derive(Accounts)]
+pub struct ProvideAndClaimSP<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + StabilityPoolDeposit::INIT_SPACE,
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            depositor.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"epoch-scale",
+            stability_pool_state.key().as_ref(),
+            stability_pool_state.current_epoch.to_le_bytes().as_ref(),
+            stability_pool_state.current_scale.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub current_epoch_scale: Box<Account<'info, EpochScale>>,
+
+    #[account(
+        mut,
+        constraint = stablecoin.key() == pool_state.stablecoin
+    )]
+    pub stablecoin: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = stability_pool_state
+    )]
+    pub sp_usv_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = depositor
+    )]
+    pub depositor_stablecoin_ata: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = stability_pool_state.deposit_fee_reserve
+    )]
+    pub sp_fee_reserve_ata: Option<Box<Account<'info, TokenAccount>>>,
+
+    #[account(
+        constraint = collateral.key() == pool_state.collateral
+    )]
+    pub collateral: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = collateral,
+        associated_token::authority = stability_pool_state
+    )]
+    pub sp_coll_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = collateral,
+        associated_token::authority = depositor,
+    )]
+    pub depositor_coll_ata: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        associated_token::mint = cvgt,
+        associated_token::authority = depositor,
+    )]
+    pub depositor_cvgt_ata: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = cvgt.key() == pool_state.cvgt
+    )]
+    pub cvgt: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = community_issuance_config.cvgt,
+        associated_token::authority = community_issuance_config
+    )]
+    pub community_issuance_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"community-issuance",
+            pool_state.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub community_issuance_config: Box<Account<'info, CommunityIssuanceConfig>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ProvideAndClaimSP<'info> {
+    pub fn transfer_usv_in(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.depositor_stablecoin_ata.to_account_info(),
+            to: self.sp_usv_vault.to_account_info(),
+            authority: self.depositor.to_account_info(),
+            mint: self.stablecoin.to_account_info(),
+        };
+
+        let cpi_program = self.token_program.to_account_info();
+
+        transfer_checked(
+            CpiContext::new(cpi_program, cpi_accounts),
+            amount,
+            self.stablecoin.decimals,
+        )
+    }
+
+    pub fn send_cvgt_to_depositor(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let cpi_accounts = TransferChecked {
+            from: self.community_issuance_vault.to_account_info(),
+            to: self.depositor_cvgt_ata.to_account_info(),
+            authority: self.community_issuance_config.to_account_info(),
+            mint: self.cvgt.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
+        let auth_seed = &self.community_issuance_config.seeds();
+
+        transfer_checked(
+            cpi_context.with_signer(&[auth_seed]),
+            amount,
+            self.cvgt.decimals,
+        )
+    }
+
+    pub fn transfer_coll_out(&self, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let pool_state_key = self.pool_state.key();
+        let auth_seed = &self.pool_state.stability_pool_seeds(&pool_state_key);
+
+        let cpi_accounts = TransferChecked {
+            from: self.sp_coll_vault.to_account_info(),
+            to: self.depositor_coll_ata.to_account_info(),
+            authority: self.stability_pool_state.to_account_info(),
+            mint: self.collateral.to_account_info(),
+        };
+
+        let cpi_program = self.token_program.to_account_info();
+
+        transfer_checked(
+            CpiContext::new(cpi_program, cpi_accounts).with_signer(&[auth_seed]),
+            amount,
+            self.collateral.decimals,
+        )
+    }
+}
+
+/// `provide_to_sp` followed by `claim_from_sp` in a single instruction
+pub fn provide_and_claim_sp_handler(
+    mut ctx: Context<ProvideAndClaimSP>,
+    usv_amt: u64,
+) -> Result<()> {
+    let depositor = ctx.accounts.depositor.key();
+    let accounts = &mut ctx.accounts;
+    let fee = provide_usv_to_sp(
+        ctx.remaining_accounts,
+        &accounts.pool_state,
+        &mut accounts.community_issuance_config,
+        Some(accounts.community_issuance_vault.amount),
+        &mut accounts.stability_pool_state,
+        &mut accounts.stability_pool_deposit,
+        &mut accounts.current_epoch_scale,
+        depositor,
+        usv_amt,
+    )?;
+
+    // Transfer the fee to the reserve and the rest of the USV to pool
+    transfer_sp_deposit_fee(
+        &ctx.accounts.token_program,
+        &ctx.accounts.stablecoin,
+        &ctx.accounts.depositor_stablecoin_ata,
+        ctx.accounts.sp_fee_reserve_ata.as_deref(),
+        &ctx.accounts.depositor,
+        fee,
+    )?;
+    ctx.accounts.transfer_usv_in(usv_amt - fee)?;
+
+    let claimed = ctx.accounts.stability_pool_deposit.claim(depositor);
+    ctx.accounts
+        .community_issuance_config
+        .record_cvgt_sent(claimed.cvgt_claimed);
+
+    // Transfer CVGT to user
+    ctx.accounts.send_cvgt_to_depositor(claimed.cvgt_claimed)?;
+    // Transfer Coll to user
+    ctx.accounts.transfer_coll_out(claimed.coll_claimed)?;
+
+    emit!(claimed);
+
+    Ok(())
+}
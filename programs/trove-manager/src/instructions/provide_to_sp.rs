@@ -6,7 +6,7 @@ use anchor_spl::{
 };
 
 use crate::{
-    errors::StabilityPoolError,
+    errors::{CommunityIssuanceError, StabilityPoolError},
     events::{CVGTPaidToDepositor, CollGainWithdrawn, UserDepositChanged},
     state::{
         get_epoch_scales, CommunityIssuanceConfig, EpochScale, PoolState, StabilityPoolDeposit,
@@ -74,6 +74,13 @@ pub struct ProvideToSP<'info> {
     )]
     pub depositor_stablecoin_ata: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = stability_pool_state.deposit_fee_reserve
+    )]
+    pub sp_fee_reserve_ata: Option<Box<Account<'info, TokenAccount>>>,
+
     #[account(mut)]
     pub depositor: Signer<'info>,
 
@@ -93,6 +100,12 @@ pub struct ProvideToSP<'info> {
     )]
     pub community_issuance_config: Box<Account<'info, CommunityIssuanceConfig>>,
 
+    #[account(
+        associated_token::mint = community_issuance_config.cvgt,
+        associated_token::authority = community_issuance_config
+    )]
+    pub community_issuance_vault: Option<Box<Account<'info, TokenAccount>>>,
+
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
@@ -115,78 +128,345 @@ impl<'info> ProvideToSP<'info> {
             self.stablecoin.decimals,
         )
     }
+}
 
-    pub fn issue_cvgt(&mut self) -> Result<u64> {
-        let config = &mut self.community_issuance_config;
-
-        let issued = config.issue_token()?;
-        Ok(issued)
+/// Sends the deposit fee to the reserve ATA, which is only required when there is a fee
+pub fn transfer_sp_deposit_fee<'info>(
+    token_program: &Program<'info, Token>,
+    stablecoin: &Account<'info, Mint>,
+    depositor_stablecoin_ata: &Account<'info, TokenAccount>,
+    sp_fee_reserve_ata: Option<&Account<'info, TokenAccount>>,
+    depositor: &Signer<'info>,
+    fee: u64,
+) -> Result<()> {
+    if fee == 0 {
+        return Ok(());
     }
+    let cpi_accounts = TransferChecked {
+        from: depositor_stablecoin_ata.to_account_info(),
+        to: sp_fee_reserve_ata
+            .ok_or(StabilityPoolError::MissingFeeReserve)?
+            .to_account_info(),
+        authority: depositor.to_account_info(),
+        mint: stablecoin.to_account_info(),
+    };
+
+    let cpi_program = token_program.to_account_info();
+
+    transfer_checked(
+        CpiContext::new(cpi_program, cpi_accounts),
+        fee,
+        stablecoin.decimals,
+    )
+}
+
+pub fn provide_to_sp_handler(mut ctx: Context<ProvideToSP>, usv_amt: u64) -> Result<()> {
+    let depositor = ctx.accounts.depositor.key();
+    let accounts = &mut ctx.accounts;
+    let fee = provide_usv_to_sp(
+        ctx.remaining_accounts,
+        &accounts.pool_state,
+        &mut accounts.community_issuance_config,
+        accounts
+            .community_issuance_vault
+            .as_ref()
+            .map(|vault| vault.amount),
+        &mut accounts.stability_pool_state,
+        &mut accounts.stability_pool_deposit,
+        &mut accounts.current_epoch_scale,
+        depositor,
+        usv_amt,
+    )?;
+
+    // Transfer the fee to the reserve and the rest of the USV to pool
+    transfer_sp_deposit_fee(
+        &ctx.accounts.token_program,
+        &ctx.accounts.stablecoin,
+        &ctx.accounts.depositor_stablecoin_ata,
+        ctx.accounts.sp_fee_reserve_ata.as_deref(),
+        &ctx.accounts.depositor,
+        fee,
+    )?;
+    ctx.accounts.transfer_usv_in(usv_amt - fee)
 }
 
-pub fn provide_to_sp_handler(ctx: Context<ProvideToSP>, usv_amt: u64) -> Result<()> {
-    let cvgt_issuance = ctx.accounts.issue_cvgt()?;
+/// Issues CVGT, realizes the deposit's gains into its claimable balances and adds `usv_amt` net of
+/// the SP deposit fee to it. `remaining_accounts` holds the epoch-scale of the deposit's snapshot
+/// and the following one. The issuance vault balance is only needed while the issuance guard is
+/// enabled. Returns the fee, the USV transfers are left to the caller.
+pub fn provide_usv_to_sp(
+    remaining_accounts: &[AccountInfo<'_>],
+    pool_state: &PoolState,
+    community_issuance_config: &mut CommunityIssuanceConfig,
+    community_issuance_vault_amount: Option<u64>,
+    sp_state: &mut Account<'_, StabilityPoolState>,
+    sp_deposit: &mut StabilityPoolDeposit,
+    current_epoch_scale: &mut Account<'_, EpochScale>,
+    depositor: Pubkey,
+    usv_amt: u64,
+) -> Result<u64> {
+    pool_state.require_sp_deposits_enabled()?;
 
-    let current_epoch_scale_key = &ctx.accounts.current_epoch_scale.key();
-    let depositor = ctx.accounts.depositor.key;
-    let sp_deposit = &mut ctx.accounts.stability_pool_deposit;
-    let sp_state = &mut ctx.accounts.stability_pool_state;
-    let current_epoch_scale = &mut ctx.accounts.current_epoch_scale;
+    let cvgt_issuance = community_issuance_config.issue_token()?;
+    if community_issuance_config.issuance_guard_enabled {
+        community_issuance_config.require_vault_covers_issuance(
+            community_issuance_vault_amount.ok_or(CommunityIssuanceError::MissingIssuanceVault)?,
+        )?;
+    }
+
+    let current_epoch_scale_key = &current_epoch_scale.key();
 
     require!(usv_amt > 0, StabilityPoolError::ZeroAmount);
+    let fee = sp_state.get_deposit_fee(usv_amt);
+    let usv_deposited = usv_amt - fee;
 
     current_epoch_scale.update_g(sp_state, cvgt_issuance);
 
     let (first_epoch_scale, second_epoch_scale) = get_epoch_scales(
-        &ctx.remaining_accounts,
+        remaining_accounts,
         current_epoch_scale_key,
-        &current_epoch_scale,
+        current_epoch_scale,
         sp_state,
         sp_deposit,
     )?;
 
-    let depositor_coll_gain = sp_deposit
-        .get_depositor_coll_gain(&first_epoch_scale, &second_epoch_scale)
-        .unwrap();
-    let compounded_usv_deposit = sp_deposit.get_compounded_usv_deposit(sp_state).unwrap();
-    let usv_loss = sp_deposit
-        .initial_value
-        .checked_sub(compounded_usv_deposit)
+    let initial_value = sp_deposit.initial_value;
+    let (depositor_coll_gain, cvgt_gain, compounded_usv_deposit) = sp_deposit
+        .provide(
+            sp_state,
+            &[first_epoch_scale, second_epoch_scale],
+            current_epoch_scale,
+            depositor,
+            usv_deposited,
+        )
         .unwrap();
+    let usv_loss = initial_value.checked_sub(compounded_usv_deposit).unwrap();
 
     // First pay out any CVGT gains
-    let cvgt_gain = sp_deposit
-        .get_cvgt_gain(&first_epoch_scale, &second_epoch_scale)
-        .unwrap();
     emit!(CVGTPaidToDepositor {
-        depositor: *depositor,
+        depositor,
         cvgt_gain
     });
 
-    sp_state.increase_usv(usv_amt);
-
-    let new_deposit = compounded_usv_deposit.checked_add(usv_amt).unwrap();
-    sp_deposit.update_deposit_and_snapshot(sp_state, current_epoch_scale, *depositor, new_deposit);
-    sp_deposit.claimable_coll = sp_deposit
-        .claimable_coll
-        .checked_add(depositor_coll_gain.try_into().unwrap())
-        .unwrap();
-    sp_deposit.claimable_cvgt = sp_deposit.claimable_cvgt.checked_add(cvgt_gain).unwrap();
+    sp_state.increase_usv(usv_deposited);
     sp_state.decrease_coll(depositor_coll_gain);
 
-    // Transfer USV to pool
-    ctx.accounts.transfer_usv_in(usv_amt)?;
-
     emit!(UserDepositChanged {
-        depositor: *depositor,
-        new_deposit
+        depositor,
+        new_deposit: sp_deposit.initial_value
     });
 
     emit!(CollGainWithdrawn {
-        depositor: *depositor,
+        depositor,
         coll: depositor_coll_gain,
         usv_loss
     });
 
-    Ok(())
+    Ok(fee)
+}
+
+#[cfg(test)]
+pub mod provide_to_sp_test {
+    use super::*;
+    use crate::{
+        constants::DECIMAL_PRECISION,
+        utils::utils_test::{
+            load_account, load_anchor_account, load_mint, load_program, load_signer,
+            load_token_account, read_token_amount, set_program_test_stubs,
+        },
+        ID,
+    };
+    use anchor_spl::{associated_token::get_associated_token_address, token::spl_token};
+    use std::collections::BTreeSet;
+
+    const DEPOSITOR_USV: u64 = 10_000_000_000;
+
+    /// Index of `sp_fee_reserve_ata` in the accounts of `load_provide_to_sp_accounts`
+    const FEE_RESERVE_INDEX: usize = 7;
+    /// Index of `community_issuance_vault` in the accounts of `load_provide_to_sp_accounts`
+    const ISSUANCE_VAULT_INDEX: usize = 11;
+
+    /// Accounts of `provide_to_sp` in declaration order, followed by the deposit's epoch-scales
+    fn load_provide_to_sp_accounts(
+        sp_state: &StabilityPoolState,
+    ) -> (Vec<AccountInfo<'static>>, Vec<AccountInfo<'static>>) {
+        let depositor = Pubkey::new_unique();
+        let stablecoin = Pubkey::new_unique();
+        let pool_state_key = Pubkey::new_unique();
+        let pool_state = PoolState {
+            stablecoin,
+            cvgt: sp_state.cvgt,
+            ..Default::default()
+        };
+        let (sp_state_key, _) =
+            Pubkey::find_program_address(&[b"stability", pool_state_key.as_ref()], &ID);
+        let (sp_deposit_key, _) = Pubkey::find_program_address(
+            &[b"sp-deposit", sp_state_key.as_ref(), depositor.as_ref()],
+            &ID,
+        );
+        let epoch_scale_key = |scale: u128| {
+            Pubkey::find_program_address(
+                &[
+                    b"epoch-scale",
+                    sp_state_key.as_ref(),
+                    0u128.to_le_bytes().as_ref(),
+                    scale.to_le_bytes().as_ref(),
+                ],
+                &ID,
+            )
+            .0
+        };
+        let (config_key, _) =
+            Pubkey::find_program_address(&[b"community-issuance", sp_state.cvgt.as_ref()], &ID);
+        let config = CommunityIssuanceConfig {
+            cvgt: sp_state.cvgt,
+            _is_dev: true,
+            ..Default::default()
+        };
+        let token_account = |mint: Pubkey, owner: Pubkey, amount: u64| {
+            load_account(
+                get_associated_token_address(&owner, &mint),
+                spl_token::ID,
+                load_token_account(mint, owner, amount),
+            )
+        };
+        let sp_deposit = load_anchor_account(sp_deposit_key, &StabilityPoolDeposit::default());
+        **sp_deposit.lamports.borrow_mut() = Rent::default().minimum_balance(sp_deposit.data_len());
+        let current_epoch_scale = load_anchor_account(epoch_scale_key(0), &EpochScale::default());
+
+        let accounts = vec![
+            load_anchor_account(pool_state_key, &pool_state),
+            load_anchor_account(sp_state_key, sp_state),
+            sp_deposit,
+            current_epoch_scale.clone(),
+            load_account(stablecoin, spl_token::ID, load_mint(Pubkey::new_unique())),
+            token_account(stablecoin, sp_state_key, 0),
+            token_account(stablecoin, depositor, DEPOSITOR_USV),
+            token_account(stablecoin, sp_state.deposit_fee_reserve, 0),
+            load_signer(depositor, 0),
+            load_account(sp_state.cvgt, Pubkey::default(), vec![]),
+            load_anchor_account(config_key, &config),
+            token_account(sp_state.cvgt, config_key, 0),
+            load_program(spl_token::ID),
+            load_program(anchor_spl::associated_token::ID),
+            load_program(System::id()),
+        ];
+        let epoch_scales = vec![
+            current_epoch_scale,
+            load_account(epoch_scale_key(1), ID, vec![]),
+        ];
+        (accounts, epoch_scales)
+    }
+
+    fn provide_to_sp(
+        accounts: &[AccountInfo<'static>],
+        epoch_scales: &[AccountInfo<'static>],
+        usv_amt: u64,
+    ) -> Result<ProvideToSP<'static>> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = ProvideToSPBumps::default();
+        let mut provide_accounts =
+            ProvideToSP::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        provide_to_sp_handler(
+            Context::new(&ID, &mut provide_accounts, epoch_scales, bumps),
+            usv_amt,
+        )?;
+        Ok(provide_accounts)
+    }
+
+    fn load_sp_state(deposit_fee_bps: u64) -> StabilityPoolState {
+        let mut sp_state = StabilityPoolState {
+            cvgt: Pubkey::new_unique(),
+            p: DECIMAL_PRECISION.into(),
+            ..Default::default()
+        };
+        sp_state
+            .set_deposit_fee(deposit_fee_bps, Pubkey::new_unique())
+            .unwrap();
+        sp_state
+    }
+
+    #[test]
+    fn deposit_fee_sent_to_reserve() {
+        set_program_test_stubs();
+        let (accounts, epoch_scales) = load_provide_to_sp_accounts(&load_sp_state(50));
+
+        let provided = provide_to_sp(&accounts, &epoch_scales, DEPOSITOR_USV).unwrap();
+
+        // The reserve ATA receives the fee, the deposit and the SP total are net of it
+        assert_eq!(read_token_amount(&accounts[FEE_RESERVE_INDEX]), 50_000_000);
+        assert_eq!(read_token_amount(&accounts[5]), 9_950_000_000);
+        assert_eq!(read_token_amount(&accounts[6]), 0);
+        assert_eq!(provided.stability_pool_deposit.initial_value, 9_950_000_000);
+        assert_eq!(
+            provided.stability_pool_state.total_usv_deposits,
+            9_950_000_000
+        );
+    }
+
+    #[test]
+    fn deposit_fee_needs_reserve_ata() {
+        set_program_test_stubs();
+        let (mut accounts, epoch_scales) = load_provide_to_sp_accounts(&load_sp_state(50));
+        accounts[FEE_RESERVE_INDEX] = load_program(ID);
+
+        assert_eq!(
+            provide_to_sp(&accounts, &epoch_scales, DEPOSITOR_USV)
+                .err()
+                .unwrap(),
+            StabilityPoolError::MissingFeeReserve.into()
+        );
+
+        // Without a fee the whole amount is deposited
+        let (mut accounts, epoch_scales) = load_provide_to_sp_accounts(&load_sp_state(0));
+        accounts[FEE_RESERVE_INDEX] = load_program(ID);
+        let provided = provide_to_sp(&accounts, &epoch_scales, DEPOSITOR_USV).unwrap();
+        assert_eq!(read_token_amount(&accounts[5]), DEPOSITOR_USV);
+        assert_eq!(provided.stability_pool_deposit.initial_value, DEPOSITOR_USV);
+    }
+
+    #[test]
+    fn issuance_vault_only_required_by_the_guard() {
+        set_program_test_stubs();
+        // Guard disabled, the deposit goes through without the vault
+        let (mut accounts, epoch_scales) = load_provide_to_sp_accounts(&load_sp_state(0));
+        accounts[ISSUANCE_VAULT_INDEX] = load_program(ID);
+        let provided = provide_to_sp(&accounts, &epoch_scales, DEPOSITOR_USV).unwrap();
+        assert_eq!(provided.stability_pool_deposit.initial_value, DEPOSITOR_USV);
+
+        let sp_state = load_sp_state(0);
+        let guarded_config = |config_key: Pubkey| {
+            load_anchor_account(
+                config_key,
+                &CommunityIssuanceConfig {
+                    cvgt: sp_state.cvgt,
+                    _is_dev: true,
+                    issuance_guard_enabled: true,
+                    total_cvgt_issued: 1_000,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let (mut accounts, epoch_scales) = load_provide_to_sp_accounts(&sp_state);
+        accounts[10] = guarded_config(accounts[10].key());
+        accounts[ISSUANCE_VAULT_INDEX] = load_program(ID);
+        assert_eq!(
+            provide_to_sp(&accounts, &epoch_scales, DEPOSITOR_USV)
+                .err()
+                .unwrap(),
+            CommunityIssuanceError::MissingIssuanceVault.into()
+        );
+
+        // The empty vault can't cover the 1000 CVGT outstanding
+        let (mut accounts, epoch_scales) = load_provide_to_sp_accounts(&sp_state);
+        accounts[10] = guarded_config(accounts[10].key());
+        assert_eq!(
+            provide_to_sp(&accounts, &epoch_scales, DEPOSITOR_USV)
+                .err()
+                .unwrap(),
+            CommunityIssuanceError::IssuanceVaultUnderfunded.into()
+        );
+    }
 }
@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::BorrowerOpsError,
+    state::{PoolState, Trove},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SurplusReconciliation {
+    // Sum of `surplus_balance` over the troves passed
+    pub troves_surplus: u64,
+    pub total_surplus: u64,
+    // `total_surplus - troves_surplus`, 0 once every trove holding a surplus is passed
+    pub difference: i128,
+}
+
+#[derive(Accounts)]
+pub struct ReconcileSurplus<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+}
+
+/// Read-only: compares `total_surplus` with the claimable surpluses of the troves in
+/// `remaining_accounts`. Each trove may only be passed once.
+pub fn reconcile_surplus_handler<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ReconcileSurplus<'info>>,
+) -> Result<SurplusReconciliation> {
+    let pool_state = &ctx.accounts.pool_state;
+
+    let mut keys: Vec<Pubkey> = ctx.remaining_accounts.iter().map(|a| a.key()).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    require!(
+        keys.len() == ctx.remaining_accounts.len(),
+        BorrowerOpsError::InvalidAccount
+    );
+
+    let troves = ctx
+        .remaining_accounts
+        .iter()
+        .map(|account| {
+            let trove = Account::<Trove>::try_from(account)?;
+            require!(
+                trove.pool_state == pool_state.key(),
+                BorrowerOpsError::InvalidAccount
+            );
+            Ok(trove.into_inner())
+        })
+        .collect::<Result<Vec<Trove>>>()?;
+
+    Ok(get_surplus_reconciliation(pool_state, &troves))
+}
+
+pub fn get_surplus_reconciliation(
+    pool_state: &PoolState,
+    troves: &[Trove],
+) -> SurplusReconciliation {
+    let troves_surplus = troves
+        .iter()
+        .map(|trove| trove.surplus_balance)
+        .fold(0u64, |sum, surplus| sum.checked_add(surplus).unwrap());
+    SurplusReconciliation {
+        troves_surplus,
+        total_surplus: pool_state.total_surplus,
+        difference: i128::from(pool_state.total_surplus) - i128::from(troves_surplus),
+    }
+}
+
+#[cfg(test)]
+pub mod reconcile_surplus_test {
+    use super::*;
+
+    #[test]
+    fn trove_surpluses_add_up_to_total() {
+        let mut pool_state = PoolState::default();
+        let mut troves = [Trove::default(), Trove::default(), Trove::default()];
+
+        // Two capped liquidations leave a surplus
+        for (trove, surplus) in troves.iter_mut().zip([3_000, 5_000]) {
+            trove.account_surplus(surplus);
+            pool_state.increase_total_surplus(surplus);
+        }
+
+        let reconciliation = get_surplus_reconciliation(&pool_state, &troves);
+        assert_eq!(reconciliation.troves_surplus, 8_000);
+        assert_eq!(reconciliation.troves_surplus, pool_state.total_surplus);
+        assert_eq!(reconciliation.difference, 0);
+
+        // A trove left out shows up as drift
+        assert_eq!(
+            get_surplus_reconciliation(&pool_state, &troves[1..]).difference,
+            3_000
+        );
+    }
+}
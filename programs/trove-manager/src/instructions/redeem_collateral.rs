@@ -9,7 +9,7 @@ use anchor_spl::{
 use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 use crate::{
-    constants::DECIMAL_PRECISION,
+    constants::{DECIMAL_PRECISION, TREASURY_VAULT},
     errors::{BorrowerOpsError, PriceFeedError},
     events::{Operation, Redemption, TroveUpdated},
     math::compute_nominal_cr,
@@ -18,6 +18,7 @@ use crate::{
         TroveStatus,
     },
     utils::{
+        get_current_timestamp, get_current_timestamp_i64, require_neighbors_outside_segment,
         require_non_zero_redeem_amount, require_sufficient_usv_balance, require_user_accepts_fee,
         require_valid_redeem_max_fee_percentage,
     },
@@ -52,6 +53,13 @@ pub struct RedeemCollateral<'info> {
     )]
     pub coll_cvgt_staking_vault: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        mut,
+        associated_token::mint = collateral,
+        associated_token::authority = TREASURY_VAULT
+    )]
+    pub treasury_coll_ata: Option<Box<Account<'info, TokenAccount>>>,
+
     #[account(
         mut,
         constraint = stablecoin.key() == pool_state.stablecoin
@@ -178,6 +186,24 @@ impl<'info> RedeemCollateral<'info> {
         CpiContext::new(cpi_program, cpi_accounts)
     }
 
+    /// The treasury ATA is only required when the pool sends it a share of the fee
+    pub fn transfer_coll_to_treasury_ctx(
+        &self,
+    ) -> Result<CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>> {
+        let cpi_accounts = TransferChecked {
+            from: self.collateral_vault.to_account_info(),
+            to: self
+                .treasury_coll_ata
+                .as_ref()
+                .ok_or(BorrowerOpsError::MissingRedemptionFeeTreasury)?
+                .to_account_info(),
+            authority: self.token_authority.to_account_info(),
+            mint: self.collateral.to_account_info(),
+        };
+        let cpi_program = self.token_program.to_account_info();
+        Ok(CpiContext::new(cpi_program, cpi_accounts))
+    }
+
     pub fn burn_stablecoin_from_user_ctx(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
         let cpi_accounts = Burn {
             mint: self.stablecoin.to_account_info(),
@@ -232,15 +258,28 @@ pub fn redeem_collateral_handler(
         &ctx.accounts.jitosol_stake_pool,
         &ctx.accounts.pyth_feed_account,
     )?;
+    emit!(ctx.accounts.price_feed_state.price_used(totals.price));
+    ctx.accounts.price_feed_state.require_price_age(
+        pool_state.redemption_max_price_age,
+        get_current_timestamp_i64()?,
+    )?;
     pool_state.require_tcr_over_mcr(totals.price)?;
     require_non_zero_redeem_amount(usv_amt)?;
     require_sufficient_usv_balance(ctx.accounts.stablecoin_receive_account.amount, usv_amt)?;
 
     totals.total_usv_supply_at_start = pool_state.get_entire_debt();
     assert!(ctx.accounts.stablecoin_receive_account.amount <= totals.total_usv_supply_at_start);
+    pool_state.require_redemption_within_max_fraction(usv_amt, totals.total_usv_supply_at_start)?;
 
     totals.remaining_usv = usv_amt;
 
+    let segment: Vec<Pubkey> = ctx.remaining_accounts.iter().map(|a| a.key()).collect();
+    require_neighbors_outside_segment(
+        &segment,
+        ctx.accounts.prev_trove.as_ref().map(|t| t.key()),
+        ctx.accounts.next_trove.as_ref().map(|t| t.key()),
+    )?;
+
     let start_index = skim_provided_troves(ctx.remaining_accounts, pool_state, totals.price)?;
     let end_index = ctx.remaining_accounts.len();
 
@@ -258,6 +297,11 @@ pub fn redeem_collateral_handler(
         let mut trove =
             Trove::try_deserialize(&mut data.as_ref()).expect("Error Deserializing Data");
 
+        // Collateral-only troves sit at the head of the list, there's no debt left to redeem
+        if trove.coll_only {
+            break;
+        }
+
         pool_state.apply_pending_reward(&mut trove)?;
 
         let single_redemption = redeem_collateral_from_trove(
@@ -276,11 +320,11 @@ pub fn redeem_collateral_handler(
             trove.remove_sorted_redemption(ctx.remaining_accounts, account.key(), pool_state)?;
             trove.try_serialize(&mut data.as_mut())?;
             drop(data);
-            if ctx.accounts.next_trove.is_some() {
-                ctx.accounts.next_trove.as_mut().unwrap().reload()?;
+            if let Some(next_trove) = ctx.accounts.next_trove.as_mut() {
+                next_trove.reload()?;
             }
-            if ctx.accounts.prev_trove.is_some() {
-                ctx.accounts.prev_trove.as_mut().unwrap().reload()?;
+            if let Some(prev_trove) = ctx.accounts.prev_trove.as_mut() {
+                prev_trove.reload()?;
             }
         } else {
             let new_nicr = compute_nominal_cr(trove.coll, trove.debt).unwrap();
@@ -322,11 +366,14 @@ pub fn redeem_collateral_handler(
         totals.total_coll_drawn,
         totals.price,
         totals.total_usv_supply_at_start,
+        get_current_timestamp(),
     )?;
 
     totals.coll_fee = pool_state.get_redemption_fee(totals.total_coll_drawn)?;
 
     require_user_accepts_fee(totals.coll_fee, totals.total_coll_drawn, max_fee_percentage)?;
+    (totals.coll_fee_to_treasury, totals.coll_fee_to_stakers) =
+        pool_state.split_redemption_fee(totals.coll_fee);
 
     pool_state.decrease_active_coll(totals.coll_fee);
 
@@ -340,6 +387,7 @@ pub fn redeem_collateral_handler(
         actual_usv_amount: totals.total_usv_to_redeem,
         coll_sent: totals.total_coll_drawn,
         coll_fee: totals.coll_fee,
+        event_nonce: pool_state.next_event_nonce(),
     });
 
     pool_state.decrease_active_debt(totals.total_usv_to_redeem);
@@ -348,49 +396,89 @@ pub fn redeem_collateral_handler(
     move_token_from_redeem(ctx, &totals)
 }
 
-fn redeem_collateral_from_trove(
-    trove: &mut Trove,
+/// Outcome of redeeming up to `max_usv_amt` from `trove`, whose pending rewards must already be applied
+pub fn preview_redemption_from_trove(
+    trove: &Trove,
     max_usv_amt: u64,
     price: u64,
-    pool_state: &mut PoolState,
-) -> Result<SingleRedemptionValues> {
-    let mut single_redemption: SingleRedemptionValues = Default::default();
+    pool_state: &PoolState,
+) -> Result<TroveRedemptionPreview> {
+    let mut preview: TroveRedemptionPreview = Default::default();
 
     // Determine the remaining amount (lot) to be redeemed, capped by the entire debt of the Trove minus the liquidation reserve
-    single_redemption.usv_lot = cmp::min(
+    preview.usv_lot = cmp::min(
         max_usv_amt,
         trove.debt.checked_sub(pool_state.gas_compensation).unwrap(),
     );
 
     // Get the Coll Lot of equivalent value in USD
-    single_redemption.coll_lot = u64::try_from(
-        u128::from(single_redemption.usv_lot)
-            .checked_mul(DECIMAL_PRECISION.into())
-            .unwrap()
-            .checked_div(price.into())
-            .unwrap(),
-    )
-    .unwrap();
+    preview.coll_lot = get_redemption_coll_lot(preview.usv_lot, price);
+
+    let new_debt = trove.debt.checked_sub(preview.usv_lot).unwrap();
+    require!(
+        preview.coll_lot <= trove.coll,
+        BorrowerOpsError::RedemptionCollAboveTroveColl
+    );
+
+    if new_debt == pool_state.gas_compensation {
+        // No debt left in the Trove (except for the liquidation reserve), therefore the trove gets closed
+        preview.would_close = true;
+    } else {
+        preview.would_cancel_partial = pool_state.get_net_debt(new_debt) < pool_state.min_net_debt;
+    }
+
+    Ok(preview)
+}
+
+fn redeem_collateral_from_trove(
+    trove: &mut Trove,
+    max_usv_amt: u64,
+    price: u64,
+    pool_state: &mut PoolState,
+) -> Result<SingleRedemptionValues> {
+    let mut single_redemption: SingleRedemptionValues = Default::default();
+
+    let preview = preview_redemption_from_trove(trove, max_usv_amt, price, pool_state)?;
+    single_redemption.usv_lot = preview.usv_lot;
+    single_redemption.coll_lot = preview.coll_lot;
 
     // Decrease the debt and collateral of the current Trove according to the USV lot and corresponding Coll to send
     let new_debt = trove.debt.checked_sub(single_redemption.usv_lot).unwrap();
     let new_coll = trove.coll.checked_sub(single_redemption.coll_lot).unwrap();
 
-    if new_debt == pool_state.gas_compensation {
-        // No debt left in the Trove (except for the liquidation reserve), therefore the trove gets closed
+    #[cfg(feature = "debug-asserts")]
+    assert!(
+        new_debt <= trove.debt && new_coll <= trove.coll,
+        "Redemption must not increase trove debt or collateral"
+    );
+
+    if preview.would_close {
         trove.remove_stake(pool_state);
         trove.close_trove(pool_state, TroveStatus::ClosedByRedemption)?;
-        redeem_close_trove(trove, pool_state, pool_state.gas_compensation, new_coll);
-        single_redemption.usv_gas_to_burn = pool_state.gas_compensation;
+        // A borrower-held reserve can't be burned here, its debt stays active until the borrower
+        // repays it before claiming the surplus
+        if trove.gas_comp_held_by_borrower {
+            trove.gas_comp_owed = pool_state.gas_compensation;
+        } else {
+            single_redemption.usv_gas_to_burn = pool_state.gas_compensation;
+        }
+        redeem_close_trove(
+            trove,
+            pool_state,
+            single_redemption.usv_gas_to_burn,
+            new_coll,
+        );
         emit!(TroveUpdated {
             borrower: trove.creator,
             debt: 0,
             coll: 0,
             stake: 0,
-            operation: Operation::RedeemCollateral
+            operation: Operation::RedeemCollateral,
+            event_nonce: pool_state.next_event_nonce()
         });
+        emit!(trove.icr_updated(pool_state, price, Operation::RedeemCollateral));
     } else {
-        if pool_state.get_net_debt(new_debt) < pool_state.min_net_debt {
+        if preview.would_cancel_partial {
             single_redemption.canceled_partial = true;
             return Ok(single_redemption);
         }
@@ -404,13 +492,27 @@ fn redeem_collateral_from_trove(
             debt: new_debt,
             coll: new_coll,
             stake: trove.stake,
-            operation: Operation::RedeemCollateral
+            operation: Operation::RedeemCollateral,
+            event_nonce: pool_state.next_event_nonce()
         });
+        emit!(trove.icr_updated(pool_state, price, Operation::RedeemCollateral));
     }
 
     Ok(single_redemption)
 }
 
+/// Coll worth `usv_lot` at `price`, rounded down so the redeemer is never over-paid
+fn get_redemption_coll_lot(usv_lot: u64, price: u64) -> u64 {
+    u64::try_from(
+        u128::from(usv_lot)
+            .checked_mul(DECIMAL_PRECISION.into())
+            .unwrap()
+            .checked_div(price.into())
+            .unwrap(),
+    )
+    .unwrap()
+}
+
 fn redeem_close_trove(trove: &mut Trove, pool_state: &mut PoolState, usv_amt: u64, coll_amt: u64) {
     pool_state.decrease_active_debt(usv_amt);
     trove.account_surplus(coll_amt);
@@ -446,15 +548,25 @@ fn move_token_from_redeem(
         redemption_totals.coll_to_send_to_redeemer,
         ctx.accounts.collateral.decimals,
     )?;
+    if redemption_totals.coll_fee_to_treasury > 0 {
+        transfer_checked(
+            ctx.accounts
+                .transfer_coll_to_treasury_ctx()?
+                .with_signer(&[&authority_seed[..]]),
+            redemption_totals.coll_fee_to_treasury,
+            ctx.accounts.collateral.decimals,
+        )?;
+    }
     transfer_checked(
         ctx.accounts
             .transfer_coll_to_staking_pool_ctx()
             .with_signer(&[&authority_seed[..]]),
-        redemption_totals.coll_fee,
+        redemption_totals.coll_fee_to_stakers,
         ctx.accounts.collateral.decimals,
     )?;
     if community_issuance_config.enable_emission {
-        ctx.accounts.increase_f_coll(redemption_totals.coll_fee)?;
+        ctx.accounts
+            .increase_f_coll(redemption_totals.coll_fee_to_stakers)?;
     }
     Ok(())
 }
@@ -469,6 +581,7 @@ fn skim_provided_troves(
     let end_index = accounts.len();
     let mut found = false;
     let mut found_index = start_index;
+    let mut next_nicr = 0;
 
     for i in start_index..end_index {
         let account = &accounts[i];
@@ -493,15 +606,17 @@ fn skim_provided_troves(
         // Trove need to be active
         trove.require_trove_active()?;
 
-        // 2nd check: The start of provided accounts need to have ICR < MCR
+        // 2nd check: The start of provided accounts need to have ICR < MCR, or to have drifted
+        // over it within the hint tolerance, in which case it is skipped like an under MCR trove
         // If the program reach to this point, we are sure that first account isnt Pubkey::default
         if i == start_index {
             require!(
-                trove.get_current_icr(pool_state, price) < pool_state.mcr,
+                pool_state.is_valid_redemption_start_hint(trove.get_current_icr(pool_state, price)),
                 BorrowerOpsError::InvalidTroveNeighbor
             );
         }
 
+        let nicr = trove.get_nominal_icr(pool_state);
         if i > start_index {
             // 3rd check: The current trove need to be linked with the previous trove
             let next_account = &accounts[i - 1];
@@ -509,12 +624,20 @@ fn skim_provided_troves(
                 trove.next == next_account.key(),
                 BorrowerOpsError::InvalidTroveNeighbor
             );
+            // Equal NICRs are consumed in the order insertion gives them, the higher pubkey first
+            require!(
+                !pool_state.nicr_tie_break_enforced
+                    || nicr != next_nicr
+                    || key < next_account.key(),
+                BorrowerOpsError::InvalidTroveNeighbor
+            );
             // Find the index of the first trove that have ICR >= MCR
             if trove.get_current_icr(pool_state, price) >= pool_state.mcr && !found {
                 found = true;
                 found_index = i;
             }
         }
+        next_nicr = nicr;
     }
     require!(found, BorrowerOpsError::InvalidTroveNeighbor);
     Ok(found_index)
@@ -526,13 +649,23 @@ pub struct RedemptionTotals {
     pub total_usv_to_redeem: u64,
     pub total_coll_drawn: u64,
     pub coll_fee: u64,
+    pub coll_fee_to_treasury: u64,
+    pub coll_fee_to_stakers: u64,
     pub coll_to_send_to_redeemer: u64,
-    pub decayed_base_rate: u64,
     pub price: u64,
     pub total_usv_supply_at_start: u64,
     pub total_usv_gas_to_burn: u64,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TroveRedemptionPreview {
+    pub usv_lot: u64,
+    pub coll_lot: u64,
+    pub would_close: bool,
+    // Nothing is redeemed from the trove, and the redemption stops there
+    pub would_cancel_partial: bool,
+}
+
 #[derive(Default)]
 pub struct SingleRedemptionValues {
     pub usv_lot: u64,
@@ -540,3 +673,484 @@ pub struct SingleRedemptionValues {
     pub canceled_partial: bool,
     pub usv_gas_to_burn: u64,
 }
+
+#[cfg(test)]
+pub mod redeem_collateral_test {
+    use super::*;
+    use crate::state::{
+        price_feed_info_test::load_price_feed_info, trove_test::load_trove_account, Status,
+    };
+    use crate::utils::utils_test::{
+        load_account, load_anchor_account, load_mint, load_program, load_signer,
+        load_token_account, read_token_amount, set_program_test_stubs,
+    };
+    use anchor_lang::Discriminator;
+    use anchor_spl::{associated_token::get_associated_token_address, token::spl_token};
+    use std::collections::BTreeSet;
+
+    fn load_pool_state() -> PoolState {
+        PoolState {
+            gas_compensation: 200_000_000,
+            min_net_debt: 1_000_000_000,
+            trove_size: 2,
+            total_stakes: 10_000_000_000,
+            active_coll: 10_000_000_000,
+            active_debt: 5_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    fn load_trove() -> Trove {
+        Trove {
+            coll: 10_000_000_000,
+            debt: 5_000_000_000,
+            stake: 10_000_000_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn redemption_never_increases_trove_debt_or_coll() {
+        let price = 2_000_000_000;
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut amounts = vec![1, 3_800_000_000, 4_800_000_000, 10_000_000_000];
+        for _ in 0..200 {
+            // xorshift
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            amounts.push(seed % 6_000_000_000 + 1);
+        }
+
+        for usv_amt in amounts {
+            let mut pool_state = load_pool_state();
+            let mut trove = load_trove();
+            let single_redemption =
+                redeem_collateral_from_trove(&mut trove, usv_amt, price, &mut pool_state).unwrap();
+
+            assert!(trove.debt <= load_trove().debt);
+            assert!(trove.coll <= load_trove().coll);
+            if single_redemption.canceled_partial {
+                assert_eq!(trove.debt, load_trove().debt);
+                assert_eq!(trove.coll, load_trove().coll);
+            } else if trove.status == TroveStatus::ClosedByRedemption {
+                assert_eq!(single_redemption.usv_lot, 4_800_000_000);
+                assert_eq!(trove.surplus_balance, 7_600_000_000);
+            } else {
+                assert_eq!(trove.debt, 5_000_000_000 - single_redemption.usv_lot);
+                assert_eq!(trove.coll, 10_000_000_000 - single_redemption.coll_lot);
+            }
+        }
+    }
+
+    #[test]
+    fn preview_matches_single_trove_redemption() {
+        let price = 2_000_000_000;
+        // Partial, closing, and partial leaving less than the minimum net debt
+        for usv_amt in [1_000_000_000, 6_000_000_000, 4_000_000_000] {
+            let mut pool_state = load_pool_state();
+            let mut trove = load_trove();
+            let preview =
+                preview_redemption_from_trove(&trove, usv_amt, price, &pool_state).unwrap();
+            let single_redemption =
+                redeem_collateral_from_trove(&mut trove, usv_amt, price, &mut pool_state).unwrap();
+
+            assert_eq!(preview.usv_lot, single_redemption.usv_lot);
+            assert_eq!(preview.coll_lot, single_redemption.coll_lot);
+            assert_eq!(
+                preview.would_close,
+                trove.status == TroveStatus::ClosedByRedemption
+            );
+            assert_eq!(
+                preview.would_cancel_partial,
+                single_redemption.canceled_partial
+            );
+            if !preview.would_close && !preview.would_cancel_partial {
+                assert_eq!(trove.debt, load_trove().debt - preview.usv_lot);
+                assert_eq!(trove.coll, load_trove().coll - preview.coll_lot);
+            }
+        }
+
+        let trove = load_trove();
+        let pool_state = load_pool_state();
+        let preview =
+            preview_redemption_from_trove(&trove, 4_000_000_000, price, &pool_state).unwrap();
+        assert!(preview.would_cancel_partial);
+        let preview =
+            preview_redemption_from_trove(&trove, 6_000_000_000, price, &pool_state).unwrap();
+        assert!(preview.would_close);
+        assert_eq!(preview.usv_lot, 4_800_000_000);
+    }
+
+    #[test]
+    fn redemption_close_keeps_borrower_held_gas_comp_owed() {
+        let price = 2_000_000_000;
+
+        let mut pool_state = load_pool_state();
+        let mut trove = load_trove();
+        let single_redemption =
+            redeem_collateral_from_trove(&mut trove, 6_000_000_000, price, &mut pool_state)
+                .unwrap();
+        assert!(trove.status == TroveStatus::ClosedByRedemption);
+        assert_eq!(single_redemption.usv_gas_to_burn, 200_000_000);
+        assert_eq!(trove.gas_comp_owed, 0);
+        assert_eq!(pool_state.active_debt, 4_800_000_000);
+
+        let mut pool_state = load_pool_state();
+        let mut trove = Trove {
+            gas_comp_held_by_borrower: true,
+            ..load_trove()
+        };
+        let single_redemption =
+            redeem_collateral_from_trove(&mut trove, 6_000_000_000, price, &mut pool_state)
+                .unwrap();
+        assert!(trove.status == TroveStatus::ClosedByRedemption);
+        assert_eq!(single_redemption.usv_gas_to_burn, 0);
+        assert_eq!(trove.gas_comp_owed, 200_000_000);
+        // The unburned reserve stays in the debt backing the USV supply
+        assert_eq!(pool_state.active_debt, 5_000_000_000);
+
+        // The trove can't reopen nor be closed until the borrower repays it
+        assert_eq!(
+            trove.require_no_gas_comp_owed().unwrap_err(),
+            BorrowerOpsError::GasCompensationOwed.into()
+        );
+        assert_eq!(
+            trove.require_account_closable().unwrap_err(),
+            BorrowerOpsError::TroveAccountNotClosable.into()
+        );
+        assert_eq!(trove.clear_gas_comp_owed(), 200_000_000);
+        assert_eq!(trove.clear_surplus(), 7_600_000_000);
+        trove.require_no_gas_comp_owed().unwrap();
+        trove.require_account_closable().unwrap();
+    }
+
+    #[test]
+    fn coll_lot_rounds_down_for_redeemer() {
+        // Prices that don't divide the USV lot value
+        let prices = [
+            3,
+            7,
+            1_999_999_999,
+            2_000_000_001,
+            2_333_333_333,
+            149_876_543_211,
+        ];
+        for price in prices {
+            let mut total_usv: u128 = 0;
+            let mut total_coll: u128 = 0;
+            for usv_lot in [1, 999, 1_000_000_007, 4_800_000_000] {
+                let coll_lot = get_redemption_coll_lot(usv_lot, price);
+                let value = u128::from(usv_lot) * u128::from(DECIMAL_PRECISION);
+                assert!(u128::from(coll_lot) * u128::from(price) <= value);
+                assert!(u128::from(coll_lot + 1) * u128::from(price) > value);
+                total_usv += u128::from(usv_lot);
+                total_coll += u128::from(coll_lot);
+            }
+            // Accumulated over many troves the redeemer still draws at most the exact value
+            assert!(total_coll * u128::from(price) <= total_usv * u128::from(DECIMAL_PRECISION));
+        }
+    }
+
+    /// Pool state at its PDA, so that troves can point to its key
+    fn load_listed_pool_state() -> PoolState {
+        let cvgt = Pubkey::new_unique();
+        let (_, bump) = Pubkey::find_program_address(&[b"state", cvgt.as_ref()], &ID);
+        PoolState {
+            cvgt,
+            bump: [bump],
+            mcr: 1_100_000_000,
+            nicr_tie_break_enforced: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn drifted_start_hint_within_tolerance() {
+        const UNIT: u64 = 1_000_000_000;
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut pool_state = load_listed_pool_state();
+        // The hinted trove drifted to 112%, the next one is at 150%
+        let accounts: Vec<AccountInfo> = [
+            (112 * UNIT, keys[1], Pubkey::default()),
+            (150 * UNIT, Pubkey::default(), keys[0]),
+        ]
+        .iter()
+        .zip(keys)
+        .map(|(&(coll, prev, next), key)| {
+            let trove = Trove {
+                pool_state: pool_state.key(),
+                coll,
+                debt: 100 * UNIT,
+                status: TroveStatus::Active,
+                prev,
+                next,
+                ..Default::default()
+            };
+            load_trove_account(key, &trove)
+        })
+        .collect();
+
+        assert_eq!(
+            skim_provided_troves(&accounts, &pool_state, DECIMAL_PRECISION).unwrap_err(),
+            BorrowerOpsError::InvalidTroveNeighbor.into()
+        );
+
+        // 1% over MCR is not enough
+        pool_state.set_redemption_hint_tolerance_bps(100).unwrap();
+        assert_eq!(
+            skim_provided_troves(&accounts, &pool_state, DECIMAL_PRECISION).unwrap_err(),
+            BorrowerOpsError::InvalidTroveNeighbor.into()
+        );
+
+        // Within 2% the hinted trove is skipped and redemption starts from the next one
+        pool_state.set_redemption_hint_tolerance_bps(200).unwrap();
+        assert_eq!(
+            skim_provided_troves(&accounts, &pool_state, DECIMAL_PRECISION).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn equal_nicr_troves_redeemed_in_pubkey_order() {
+        const UNIT: u64 = 1_000_000_000;
+        let mut pool_state = load_listed_pool_state();
+        let mut equal_keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        equal_keys.sort();
+        let [low, high] = equal_keys;
+        let under_mcr = Pubkey::new_unique();
+
+        // `walk` goes from the tail towards the head, as the remaining accounts of a redemption
+        let skim = |walk: [Pubkey; 3], pool_state: &PoolState| {
+            let accounts: Vec<AccountInfo> = walk
+                .iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    let trove = Trove {
+                        pool_state: pool_state.key(),
+                        coll: if *key == under_mcr {
+                            100 * UNIT
+                        } else {
+                            150 * UNIT
+                        },
+                        debt: 100 * UNIT,
+                        status: TroveStatus::Active,
+                        prev: walk.get(i + 1).copied().unwrap_or_default(),
+                        next: if i == 0 {
+                            Pubkey::default()
+                        } else {
+                            walk[i - 1]
+                        },
+                        ..Default::default()
+                    };
+                    load_trove_account(*key, &trove)
+                })
+                .collect();
+            skim_provided_troves(&accounts, pool_state, DECIMAL_PRECISION)
+        };
+
+        // The higher pubkey sits closer to the tail and is redeemed first
+        assert_eq!(skim([under_mcr, high, low], &pool_state).unwrap(), 1);
+        assert_eq!(
+            skim([under_mcr, low, high], &pool_state).unwrap_err(),
+            BorrowerOpsError::InvalidTroveNeighbor.into()
+        );
+
+        // A list sorted before the tie-break is walked as linked until the rule is enabled
+        pool_state.nicr_tie_break_enforced = false;
+        assert_eq!(skim([under_mcr, low, high], &pool_state).unwrap(), 1);
+        assert_eq!(skim([under_mcr, high, low], &pool_state).unwrap(), 1);
+    }
+
+    #[test]
+    fn coll_lot_above_trove_coll_is_rejected() {
+        let mut pool_state = load_pool_state();
+        let mut trove = Trove {
+            coll: 1_000_000_000,
+            ..load_trove()
+        };
+        // 4.8 USV at a price of 1 is worth more than the trove coll
+        assert_eq!(
+            redeem_collateral_from_trove(&mut trove, 4_800_000_000, 1_000_000_000, &mut pool_state)
+                .err()
+                .unwrap(),
+            BorrowerOpsError::RedemptionCollAboveTroveColl.into()
+        );
+        assert_eq!(trove.coll, 1_000_000_000);
+    }
+
+    /// Accounts of `redeem_collateral` in declaration order, without trove neighbors
+    fn load_redeem_accounts(
+        pool_state: &PoolState,
+        with_treasury: bool,
+        coll_in_vault: u64,
+    ) -> Vec<AccountInfo<'static>> {
+        let absent = || load_program(ID);
+        let redeemer = Pubkey::new_unique();
+        let pool_state_key = Pubkey::new_unique();
+        let (token_authority, _) =
+            Pubkey::find_program_address(&[b"token-authority", pool_state_key.as_ref()], &ID);
+        let (community_issuance_config, _) =
+            Pubkey::find_program_address(&[b"community-issuance", pool_state.cvgt.as_ref()], &ID);
+        let (price_feed_key, price_feed_bump) =
+            Pubkey::find_program_address(&[b"price_feed", pool_state.cvgt.as_ref()], &ID);
+        let price_feed_state = PriceFeedState {
+            bump: price_feed_bump,
+            ..load_price_feed_info(DECIMAL_PRECISION, Status::PythWorking)
+        };
+        let token_account = |mint: Pubkey, owner: Pubkey, amount: u64| {
+            load_account(
+                get_associated_token_address(&owner, &mint),
+                spl_token::ID,
+                load_token_account(mint, owner, amount),
+            )
+        };
+        let mut pyth_feed_account = PriceUpdateV2::DISCRIMINATOR.to_vec();
+        pyth_feed_account.resize(PriceUpdateV2::LEN, 0);
+        // `init_if_needed` checks an existing ATA is rent exempt
+        let user_coll_ata = token_account(pool_state.collateral, redeemer, 0);
+        **user_coll_ata.lamports.borrow_mut() = Rent::default().minimum_balance(TokenAccount::LEN);
+
+        vec![
+            load_anchor_account(pool_state_key, pool_state),
+            absent(),
+            absent(),
+            token_account(pool_state.stablecoin, redeemer, 0),
+            token_account(pool_state.collateral, pool_state.cvgt_staking_state, 0),
+            if with_treasury {
+                token_account(pool_state.collateral, TREASURY_VAULT, 0)
+            } else {
+                absent()
+            },
+            load_account(
+                pool_state.stablecoin,
+                spl_token::ID,
+                load_mint(token_authority),
+            ),
+            load_account(
+                pool_state.collateral,
+                spl_token::ID,
+                load_mint(token_authority),
+            ),
+            load_account(token_authority, Pubkey::default(), vec![]),
+            user_coll_ata,
+            token_account(pool_state.collateral, token_authority, coll_in_vault),
+            token_account(pool_state.stablecoin, token_authority, 0),
+            load_signer(redeemer, 1_000_000_000),
+            load_anchor_account(
+                community_issuance_config,
+                &CommunityIssuanceConfig {
+                    enable_emission: true,
+                    ..Default::default()
+                },
+            ),
+            load_anchor_account(
+                pool_state.cvgt_staking_state,
+                &CVGTStakingPoolState {
+                    usv: pool_state.stablecoin,
+                    collateral: pool_state.collateral,
+                    cvgt: pool_state.cvgt,
+                    f_usv: 0,
+                    f_coll: 0,
+                    total_cvgt_staked: 1_000 * DECIMAL_PRECISION,
+                    bump: [0],
+                    min_stake_duration: 0,
+                },
+            ),
+            load_anchor_account(price_feed_key, &price_feed_state),
+            load_account(
+                price_feed_state.pyth_feed_account,
+                pyth_solana_receiver_sdk::ID,
+                pyth_feed_account,
+            ),
+            load_account(price_feed_state.chainlink_feed, Pubkey::default(), vec![]),
+            load_account(
+                price_feed_state.jitosol_stake_pool,
+                Pubkey::default(),
+                vec![],
+            ),
+            load_program(chainlink_solana::ID),
+            load_program(spl_token::ID),
+            load_program(anchor_spl::associated_token::ID),
+            load_program(System::id()),
+        ]
+    }
+
+    fn move_redeemed_tokens(
+        accounts: &[AccountInfo<'static>],
+        totals: &RedemptionTotals,
+    ) -> Result<()> {
+        // Clones share the lamports and data of `accounts`
+        let mut infos: &[AccountInfo<'static>] = Box::leak(accounts.to_vec().into_boxed_slice());
+        let mut bumps = RedeemCollateralBumps::default();
+        let mut redeem_accounts =
+            RedeemCollateral::try_accounts(&ID, &mut infos, &[], &mut bumps, &mut BTreeSet::new())?;
+        move_token_from_redeem(Context::new(&ID, &mut redeem_accounts, &[], bumps), totals)
+    }
+
+    fn read_f_coll(info: &AccountInfo) -> u64 {
+        CVGTStakingPoolState::try_deserialize(&mut &info.try_borrow_data().unwrap()[..])
+            .unwrap()
+            .f_coll
+    }
+
+    #[test]
+    fn redemption_fee_split_moves_treasury_share() {
+        set_program_test_stubs();
+        let mut pool_state = PoolState {
+            stablecoin: Pubkey::new_unique(),
+            collateral: Pubkey::new_unique(),
+            cvgt: Pubkey::new_unique(),
+            cvgt_staking_state: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        pool_state.set_redemption_fee_treasury_bps(3_000).unwrap();
+        let mut totals = RedemptionTotals {
+            coll_fee: 1_000_000_000,
+            coll_to_send_to_redeemer: 9_000_000_000,
+            ..Default::default()
+        };
+        (totals.coll_fee_to_treasury, totals.coll_fee_to_stakers) =
+            pool_state.split_redemption_fee(totals.coll_fee);
+
+        let accounts = load_redeem_accounts(&pool_state, true, 10_000_000_000);
+        move_redeemed_tokens(&accounts, &totals).unwrap();
+        assert_eq!(read_token_amount(&accounts[5]), 300_000_000);
+        assert_eq!(read_token_amount(&accounts[4]), 700_000_000);
+        assert_eq!(read_token_amount(&accounts[9]), 9_000_000_000);
+        assert_eq!(read_token_amount(&accounts[10]), 0);
+        // 0.7 collateral per 1000 CVGT staked
+        assert_eq!(read_f_coll(&accounts[14]), 700_000);
+
+        // The treasury ATA is required for its share
+        let accounts = load_redeem_accounts(&pool_state, false, 10_000_000_000);
+        assert_eq!(
+            move_redeemed_tokens(&accounts, &totals).unwrap_err(),
+            BorrowerOpsError::MissingRedemptionFeeTreasury.into()
+        );
+    }
+
+    #[test]
+    fn redemption_fee_without_treasury_share_needs_no_treasury_ata() {
+        set_program_test_stubs();
+        let pool_state = PoolState {
+            stablecoin: Pubkey::new_unique(),
+            collateral: Pubkey::new_unique(),
+            cvgt: Pubkey::new_unique(),
+            cvgt_staking_state: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        let mut totals = RedemptionTotals {
+            coll_fee: 1_000_000_000,
+            ..Default::default()
+        };
+        (totals.coll_fee_to_treasury, totals.coll_fee_to_stakers) =
+            pool_state.split_redemption_fee(totals.coll_fee);
+
+        let accounts = load_redeem_accounts(&pool_state, false, 1_000_000_000);
+        move_redeemed_tokens(&accounts, &totals).unwrap();
+        assert_eq!(read_token_amount(&accounts[4]), 1_000_000_000);
+        assert_eq!(read_f_coll(&accounts[14]), 1_000_000);
+    }
+}
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::{
+    errors::PriceFeedError,
+    state::{PoolState, PriceFeedState, Trove},
+};
+
+use super::{preview_redemption_from_trove, TroveRedemptionPreview};
+
+#[derive(Accounts)]
+pub struct PreviewRedeemFromTrove<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"trove",
+            pool_state.key().as_ref(),
+            borrower.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub trove: Box<Account<'info, Trove>>,
+
+    /// CHECK: Only used to derive the trove address
+    pub borrower: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"price_feed",
+            pool_state.cvgt.as_ref()
+        ],
+        bump = price_feed_state.bump
+    )]
+    pub price_feed_state: Box<Account<'info, PriceFeedState>>,
+
+    #[account(
+        constraint = pyth_feed_account.key() == price_feed_state.pyth_feed_account @ PriceFeedError::PythWrongFeed
+    )]
+    pub pyth_feed_account: Box<Account<'info, PriceUpdateV2>>,
+
+    #[account(
+        constraint = chainlink_feed.key == &price_feed_state.chainlink_feed @ PriceFeedError::ChainlinkWrongFeed
+    )]
+    /// CHECK: This is the Chainlink feed account
+    pub chainlink_feed: AccountInfo<'info>,
+
+    #[account(
+        constraint = jitosol_stake_pool.key() == price_feed_state.jitosol_stake_pool @ PriceFeedError::JitoSolStakePoolWrong
+    )]
+    /// CHECK: This is the Jito staking pool
+    pub jitosol_stake_pool: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = chainlink_program.key() == chainlink_solana::ID
+    )]
+    /// CHECK: This is the Chainlink program library
+    pub chainlink_program: AccountInfo<'info>,
+}
+
+/// Read-only: what `redeem_collateral` would draw from this one trove for `usv_amt` at the current
+/// price, before the redemption fee. Redemptions skip collateral-only troves, they preview as empty.
+pub fn redeem_from_trove_preview_handler(
+    ctx: Context<PreviewRedeemFromTrove>,
+    usv_amt: u64,
+) -> Result<TroveRedemptionPreview> {
+    let price = ctx.accounts.price_feed_state.fetch_price(
+        &ctx.accounts.chainlink_program,
+        &ctx.accounts.chainlink_feed,
+        &ctx.accounts.jitosol_stake_pool,
+        &ctx.accounts.pyth_feed_account,
+    )?;
+    let pool_state = &ctx.accounts.pool_state;
+    let trove = &ctx.accounts.trove;
+
+    trove.require_trove_active()?;
+    if trove.coll_only {
+        return Ok(Default::default());
+    }
+
+    // Redemptions apply pending rewards first
+    let mut current_trove = (***trove).clone();
+    (current_trove.coll, current_trove.debt) = trove.get_current_amounts(pool_state);
+
+    preview_redemption_from_trove(&current_trove, usv_amt, price, pool_state)
+}
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::PriceFeedError, state::PriceFeedState};
+
+#[derive(Accounts)]
+pub struct SetJitoSolMaxEpochLag<'info> {
+    #[account(mut)]
+    pub price_feed_state: Account<'info, PriceFeedState>,
+
+    #[account(
+        constraint = creator.key() == price_feed_state.creator @ PriceFeedError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+}
+
+/// Lets prices be fetched right after an epoch boundary, before the stake pool is updated
+pub fn set_jitosol_max_epoch_lag_handler(
+    ctx: Context<SetJitoSolMaxEpochLag>,
+    jitosol_max_epoch_lag: u64,
+) -> Result<()> {
+    ctx.accounts
+        .price_feed_state
+        .set_jitosol_max_epoch_lag(jitosol_max_epoch_lag)
+}
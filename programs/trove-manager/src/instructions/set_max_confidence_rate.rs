@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+use crate::{errors::PriceFeedError, state::PriceFeedState};
+
+#[derive(Accounts)]
+pub struct SetMaxConfidenceRate<'info> {
+    #[account(mut)]
+    pub price_feed_state: Account<'info, PriceFeedState>,
+
+    #[account(
+        constraint = creator.key() == price_feed_state.creator @ PriceFeedError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+}
+
+/// Above this rate a Pyth answer is cross-checked with the secondary source before it is used
+pub fn set_max_confidence_rate_handler(
+    ctx: Context<SetMaxConfidenceRate>,
+    max_confidence_rate: u64,
+) -> Result<()> {
+    ctx.accounts
+        .price_feed_state
+        .set_max_confidence_rate(max_confidence_rate)
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::PriceFeedError,
+    state::{PriceFeedState, SecondarySource},
+    utils::SwitchboardResponse,
+};
+
+#[derive(Accounts)]
+pub struct SetSecondarySource<'info> {
+    #[account(mut)]
+    pub price_feed_state: Account<'info, PriceFeedState>,
+
+    #[account(
+        constraint = creator.key() == price_feed_state.creator @ PriceFeedError::InvalidSigner
+    )]
+    pub creator: Signer<'info>,
+
+    /// CHECK: Chainlink feed or Switchboard aggregator, the aggregator is checked in the handler
+    pub secondary_feed: AccountInfo<'info>,
+}
+
+/// Switches the oracle used as the fallback of Pyth, the status is kept
+pub fn set_secondary_source_handler(
+    ctx: Context<SetSecondarySource>,
+    secondary_source: SecondarySource,
+) -> Result<()> {
+    if secondary_source == SecondarySource::Switchboard {
+        SwitchboardResponse::from_aggregator(&ctx.accounts.secondary_feed)?;
+    }
+    let price_feed_state = &mut ctx.accounts.price_feed_state;
+    price_feed_state.secondary_source = secondary_source;
+    price_feed_state.chainlink_feed = ctx.accounts.secondary_feed.key();
+    Ok(())
+}
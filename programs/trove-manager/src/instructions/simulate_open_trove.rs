@@ -0,0 +1,310 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::{
+    errors::PriceFeedError,
+    state::{PoolState, PriceFeedState},
+    utils::{require_user_accepts_fee, require_valid_borrow_max_fee_percentage},
+};
+
+use super::{get_open_trove_values, require_can_open_trove, OpenTroveValues};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenTroveSimulation {
+    pub would_succeed: bool,
+    pub icr: u64,
+    pub nicr: u64,
+    pub new_tcr: u64,
+    pub usv_fee: u64,
+    // Error code `open_trove` would fail with
+    pub revert_reason: Option<u64>,
+}
+
+#[derive(Accounts)]
+pub struct SimulateOpenTrove<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"price_feed",
+            pool_state.cvgt.as_ref()
+        ],
+        bump = price_feed_state.bump
+    )]
+    pub price_feed_state: Box<Account<'info, PriceFeedState>>,
+
+    #[account(
+        constraint = pyth_feed_account.key() == price_feed_state.pyth_feed_account @ PriceFeedError::PythWrongFeed
+    )]
+    pub pyth_feed_account: Box<Account<'info, PriceUpdateV2>>,
+
+    #[account(
+        constraint = chainlink_feed.key == &price_feed_state.chainlink_feed @ PriceFeedError::ChainlinkWrongFeed
+    )]
+    /// CHECK: This is the Chainlink feed account
+    pub chainlink_feed: AccountInfo<'info>,
+
+    #[account(
+        constraint = jitosol_stake_pool.key() == price_feed_state.jitosol_stake_pool @ PriceFeedError::JitoSolStakePoolWrong
+    )]
+    /// CHECK: This is the Jito staking pool
+    pub jitosol_stake_pool: UncheckedAccount<'info>,
+
+    #[account(
+        constraint = chainlink_program.key() == chainlink_solana::ID
+    )]
+    /// CHECK: This is the Chainlink program library
+    pub chainlink_program: AccountInfo<'info>,
+}
+
+/// Runs the `open_trove` checks for `coll_amt` of collateral, without minting or moving tokens.
+/// Collateral is taken as already staked, so `min_coll_out` is not checked.
+pub fn simulate_open_trove_handler(
+    ctx: Context<SimulateOpenTrove>,
+    coll_amt: u64,
+    usv_amt: u64,
+    max_fee_percentage: u64,
+) -> Result<OpenTroveSimulation> {
+    let price = ctx.accounts.price_feed_state.fetch_price(
+        &ctx.accounts.chainlink_program,
+        &ctx.accounts.chainlink_feed,
+        &ctx.accounts.jitosol_stake_pool,
+        &ctx.accounts.pyth_feed_account,
+    )?;
+    let pool_state = &ctx.accounts.pool_state;
+    let borrowing_fee = pool_state.get_decayed_borrowing_fee(usv_amt, price);
+
+    Ok(simulate_open_trove(
+        pool_state,
+        &ctx.accounts.price_feed_state,
+        coll_amt,
+        usv_amt,
+        max_fee_percentage,
+        borrowing_fee,
+        price,
+    ))
+}
+
+/// `borrowing_fee` is only charged outside of recovery mode, as in `open_trove`
+pub fn simulate_open_trove(
+    pool_state: &PoolState,
+    price_feed_state: &PriceFeedState,
+    coll_amt: u64,
+    usv_amt: u64,
+    max_fee_percentage: u64,
+    borrowing_fee: u64,
+    price: u64,
+) -> OpenTroveSimulation {
+    let is_recovery_mode = pool_state.check_recovery_mode(price);
+    let usv_fee = if is_recovery_mode { 0 } else { borrowing_fee };
+
+    let values = require_can_open_trove(pool_state, price_feed_state, usv_amt)
+        .and_then(|_| require_valid_borrow_max_fee_percentage(max_fee_percentage, is_recovery_mode))
+        .and_then(|_| {
+            if is_recovery_mode || usv_amt == 0 {
+                return Ok(());
+            }
+            require_user_accepts_fee(usv_fee, usv_amt, max_fee_percentage)
+        })
+        .and_then(|_| {
+            get_open_trove_values(
+                pool_state,
+                coll_amt,
+                usv_amt.checked_add(usv_fee).unwrap(),
+                price,
+                is_recovery_mode,
+            )
+        });
+
+    match values {
+        Ok(OpenTroveValues {
+            icr, nicr, new_tcr, ..
+        }) => OpenTroveSimulation {
+            would_succeed: true,
+            icr,
+            nicr,
+            new_tcr,
+            usv_fee,
+            revert_reason: None,
+        },
+        Err(error) => OpenTroveSimulation {
+            usv_fee,
+            revert_reason: Some(ProgramError::from(error).into()),
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+pub mod simulate_open_trove_test {
+    use super::*;
+    use crate::{
+        errors::BorrowerOpsError,
+        state::{price_feed_info_test::load_price_feed_info, Status},
+    };
+
+    const PRICE: u64 = 100_000_000_000;
+
+    fn load_pool_state() -> PoolState {
+        PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 100_000_000_000,
+            gas_compensation: 10_000_000_000,
+            active_coll: 100_000_000_000,
+            active_debt: 2_000_000_000_000,
+            ..Default::default()
+        }
+    }
+
+    fn error_code(error: BorrowerOpsError) -> Option<u64> {
+        Some(ProgramError::from(Error::from(error)).into())
+    }
+
+    #[test]
+    fn simulation_returns_values_of_valid_trove() {
+        let pool_state = load_pool_state();
+        // 0.5% fee floor
+        let simulation = simulate_open_trove(
+            &pool_state,
+            &load_price_feed_info(PRICE, Status::PythWorking),
+            10_000_000_000,
+            500_000_000_000,
+            5_000_000,
+            2_500_000_000,
+            PRICE,
+        );
+
+        assert_eq!(
+            simulation,
+            OpenTroveSimulation {
+                would_succeed: true,
+                icr: 1_951_219_512,
+                nicr: 1_951_219_512,
+                new_tcr: 4_378_109_452,
+                usv_fee: 2_500_000_000,
+                revert_reason: None,
+            }
+        );
+    }
+
+    #[test]
+    fn simulation_of_coll_only_trove() {
+        let pool_state = load_pool_state();
+        let simulation = simulate_open_trove(
+            &pool_state,
+            &load_price_feed_info(PRICE, Status::PythWorking),
+            10_000_000_000,
+            0,
+            5_000_000,
+            0,
+            PRICE,
+        );
+
+        assert!(simulation.would_succeed);
+        assert_eq!(simulation.icr, u64::MAX);
+        assert_eq!(simulation.nicr, u64::MAX);
+        assert_eq!(simulation.usv_fee, 0);
+    }
+
+    #[test]
+    fn simulation_reports_under_mcr() {
+        let pool_state = load_pool_state();
+        let simulation = simulate_open_trove(
+            &pool_state,
+            &load_price_feed_info(PRICE, Status::PythWorking),
+            10_000_000_000,
+            900_000_000_000,
+            5_000_000,
+            4_500_000_000,
+            PRICE,
+        );
+
+        assert!(!simulation.would_succeed);
+        assert_eq!(simulation.nicr, 0);
+        assert_eq!(simulation.usv_fee, 4_500_000_000);
+        assert_eq!(
+            simulation.revert_reason,
+            error_code(BorrowerOpsError::ICRLowerThanMCR)
+        );
+    }
+
+    #[test]
+    fn simulation_reports_fee_above_max() {
+        let pool_state = load_pool_state();
+        let simulation = simulate_open_trove(
+            &pool_state,
+            &load_price_feed_info(PRICE, Status::PythWorking),
+            10_000_000_000,
+            500_000_000_000,
+            5_000_000,
+            5_000_000_000,
+            PRICE,
+        );
+
+        assert!(!simulation.would_succeed);
+        assert_eq!(
+            simulation.revert_reason,
+            error_code(BorrowerOpsError::FeeExceededMax)
+        );
+    }
+
+    #[test]
+    fn simulation_reports_trove_count_at_max() {
+        let pool_state = PoolState {
+            trove_size: 3,
+            max_troves: 3,
+            ..load_pool_state()
+        };
+        let simulation = simulate_open_trove(
+            &pool_state,
+            &load_price_feed_info(PRICE, Status::PythWorking),
+            10_000_000_000,
+            500_000_000_000,
+            5_000_000,
+            2_500_000_000,
+            PRICE,
+        );
+
+        assert!(!simulation.would_succeed);
+        assert_eq!(
+            simulation.revert_reason,
+            error_code(BorrowerOpsError::TroveCountAtMax)
+        );
+    }
+
+    #[test]
+    fn simulation_reports_untrusted_price_for_debt() {
+        let pool_state = load_pool_state();
+        let price_feed_state = load_price_feed_info(PRICE, Status::BothOraclesUntrusted);
+        let simulation = simulate_open_trove(
+            &pool_state,
+            &price_feed_state,
+            10_000_000_000,
+            500_000_000_000,
+            5_000_000,
+            2_500_000_000,
+            PRICE,
+        );
+
+        assert!(!simulation.would_succeed);
+        assert_eq!(
+            simulation.revert_reason,
+            Some(ProgramError::from(Error::from(PriceFeedError::BothOraclesUntrusted)).into())
+        );
+
+        // A collateral-only trove takes no debt off the untrusted price
+        let simulation = simulate_open_trove(
+            &pool_state,
+            &price_feed_state,
+            10_000_000_000,
+            0,
+            5_000_000,
+            0,
+            PRICE,
+        );
+        assert!(simulation.would_succeed);
+    }
+}
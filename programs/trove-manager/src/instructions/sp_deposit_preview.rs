@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::StabilityPoolError,
+    state::{
+        get_epoch_scales, CommunityIssuanceConfig, EpochScale, PoolState, SPDepositPreview,
+        StabilityPoolDeposit, StabilityPoolState,
+    },
+};
+
+#[derive(Accounts)]
+pub struct PreviewSPDeposit<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            depositor.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(
+        seeds = [
+            b"epoch-scale",
+            stability_pool_state.key().as_ref(),
+            stability_pool_state.current_epoch.to_le_bytes().as_ref(),
+            stability_pool_state.current_scale.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub current_epoch_scale: Box<Account<'info, EpochScale>>,
+
+    /// CHECK: Only used to derive the deposit address
+    pub depositor: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [
+            b"community-issuance",
+            pool_state.cvgt.as_ref()
+        ],
+        bump
+    )]
+    pub community_issuance_config: Box<Account<'info, CommunityIssuanceConfig>>,
+}
+
+/// Read-only: returns what `withdraw_from_sp(0)` would leave claimable now, including CVGT not issued yet
+/// and gains realized earlier.
+/// `remaining_accounts` holds the two epoch-scale accounts from the deposit's snapshot, as in `withdraw_from_sp`.
+pub fn sp_deposit_preview_handler(ctx: Context<PreviewSPDeposit>) -> Result<SPDepositPreview> {
+    let sp_state = &ctx.accounts.stability_pool_state;
+    let sp_deposit = &ctx.accounts.stability_pool_deposit;
+    let current_epoch_scale_key = &ctx.accounts.current_epoch_scale.key();
+
+    sp_deposit.require_user_has_deposit()?;
+
+    let cvgt_issuance = ctx
+        .accounts
+        .community_issuance_config
+        .get_pending_issuance()?;
+    let mut current_epoch_scale = (**ctx.accounts.current_epoch_scale).clone();
+    current_epoch_scale.g = current_epoch_scale.get_pending_g(sp_state, cvgt_issuance);
+
+    let (first_epoch_scale, second_epoch_scale) = get_epoch_scales(
+        ctx.remaining_accounts,
+        current_epoch_scale_key,
+        &current_epoch_scale,
+        sp_state,
+        sp_deposit,
+    )?;
+
+    let preview = sp_deposit
+        .preview(sp_state, &first_epoch_scale, &second_epoch_scale)
+        .ok_or(StabilityPoolError::InvalidEpochScale)?;
+    Ok(preview)
+}
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, Token, TokenAccount},
+    token_2022::{burn, Burn},
+};
+
+use crate::state::PoolState;
+
+#[derive(Accounts)]
+pub struct SweepGasCompResidual<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        constraint = creator.key() == pool_state.creator
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = stablecoin.key() == pool_state.stablecoin
+    )]
+    pub stablecoin: Box<Account<'info, Mint>>,
+
+    /// CHECK: This account is not read or written
+    #[account(
+        seeds = [
+            b"token-authority",
+            pool_state.key().as_ref()
+        ],
+        bump
+    )]
+    pub token_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = stablecoin,
+        associated_token::authority = token_authority
+    )]
+    pub gas_compensation_vault: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> SweepGasCompResidual<'info> {
+    pub fn burn_stablecoin_from_gas_compensation_ctx(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        let cpi_accounts = Burn {
+            mint: self.stablecoin.to_account_info(),
+            from: self.gas_compensation_vault.to_account_info(),
+            authority: self.token_authority.to_account_info(),
+        };
+
+        let cpi_program = self.token_program.to_account_info();
+
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+/// Burns gas compensation vault USV above `trove_size * gas_compensation`
+pub fn sweep_gas_comp_residual_handler(ctx: Context<SweepGasCompResidual>) -> Result<()> {
+    let pool_state = &ctx.accounts.pool_state;
+    let residual = pool_state.get_gas_comp_residual(ctx.accounts.gas_compensation_vault.amount);
+    if residual == 0 {
+        return Ok(());
+    }
+
+    let pool_state_key = pool_state.key();
+    let authority_seed = &pool_state.token_auth_seeds(&pool_state_key);
+    burn(
+        ctx.accounts
+            .burn_stablecoin_from_gas_compensation_ctx()
+            .with_signer(&[&authority_seed[..]]),
+        residual,
+    )?;
+    Ok(())
+}
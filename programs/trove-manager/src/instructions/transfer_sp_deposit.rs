@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    events::SPDepositTransferred,
+    state::{PoolState, StabilityPoolDeposit, StabilityPoolState},
+};
+
+#[derive(Accounts)]
+pub struct TransferSPDeposit<'info> {
+    #[account()]
+    pub pool_state: Box<Account<'info, PoolState>>,
+
+    #[account(
+        seeds = [
+            b"stability",
+            pool_state.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_state: Box<Account<'info, StabilityPoolState>>,
+
+    #[account(
+        mut,
+        close = depositor,
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            depositor.key().as_ref(),
+        ],
+        bump
+    )]
+    pub stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = 8 + StabilityPoolDeposit::INIT_SPACE,
+        seeds = [
+            b"sp-deposit",
+            stability_pool_state.key().as_ref(),
+            new_depositor.key().as_ref(),
+        ],
+        bump
+    )]
+    pub new_stability_pool_deposit: Box<Account<'info, StabilityPoolDeposit>>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    /// CHECK: Only used as the seed of the destination deposit
+    pub new_depositor: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Hands the whole SP position to another wallet without realizing gains or resetting snapshots.
+/// The source deposit account is closed and its rent returned to the depositor.
+pub fn transfer_sp_deposit_handler(ctx: Context<TransferSPDeposit>) -> Result<()> {
+    let sp_deposit = &mut ctx.accounts.stability_pool_deposit;
+    let new_sp_deposit = &mut ctx.accounts.new_stability_pool_deposit;
+
+    sp_deposit.require_user_has_deposit()?;
+    sp_deposit.transfer_to(new_sp_deposit)?;
+
+    emit!(SPDepositTransferred {
+        from: ctx.accounts.depositor.key(),
+        to: ctx.accounts.new_depositor.key(),
+        initial_value: new_sp_deposit.initial_value,
+    });
+
+    Ok(())
+}
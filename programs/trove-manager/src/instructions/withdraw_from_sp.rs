@@ -15,6 +15,7 @@ use crate::{
         get_epoch_scales, CommunityIssuanceConfig, EpochScale, PoolState, PriceFeedState,
         StabilityPoolDeposit, StabilityPoolState, Trove,
     },
+    utils::require_valid_lowest_trove,
 };
 
 #[derive(Accounts)]
@@ -56,7 +57,7 @@ pub struct WithdrawFromSP<'info> {
     pub current_epoch_scale: Box<Account<'info, EpochScale>>,
 
     #[account(
-        constraint = lowest_trove.key() == pool_state.trove_tail
+        constraint = lowest_trove.key() == pool_state.trove_tail @ StabilityPoolError::InvalidLowestTrove
     )]
     pub lowest_trove: Option<Box<Account<'info, Trove>>>,
 
@@ -169,6 +170,28 @@ impl<'info> WithdrawFromSP<'info> {
     }
 }
 
+/// USV leaving the pool must not leave the lowest trove below MCR. A deposit emptied by
+/// liquidations, or compounded below a billionth of its initial value, withdraws no USV and
+/// realizes its coll gain without the price or a healthy lowest trove.
+pub fn require_lowest_trove_allows_withdrawal(
+    pool_state: &PoolState,
+    lowest_trove: Option<(Pubkey, &Trove)>,
+    usv_to_withdraw: u64,
+    fetch_price: impl FnOnce() -> Result<u64>,
+) -> Result<()> {
+    if usv_to_withdraw == 0 {
+        return Ok(());
+    }
+
+    // An empty list has a default tail, which no trove account can match
+    require_valid_lowest_trove(lowest_trove.map(|(key, _)| key), pool_state.trove_tail)?;
+    if let Some((_, lowest_trove)) = lowest_trove {
+        let icr = lowest_trove.get_current_icr(pool_state, fetch_price()?);
+        require!(icr >= pool_state.mcr, StabilityPoolError::TroveUnderColl);
+    }
+    Ok(())
+}
+
 pub fn withdraw_from_sp_handler(ctx: Context<WithdrawFromSP>, usv_amt: u64) -> Result<()> {
     let cvgt_issuance = ctx.accounts.issue_cvgt()?;
 
@@ -182,31 +205,12 @@ pub fn withdraw_from_sp_handler(ctx: Context<WithdrawFromSP>, usv_amt: u64) -> R
 
     current_epoch_scale.update_g(sp_state, cvgt_issuance);
 
-    if usv_amt > 0 {
-        if lowest_trove_option.is_some() {
-            let lowest_trove = lowest_trove_option.as_ref().unwrap().as_ref();
-            let price = ctx.accounts.price_feed_state.fetch_price(
-                &ctx.accounts.chainlink_program,
-                &ctx.accounts.chainlink_feed,
-                &ctx.accounts.jitosol_stake_pool,
-                &ctx.accounts.pyth_feed_account,
-            )?;
-            let icr = lowest_trove.get_current_icr(pool_state, price);
-            require!(icr >= pool_state.mcr, StabilityPoolError::TroveUnderColl);
-        } else {
-            require!(
-                pool_state.trove_tail == Pubkey::default(),
-                StabilityPoolError::InvalidLowestTrove
-            );
-        }
-    }
-
     sp_deposit.require_user_has_deposit()?;
 
     let (first_epoch_scale, second_epoch_scale) = get_epoch_scales(
-        &ctx.remaining_accounts,
+        ctx.remaining_accounts,
         current_epoch_scale_key,
-        &current_epoch_scale,
+        current_epoch_scale,
         sp_state,
         sp_deposit,
     )?;
@@ -216,6 +220,23 @@ pub fn withdraw_from_sp_handler(ctx: Context<WithdrawFromSP>, usv_amt: u64) -> R
         .unwrap();
     let compounded_usv_deposit = sp_deposit.get_compounded_usv_deposit(sp_state).unwrap();
     let usv_to_withdraw = min(usv_amt, compounded_usv_deposit);
+
+    require_lowest_trove_allows_withdrawal(
+        pool_state,
+        lowest_trove_option
+            .as_ref()
+            .map(|t| (t.key(), t.as_ref().as_ref())),
+        usv_to_withdraw,
+        || {
+            ctx.accounts.price_feed_state.fetch_price(
+                &ctx.accounts.chainlink_program,
+                &ctx.accounts.chainlink_feed,
+                &ctx.accounts.jitosol_stake_pool,
+                &ctx.accounts.pyth_feed_account,
+            )
+        },
+    )?;
+
     let usv_loss = sp_deposit
         .initial_value
         .checked_sub(compounded_usv_deposit)
@@ -239,7 +260,7 @@ pub fn withdraw_from_sp_handler(ctx: Context<WithdrawFromSP>, usv_amt: u64) -> R
     sp_deposit.update_deposit_and_snapshot(sp_state, current_epoch_scale, *depositor, new_deposit);
     sp_deposit.claimable_coll = sp_deposit
         .claimable_coll
-        .checked_add(depositor_coll_gain.try_into().unwrap())
+        .checked_add(depositor_coll_gain)
         .unwrap();
     sp_deposit.claimable_cvgt = sp_deposit.claimable_cvgt.checked_add(cvgt_gain).unwrap();
     sp_state.decrease_coll(depositor_coll_gain);
@@ -260,3 +281,175 @@ pub fn withdraw_from_sp_handler(ctx: Context<WithdrawFromSP>, usv_amt: u64) -> R
 
     Ok(())
 }
+
+#[cfg(test)]
+pub mod withdraw_from_sp_test {
+    use super::*;
+    use crate::state::{
+        stability_pool_deposit_test::{load_current_epoch_scale, load_unit_sp_state},
+        stability_pool_state_test::{load_deposit, load_sp_state, DEPOSIT},
+        LiquidationTotals, TroveStatus,
+    };
+
+    const PRICE: u64 = 100_000_000_000;
+
+    fn load_pool_state(trove_tail: Pubkey) -> PoolState {
+        PoolState {
+            mcr: 1_100_000_000,
+            trove_tail,
+            ..Default::default()
+        }
+    }
+
+    /// 10 coll at 100 against 1000 debt, an ICR of 1.0
+    fn load_under_mcr_trove() -> Trove {
+        Trove {
+            coll: 10_000_000_000,
+            debt: 1_000_000_000_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        }
+    }
+
+    fn offset(sp_state: &mut StabilityPoolState, epoch_scale: &mut EpochScale, debt: u64) {
+        let totals = LiquidationTotals {
+            total_debt_to_offset: debt,
+            total_coll_to_send_to_sp: 1_000_000_000,
+            ..Default::default()
+        };
+        sp_state.offset(epoch_scale, &totals, 0).unwrap();
+        sp_state.decrease_usv(debt);
+        sp_state.increase_coll(totals.total_coll_to_send_to_sp);
+    }
+
+    #[test]
+    fn under_mcr_lowest_trove_blocks_withdrawal() {
+        let key = Pubkey::new_unique();
+        let pool_state = load_pool_state(key);
+        let mut trove = load_under_mcr_trove();
+
+        assert_eq!(
+            require_lowest_trove_allows_withdrawal(&pool_state, Some((key, &trove)), 1, || Ok(
+                PRICE
+            ))
+            .unwrap_err(),
+            StabilityPoolError::TroveUnderColl.into()
+        );
+        assert_eq!(
+            require_lowest_trove_allows_withdrawal(
+                &pool_state,
+                Some((Pubkey::new_unique(), &trove)),
+                1,
+                || Ok(PRICE)
+            )
+            .unwrap_err(),
+            StabilityPoolError::InvalidLowestTrove.into()
+        );
+
+        // 12 coll at 100 against 1000 debt
+        trove.coll = 12_000_000_000;
+        assert!(require_lowest_trove_allows_withdrawal(
+            &pool_state,
+            Some((key, &trove)),
+            1,
+            || Ok(PRICE)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn emptied_deposit_withdraws_under_mcr_lowest_trove() {
+        let key = Pubkey::new_unique();
+        let pool_state = load_pool_state(key);
+        let trove = load_under_mcr_trove();
+        let mut sp_state = load_sp_state();
+        let mut epoch_scale = EpochScale::default();
+        let deposit = load_deposit(&sp_state);
+
+        // Pool-emptying liquidation
+        offset(&mut sp_state, &mut epoch_scale, DEPOSIT);
+        let compounded = deposit.get_compounded_usv_deposit(&sp_state).unwrap();
+        assert_eq!(compounded, 0);
+
+        // The price isn't fetched when no USV leaves the pool
+        let usv_to_withdraw = min(DEPOSIT, compounded);
+        assert!(require_lowest_trove_allows_withdrawal(
+            &pool_state,
+            Some((key, &trove)),
+            usv_to_withdraw,
+            || unreachable!()
+        )
+        .is_ok());
+        assert_eq!(
+            deposit
+                .get_depositor_coll_gain(&epoch_scale, &EpochScale::default())
+                .unwrap(),
+            1_000_000_000
+        );
+    }
+
+    #[test]
+    fn below_dust_deposit_withdraws_under_mcr_lowest_trove() {
+        let key = Pubkey::new_unique();
+        let pool_state = load_pool_state(key);
+        let trove = load_under_mcr_trove();
+        let mut sp_state = load_sp_state();
+        let mut epoch_scale = EpochScale::default();
+        let deposit = load_deposit(&sp_state);
+
+        // A single unit is left in the pool, below a billionth of the deposit
+        offset(&mut sp_state, &mut epoch_scale, DEPOSIT - 1);
+        assert_eq!(sp_state.total_usv_deposits, 1);
+        let compounded = deposit.get_compounded_usv_deposit(&sp_state).unwrap();
+        assert_eq!(compounded, 0);
+
+        let usv_to_withdraw = min(DEPOSIT, compounded);
+        assert!(require_lowest_trove_allows_withdrawal(
+            &pool_state,
+            Some((key, &trove)),
+            usv_to_withdraw,
+            || unreachable!()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn claim_sp_gains_realizes_under_mcr_lowest_trove() {
+        let key = Pubkey::new_unique();
+        let pool_state = load_pool_state(key);
+        let trove = load_under_mcr_trove();
+        let sp_state = load_unit_sp_state();
+        let current = load_current_epoch_scale();
+        let mut deposit = StabilityPoolDeposit {
+            initial_value: 1_000_000_000,
+            ..load_deposit(&sp_state)
+        };
+
+        // Withdrawing any of the deposit is blocked by the lowest trove
+        let compounded = deposit.get_compounded_usv_deposit(&sp_state).unwrap();
+        assert_eq!(compounded, 1_000_000_000);
+        assert_eq!(
+            require_lowest_trove_allows_withdrawal(
+                &pool_state,
+                Some((key, &trove)),
+                min(1, compounded),
+                || Ok(PRICE)
+            )
+            .unwrap_err(),
+            StabilityPoolError::TroveUnderColl.into()
+        );
+
+        // `claim_sp_gains` takes neither the lowest trove nor the price
+        deposit
+            .realize_gains(
+                &sp_state,
+                std::slice::from_ref(&current),
+                &current,
+                Pubkey::new_unique(),
+            )
+            .unwrap();
+        assert_eq!(deposit.claimable_coll, 500_000_000);
+        assert_eq!(deposit.claimable_cvgt, 200_000_000);
+        assert_eq!(deposit.initial_value, 1_000_000_000);
+    }
+}
@@ -1,3 +1,6 @@
+// Instruction arguments mirror the client API
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::{borsh::BorshDeserialize, *};
 
 declare_id!("CVGT8oK6mDzdr3cfXABSGZLuhAgLY5LBz1qpvQiCmwqQ");
@@ -11,6 +14,10 @@ mod state;
 mod utils;
 
 use instructions::*;
+use state::{
+    CVGTEmissionState, SPDepositPreview, SecondarySource, StakingGainsPreview, StatusTransition,
+    TrovePageEntry,
+};
 
 #[program]
 pub mod trove_manager {
@@ -47,25 +54,96 @@ pub mod trove_manager {
         Ok(())
     }
 
+    pub fn close_epoch_scale(
+        ctx: Context<CloseEpochScale>,
+        epoch: u128,
+        scale: u128,
+    ) -> Result<()> {
+        close_epoch_scale_handler(ctx, epoch, scale)
+    }
+
     pub fn dev_change_price(ctx: Context<DevChangePrice>, new_price: u64) -> Result<()> {
         dev_change_price_handler(ctx, new_price)
     }
 
+    pub fn set_secondary_source(
+        ctx: Context<SetSecondarySource>,
+        secondary_source: SecondarySource,
+    ) -> Result<()> {
+        set_secondary_source_handler(ctx, secondary_source)
+    }
+
+    pub fn set_jitosol_max_epoch_lag(
+        ctx: Context<SetJitoSolMaxEpochLag>,
+        jitosol_max_epoch_lag: u64,
+    ) -> Result<()> {
+        set_jitosol_max_epoch_lag_handler(ctx, jitosol_max_epoch_lag)
+    }
+
+    pub fn set_max_confidence_rate(
+        ctx: Context<SetMaxConfidenceRate>,
+        max_confidence_rate: u64,
+    ) -> Result<()> {
+        set_max_confidence_rate_handler(ctx, max_confidence_rate)
+    }
+
     pub fn open_trove(
         ctx: Context<OpenTrove>,
         max_fee_percentage: u64,
         is_lamport: bool,
         coll_amt: u64,
         usv_amt: u64,
+        min_coll_out: u64,
+        referral: Option<Pubkey>,
+    ) -> Result<()> {
+        open_trove_handler(
+            ctx,
+            max_fee_percentage,
+            is_lamport,
+            coll_amt,
+            usv_amt,
+            min_coll_out,
+            referral,
+        )
+    }
+
+    pub fn open_trove_and_provide_sp<'info>(
+        ctx: Context<'_, '_, '_, 'info, OpenTroveAndProvideSP<'info>>,
+        max_fee_percentage: u64,
+        is_lamport: bool,
+        coll_amt: u64,
+        usv_amt: u64,
+        min_coll_out: u64,
+        referral: Option<Pubkey>,
+        sp_usv_amt: u64,
     ) -> Result<()> {
-        open_trove_handler(ctx, max_fee_percentage, is_lamport, coll_amt, usv_amt)
+        open_trove_and_provide_sp_handler(
+            ctx,
+            max_fee_percentage,
+            is_lamport,
+            coll_amt,
+            usv_amt,
+            min_coll_out,
+            referral,
+            sp_usv_amt,
+        )
+    }
+
+    pub fn simulate_open_trove(
+        ctx: Context<SimulateOpenTrove>,
+        coll_amt: u64,
+        usv_amt: u64,
+        max_fee_percentage: u64,
+    ) -> Result<OpenTroveSimulation> {
+        simulate_open_trove_handler(ctx, coll_amt, usv_amt, max_fee_percentage)
     }
 
-    pub fn add_coll(
-        ctx: Context<AdjustTrove>,
+    pub fn add_coll<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdjustTrove<'info>>,
         max_fee_percentage: u64,
         is_lamport: bool,
         coll_amt: u64,
+        min_coll_out: u64,
     ) -> Result<()> {
         adjust_trove_handler(
             ctx,
@@ -75,41 +153,43 @@ pub mod trove_manager {
             true,
             0,
             false,
+            min_coll_out,
         )
     }
 
-    pub fn withdraw_coll(
-        ctx: Context<AdjustTrove>,
+    pub fn withdraw_coll<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdjustTrove<'info>>,
         max_fee_percentage: u64,
         coll_amt: u64,
     ) -> Result<()> {
-        adjust_trove_handler(ctx, max_fee_percentage, false, coll_amt, false, 0, false)
+        adjust_trove_handler(ctx, max_fee_percentage, false, coll_amt, false, 0, false, 0)
     }
 
-    pub fn withdraw_usv(
-        ctx: Context<AdjustTrove>,
+    pub fn withdraw_usv<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdjustTrove<'info>>,
         max_fee_percentage: u64,
         usv_amt: u64,
     ) -> Result<()> {
-        adjust_trove_handler(ctx, max_fee_percentage, false, 0, false, usv_amt, true)
+        adjust_trove_handler(ctx, max_fee_percentage, false, 0, false, usv_amt, true, 0)
     }
 
-    pub fn repay_usv(
-        ctx: Context<AdjustTrove>,
+    pub fn repay_usv<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdjustTrove<'info>>,
         max_fee_percentage: u64,
         usv_amt: u64,
     ) -> Result<()> {
-        adjust_trove_handler(ctx, max_fee_percentage, false, 0, false, usv_amt, false)
+        adjust_trove_handler(ctx, max_fee_percentage, false, 0, false, usv_amt, false, 0)
     }
 
-    pub fn adjust_trove(
-        ctx: Context<AdjustTrove>,
+    pub fn adjust_trove<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdjustTrove<'info>>,
         max_fee_percentage: u64,
         is_lamport: bool,
         coll_change: u64,
         is_coll_increase: bool,
         usv_change: u64,
         is_debt_increase: bool,
+        min_coll_out: u64,
     ) -> Result<()> {
         adjust_trove_handler(
             ctx,
@@ -119,6 +199,7 @@ pub mod trove_manager {
             is_coll_increase,
             usv_change,
             is_debt_increase,
+            min_coll_out,
         )
     }
 
@@ -126,14 +207,26 @@ pub mod trove_manager {
         close_trove_handler(ctx)
     }
 
+    pub fn close_trove_and_claim(ctx: Context<CloseTrove>) -> Result<()> {
+        close_trove_and_claim_handler(ctx)
+    }
+
     pub fn liquidate_trove(ctx: Context<LiquidateTrove>) -> Result<()> {
         liquidate_trove_handler(ctx)
     }
 
+    pub fn liquidate_trove_usv_comp(ctx: Context<LiquidateTrove>) -> Result<()> {
+        liquidate_trove_usv_comp_handler(ctx)
+    }
+
     pub fn batch_liquidate_troves(ctx: Context<BatchLiquidateTroves>) -> Result<()> {
         batch_liquidate_troves_handler(ctx)
     }
 
+    pub fn recovery_sweep(ctx: Context<BatchLiquidateTroves>) -> Result<()> {
+        recovery_sweep_handler(ctx)
+    }
+
     pub fn redeem_collateral(
         ctx: Context<RedeemCollateral>,
         max_fee_percentage: u64,
@@ -142,10 +235,47 @@ pub mod trove_manager {
         redeem_collateral_handler(ctx, max_fee_percentage, usv_amt)
     }
 
+    pub fn redeem_from_trove_preview(
+        ctx: Context<PreviewRedeemFromTrove>,
+        usv_amt: u64,
+    ) -> Result<TroveRedemptionPreview> {
+        redeem_from_trove_preview_handler(ctx, usv_amt)
+    }
+
     pub fn claim_coll_surplus(ctx: Context<ClaimCollSurplus>) -> Result<()> {
         claim_coll_surplus_handler(ctx)
     }
 
+    pub fn close_trove_account(ctx: Context<CloseTroveAccount>) -> Result<()> {
+        close_trove_account_handler(ctx)
+    }
+
+    pub fn close_user_atas(ctx: Context<CloseUserAtas>) -> Result<()> {
+        close_user_atas_handler(ctx)
+    }
+
+    pub fn crank_apply_rewards(ctx: Context<CrankApplyRewards>) -> Result<()> {
+        crank_apply_rewards_handler(ctx)
+    }
+
+    pub fn list_troves_page<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ListTrovesPage<'info>>,
+        start: Pubkey,
+        limit: u8,
+    ) -> Result<Vec<TrovePageEntry>> {
+        list_troves_page_handler(ctx, start, limit)
+    }
+
+    pub fn check_invariants(ctx: Context<CheckInvariants>, require_all: bool) -> Result<u8> {
+        check_invariants_handler(ctx, require_all)
+    }
+
+    pub fn reconcile_surplus<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReconcileSurplus<'info>>,
+    ) -> Result<SurplusReconciliation> {
+        reconcile_surplus_handler(ctx)
+    }
+
     // Stability Pool
     pub fn provide_to_sp(ctx: Context<ProvideToSP>, usv_amt: u64) -> Result<()> {
         provide_to_sp_handler(ctx, usv_amt)
@@ -159,15 +289,216 @@ pub mod trove_manager {
         claim_from_sp_handler(ctx)
     }
 
+    pub fn provide_and_claim_sp(ctx: Context<ProvideAndClaimSP>, usv_amt: u64) -> Result<()> {
+        provide_and_claim_sp_handler(ctx, usv_amt)
+    }
+
+    pub fn claim_all_sp_gains(ctx: Context<ClaimAllSPGains>) -> Result<()> {
+        claim_all_sp_gains_handler(ctx)
+    }
+
+    pub fn claim_sp_gains(ctx: Context<ClaimSPGains>) -> Result<()> {
+        claim_sp_gains_handler(ctx)
+    }
+
+    pub fn force_realize_sp_deposit(
+        ctx: Context<ForceRealizeSPDeposit>,
+        depositor: Pubkey,
+    ) -> Result<()> {
+        force_realize_sp_deposit_handler(ctx, depositor)
+    }
+
+    pub fn transfer_sp_deposit(ctx: Context<TransferSPDeposit>) -> Result<()> {
+        transfer_sp_deposit_handler(ctx)
+    }
+
+    pub fn close_sp_deposit(ctx: Context<CloseSPDeposit>) -> Result<()> {
+        close_sp_deposit_handler(ctx)
+    }
+
+    pub fn sp_deposit_preview(ctx: Context<PreviewSPDeposit>) -> Result<SPDepositPreview> {
+        sp_deposit_preview_handler(ctx)
+    }
+
+    pub fn get_tvl(ctx: Context<GetTvl>) -> Result<TvlBreakdown> {
+        get_tvl_handler(ctx)
+    }
+
+    pub fn liquidate_preview(ctx: Context<PreviewLiquidation>) -> Result<LiquidationPreview> {
+        liquidate_preview_handler(ctx)
+    }
+
     // Admin
     pub fn config_pool_state(ctx: Context<ConfigPoolState>) -> Result<()> {
         config_pool_state_handler(ctx)
     }
 
+    pub fn set_redemption_fee_treasury_bps(ctx: Context<UpdatePoolConfig>, bps: u64) -> Result<()> {
+        set_redemption_fee_treasury_bps_handler(ctx, bps)
+    }
+
+    pub fn update_risk_params(
+        ctx: Context<UpdatePoolConfig>,
+        mcr: u64,
+        ccr: u64,
+        min_net_debt: u64,
+    ) -> Result<()> {
+        update_risk_params_handler(ctx, mcr, ccr, min_net_debt)
+    }
+
+    pub fn set_recovery_proximity_surcharge(
+        ctx: Context<UpdatePoolConfig>,
+        bps: u64,
+        band: u64,
+    ) -> Result<()> {
+        set_recovery_proximity_surcharge_handler(ctx, bps, band)
+    }
+
+    pub fn set_gas_compensation(
+        ctx: Context<UpdatePoolConfig>,
+        gas_compensation: u64,
+    ) -> Result<()> {
+        set_gas_compensation_handler(ctx, gas_compensation)
+    }
+
+    pub fn set_sp_deposit_fee(
+        ctx: Context<UpdateSPConfig>,
+        bps: u64,
+        reserve: Pubkey,
+    ) -> Result<()> {
+        set_sp_deposit_fee_handler(ctx, bps, reserve)
+    }
+
+    pub fn set_coll_gas_comp_bounds(
+        ctx: Context<UpdatePoolConfig>,
+        min_coll_gas_comp: u64,
+        max_coll_gas_comp: u64,
+    ) -> Result<()> {
+        set_coll_gas_comp_bounds_handler(ctx, min_coll_gas_comp, max_coll_gas_comp)
+    }
+
+    pub fn set_liquidation_bonus_bps(ctx: Context<UpdatePoolConfig>, bps: u64) -> Result<()> {
+        set_liquidation_bonus_bps_handler(ctx, bps)
+    }
+
+    pub fn set_liquidator_gas_comp_bps(ctx: Context<UpdatePoolConfig>, bps: u64) -> Result<()> {
+        set_liquidator_gas_comp_bps_handler(ctx, bps)
+    }
+
+    pub fn set_gas_comp_to_borrower(
+        ctx: Context<UpdatePoolConfig>,
+        gas_comp_to_borrower: bool,
+    ) -> Result<()> {
+        set_gas_comp_to_borrower_handler(ctx, gas_comp_to_borrower)
+    }
+
+    pub fn set_sp_deposits_disabled(
+        ctx: Context<UpdatePoolConfig>,
+        sp_deposits_disabled: bool,
+    ) -> Result<()> {
+        set_sp_deposits_disabled_handler(ctx, sp_deposits_disabled)
+    }
+
+    pub fn set_max_trove_coll(ctx: Context<UpdatePoolConfig>, max_trove_coll: u64) -> Result<()> {
+        set_max_trove_coll_handler(ctx, max_trove_coll)
+    }
+
+    pub fn set_max_system_debt(ctx: Context<UpdatePoolConfig>, max_system_debt: u64) -> Result<()> {
+        set_max_system_debt_handler(ctx, max_system_debt)
+    }
+
+    pub fn set_max_troves(ctx: Context<UpdatePoolConfig>, max_troves: u64) -> Result<()> {
+        set_max_troves_handler(ctx, max_troves)
+    }
+
+    pub fn set_nicr_tie_break_enforced(
+        ctx: Context<UpdatePoolConfig>,
+        nicr_tie_break_enforced: bool,
+    ) -> Result<()> {
+        set_nicr_tie_break_enforced_handler(ctx, nicr_tie_break_enforced)
+    }
+
+    pub fn set_pre_launch_fee_treasury(
+        ctx: Context<UpdatePoolConfig>,
+        pre_launch_fee_treasury: Pubkey,
+    ) -> Result<()> {
+        set_pre_launch_fee_treasury_handler(ctx, pre_launch_fee_treasury)
+    }
+
+    pub fn set_redemption_hint_tolerance_bps(
+        ctx: Context<UpdatePoolConfig>,
+        bps: u64,
+    ) -> Result<()> {
+        set_redemption_hint_tolerance_bps_handler(ctx, bps)
+    }
+
+    pub fn set_max_redemption_fraction_bps(ctx: Context<UpdatePoolConfig>, bps: u64) -> Result<()> {
+        set_max_redemption_fraction_bps_handler(ctx, bps)
+    }
+
+    pub fn set_minute_decay_factor(
+        ctx: Context<UpdatePoolConfig>,
+        minute_decay_factor: u64,
+    ) -> Result<()> {
+        set_minute_decay_factor_handler(ctx, minute_decay_factor)
+    }
+
+    pub fn set_self_liquidation_allowed(
+        ctx: Context<UpdatePoolConfig>,
+        self_liquidation_allowed: bool,
+    ) -> Result<()> {
+        set_self_liquidation_allowed_handler(ctx, self_liquidation_allowed)
+    }
+
+    pub fn set_debt_increase_cooldown(
+        ctx: Context<UpdatePoolConfig>,
+        debt_increase_cooldown: u64,
+    ) -> Result<()> {
+        set_debt_increase_cooldown_handler(ctx, debt_increase_cooldown)
+    }
+
+    pub fn set_redemption_max_price_age(
+        ctx: Context<UpdatePoolConfig>,
+        redemption_max_price_age: u64,
+    ) -> Result<()> {
+        set_redemption_max_price_age_handler(ctx, redemption_max_price_age)
+    }
+
+    pub fn set_liquidation_grace_period(
+        ctx: Context<UpdatePoolConfig>,
+        liquidation_grace_period: u64,
+    ) -> Result<()> {
+        set_liquidation_grace_period_handler(ctx, liquidation_grace_period)
+    }
+
+    pub fn sweep_gas_comp_residual(ctx: Context<SweepGasCompResidual>) -> Result<()> {
+        sweep_gas_comp_residual_handler(ctx)
+    }
+
+    pub fn migrate_pool_state(ctx: Context<MigratePoolState>) -> Result<()> {
+        migrate_pool_state_handler(ctx)
+    }
+
     pub fn fetch_price(ctx: Context<FetchPrice>) -> Result<()> {
         fetch_price_handler(ctx)
     }
 
+    pub fn price_feed_history(ctx: Context<GetPriceFeedHistory>) -> Result<Vec<StatusTransition>> {
+        price_feed_history_handler(ctx)
+    }
+
+    pub fn migrate_price_feed_state(ctx: Context<MigratePriceFeedState>) -> Result<()> {
+        migrate_price_feed_state_handler(ctx)
+    }
+
+    pub fn migrate_stability_pool_state(ctx: Context<MigrateStabilityPoolState>) -> Result<()> {
+        migrate_stability_pool_state_handler(ctx)
+    }
+
+    pub fn migrate_trove(ctx: Context<MigrateTrove>) -> Result<()> {
+        migrate_trove_handler(ctx)
+    }
+
     // Community Issuance
     pub fn initialize_community_issuance(
         ctx: Context<InitializeCommunityIssuance>,
@@ -177,8 +508,12 @@ pub mod trove_manager {
         initialize_community_issuance_handler(ctx, emission_rate, is_dev)
     }
 
-    pub fn change_authority(ctx: Context<ChangeAuthority>) -> Result<()> {
-        change_authority_handler(ctx)
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        propose_authority_handler(ctx, new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        accept_authority_handler(ctx)
     }
 
     pub fn change_emission_rate(ctx: Context<ChangeEmissionRate>, new_rate: u64) -> Result<()> {
@@ -189,6 +524,32 @@ pub mod trove_manager {
         enable_emission_handler(ctx)
     }
 
+    pub fn set_issuance_guard(
+        ctx: Context<SetIssuanceGuard>,
+        enabled: bool,
+        max_shortfall: u64,
+    ) -> Result<()> {
+        set_issuance_guard_handler(ctx, enabled, max_shortfall)
+    }
+
+    pub fn set_emission_decay_factor(
+        ctx: Context<SetEmissionDecayFactor>,
+        decay_factor: u64,
+    ) -> Result<()> {
+        set_emission_decay_factor_handler(ctx, decay_factor)
+    }
+
+    pub fn migrate_community_issuance_config(
+        ctx: Context<MigrateCommunityIssuanceConfig>,
+        total_cvgt_sent: u64,
+    ) -> Result<()> {
+        migrate_community_issuance_config_handler(ctx, total_cvgt_sent)
+    }
+
+    pub fn cvgt_emission_state(ctx: Context<GetCVGTEmissionState>) -> Result<CVGTEmissionState> {
+        cvgt_emission_state_handler(ctx)
+    }
+
     pub fn dev_set_timestamp(ctx: Context<SetTimestamp>, new_timestamp: u64) -> Result<()> {
         set_timestamp_handler(ctx, new_timestamp)
     }
@@ -205,4 +566,25 @@ pub mod trove_manager {
     pub fn unstake(ctx: Context<Unstake>, cvgt_amt: u64) -> Result<()> {
         unstake_handler(ctx, cvgt_amt)
     }
+
+    pub fn set_min_stake_duration(
+        ctx: Context<SetMinStakeDuration>,
+        min_stake_duration: u64,
+    ) -> Result<()> {
+        set_min_stake_duration_handler(ctx, min_stake_duration)
+    }
+
+    pub fn migrate_cvgt_staking_pool_state(
+        ctx: Context<MigrateCVGTStakingPoolState>,
+    ) -> Result<()> {
+        migrate_cvgt_staking_pool_state_handler(ctx)
+    }
+
+    pub fn migrate_cvgt_staking_info(ctx: Context<MigrateCVGTStakingInfo>) -> Result<()> {
+        migrate_cvgt_staking_info_handler(ctx)
+    }
+
+    pub fn staking_gains_preview(ctx: Context<PreviewStakingGains>) -> Result<StakingGainsPreview> {
+        staking_gains_preview_handler(ctx)
+    }
 }
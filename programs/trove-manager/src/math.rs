@@ -1,4 +1,4 @@
-use crate::constants::{DECIMAL_PRECISION, NICR_PRECISION};
+use crate::constants::{DECIMAL_PRECISION, MAX_DEC_POW_MINUTES, NICR_PRECISION};
 
 pub fn dec_mul(x: u64, y: u64) -> Option<u64> {
     let prod_xy = x.checked_mul(y).unwrap();
@@ -8,20 +8,20 @@ pub fn dec_mul(x: u64, y: u64) -> Option<u64> {
 }
 
 pub fn dec_pow(base: u64, minutes: u64) -> Option<u64> {
-    let mut capped_minutes = minutes;
-    if minutes > 525600000 {
-        capped_minutes = 525600000;
-    }
     if minutes == 0 {
         return Some(DECIMAL_PRECISION);
     }
 
     let mut y = DECIMAL_PRECISION;
     let mut x = base;
-    let mut n = capped_minutes;
+    let mut n = minutes.min(MAX_DEC_POW_MINUTES);
 
     while n > 1 {
-        if n % 2 == 0 {
+        // Any further product rounds to zero
+        if x == 0 || y == 0 {
+            return Some(0);
+        }
+        if n.is_multiple_of(2) {
             x = dec_mul(x, x)?;
             n = n.checked_div(2)?;
         } else {
@@ -33,8 +33,12 @@ pub fn dec_pow(base: u64, minutes: u64) -> Option<u64> {
     dec_mul(x, y)
 }
 
+/// `None` for a zero price, which would rate every trove with debt at 0%
 pub fn compute_cr(coll: u64, debt: u64, price: u64) -> Option<u64> {
     if debt > 0 {
+        if price == 0 {
+            return None;
+        }
         return u64::try_from(
             (coll as u128)
                 .checked_mul(price.into())?
@@ -45,14 +49,69 @@ pub fn compute_cr(coll: u64, debt: u64, price: u64) -> Option<u64> {
     Some(u64::MAX)
 }
 
+/// USD value of `coll` at `price`, in stablecoin units
+pub fn compute_value(coll: u64, price: u64) -> Option<u64> {
+    u64::try_from(
+        (coll as u128)
+            .checked_mul(price.into())?
+            .checked_div(DECIMAL_PRECISION.into())?,
+    )
+    .ok()
+}
+
+/// Saturates at `u64::MAX`, the NICR of a trove without debt, so a trove with a huge collateral
+/// to debt ratio sorts among them at the head, ordered by pubkey
 pub fn compute_nominal_cr(coll: u64, debt: u64) -> Option<u64> {
     if debt > 0 {
-        return u64::try_from(
-            (coll as u128)
-                .checked_mul(NICR_PRECISION.into())?
-                .checked_div(debt.into())?,
-        )
-        .ok();
+        return Some(
+            u64::try_from(
+                (coll as u128)
+                    .checked_mul(NICR_PRECISION.into())?
+                    .checked_div(debt.into())?,
+            )
+            .unwrap_or(u64::MAX),
+        );
     }
     Some(u64::MAX)
 }
+
+#[cfg(test)]
+pub mod math_test {
+    use super::*;
+    use crate::constants::MINUTE_DECAY_FACTOR;
+
+    #[test]
+    fn nominal_cr_saturates_at_max() {
+        assert_eq!(compute_nominal_cr(1, 1), Some(NICR_PRECISION));
+        assert_eq!(compute_nominal_cr(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(
+            compute_nominal_cr(u64::MAX / NICR_PRECISION, 1),
+            Some(u64::MAX / NICR_PRECISION * NICR_PRECISION)
+        );
+        assert_eq!(compute_nominal_cr(0, 1), Some(0));
+    }
+
+    #[test]
+    fn dec_pow_matches_half_life() {
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, 0), Some(DECIMAL_PRECISION));
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, 1), Some(MINUTE_DECAY_FACTOR));
+        // 12h half-life
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, 720), Some(500_000_072));
+    }
+
+    #[test]
+    fn zero_price_has_no_cr() {
+        assert_eq!(compute_cr(1_000, 500, 0), None);
+        assert_eq!(compute_cr(1_000, 0, 0), Some(u64::MAX));
+        assert_eq!(
+            compute_cr(1_000, 500, DECIMAL_PRECISION),
+            Some(2_000_000_000)
+        );
+    }
+
+    #[test]
+    fn dec_pow_huge_exponent_is_zero() {
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, MAX_DEC_POW_MINUTES), Some(0));
+        assert_eq!(dec_pow(MINUTE_DECAY_FACTOR, u64::MAX), Some(0));
+    }
+}
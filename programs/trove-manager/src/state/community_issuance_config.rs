@@ -1,9 +1,23 @@
 use anchor_lang::prelude::*;
 
-use crate::events::TotalTokenIssuedUpdated;
+use crate::{
+    constants::{DECIMAL_PRECISION, EMISSION_DECAY_PERIOD, MIN_EMISSION_RUNWAY},
+    errors::CommunityIssuanceError,
+    events::TotalTokenIssuedUpdated,
+    math::dec_pow,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CVGTEmissionState {
+    pub emission_rate: u64,
+    pub total_issued: u64,
+    pub enable_emission: bool,
+    pub is_dev: bool,
+    pub current_timestamp: u64,
+}
 
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct CommunityIssuanceConfig {
     pub creator: Pubkey,
     pub cvgt: Pubkey,
@@ -19,6 +33,18 @@ pub struct CommunityIssuanceConfig {
     pub _timestamp: u64,
 
     pub bump: [u8; 1],
+
+    // CVGT already paid out of the issuance vault
+    pub total_cvgt_sent: u64,
+    // Pause new SP deposits when unpaid CVGT exceeds the vault balance by more than this
+    pub issuance_guard_enabled: bool,
+    pub max_issuance_shortfall: u64,
+    // Proposed authority, set as `authority` once it accepts
+    pub pending_authority: Pubkey,
+    // Emission rate multiplier per `EMISSION_DECAY_PERIOD` elapsed since `emission_decay_start`,
+    // in DECIMAL_PRECISION. `emission_rate` is the rate of the first period, 0 emits flat
+    pub emission_decay_factor: u64,
+    pub emission_decay_start: u64,
 }
 
 impl CommunityIssuanceConfig {
@@ -37,9 +63,15 @@ impl CommunityIssuanceConfig {
         Ok(Clock::get()?.unix_timestamp.try_into().unwrap())
     }
 
+    /// CVGT the next `issue_token` call would issue, without issuing it
+    pub fn get_pending_issuance(&self) -> Result<u64> {
+        let current_timestamp = self.get_current_timestamp()?;
+        Ok(compute_emission_amount(self, current_timestamp).unwrap())
+    }
+
     pub fn issue_token(&mut self) -> Result<u64> {
         let current_timestamp = self.get_current_timestamp()?;
-        let amount = compute_emission_amount(self, current_timestamp).unwrap();
+        let amount = self.get_pending_issuance()?;
 
         self.last_reward_timestamp = current_timestamp;
         self.total_cvgt_issued = self.total_cvgt_issued.checked_add(amount).unwrap();
@@ -50,6 +82,94 @@ impl CommunityIssuanceConfig {
         });
         Ok(amount)
     }
+
+    pub fn get_emission_state(&self, current_timestamp: u64) -> CVGTEmissionState {
+        CVGTEmissionState {
+            emission_rate: self.emission_rate,
+            total_issued: self.total_cvgt_issued,
+            enable_emission: self.enable_emission,
+            is_dev: self._is_dev,
+            current_timestamp,
+        }
+    }
+
+    pub fn get_outstanding_cvgt(&self) -> u64 {
+        self.total_cvgt_issued.saturating_sub(self.total_cvgt_sent)
+    }
+
+    pub fn record_cvgt_sent(&mut self, amount: u64) {
+        self.total_cvgt_sent = self.total_cvgt_sent.checked_add(amount).unwrap();
+    }
+
+    /// The vault balance not owed to depositors yet, issued or pending, must fund `new_rate`
+    /// for `MIN_EMISSION_RUNWAY` seconds
+    pub fn require_emission_runway(&self, new_rate: u64, vault_balance: u64) -> Result<()> {
+        let remaining = vault_balance
+            .saturating_sub(self.get_outstanding_cvgt())
+            .saturating_sub(self.get_pending_issuance()?);
+        require!(
+            new_rate.checked_mul(MIN_EMISSION_RUNWAY).unwrap() <= remaining,
+            CommunityIssuanceError::EmissionRunwayTooShort
+        );
+        Ok(())
+    }
+
+    /// The schedule restarts at the last issuance from the rate decayed so far, so the CVGT
+    /// pending until now is issued at that rate unless a whole period has passed
+    pub fn set_emission_decay_factor(&mut self, decay_factor: u64) -> Result<()> {
+        require!(
+            decay_factor < DECIMAL_PRECISION,
+            CommunityIssuanceError::InvalidDecayFactor
+        );
+        self.emission_rate = self.get_decayed_emission_rate(self.last_reward_timestamp);
+        self.emission_decay_factor = decay_factor;
+        self.emission_decay_start = self.last_reward_timestamp;
+        Ok(())
+    }
+
+    /// Rate of the decay period `timestamp` falls in
+    pub fn get_decayed_emission_rate(&self, timestamp: u64) -> u64 {
+        if self.emission_decay_factor == 0 {
+            return self.emission_rate;
+        }
+        let periods = timestamp.saturating_sub(self.emission_decay_start) / EMISSION_DECAY_PERIOD;
+        let decay = dec_pow(self.emission_decay_factor, periods).unwrap();
+        u64::try_from(
+            (self.emission_rate as u128)
+                .checked_mul(decay.into())
+                .unwrap()
+                .checked_div(DECIMAL_PRECISION.into())
+                .unwrap(),
+        )
+        .unwrap()
+    }
+
+    /// Overwrites any earlier proposal
+    pub fn propose_authority(&mut self, new_authority: Pubkey) {
+        self.pending_authority = new_authority;
+    }
+
+    pub fn accept_authority(&mut self, signer: Pubkey) -> Result<()> {
+        require!(
+            self.pending_authority != Pubkey::default() && signer == self.pending_authority,
+            CommunityIssuanceError::NotPendingAuthority
+        );
+        self.authority = signer;
+        self.pending_authority = Pubkey::default();
+        Ok(())
+    }
+
+    pub fn require_vault_covers_issuance(&self, vault_balance: u64) -> Result<()> {
+        if !self.issuance_guard_enabled {
+            return Ok(());
+        }
+        require!(
+            self.get_outstanding_cvgt()
+                <= vault_balance.saturating_add(self.max_issuance_shortfall),
+            CommunityIssuanceError::IssuanceVaultUnderfunded
+        );
+        Ok(())
+    }
 }
 
 fn compute_emission_amount(
@@ -61,8 +181,234 @@ fn compute_emission_amount(
     }
     let last_reward_timestamp = config.last_reward_timestamp;
 
-    let amount = current_timestamp
-        .checked_sub(last_reward_timestamp)?
-        .checked_mul(config.emission_rate)?;
+    if config.emission_decay_factor == 0 {
+        let amount = current_timestamp
+            .checked_sub(last_reward_timestamp)?
+            .checked_mul(config.emission_rate)?;
+        return Some(amount);
+    }
+
+    // Rounding may leave the cumulative emission a unit lower right after a period boundary
+    let amount = compute_decayed_emission(config, current_timestamp)?
+        .saturating_sub(compute_decayed_emission(config, last_reward_timestamp)?);
     Some(amount)
 }
+
+/// CVGT emitted from `emission_decay_start` to `timestamp`. Whole periods sum as a geometric
+/// series of `emission_decay_factor`, the current period emits at the decayed rate.
+fn compute_decayed_emission(config: &CommunityIssuanceConfig, timestamp: u64) -> Option<u64> {
+    let elapsed = timestamp.saturating_sub(config.emission_decay_start);
+    let periods = elapsed / EMISSION_DECAY_PERIOD;
+    let decay = dec_pow(config.emission_decay_factor, periods)?;
+
+    let rate = config.emission_rate as u128;
+    let precision = DECIMAL_PRECISION as u128;
+    let whole_periods = rate
+        .checked_mul(EMISSION_DECAY_PERIOD.into())?
+        .checked_mul(precision.checked_sub(decay.into())?)?
+        .checked_div(precision.checked_sub(config.emission_decay_factor.into())?)?;
+    let current_period = rate
+        .checked_mul((elapsed % EMISSION_DECAY_PERIOD).into())?
+        .checked_mul(decay.into())?
+        .checked_div(precision)?;
+
+    whole_periods.checked_add(current_period)?.try_into().ok()
+}
+
+#[cfg(test)]
+pub mod community_issuance_config_test {
+    use super::*;
+
+    fn load_config(total_cvgt_issued: u64, total_cvgt_sent: u64) -> CommunityIssuanceConfig {
+        CommunityIssuanceConfig {
+            total_cvgt_issued,
+            total_cvgt_sent,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn authority_transfer_needs_acceptance() {
+        let authority = Pubkey::new_unique();
+        let mut config = CommunityIssuanceConfig {
+            authority,
+            ..Default::default()
+        };
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+
+        // Nothing proposed
+        assert_eq!(
+            config.accept_authority(Pubkey::default()).unwrap_err(),
+            CommunityIssuanceError::NotPendingAuthority.into()
+        );
+
+        // A new proposal overwrites the pending one
+        config.propose_authority(first);
+        config.propose_authority(second);
+        assert_eq!(config.pending_authority, second);
+        assert_eq!(config.authority, authority);
+
+        assert_eq!(
+            config.accept_authority(first).unwrap_err(),
+            CommunityIssuanceError::NotPendingAuthority.into()
+        );
+        assert_eq!(config.authority, authority);
+
+        config.accept_authority(second).unwrap();
+        assert_eq!(config.authority, second);
+        assert_eq!(config.pending_authority, Pubkey::default());
+    }
+
+    #[test]
+    fn issuance_guard_disabled_by_default() {
+        let config = load_config(1_000, 0);
+        assert!(config.require_vault_covers_issuance(0).is_ok());
+    }
+
+    #[test]
+    fn issuance_guard_rejects_underfunded_vault() {
+        let mut config = load_config(1_000, 200);
+        config.issuance_guard_enabled = true;
+        config.max_issuance_shortfall = 100;
+
+        assert_eq!(config.get_outstanding_cvgt(), 800);
+        assert!(config.require_vault_covers_issuance(699).is_err());
+        assert!(config.require_vault_covers_issuance(700).is_ok());
+
+        config.record_cvgt_sent(100);
+        assert!(config.require_vault_covers_issuance(600).is_ok());
+    }
+
+    #[test]
+    fn emission_state_reports_issued_total() {
+        let mut config = CommunityIssuanceConfig {
+            enable_emission: true,
+            emission_rate: 10,
+            last_reward_timestamp: 100,
+            _is_dev: true,
+            _timestamp: 150,
+            ..Default::default()
+        };
+        assert_eq!(config.get_emission_state(150).total_issued, 0);
+
+        // A stability pool interaction issues the pending CVGT
+        assert_eq!(config.issue_token().unwrap(), 500);
+        assert_eq!(
+            config.get_emission_state(150),
+            CVGTEmissionState {
+                emission_rate: 10,
+                total_issued: 500,
+                enable_emission: true,
+                is_dev: true,
+                current_timestamp: 150,
+            }
+        );
+    }
+
+    #[test]
+    fn decayed_emission_tapers_by_period() {
+        let mut config = CommunityIssuanceConfig {
+            enable_emission: true,
+            emission_rate: 1_000,
+            last_reward_timestamp: 100,
+            _is_dev: true,
+            _timestamp: 100,
+            ..Default::default()
+        };
+        let mut flat = config.clone();
+        assert_eq!(
+            config
+                .set_emission_decay_factor(DECIMAL_PRECISION)
+                .unwrap_err(),
+            CommunityIssuanceError::InvalidDecayFactor.into()
+        );
+        config
+            .set_emission_decay_factor(DECIMAL_PRECISION / 2)
+            .unwrap();
+
+        // Issued every half period over three periods
+        let mut issued = 0;
+        let mut flat_issued = 0;
+        for step in 1..=6 {
+            config._timestamp = 100 + step * EMISSION_DECAY_PERIOD / 2;
+            flat._timestamp = config._timestamp;
+            issued += config.issue_token().unwrap();
+            flat_issued += flat.issue_token().unwrap();
+        }
+
+        // 1 + 1/2 + 1/4 of the flat emission of a period
+        assert_eq!(issued, 1_750 * EMISSION_DECAY_PERIOD);
+        assert_eq!(flat_issued, 3_000 * EMISSION_DECAY_PERIOD);
+        assert_eq!(config.total_cvgt_issued, issued);
+
+        // A single issuance over the same time matches
+        let mut once = CommunityIssuanceConfig {
+            _timestamp: 100 + 3 * EMISSION_DECAY_PERIOD,
+            last_reward_timestamp: 100,
+            total_cvgt_issued: 0,
+            ..config.clone()
+        };
+        assert_eq!(once.issue_token().unwrap(), issued);
+    }
+
+    #[test]
+    fn decay_factor_change_keeps_decayed_rate() {
+        let mut config = CommunityIssuanceConfig {
+            enable_emission: true,
+            emission_rate: 1_000,
+            last_reward_timestamp: 100,
+            _is_dev: true,
+            _timestamp: 100,
+            ..Default::default()
+        };
+        config
+            .set_emission_decay_factor(DECIMAL_PRECISION / 2)
+            .unwrap();
+        config._timestamp = 100 + 2 * EMISSION_DECAY_PERIOD;
+        config.issue_token().unwrap();
+        assert_eq!(config.get_decayed_emission_rate(config._timestamp), 250);
+
+        // The new factor applies from the rate reached, not from the first period's
+        config
+            .set_emission_decay_factor(DECIMAL_PRECISION / 4 * 3)
+            .unwrap();
+        assert_eq!(config.emission_rate, 250);
+        assert_eq!(config.emission_decay_start, config._timestamp);
+        config._timestamp += EMISSION_DECAY_PERIOD;
+        assert_eq!(config.issue_token().unwrap(), 250 * EMISSION_DECAY_PERIOD);
+        assert_eq!(config.get_decayed_emission_rate(config._timestamp), 187);
+
+        // Disabling the decay emits flat at the last decayed rate
+        config.set_emission_decay_factor(0).unwrap();
+        assert_eq!(config.emission_rate, 187);
+        config._timestamp += EMISSION_DECAY_PERIOD;
+        assert_eq!(config.issue_token().unwrap(), 187 * EMISSION_DECAY_PERIOD);
+    }
+
+    #[test]
+    fn emission_rate_needs_runway() {
+        let config = CommunityIssuanceConfig {
+            enable_emission: true,
+            emission_rate: 10,
+            last_reward_timestamp: 100,
+            total_cvgt_issued: 3_000,
+            total_cvgt_sent: 1_000,
+            _is_dev: true,
+            _timestamp: 200,
+            ..Default::default()
+        };
+        // 2_000 issued but unsent and 1_000 pending are owed already
+        let vault_balance = 3_000 + 5 * MIN_EMISSION_RUNWAY;
+
+        assert!(config.require_emission_runway(5, vault_balance).is_ok());
+        assert!(config.require_emission_runway(0, 0).is_ok());
+        assert_eq!(
+            config
+                .require_emission_runway(5, vault_balance - 1)
+                .unwrap_err(),
+            CommunityIssuanceError::EmissionRunwayTooShort.into()
+        );
+        assert!(config.require_emission_runway(6, vault_balance).is_err());
+    }
+}
@@ -1,15 +1,25 @@
 use anchor_lang::prelude::*;
 
-use crate::{constants::DECIMAL_PRECISION, events::StakerSnapshotsUpdated};
+use crate::{
+    constants::DECIMAL_PRECISION, errors::CVGTStakingError, events::StakerSnapshotsUpdated,
+};
 
 use super::CVGTStakingPoolState;
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StakingGainsPreview {
+    pub pending_coll_gain: u64,
+    pub pending_usv_gain: u64,
+    pub balance: u64,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct CVGTStakingInfo {
     pub balance: u64,
     pub f_coll_snapshot: u64,
     pub f_usv_snapshot: u64,
+    pub last_stake_time: u64,
 }
 
 impl CVGTStakingInfo {
@@ -39,6 +49,30 @@ impl CVGTStakingInfo {
         u64::try_from(usv_gain).unwrap()
     }
 
+    /// Gains the next `stake` or `unstake` would pay out
+    pub fn get_gains_preview(&self, pool_state: &CVGTStakingPoolState) -> StakingGainsPreview {
+        StakingGainsPreview {
+            pending_coll_gain: self.get_pending_coll_gain(pool_state),
+            pending_usv_gain: self.get_pending_usv_gain(pool_state),
+            balance: self.balance,
+        }
+    }
+
+    /// Principal can only leave once `min_stake_duration` has passed since the
+    /// last stake; gains are not subject to the cooldown.
+    pub fn require_stake_cooldown_elapsed(
+        &self,
+        pool_state: &CVGTStakingPoolState,
+        current_timestamp: u64,
+    ) -> Result<()> {
+        let staked_for = current_timestamp.saturating_sub(self.last_stake_time);
+        require!(
+            staked_for >= pool_state.min_stake_duration,
+            CVGTStakingError::StakeCooldownNotElapsed
+        );
+        Ok(())
+    }
+
     pub fn update_snapshot(&mut self, user_key: &Pubkey, pool_state: &CVGTStakingPoolState) {
         self.f_coll_snapshot = pool_state.f_coll;
         self.f_usv_snapshot = pool_state.f_usv;
@@ -49,3 +83,90 @@ impl CVGTStakingInfo {
         });
     }
 }
+
+#[cfg(test)]
+pub mod cvgt_staking_info_test {
+    use super::*;
+
+    fn pool_state(min_stake_duration: u64) -> CVGTStakingPoolState {
+        CVGTStakingPoolState {
+            usv: Pubkey::default(),
+            collateral: Pubkey::default(),
+            cvgt: Pubkey::default(),
+            f_usv: 0,
+            f_coll: 0,
+            total_cvgt_staked: 1_000,
+            bump: [0],
+            min_stake_duration,
+        }
+    }
+
+    #[test]
+    fn stake_cooldown() {
+        let pool_state = pool_state(3_600);
+        let staking_info = CVGTStakingInfo {
+            balance: 1_000,
+            f_coll_snapshot: 0,
+            f_usv_snapshot: 0,
+            last_stake_time: 1_000_000,
+        };
+
+        assert_eq!(
+            staking_info
+                .require_stake_cooldown_elapsed(&pool_state, 1_000_000 + 3_599)
+                .unwrap_err(),
+            CVGTStakingError::StakeCooldownNotElapsed.into()
+        );
+        assert!(staking_info
+            .require_stake_cooldown_elapsed(&pool_state, 1_000_000 + 3_600)
+            .is_ok());
+
+        // No cooldown configured
+        assert!(staking_info
+            .require_stake_cooldown_elapsed(&self::pool_state(0), 1_000_000)
+            .is_ok());
+    }
+
+    #[test]
+    fn gains_preview_matches_unstake() {
+        let mut pool_state = pool_state(0);
+        let mut staking_info = CVGTStakingInfo {
+            balance: 250,
+            f_coll_snapshot: 0,
+            f_usv_snapshot: 0,
+            last_stake_time: 1_000_000,
+        };
+
+        // Redemption fee in coll and a borrowing fee in USV
+        pool_state.increase_f_coll(4_000);
+        pool_state.increase_f_usv(2_000);
+
+        let preview = staking_info.get_gains_preview(&pool_state);
+        assert_eq!(
+            preview,
+            StakingGainsPreview {
+                pending_coll_gain: 1_000,
+                pending_usv_gain: 500,
+                balance: 250,
+            }
+        );
+
+        // `unstake(0)` pays out the same gains and resets the snapshots
+        assert_eq!(
+            staking_info.get_pending_coll_gain(&pool_state),
+            preview.pending_coll_gain
+        );
+        assert_eq!(
+            staking_info.get_pending_usv_gain(&pool_state),
+            preview.pending_usv_gain
+        );
+        staking_info.update_snapshot(&Pubkey::default(), &pool_state);
+        assert_eq!(
+            staking_info.get_gains_preview(&pool_state),
+            StakingGainsPreview {
+                balance: 250,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -17,14 +17,16 @@ pub struct CVGTStakingPoolState {
     pub total_cvgt_staked: u64,
 
     pub bump: [u8; 1],
+
+    pub min_stake_duration: u64,
 }
 
 impl CVGTStakingPoolState {
     pub fn seeds(&self) -> [&[u8]; 3] {
         [
             &b"staking-state"[..],
-            &self.cvgt.as_ref(),
-            &self.bump.as_ref(),
+            self.cvgt.as_ref(),
+            self.bump.as_ref(),
         ]
     }
 
@@ -29,6 +29,17 @@ impl EpochScale {
         });
     }
 
+    /// G after `cvgt_issuance` is distributed, without updating any state
+    pub fn get_pending_g(&self, sp_state: &StabilityPoolState, cvgt_issuance: u64) -> u128 {
+        if sp_state.total_usv_deposits == 0 || cvgt_issuance == 0 {
+            return self.g;
+        }
+
+        let (cvgt_per_unit_staked, _) = sp_state.get_cvgt_per_unit_staked(cvgt_issuance).unwrap();
+        let marginal_cvgt_gain = cvgt_per_unit_staked.checked_mul(sp_state.p).unwrap();
+        self.g.checked_add(marginal_cvgt_gain).unwrap()
+    }
+
     pub fn deserialize(
         data: std::cell::RefMut<'_, &mut [u8]>,
         key: &Pubkey,
@@ -102,3 +113,58 @@ pub fn get_epoch_scales(
 
     Ok((first_epoch_scale, second_epoch_scale))
 }
+
+/// Loads the ordered run of epoch-scale accounts a deposit has crossed since its
+/// last snapshot: `(snapshots_epoch, snapshots_scale)`, `(snapshots_epoch, snapshots_scale + 1)`, ...
+/// When the deposit was made in the current epoch the run has to reach the current scale.
+pub fn get_epoch_scale_range(
+    remaining_accounts: &[AccountInfo<'_>],
+    current_epoch_scale_key: &Pubkey,
+    current_epoch_scale: &EpochScale,
+    sp_state: &Account<'_, StabilityPoolState>,
+    sp_deposit: &StabilityPoolDeposit,
+) -> Result<Vec<EpochScale>> {
+    require!(
+        !remaining_accounts.is_empty(),
+        StabilityPoolError::InvalidEpochScale
+    );
+    if sp_deposit.snapshots_epoch == sp_state.current_epoch {
+        let scale_span = sp_state
+            .current_scale
+            .checked_sub(sp_deposit.snapshots_scale)
+            .unwrap();
+        require!(
+            (remaining_accounts.len() as u128) > scale_span,
+            StabilityPoolError::InvalidEpochScale
+        );
+    }
+
+    let sp_state_key = sp_state.key();
+    let mut epoch_scales = Vec::with_capacity(remaining_accounts.len());
+    for (i, epoch_scale_acc) in remaining_accounts.iter().enumerate() {
+        let scale = sp_deposit.snapshots_scale.checked_add(i as u128).unwrap();
+        let (expected_key, _) = Pubkey::find_program_address(
+            &[
+                b"epoch-scale",
+                sp_state_key.as_ref(),
+                sp_deposit.snapshots_epoch.to_le_bytes().as_ref(),
+                scale.to_le_bytes().as_ref(),
+            ],
+            &ID,
+        );
+        require!(
+            epoch_scale_acc.key == &expected_key,
+            StabilityPoolError::InvalidEpochScale
+        );
+
+        let data = epoch_scale_acc.try_borrow_mut_data()?;
+        epoch_scales.push(EpochScale::deserialize(
+            data,
+            epoch_scale_acc.key,
+            current_epoch_scale_key,
+            current_epoch_scale,
+        ));
+    }
+
+    Ok(epoch_scales)
+}
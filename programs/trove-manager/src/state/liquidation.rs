@@ -1,3 +1,7 @@
+use crate::constants::DECIMAL_PRECISION;
+
+use super::calc_bps_share;
+
 #[derive(Default)]
 pub struct LiquidationTotals {
     pub total_coll_in_sequence: u64,
@@ -9,6 +13,8 @@ pub struct LiquidationTotals {
     pub total_debt_to_redistribute: u64,
     pub total_coll_to_redistribute: u64,
     pub total_coll_surplus: u64,
+    // Part of the USV gas compensation kept by the SP, it lowers the depositors' loss
+    pub total_usv_gas_comp_to_sp: u64,
 }
 
 #[derive(Default)]
@@ -103,6 +109,76 @@ impl LiquidationTotals {
             .checked_add(single_liquidation.coll_surplus)
             .unwrap();
     }
+
+    /// Pays the coll gas compensation out as its USV value at `price` instead. The coll stays in the
+    /// vault and is redistributed along with the same amount of new debt, so the minted USV is backed.
+    /// Returns the USV value.
+    pub fn convert_coll_gas_comp_to_usv(&mut self, price: u64) -> u64 {
+        let usv_comp = u64::try_from(
+            (self.total_coll_gas_compensation as u128)
+                .checked_mul(price.into())
+                .unwrap()
+                .checked_div(DECIMAL_PRECISION.into())
+                .unwrap(),
+        )
+        .unwrap();
+        self.total_coll_to_redistribute = self
+            .total_coll_to_redistribute
+            .checked_add(self.total_coll_gas_compensation)
+            .unwrap();
+        self.total_debt_to_redistribute = self
+            .total_debt_to_redistribute
+            .checked_add(usv_comp)
+            .unwrap();
+        self.total_usv_gas_compensation = self
+            .total_usv_gas_compensation
+            .checked_add(usv_comp)
+            .unwrap();
+        self.total_coll_gas_compensation = 0;
+        usv_comp
+    }
+
+    /// Only `liquidator_bps` of the gas compensation goes to the liquidator, the SP must be offsetting
+    /// debt. Its depositors gain the rest of the coll, and the rest of the USV is burned from the
+    /// offset debt in place of theirs, up to the offset debt.
+    pub fn retain_gas_comp_in_sp(&mut self, liquidator_bps: u64) {
+        let retained_coll = self
+            .total_coll_gas_compensation
+            .checked_sub(calc_bps_share(
+                self.total_coll_gas_compensation,
+                liquidator_bps,
+            ))
+            .unwrap();
+        self.total_coll_gas_compensation = self
+            .total_coll_gas_compensation
+            .checked_sub(retained_coll)
+            .unwrap();
+        self.total_coll_to_send_to_sp = self
+            .total_coll_to_send_to_sp
+            .checked_add(retained_coll)
+            .unwrap();
+
+        let retained_usv = self
+            .total_usv_gas_compensation
+            .checked_sub(calc_bps_share(
+                self.total_usv_gas_compensation,
+                liquidator_bps,
+            ))
+            .unwrap()
+            .min(self.total_debt_to_offset);
+        self.total_usv_gas_compensation = self
+            .total_usv_gas_compensation
+            .checked_sub(retained_usv)
+            .unwrap();
+        self.total_usv_gas_comp_to_sp = retained_usv;
+    }
+
+    /// USV burned from the SP deposits for the offset debt
+    pub fn get_sp_usv_loss(&self) -> u64 {
+        self.total_debt_to_offset
+            .checked_sub(self.total_usv_gas_comp_to_sp)
+            .unwrap()
+    }
 }
 
 impl LocalVariablesLiquidationSequence {
@@ -125,3 +201,191 @@ impl LocalVariablesLiquidationSequence {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+pub mod liquidation_test {
+    use super::*;
+    use crate::state::{PoolState, StabilityPoolState};
+
+    #[test]
+    fn offset_limited_to_sp_balance() {
+        let mut single_liquidation = LiquidationValues {
+            entire_trove_debt: 2_000_000_000,
+            ..Default::default()
+        };
+        // The SP only covers a quarter of the debt
+        single_liquidation.offset_and_redistribute(1_000_000_000, 500_000_000);
+
+        assert_eq!(single_liquidation.debt_to_offset, 500_000_000);
+        assert_eq!(single_liquidation.coll_to_send_to_sp, 250_000_000);
+        assert_eq!(single_liquidation.debt_to_redistribute, 1_500_000_000);
+        assert_eq!(single_liquidation.coll_to_redistribute, 750_000_000);
+
+        let mut totals = LiquidationTotals::default();
+        totals.add_liquidation_values(&single_liquidation);
+        let mut sp_state = StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            total_usv_deposits: 500_000_000,
+            ..Default::default()
+        };
+        assert!(sp_state.offset(&mut Default::default(), &totals, 0).is_ok());
+    }
+
+    #[test]
+    fn offset_above_sp_balance_is_rejected() {
+        let mut sp_state = StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            total_usv_deposits: 500_000_000,
+            ..Default::default()
+        };
+        assert_eq!(
+            sp_state.compute_rewards_per_unit_staked(1_000_000_000, 500_000_001),
+            None
+        );
+
+        let totals = LiquidationTotals {
+            total_debt_to_offset: 2_000_000_000,
+            total_coll_to_send_to_sp: 1_000_000_000,
+            ..Default::default()
+        };
+        assert!(sp_state
+            .offset(&mut Default::default(), &totals, 0)
+            .is_err());
+        assert_eq!(sp_state.p, u128::from(DECIMAL_PRECISION));
+    }
+
+    #[test]
+    fn gas_comp_split_with_sp() {
+        let mut single_liquidation = LiquidationValues {
+            entire_trove_debt: 2_000_000_000,
+            entire_trove_coll: 1_010_000_000,
+            coll_gas_compensation: 10_000_000,
+            usv_gas_compensation: 200_000_000,
+            ..Default::default()
+        };
+        single_liquidation.offset_and_redistribute(1_000_000_000, 10_000_000_000);
+
+        let mut totals = LiquidationTotals::default();
+        totals.add_liquidation_values(&single_liquidation);
+        totals.retain_gas_comp_in_sp(5_000);
+
+        // The liquidator receives half
+        assert_eq!(totals.total_coll_gas_compensation, 5_000_000);
+        assert_eq!(totals.total_usv_gas_compensation, 100_000_000);
+        assert_eq!(totals.total_usv_gas_comp_to_sp, 100_000_000);
+        assert_eq!(totals.get_sp_usv_loss(), 1_900_000_000);
+
+        let mut pool_state = PoolState {
+            active_coll: 1_010_000_000,
+            active_debt: 2_000_000_000,
+            ..Default::default()
+        };
+        let mut sp_state = StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            total_usv_deposits: 10_000_000_000,
+            ..Default::default()
+        };
+        sp_state
+            .offset(&mut Default::default(), &totals, 0)
+            .unwrap();
+        pool_state.move_coll_debt_from_liquidate(&mut sp_state, &totals);
+
+        // The SP collateral holds the retained half
+        assert_eq!(sp_state.total_collateral, 1_005_000_000);
+        assert_eq!(sp_state.total_usv_deposits, 8_100_000_000);
+        assert_eq!(pool_state.active_coll, 0);
+        assert_eq!(pool_state.active_debt, 0);
+    }
+
+    #[test]
+    fn gas_comp_goes_to_liquidator_by_default() {
+        let pool_state = PoolState::default();
+        let mut totals = LiquidationTotals {
+            total_coll_gas_compensation: 10_000_000,
+            total_usv_gas_compensation: 200_000_000,
+            total_debt_to_offset: 2_000_000_000,
+            total_coll_to_send_to_sp: 1_000_000_000,
+            ..Default::default()
+        };
+        totals.retain_gas_comp_in_sp(pool_state.get_liquidator_gas_comp_bps());
+
+        assert_eq!(totals.total_coll_gas_compensation, 10_000_000);
+        assert_eq!(totals.total_usv_gas_compensation, 200_000_000);
+        assert_eq!(totals.total_coll_to_send_to_sp, 1_000_000_000);
+        assert_eq!(totals.get_sp_usv_loss(), 2_000_000_000);
+    }
+
+    #[test]
+    fn sp_keeps_gas_comp_at_zero_bps() {
+        let mut pool_state = PoolState::default();
+        pool_state.set_liquidator_gas_comp_bps(0).unwrap();
+        let mut totals = LiquidationTotals {
+            total_coll_gas_compensation: 10_000_000,
+            total_usv_gas_compensation: 200_000_000,
+            total_debt_to_offset: 2_000_000_000,
+            total_coll_to_send_to_sp: 1_000_000_000,
+            ..Default::default()
+        };
+        totals.retain_gas_comp_in_sp(pool_state.get_liquidator_gas_comp_bps());
+
+        assert_eq!(totals.total_coll_gas_compensation, 0);
+        assert_eq!(totals.total_usv_gas_compensation, 0);
+        assert_eq!(totals.total_coll_to_send_to_sp, 1_010_000_000);
+        assert_eq!(totals.total_usv_gas_comp_to_sp, 200_000_000);
+        assert_eq!(totals.get_sp_usv_loss(), 1_800_000_000);
+    }
+
+    #[test]
+    fn usv_comp_matches_coll_comp_value() {
+        let price = 150_000_000_000;
+        let pool_state = PoolState {
+            coll_gas_comp_percent_divisor: 200,
+            ..Default::default()
+        };
+        let mut single_liquidation = LiquidationValues {
+            entire_trove_debt: 2_000_000_000_000,
+            entire_trove_coll: 14_000_000_001,
+            usv_gas_compensation: 200_000_000,
+            ..Default::default()
+        };
+        single_liquidation.coll_gas_compensation = pool_state.get_coll_liquidation_compensation(
+            single_liquidation.entire_trove_coll,
+            single_liquidation.entire_trove_debt,
+            price,
+        );
+        let coll_to_liquidate =
+            single_liquidation.entire_trove_coll - single_liquidation.coll_gas_compensation;
+        single_liquidation.offset_and_redistribute(coll_to_liquidate, 1_000_000_000_000);
+
+        let mut standard = LiquidationTotals::default();
+        standard.add_liquidation_values(&single_liquidation);
+        let mut usv_comp = LiquidationTotals::default();
+        usv_comp.add_liquidation_values(&single_liquidation);
+        let usv_value = usv_comp.convert_coll_gas_comp_to_usv(price);
+
+        // The liquidator gets the price-valued coll of the standard path, rounded down
+        let coll_value = u128::from(standard.total_coll_gas_compensation) * u128::from(price);
+        assert!(u128::from(usv_value) * u128::from(DECIMAL_PRECISION) <= coll_value);
+        assert!(u128::from(usv_value + 1) * u128::from(DECIMAL_PRECISION) > coll_value);
+        assert_eq!(usv_comp.total_coll_gas_compensation, 0);
+        assert_eq!(
+            usv_comp.total_usv_gas_compensation,
+            standard.total_usv_gas_compensation + usv_value
+        );
+
+        // The retained coll is redistributed with the new debt, the SP offset is unchanged
+        assert_eq!(
+            usv_comp.total_coll_to_redistribute,
+            standard.total_coll_to_redistribute + standard.total_coll_gas_compensation
+        );
+        assert_eq!(
+            usv_comp.total_debt_to_redistribute,
+            standard.total_debt_to_redistribute + usv_value
+        );
+        assert_eq!(usv_comp.total_debt_to_offset, standard.total_debt_to_offset);
+        assert_eq!(
+            usv_comp.total_coll_to_send_to_sp,
+            standard.total_coll_to_send_to_sp
+        );
+    }
+}
@@ -1,10 +1,14 @@
 use crate::{
     constants::{
-        BORROWING_FEE_FLOOR, DECIMAL_PRECISION, MAX_BORROWING_FEE, MINUTE_DECAY_FACTOR,
-        REDEMPTION_FEE_FLOOR, SECOND_IN_ONE_MINUTE,
+        BORROWING_FEE_FLOOR, DECIMAL_PRECISION, INVARIANT_COLL_VAULT, INVARIANT_SP_COLL_VAULT,
+        INVARIANT_SP_USV_VAULT, INVARIANT_STABLECOIN_SUPPLY, MAX_BORROWING_FEE, MAX_BPS,
+        MAX_MINUTE_DECAY_FACTOR, MINUTE_DECAY_FACTOR, MIN_MINUTE_DECAY_FACTOR,
+        ONE_HUNDERED_PERCENT, REDEMPTION_FEE_FLOOR, SECOND_IN_ONE_MINUTE,
+    },
+    errors::{BorrowerOpsError, PoolConfigError, StabilityPoolError},
+    events::{
+        BaseRateUpdated, LastFeeOpTimeUpdated, Operation, SystemSnapshotsUpdated, TroveUpdated,
     },
-    errors::BorrowerOpsError,
-    events::{BaseRateUpdated, LastFeeOpTimeUpdated, SystemSnapshotsUpdated},
     math::{compute_cr, dec_pow},
     utils::get_current_timestamp,
     ID,
@@ -12,10 +16,12 @@ use crate::{
 use anchor_lang::prelude::*;
 use std::cmp;
 
-use super::{LiquidationTotals, LiquidationValues, StabilityPoolState, Trove};
+use super::{
+    CVGTStakingPoolState, LiquidationTotals, LiquidationValues, StabilityPoolState, Trove,
+};
 
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct PoolState {
     pub creator: Pubkey,
     pub stablecoin: Pubkey,
@@ -62,6 +68,78 @@ pub struct PoolState {
     pub token_auth_bump: [u8; 1],
     pub stability_pool_bump: [u8; 1],
     pub bump: [u8; 1],
+
+    // Fee split
+    pub redemption_fee_treasury_bps: u64,
+
+    // Mint gas compensation to the borrower instead of the gas compensation vault
+    pub gas_comp_to_borrower: bool,
+
+    // Extra collateral paid to the liquidator on top of gas compensation
+    pub liquidation_bonus_bps: u64,
+
+    // Wind-down: new SP deposits are rejected, withdrawals and liquidations are unaffected
+    pub sp_deposits_disabled: bool,
+
+    // Nonce of the last TroveUpdated, Liquidation or Redemption event
+    pub event_nonce: u64,
+
+    // Per-trove collateral cap, 0 is unlimited
+    pub max_trove_coll: u64,
+
+    // System debt cap, 0 is unlimited
+    pub max_system_debt: u64,
+
+    // Borrowers may liquidate their own trove
+    pub self_liquidation_allowed: bool,
+
+    // Base rate decay per minute, 0 for migrated pools stands for MINUTE_DECAY_FACTOR
+    pub minute_decay_factor: u64,
+
+    // Share of the total debt a single redemption may redeem, 0 is unlimited
+    pub max_redemption_fraction_bps: u64,
+
+    // Minimum seconds between two debt increases of a trove, 0 is disabled
+    pub debt_increase_cooldown: u64,
+
+    // Bounds of the coll gas compensation, 0 disables a bound
+    pub min_coll_gas_comp: u64,
+    pub max_coll_gas_comp: u64,
+
+    // Margin over MCR the first redemption hint may have drifted to, 0 requires it under MCR
+    pub redemption_hint_tolerance_bps: u64,
+
+    // Owner of the ATA receiving USV borrowing fees until CVGT emission starts, unset keeps them
+    // in the staking vault
+    pub pre_launch_fee_treasury: Pubkey,
+
+    // Maximum age in seconds of the price a redemption goes through with, 0 only relies on the
+    // oracle timeouts
+    pub redemption_max_price_age: u64,
+
+    // Seconds after opening during which a trove above 100% ICR can't be liquidated in normal
+    // mode, 0 disables it
+    pub liquidation_grace_period: u64,
+
+    // Share of the liquidation gas compensation paid to the liquidator when the SP offsets debt,
+    // the SP keeps the rest. Reads as MAX_BPS until set, see liquidator_gas_comp_bps_set
+    pub liquidator_gas_comp_bps: u64,
+
+    // Added to the borrowing rate while the TCR is above the CCR by less than the band, in bps.
+    // The band is in DECIMAL_PRECISION. 0 disables it
+    pub recovery_proximity_surcharge_bps: u64,
+    pub recovery_proximity_band: u64,
+
+    // Cap on the number of troves in the sorted list, 0 is unlimited
+    pub max_troves: u64,
+
+    // Redemptions walk equal NICR troves in pubkey order. Lists built before the tie-break may
+    // hold such pairs in either order, so migrated pools only enforce it once enabled
+    pub nicr_tie_break_enforced: bool,
+
+    // Migrated accounts read liquidator_gas_comp_bps as zero, which would send the whole gas
+    // compensation to the SP. Until this is set the liquidator keeps paying MAX_BPS
+    pub liquidator_gas_comp_bps_set: bool,
 }
 
 impl PoolState {
@@ -109,6 +187,34 @@ impl PoolState {
         self.total_surplus = 0;
         self.trove_head = Pubkey::default();
         self.trove_tail = Pubkey::default();
+        self.redemption_fee_treasury_bps = 0;
+        self.gas_comp_to_borrower = false;
+        self.liquidation_bonus_bps = 0;
+        self.sp_deposits_disabled = false;
+        self.event_nonce = 0;
+        self.max_trove_coll = 0;
+        self.max_system_debt = 0;
+        self.self_liquidation_allowed = false;
+        self.minute_decay_factor = MINUTE_DECAY_FACTOR;
+        self.max_redemption_fraction_bps = MAX_BPS;
+        self.debt_increase_cooldown = 0;
+        self.min_coll_gas_comp = 0;
+        self.max_coll_gas_comp = 0;
+        self.redemption_hint_tolerance_bps = 0;
+        self.pre_launch_fee_treasury = Pubkey::default();
+        self.redemption_max_price_age = 0;
+        self.liquidation_grace_period = 0;
+        self.liquidator_gas_comp_bps = MAX_BPS;
+        self.recovery_proximity_surcharge_bps = 0;
+        self.recovery_proximity_band = 0;
+        self.max_troves = 0;
+        self.nicr_tie_break_enforced = true;
+        self.liquidator_gas_comp_bps_set = true;
+    }
+
+    pub fn next_event_nonce(&mut self) -> u64 {
+        self.event_nonce = self.event_nonce.checked_add(1).unwrap();
+        self.event_nonce
     }
 
     pub fn require_at_least_min_net_debt(&self, net_debt: u64) -> Result<()> {
@@ -120,6 +226,47 @@ impl PoolState {
         Ok(())
     }
 
+    pub fn require_trove_coll_within_cap(&self, coll: u64) -> Result<()> {
+        require!(
+            self.max_trove_coll == 0 || coll <= self.max_trove_coll,
+            BorrowerOpsError::TroveCollAboveMax
+        );
+        Ok(())
+    }
+
+    /// Only checked when a trove is opened, adjustments and closes are allowed at the cap
+    pub fn require_trove_count_below_cap(&self) -> Result<()> {
+        require!(
+            self.max_troves == 0 || self.trove_size < self.max_troves,
+            BorrowerOpsError::TroveCountAtMax
+        );
+        Ok(())
+    }
+
+    /// Only checked when debt is added, repayments and redemptions are allowed above the cap
+    pub fn require_system_debt_within_cap(&self, debt_increase: u64) -> Result<()> {
+        require!(
+            self.max_system_debt == 0
+                || self.get_entire_debt().checked_add(debt_increase).unwrap()
+                    <= self.max_system_debt,
+            BorrowerOpsError::SystemDebtAboveMax
+        );
+        Ok(())
+    }
+
+    /// Liquidating your own trove collects the gas compensation on top of the trove's surplus
+    pub fn require_not_self_liquidation(
+        &self,
+        liquidator: &Pubkey,
+        borrower: &Pubkey,
+    ) -> Result<()> {
+        require!(
+            self.self_liquidation_allowed || liquidator != borrower,
+            BorrowerOpsError::SelfLiquidation
+        );
+        Ok(())
+    }
+
     pub fn require_not_in_recovery_mode(&self, price: u64) -> Result<()> {
         require!(
             !self.check_recovery_mode(price),
@@ -128,12 +275,20 @@ impl PoolState {
         Ok(())
     }
 
+    pub fn require_sp_deposits_enabled(&self) -> Result<()> {
+        require!(
+            !self.sp_deposits_disabled,
+            StabilityPoolError::DepositsDisabled
+        );
+        Ok(())
+    }
+
     pub fn require_more_than_one_trove_in_system(&self) -> Result<()> {
         require!(self.trove_size > 1, BorrowerOpsError::OnlyOneTrove);
         Ok(())
     }
 
-    pub fn stability_pool_seeds<'a, 'b: 'a>(&'a self, key: &'b Pubkey) -> [&[u8]; 3] {
+    pub fn stability_pool_seeds<'a, 'b: 'a>(&'a self, key: &'b Pubkey) -> [&'a [u8]; 3] {
         [
             &b"stability"[..],
             key.as_ref(),
@@ -141,7 +296,7 @@ impl PoolState {
         ]
     }
 
-    pub fn token_auth_seeds<'a, 'b: 'a>(&'a self, key: &'b Pubkey) -> [&[u8]; 3] {
+    pub fn token_auth_seeds<'a, 'b: 'a>(&'a self, key: &'b Pubkey) -> [&'a [u8]; 3] {
         [
             &b"token-authority"[..],
             key.as_ref(),
@@ -165,8 +320,61 @@ impl PoolState {
         debt.checked_sub(self.gas_compensation).unwrap()
     }
 
+    /// Gas compensation the vault must keep for the troves in the list
+    pub fn get_gas_comp_reserve(&self) -> u64 {
+        self.trove_size.checked_mul(self.gas_compensation).unwrap()
+    }
+
+    /// Vault USV above the reserve that belongs to no trove
+    pub fn get_gas_comp_residual(&self, vault_balance: u64) -> u64 {
+        vault_balance.saturating_sub(self.get_gas_comp_reserve())
+    }
+
+    /// `entire_coll / coll_gas_comp_percent_divisor` clamped to the configured bounds.
+    /// The floor never takes more than the trove's coll.
     pub fn get_coll_gas_compensation(&self, entire_coll: u64) -> u64 {
-        entire_coll / self.coll_gas_comp_percent_divisor
+        let mut coll_gas_compensation = entire_coll / self.coll_gas_comp_percent_divisor;
+        if self.max_coll_gas_comp > 0 {
+            coll_gas_compensation = cmp::min(coll_gas_compensation, self.max_coll_gas_comp);
+        }
+        if self.min_coll_gas_comp > 0 {
+            coll_gas_compensation = cmp::max(coll_gas_compensation, self.min_coll_gas_comp);
+        }
+        cmp::min(coll_gas_compensation, entire_coll)
+    }
+
+    pub fn set_coll_gas_comp_bounds(&mut self, min: u64, max: u64) -> Result<()> {
+        require!(
+            min == 0 || max == 0 || min <= max,
+            PoolConfigError::InvalidCollGasCompBounds
+        );
+        self.min_coll_gas_comp = min;
+        self.max_coll_gas_comp = max;
+        Ok(())
+    }
+
+    /// Coll paid to the liquidator, gas compensation plus the liquidation bonus.
+    /// The bonus is capped so the remaining coll is still worth `entire_debt` at `price`.
+    pub fn get_coll_liquidation_compensation(
+        &self,
+        entire_coll: u64,
+        entire_debt: u64,
+        price: u64,
+    ) -> u64 {
+        let coll_gas_compensation = self.get_coll_gas_compensation(entire_coll);
+        let coll_to_liquidate = entire_coll.checked_sub(coll_gas_compensation).unwrap();
+        let coll_for_debt = u64::try_from(
+            (entire_debt as u128)
+                .checked_mul(DECIMAL_PRECISION.into())
+                .unwrap()
+                .div_ceil(price.into()),
+        )
+        .unwrap_or(u64::MAX);
+        let bonus = cmp::min(
+            calc_bps_share(coll_to_liquidate, self.liquidation_bonus_bps),
+            coll_to_liquidate.saturating_sub(coll_for_debt),
+        );
+        coll_gas_compensation.checked_add(bonus).unwrap()
     }
 
     pub fn get_entire_coll(&self) -> u64 {
@@ -177,6 +385,29 @@ impl PoolState {
         self.active_debt.checked_add(self.closed_debt).unwrap()
     }
 
+    /// Bitmask of the `INVARIANT_*` checks that hold for the given vault balances
+    pub fn get_invariants(&self, sp_state: &StabilityPoolState, balances: &VaultBalances) -> u8 {
+        let mut invariants = 0;
+        // Surplus coll waits in the collateral vault until claimed
+        let coll = self.get_entire_coll().checked_add(self.total_surplus);
+        if coll == Some(balances.collateral_vault) {
+            invariants |= INVARIANT_COLL_VAULT;
+        }
+        let supply = balances
+            .stablecoin_supply
+            .checked_sub(self.get_gas_comp_residual(balances.gas_compensation_vault));
+        if supply == Some(self.get_entire_debt()) {
+            invariants |= INVARIANT_STABLECOIN_SUPPLY;
+        }
+        if sp_state.total_usv_deposits == balances.sp_usv_vault {
+            invariants |= INVARIANT_SP_USV_VAULT;
+        }
+        if sp_state.total_collateral == balances.sp_coll_vault {
+            invariants |= INVARIANT_SP_COLL_VAULT;
+        }
+        invariants
+    }
+
     pub fn get_tcr(&self, price: u64) -> u64 {
         let entire_coll = self.get_entire_coll();
         let entire_debt = self.get_entire_debt();
@@ -199,7 +430,10 @@ impl PoolState {
     }
 
     pub fn minutes_passed_since_last_fee_op(&self) -> u64 {
-        let cur_timestamp = get_current_timestamp();
+        self.minutes_passed_since_last_fee_op_at(get_current_timestamp())
+    }
+
+    pub fn minutes_passed_since_last_fee_op_at(&self, cur_timestamp: u64) -> u64 {
         cur_timestamp
             .checked_sub(self.last_fee_operation_time)
             .unwrap()
@@ -207,9 +441,30 @@ impl PoolState {
             .unwrap()
     }
 
+    pub fn get_minute_decay_factor(&self) -> u64 {
+        if self.minute_decay_factor == 0 {
+            MINUTE_DECAY_FACTOR
+        } else {
+            self.minute_decay_factor
+        }
+    }
+
+    /// Also applies to the time passed since the last fee operation
+    pub fn set_minute_decay_factor(&mut self, minute_decay_factor: u64) -> Result<()> {
+        require!(
+            (MIN_MINUTE_DECAY_FACTOR..=MAX_MINUTE_DECAY_FACTOR).contains(&minute_decay_factor),
+            PoolConfigError::InvalidMinuteDecayFactor
+        );
+        self.minute_decay_factor = minute_decay_factor;
+        Ok(())
+    }
+
     pub fn calc_decayed_base_rate(&self) -> u64 {
-        let minutes_passed = self.minutes_passed_since_last_fee_op();
-        let decay_factor = dec_pow(MINUTE_DECAY_FACTOR, minutes_passed).unwrap();
+        self.calc_base_rate_decayed_over(self.minutes_passed_since_last_fee_op())
+    }
+
+    pub fn calc_base_rate_decayed_over(&self, minutes_passed: u64) -> u64 {
+        let decay_factor = dec_pow(self.get_minute_decay_factor(), minutes_passed).unwrap();
         u64::try_from(
             (self.base_rate as u128)
                 .checked_mul(decay_factor.into())
@@ -224,8 +479,43 @@ impl PoolState {
         calc_borrowing_rate(self.base_rate)
     }
 
-    pub fn get_borrowing_fee(&self, usv_debt: u64) -> u64 {
-        calc_borrowing_fee(self.get_borrowing_rate(), usv_debt)
+    /// The surcharge counts toward MAX_BORROWING_FEE
+    pub fn get_borrowing_fee(&self, usv_debt: u64, price: u64) -> u64 {
+        let borrowing_rate = calc_borrowing_rate(
+            self.base_rate
+                .checked_add(self.get_recovery_proximity_surcharge(price))
+                .unwrap(),
+        );
+        calc_borrowing_fee(borrowing_rate, usv_debt)
+    }
+
+    /// Fee `decay_base_rate_from_borrowing` would lead to, without updating the base rate
+    pub fn get_decayed_borrowing_fee(&self, usv_debt: u64, price: u64) -> u64 {
+        let borrowing_rate = calc_borrowing_rate(
+            self.calc_decayed_base_rate()
+                .checked_add(self.get_recovery_proximity_surcharge(price))
+                .unwrap(),
+        );
+        calc_borrowing_fee(borrowing_rate, usv_debt)
+    }
+
+    /// Not charged in recovery mode, where borrowing pays no fee at all
+    pub fn get_recovery_proximity_surcharge(&self, price: u64) -> u64 {
+        if self.recovery_proximity_surcharge_bps == 0 {
+            return 0;
+        }
+        let tcr = self.get_tcr(price);
+        if tcr < self.ccr || tcr >= self.ccr.saturating_add(self.recovery_proximity_band) {
+            return 0;
+        }
+        calc_bps_share(DECIMAL_PRECISION, self.recovery_proximity_surcharge_bps)
+    }
+
+    pub fn set_recovery_proximity_surcharge(&mut self, bps: u64, band: u64) -> Result<()> {
+        require!(bps <= MAX_BPS, PoolConfigError::InvalidBps);
+        self.recovery_proximity_surcharge_bps = bps;
+        self.recovery_proximity_band = band;
+        Ok(())
     }
 
     pub fn get_redemption_fee(&self, coll_drawn: u64) -> Result<u64> {
@@ -236,6 +526,125 @@ impl PoolState {
         calc_redemption_rate(self.base_rate)
     }
 
+    /// Splits the redemption fee into (treasury share, CVGT staker share)
+    pub fn split_redemption_fee(&self, coll_fee: u64) -> (u64, u64) {
+        let treasury_share = calc_bps_share(coll_fee, self.redemption_fee_treasury_bps);
+        (
+            treasury_share,
+            coll_fee.checked_sub(treasury_share).unwrap(),
+        )
+    }
+
+    pub fn routes_usv_fee_to_treasury(&self, enable_emission: bool) -> bool {
+        !enable_emission && self.pre_launch_fee_treasury != Pubkey::default()
+    }
+
+    pub fn set_redemption_fee_treasury_bps(&mut self, bps: u64) -> Result<()> {
+        require!(bps <= MAX_BPS, PoolConfigError::InvalidBps);
+        self.redemption_fee_treasury_bps = bps;
+        Ok(())
+    }
+
+    /// Repoints protocol fees from the treasury to a staking pool paying out this pool's mints
+    pub fn set_cvgt_staking_state(
+        &mut self,
+        key: Pubkey,
+        cvgt_staking_state: &CVGTStakingPoolState,
+    ) -> Result<()> {
+        require!(
+            cvgt_staking_state.cvgt == self.cvgt
+                && cvgt_staking_state.usv == self.stablecoin
+                && cvgt_staking_state.collateral == self.collateral,
+            PoolConfigError::StakingPoolMismatch
+        );
+        self.cvgt_staking_state = key;
+        Ok(())
+    }
+
+    /// The trove before the first redeemed one must be under MCR, or above it by no more
+    /// than `redemption_hint_tolerance_bps` of MCR after the price moved since the hint was built
+    pub fn is_valid_redemption_start_hint(&self, icr: u64) -> bool {
+        let tolerance = calc_bps_share(self.mcr, self.redemption_hint_tolerance_bps);
+        icr < self.mcr.checked_add(tolerance).unwrap()
+    }
+
+    pub fn set_redemption_hint_tolerance_bps(&mut self, bps: u64) -> Result<()> {
+        require!(bps <= MAX_BPS, PoolConfigError::InvalidBps);
+        self.redemption_hint_tolerance_bps = bps;
+        Ok(())
+    }
+
+    pub fn set_max_redemption_fraction_bps(&mut self, bps: u64) -> Result<()> {
+        require!(bps <= MAX_BPS, PoolConfigError::InvalidBps);
+        self.max_redemption_fraction_bps = bps;
+        Ok(())
+    }
+
+    /// Caps the base rate jump a single redemption can cause
+    pub fn require_redemption_within_max_fraction(
+        &self,
+        usv_amt: u64,
+        total_usv_supply: u64,
+    ) -> Result<()> {
+        if self.max_redemption_fraction_bps == 0 {
+            return Ok(());
+        }
+        // Compared without dividing, so rounding can't let an amount over the cap through and
+        // an empty supply rejects any amount
+        require!(
+            u128::from(usv_amt) * u128::from(MAX_BPS)
+                <= u128::from(self.max_redemption_fraction_bps) * u128::from(total_usv_supply),
+            BorrowerOpsError::RedemptionAboveMaxFraction
+        );
+        Ok(())
+    }
+
+    pub fn update_risk_params(&mut self, mcr: u64, ccr: u64, min_net_debt: u64) -> Result<()> {
+        require!(mcr > ONE_HUNDERED_PERCENT, PoolConfigError::InvalidMcr);
+        require!(ccr > mcr, PoolConfigError::InvalidCcr);
+        require!(
+            min_net_debt > self.gas_compensation,
+            PoolConfigError::InvalidMinNetDebt
+        );
+        self.mcr = mcr;
+        self.ccr = ccr;
+        self.min_net_debt = min_net_debt;
+        Ok(())
+    }
+
+    /// Every open trove's debt includes the gas compensation, so it can only change with none open
+    pub fn set_gas_compensation(&mut self, gas_compensation: u64) -> Result<()> {
+        require!(self.trove_size == 0, PoolConfigError::TrovesOutstanding);
+        require!(
+            self.min_net_debt > gas_compensation,
+            PoolConfigError::InvalidMinNetDebt
+        );
+        self.gas_compensation = gas_compensation;
+        Ok(())
+    }
+
+    pub fn get_liquidator_gas_comp_bps(&self) -> u64 {
+        if self.liquidator_gas_comp_bps_set {
+            self.liquidator_gas_comp_bps
+        } else {
+            MAX_BPS
+        }
+    }
+
+    /// 0 leaves the whole gas compensation to the SP
+    pub fn set_liquidator_gas_comp_bps(&mut self, bps: u64) -> Result<()> {
+        require!(bps <= MAX_BPS, PoolConfigError::InvalidBps);
+        self.liquidator_gas_comp_bps = bps;
+        self.liquidator_gas_comp_bps_set = true;
+        Ok(())
+    }
+
+    pub fn set_liquidation_bonus_bps(&mut self, bps: u64) -> Result<()> {
+        require!(bps <= MAX_BPS, PoolConfigError::InvalidBps);
+        self.liquidation_bonus_bps = bps;
+        Ok(())
+    }
+
     pub fn get_new_tcr_from_trove_change(
         &self,
         coll_change: u64,
@@ -268,9 +677,11 @@ impl PoolState {
         entire_trove_coll: u64,
         price: u64,
     ) -> Result<LiquidationValues> {
-        let mut single_liquidation: LiquidationValues = Default::default();
-        single_liquidation.entire_trove_debt = entire_trove_debt;
-        single_liquidation.entire_trove_coll = entire_trove_coll;
+        let mut single_liquidation = LiquidationValues {
+            entire_trove_debt,
+            entire_trove_coll,
+            ..Default::default()
+        };
         let capped_coll_portion = u64::try_from(
             (entire_trove_debt as u128)
                 .checked_mul(self.mcr.into())
@@ -281,7 +692,7 @@ impl PoolState {
         .unwrap();
 
         single_liquidation.coll_gas_compensation =
-            self.get_coll_gas_compensation(capped_coll_portion);
+            self.get_coll_liquidation_compensation(capped_coll_portion, entire_trove_debt, price);
         single_liquidation.usv_gas_compensation = self.gas_compensation;
 
         single_liquidation.debt_to_offset = entire_trove_debt;
@@ -300,6 +711,15 @@ impl PoolState {
         Ok(())
     }
 
+    /// Debt can't be added to a trove already under MCR once its pending rewards are applied
+    pub fn require_baseline_icr_for_debt_increase(&self, baseline_icr: u64) -> Result<()> {
+        require!(
+            baseline_icr >= self.mcr,
+            BorrowerOpsError::BaselineICRLowerThanMCR
+        );
+        Ok(())
+    }
+
     pub fn require_icr_is_above_ccr(&self, new_icr: u64) -> Result<()> {
         require!(new_icr >= self.ccr, BorrowerOpsError::ICRLowerThanCCR);
         Ok(())
@@ -382,7 +802,7 @@ impl PoolState {
             base_rate: self.base_rate
         });
 
-        self.update_last_fee_op_time();
+        self.update_last_fee_op_time(get_current_timestamp());
         Ok(())
     }
 
@@ -397,8 +817,10 @@ impl PoolState {
         coll_drawn: u64,
         price: u64,
         total_usv_supply: u64,
+        cur_timestamp: u64,
     ) -> Result<u64> {
-        let decayed_base_rate = self.calc_decayed_base_rate();
+        let decayed_base_rate = self
+            .calc_base_rate_decayed_over(self.minutes_passed_since_last_fee_op_at(cur_timestamp));
 
         let redeemed_usv_fraction = u64::try_from(
             u128::from(coll_drawn)
@@ -422,20 +844,26 @@ impl PoolState {
             base_rate: new_base_rate
         });
 
-        self.update_last_fee_op_time();
+        self.update_last_fee_op_time(cur_timestamp);
 
         Ok(new_base_rate)
     }
 
-    pub fn update_last_fee_op_time(&mut self) {
-        let cur_timestamp = get_current_timestamp();
-        let time_passed = cur_timestamp
-            .checked_sub(self.last_fee_operation_time)
-            .unwrap();
-        if time_passed >= SECOND_IN_ONE_MINUTE.into() {
-            self.last_fee_operation_time = cur_timestamp;
+    /// Advances by the whole minutes the base rate was decayed over, so operations within a minute
+    /// neither decay the rate again nor drop the seconds left towards the next decay
+    pub fn update_last_fee_op_time(&mut self, cur_timestamp: u64) {
+        let minutes_passed = self.minutes_passed_since_last_fee_op_at(cur_timestamp);
+        if minutes_passed > 0 {
+            self.last_fee_operation_time = self
+                .last_fee_operation_time
+                .checked_add(
+                    minutes_passed
+                        .checked_mul(SECOND_IN_ONE_MINUTE.into())
+                        .unwrap(),
+                )
+                .unwrap();
             emit!(LastFeeOpTimeUpdated {
-                last_fee_op_time: cur_timestamp
+                last_fee_op_time: self.last_fee_operation_time
             });
         }
     }
@@ -455,7 +883,7 @@ impl PoolState {
         self.decrease_active_coll(totals.total_coll_gas_compensation);
 
         self.decrease_active_debt(totals.total_debt_to_offset);
-        sp_state.decrease_usv(totals.total_debt_to_offset);
+        sp_state.decrease_usv(totals.get_sp_usv_loss());
 
         self.decrease_active_coll(totals.total_coll_to_send_to_sp);
         sp_state.increase_coll(totals.total_coll_to_send_to_sp);
@@ -474,24 +902,36 @@ impl PoolState {
             trove.update_reward_snapshot(self);
 
             self.move_pending_trove_rewards_to_active(pending_debt_reward, pending_coll_reward);
-            // emit event
+            emit!(TroveUpdated {
+                borrower: trove.creator,
+                debt: trove.debt,
+                coll: trove.coll,
+                stake: trove.stake,
+                operation: Operation::ApplyPendingRewards,
+                event_nonce: self.next_event_nonce()
+            });
         }
         Ok(())
     }
 
-    pub fn redistribute_debt_and_coll(&mut self, debt: u64, coll: u64) {
+    /// Fails when no stake is left to spread the debt over
+    pub fn redistribute_debt_and_coll(&mut self, debt: u64, coll: u64) -> Result<()> {
         if debt == 0 {
-            return;
+            return Ok(());
         }
+        require!(
+            self.total_stakes > 0,
+            BorrowerOpsError::NoTrovesToRedistribute
+        );
         let coll_numerator = (coll as u128)
             .checked_mul(DECIMAL_PRECISION.into())
             .unwrap()
-            .checked_add(self.last_coll_error_redistribution.into())
+            .checked_add(self.last_coll_error_redistribution)
             .unwrap();
         let usv_debt_numerator = (debt as u128)
             .checked_mul(DECIMAL_PRECISION.into())
             .unwrap()
-            .checked_add(self.last_usv_debt_error_redistribution.into())
+            .checked_add(self.last_usv_debt_error_redistribution)
             .unwrap();
 
         let coll_reward_per_unit_staked = coll_numerator
@@ -531,6 +971,7 @@ impl PoolState {
         self.increase_closed_debt(debt);
         self.decrease_active_coll(coll);
         self.increase_liquidated_coll(coll);
+        Ok(())
     }
 
     pub fn update_system_snapshots_exclude_coll_remainder(&mut self, coll_remainder: u64) {
@@ -562,6 +1003,15 @@ fn calc_borrowing_fee(borrowing_rate: u64, usv_debt: u64) -> u64 {
     .unwrap()
 }
 
+/// Token balances checked against the pool accounting by `get_invariants`
+pub struct VaultBalances {
+    pub collateral_vault: u64,
+    pub stablecoin_supply: u64,
+    pub gas_compensation_vault: u64,
+    pub sp_usv_vault: u64,
+    pub sp_coll_vault: u64,
+}
+
 pub fn calc_borrowing_rate(base_rate: u64) -> u64 {
     cmp::min(
         BORROWING_FEE_FLOOR.checked_add(base_rate).unwrap(),
@@ -586,9 +1036,831 @@ pub fn calc_redemption_fee(redemption_rate: u64, coll_drawn: u64) -> Result<u64>
     Ok(redemption_fee)
 }
 
+pub fn calc_bps_share(amount: u64, bps: u64) -> u64 {
+    u64::try_from(
+        (amount as u128)
+            .checked_mul(bps.into())
+            .unwrap()
+            .checked_div(MAX_BPS.into())
+            .unwrap(),
+    )
+    .unwrap()
+}
+
 pub fn calc_redemption_rate(base_rate: u64) -> u64 {
     cmp::min(
         REDEMPTION_FEE_FLOOR.checked_add(base_rate).unwrap(),
         DECIMAL_PRECISION,
     )
 }
+
+#[cfg(test)]
+pub mod pool_state_test {
+    use super::*;
+    use crate::{
+        state::{EpochScale, TroveStatus},
+        utils::utils_test::set_program_test_stubs,
+    };
+
+    #[test]
+    fn redemption_fee_goes_to_stakers_by_default() {
+        let pool_state = PoolState::default();
+        assert_eq!(pool_state.split_redemption_fee(1_000_000), (0, 1_000_000));
+    }
+
+    #[test]
+    fn usv_fee_goes_to_treasury_until_emission() {
+        let mut pool_state = PoolState::default();
+        assert!(!pool_state.routes_usv_fee_to_treasury(false));
+        assert!(!pool_state.routes_usv_fee_to_treasury(true));
+
+        pool_state.pre_launch_fee_treasury = Pubkey::new_unique();
+        assert!(pool_state.routes_usv_fee_to_treasury(false));
+        assert!(!pool_state.routes_usv_fee_to_treasury(true));
+    }
+
+    #[test]
+    fn redemption_fee_split_with_treasury_share() {
+        let mut pool_state = PoolState::default();
+        pool_state.set_redemption_fee_treasury_bps(3_000).unwrap();
+        assert_eq!(
+            pool_state.split_redemption_fee(1_000_000),
+            (300_000, 700_000)
+        );
+        // Rounding dust stays with the stakers
+        assert_eq!(pool_state.split_redemption_fee(7), (2, 5));
+    }
+
+    #[test]
+    fn redemption_fee_treasury_bps_is_bounded() {
+        let mut pool_state = PoolState::default();
+        assert!(pool_state
+            .set_redemption_fee_treasury_bps(MAX_BPS + 1)
+            .is_err());
+        assert!(pool_state.set_redemption_fee_treasury_bps(MAX_BPS).is_ok());
+    }
+
+    #[test]
+    fn applying_pending_rewards_keeps_nicr() {
+        let mut pool_state = PoolState {
+            total_stakes: 3_000_000_000,
+            l_coll: 20_000_000,
+            l_usv_debt: 1_500_000_000,
+            liquidated_coll: 60_000_000,
+            closed_debt: 4_500_000_000,
+            ..Default::default()
+        };
+        let mut troves: Vec<Trove> = [(1_000_000_000, 1_200_000_000), (2_000_000_000, 900_000_000)]
+            .iter()
+            .map(|&(coll, debt)| Trove {
+                coll,
+                debt,
+                stake: coll,
+                status: TroveStatus::Active,
+                ..Default::default()
+            })
+            .collect();
+
+        for trove in troves.iter_mut() {
+            let nicr = trove.get_nominal_icr(&pool_state);
+            pool_state.apply_pending_reward(trove).unwrap();
+            assert!(!trove.has_pending_rewards(&pool_state));
+            assert_eq!(trove.get_nominal_icr(&pool_state), nicr);
+        }
+        assert_eq!(pool_state.liquidated_coll, 0);
+        assert_eq!(pool_state.closed_debt, 0);
+    }
+
+    #[test]
+    fn debt_increase_rejected_when_pending_rewards_push_under_mcr() {
+        let price = 1_000_000_000;
+        let mut pool_state = PoolState {
+            mcr: 1_100_000_000,
+            total_stakes: 1_000_000_000,
+            l_coll: 100_000_000,
+            l_usv_debt: 500_000_000,
+            liquidated_coll: 100_000_000,
+            closed_debt: 500_000_000,
+            ..Default::default()
+        };
+        let mut trove = Trove {
+            coll: 1_000_000_000,
+            debt: 600_000_000,
+            stake: 1_000_000_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+        assert!(pool_state
+            .require_baseline_icr_for_debt_increase(trove.get_icr(price))
+            .is_ok());
+
+        pool_state.apply_pending_reward(&mut trove).unwrap();
+        assert_eq!(trove.get_icr(price), 1_000_000_000);
+        assert_eq!(
+            pool_state
+                .require_baseline_icr_for_debt_increase(trove.get_icr(price))
+                .unwrap_err(),
+            BorrowerOpsError::BaselineICRLowerThanMCR.into()
+        );
+    }
+
+    #[test]
+    fn applying_pending_rewards_emits_before_adjustment() {
+        let mut pool_state = PoolState {
+            total_stakes: 1_000_000_000,
+            l_coll: 100_000_000,
+            l_usv_debt: 500_000_000,
+            liquidated_coll: 100_000_000,
+            closed_debt: 500_000_000,
+            event_nonce: 7,
+            ..Default::default()
+        };
+        let mut trove = Trove {
+            coll: 1_000_000_000,
+            debt: 600_000_000,
+            stake: 1_000_000_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+
+        // add_coll applies pending rewards first, the ApplyPendingRewards event takes the next nonce
+        pool_state.apply_pending_reward(&mut trove).unwrap();
+        assert_eq!(pool_state.event_nonce, 8);
+        let (new_coll, _) = trove.update_from_adjustment(1_000_000_000, true, 0, false);
+        assert_eq!(new_coll, 2_100_000_000);
+        // so the AdjustTrove event comes after it
+        assert_eq!(pool_state.next_event_nonce(), 9);
+
+        // Nothing pending, no event
+        pool_state.apply_pending_reward(&mut trove).unwrap();
+        assert_eq!(pool_state.event_nonce, 9);
+    }
+
+    #[test]
+    fn redistribution_without_stakes_is_rejected() {
+        let mut pool_state = PoolState {
+            active_coll: 1_000_000_000,
+            active_debt: 2_000_000_000,
+            total_stakes: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            pool_state
+                .redistribute_debt_and_coll(2_000_000_000, 1_000_000_000)
+                .unwrap_err(),
+            BorrowerOpsError::NoTrovesToRedistribute.into()
+        );
+        assert_eq!(pool_state.l_coll, 0);
+        assert_eq!(pool_state.active_debt, 2_000_000_000);
+
+        // Nothing to spread
+        assert!(pool_state.redistribute_debt_and_coll(0, 0).is_ok());
+    }
+
+    fn load_liquidation_pool_state(liquidation_bonus_bps: u64) -> PoolState {
+        PoolState {
+            mcr: 1_100_000_000,
+            coll_gas_comp_percent_divisor: 200,
+            liquidation_bonus_bps,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn liquidation_bonus_adds_to_gas_compensation() {
+        let price = 2_000_000_000;
+        // ICR of 105%
+        let (coll, debt) = (1_050_000_000, 2_000_000_000);
+
+        let without_bonus = load_liquidation_pool_state(0);
+        assert_eq!(
+            without_bonus.get_coll_liquidation_compensation(coll, debt, price),
+            without_bonus.get_coll_gas_compensation(coll)
+        );
+        assert_eq!(
+            without_bonus.get_coll_liquidation_compensation(coll, debt, price),
+            5_250_000
+        );
+
+        // 2% of the coll left after gas compensation
+        let with_bonus = load_liquidation_pool_state(200);
+        assert_eq!(
+            with_bonus.get_coll_liquidation_compensation(coll, debt, price),
+            5_250_000 + 20_895_000
+        );
+    }
+
+    #[test]
+    fn coll_gas_compensation_bounds() {
+        use crate::state::LiquidationTotals;
+
+        let price = 2_000_000_000;
+        let mut pool_state = load_liquidation_pool_state(0);
+        assert!(pool_state
+            .set_coll_gas_comp_bounds(10_000_000, 5_000_000)
+            .is_err());
+        pool_state
+            .set_coll_gas_comp_bounds(1_000_000, 50_000_000)
+            .unwrap();
+
+        // Small trove hits the floor, never more than its coll
+        assert_eq!(pool_state.get_coll_gas_compensation(105_000_000), 1_000_000);
+        assert_eq!(pool_state.get_coll_gas_compensation(500_000), 500_000);
+        // Large trove hits the ceiling
+        assert_eq!(
+            pool_state.get_coll_gas_compensation(105_000_000_000),
+            50_000_000
+        );
+        // Mid-size trove is unaffected
+        assert_eq!(
+            pool_state.get_coll_gas_compensation(1_050_000_000),
+            5_250_000
+        );
+
+        // The clamped value flows into the liquidation totals, capped offsets at ICR of 120%
+        let mut totals = LiquidationTotals::default();
+        for (coll, debt) in [
+            (120_000_000, 200_000_000),
+            (120_000_000_000, 200_000_000_000),
+            (1_200_000_000, 2_000_000_000),
+        ] {
+            let single_liquidation = pool_state
+                .get_capped_offset_vals(debt, coll, price)
+                .unwrap();
+            totals.add_liquidation_values(&single_liquidation);
+        }
+        assert_eq!(
+            totals.total_coll_gas_compensation,
+            1_000_000 + 50_000_000 + 5_500_000
+        );
+    }
+
+    #[test]
+    fn liquidation_bonus_keeps_coll_backing_debt() {
+        let price = 2_000_000_000;
+        let pool_state = load_liquidation_pool_state(1_000);
+
+        // ICR of 102%, the bonus is capped by the coll in excess of the debt
+        let (coll, debt) = (1_020_000_000, 2_000_000_000);
+        let compensation = pool_state.get_coll_liquidation_compensation(coll, debt, price);
+        assert_eq!(compensation, 20_000_000);
+        assert_eq!(
+            compute_cr(coll - compensation, debt, price),
+            Some(1_000_000_000)
+        );
+
+        // No bonus for troves at or under 100%
+        let (coll, debt) = (900_000_000, 2_000_000_000);
+        assert_eq!(
+            pool_state.get_coll_liquidation_compensation(coll, debt, price),
+            pool_state.get_coll_gas_compensation(coll)
+        );
+    }
+
+    #[test]
+    fn capped_offset_keeps_sp_whole_with_bonus() {
+        let price = 2_000_000_000;
+        let pool_state = load_liquidation_pool_state(1_000);
+        let debt = 2_000_000_000;
+
+        let single_liquidation = pool_state
+            .get_capped_offset_vals(debt, 1_300_000_000, price)
+            .unwrap();
+        assert!(
+            compute_cr(single_liquidation.coll_to_send_to_sp, debt, price).unwrap()
+                >= DECIMAL_PRECISION
+        );
+        assert_eq!(
+            single_liquidation.coll_gas_compensation + single_liquidation.coll_to_send_to_sp,
+            1_100_000_000
+        );
+    }
+
+    #[test]
+    fn liquidation_bonus_bps_is_bounded() {
+        let mut pool_state = PoolState::default();
+        assert!(pool_state.set_liquidation_bonus_bps(MAX_BPS + 1).is_err());
+        assert!(pool_state.set_liquidation_bonus_bps(500).is_ok());
+    }
+
+    #[test]
+    fn disabled_sp_deposits_still_absorb_liquidations() {
+        let mut pool_state = PoolState {
+            active_coll: 1_000_000_000,
+            active_debt: 2_000_000_000,
+            ..Default::default()
+        };
+        assert!(pool_state.require_sp_deposits_enabled().is_ok());
+
+        pool_state.sp_deposits_disabled = true;
+        assert_eq!(
+            pool_state.require_sp_deposits_enabled().unwrap_err(),
+            StabilityPoolError::DepositsDisabled.into()
+        );
+
+        // Offsets and withdrawals don't check the flag
+        let mut sp_state = StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            total_usv_deposits: 2_000_000_000,
+            ..Default::default()
+        };
+        let totals = LiquidationTotals {
+            total_debt_to_offset: 500_000_000,
+            total_coll_to_send_to_sp: 300_000_000,
+            ..Default::default()
+        };
+        sp_state
+            .offset(&mut EpochScale::default(), &totals, 0)
+            .unwrap();
+        pool_state.move_coll_debt_from_liquidate(&mut sp_state, &totals);
+        assert!(sp_state.p < DECIMAL_PRECISION.into());
+        assert_eq!(sp_state.total_usv_deposits, 1_500_000_000);
+        assert_eq!(sp_state.total_collateral, 300_000_000);
+
+        sp_state.decrease_usv(1_500_000_000);
+        assert_eq!(sp_state.total_usv_deposits, 0);
+    }
+
+    #[test]
+    fn event_nonce_strictly_increases() {
+        let mut pool_state = PoolState {
+            event_nonce: 41,
+            ..Default::default()
+        };
+
+        // open_trove, then a liquidation of two troves in one instruction
+        let mut nonces = vec![pool_state.next_event_nonce()];
+        for _ in 0..2 {
+            nonces.push(pool_state.next_event_nonce());
+        }
+        nonces.push(pool_state.next_event_nonce());
+
+        assert_eq!(nonces, vec![42, 43, 44, 45]);
+        assert!(nonces.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(pool_state.event_nonce, 45);
+    }
+
+    #[test]
+    fn risk_params_update() {
+        let mut pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 1_800_000_000,
+            gas_compensation: 200_000_000,
+            ..Default::default()
+        };
+
+        pool_state
+            .update_risk_params(1_200_000_000, 1_600_000_000, 1_000_000_000)
+            .unwrap();
+        assert_eq!(pool_state.mcr, 1_200_000_000);
+        assert_eq!(pool_state.ccr, 1_600_000_000);
+        assert_eq!(pool_state.min_net_debt, 1_000_000_000);
+    }
+
+    #[test]
+    fn risk_params_update_rejects_invalid_bounds() {
+        let mut pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ccr: 1_500_000_000,
+            min_net_debt: 1_800_000_000,
+            gas_compensation: 200_000_000,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pool_state
+                .update_risk_params(1_100_000_000, 1_100_000_000, 1_800_000_000)
+                .unwrap_err(),
+            PoolConfigError::InvalidCcr.into()
+        );
+        assert_eq!(
+            pool_state
+                .update_risk_params(1_100_000_000, 1_000_000_000, 1_800_000_000)
+                .unwrap_err(),
+            PoolConfigError::InvalidCcr.into()
+        );
+        assert_eq!(
+            pool_state
+                .update_risk_params(ONE_HUNDERED_PERCENT, 1_500_000_000, 1_800_000_000)
+                .unwrap_err(),
+            PoolConfigError::InvalidMcr.into()
+        );
+        assert_eq!(
+            pool_state
+                .update_risk_params(900_000_000, 1_500_000_000, 1_800_000_000)
+                .unwrap_err(),
+            PoolConfigError::InvalidMcr.into()
+        );
+        assert_eq!(
+            pool_state
+                .update_risk_params(1_100_000_000, 1_500_000_000, 200_000_000)
+                .unwrap_err(),
+            PoolConfigError::InvalidMinNetDebt.into()
+        );
+
+        // Nothing changed
+        assert_eq!(pool_state.mcr, 1_100_000_000);
+        assert_eq!(pool_state.ccr, 1_500_000_000);
+        assert_eq!(pool_state.min_net_debt, 1_800_000_000);
+    }
+
+    #[test]
+    fn borrowing_fee_surcharge_near_recovery_mode() {
+        set_program_test_stubs();
+        // CCR 150%, surcharge of 1% while the TCR is under 160%
+        let mut pool_state = PoolState {
+            ccr: 1_500_000_000,
+            active_coll: 10_000_000_000,
+            active_debt: 1_000_000_000_000,
+            ..Default::default()
+        };
+        pool_state
+            .set_recovery_proximity_surcharge(100, 100_000_000)
+            .unwrap();
+        let usv_amt = 100_000_000_000;
+
+        // TCR 200%, far above the CCR
+        let price = 200_000_000_000;
+        assert_eq!(pool_state.get_recovery_proximity_surcharge(price), 0);
+        assert_eq!(pool_state.get_borrowing_fee(usv_amt, price), 500_000_000);
+
+        // TCR 155%, within the band
+        let price = 155_000_000_000;
+        assert_eq!(
+            pool_state.get_recovery_proximity_surcharge(price),
+            10_000_000
+        );
+        assert_eq!(pool_state.get_borrowing_fee(usv_amt, price), 1_500_000_000);
+
+        // The borrowing rate stays capped with the surcharge
+        pool_state.base_rate = MAX_BORROWING_FEE;
+        assert_eq!(
+            pool_state.get_borrowing_fee(usv_amt, price),
+            calc_borrowing_fee(MAX_BORROWING_FEE, usv_amt)
+        );
+        assert_eq!(
+            pool_state.get_decayed_borrowing_fee(usv_amt, price),
+            calc_borrowing_fee(MAX_BORROWING_FEE, usv_amt)
+        );
+        pool_state.base_rate = 0;
+
+        // TCR 140%, recovery mode waives the borrowing fee
+        let price = 140_000_000_000;
+        assert!(pool_state.check_recovery_mode(price));
+        assert_eq!(pool_state.get_recovery_proximity_surcharge(price), 0);
+
+        assert_eq!(
+            pool_state
+                .set_recovery_proximity_surcharge(MAX_BPS + 1, 100_000_000)
+                .unwrap_err(),
+            PoolConfigError::InvalidBps.into()
+        );
+    }
+
+    #[test]
+    fn gas_compensation_changes_only_without_troves() {
+        let mut pool_state = PoolState {
+            min_net_debt: 1_800_000_000,
+            gas_compensation: 200_000_000,
+            ..Default::default()
+        };
+
+        assert!(pool_state.set_gas_compensation(300_000_000).is_ok());
+        assert_eq!(pool_state.gas_compensation, 300_000_000);
+        assert_eq!(
+            pool_state.set_gas_compensation(1_800_000_000).unwrap_err(),
+            PoolConfigError::InvalidMinNetDebt.into()
+        );
+
+        pool_state.trove_size = 1;
+        assert_eq!(
+            pool_state.set_gas_compensation(200_000_000).unwrap_err(),
+            PoolConfigError::TrovesOutstanding.into()
+        );
+        assert_eq!(pool_state.gas_compensation, 300_000_000);
+    }
+
+    #[test]
+    fn trove_coll_cap() {
+        let mut pool_state = PoolState::default();
+        // Unlimited by default
+        assert!(pool_state.require_trove_coll_within_cap(u64::MAX).is_ok());
+
+        pool_state.max_trove_coll = 10_000_000_000;
+        assert!(pool_state
+            .require_trove_coll_within_cap(10_000_000_000)
+            .is_ok());
+        assert_eq!(
+            pool_state
+                .require_trove_coll_within_cap(10_000_000_001)
+                .unwrap_err(),
+            BorrowerOpsError::TroveCollAboveMax.into()
+        );
+    }
+
+    #[test]
+    fn add_coll_crossing_cap_is_rejected() {
+        let pool_state = PoolState {
+            max_trove_coll: 10_000_000_000,
+            ..Default::default()
+        };
+        let mut trove = Trove {
+            coll: 9_000_000_000,
+            debt: 2_000_000_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+
+        let (new_coll, _) = trove.update_from_adjustment(1_000_000_000, true, 0, false);
+        assert!(pool_state.require_trove_coll_within_cap(new_coll).is_ok());
+
+        let (new_coll, _) = trove.update_from_adjustment(1, true, 0, false);
+        assert_eq!(
+            pool_state
+                .require_trove_coll_within_cap(new_coll)
+                .unwrap_err(),
+            BorrowerOpsError::TroveCollAboveMax.into()
+        );
+    }
+
+    #[test]
+    fn gas_comp_residual_above_reserve() {
+        let pool_state = PoolState {
+            trove_size: 3,
+            gas_compensation: 200_000_000,
+            ..Default::default()
+        };
+        assert_eq!(pool_state.get_gas_comp_reserve(), 600_000_000);
+
+        // Vault inflated by dust, only the excess is swept
+        assert_eq!(pool_state.get_gas_comp_residual(600_000_123), 123);
+        assert_eq!(pool_state.get_gas_comp_residual(600_000_000), 0);
+        // Never below the reserve
+        assert_eq!(pool_state.get_gas_comp_residual(400_000_000), 0);
+    }
+
+    #[test]
+    fn cvgt_staking_state_repointed_to_matching_pool() {
+        let mut pool_state = PoolState {
+            stablecoin: Pubkey::new_unique(),
+            collateral: Pubkey::new_unique(),
+            cvgt: Pubkey::new_unique(),
+            cvgt_staking_state: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        let treasury = pool_state.cvgt_staking_state;
+        let mut staking_state = CVGTStakingPoolState {
+            usv: pool_state.stablecoin,
+            collateral: Pubkey::new_unique(),
+            cvgt: pool_state.cvgt,
+            f_usv: 0,
+            f_coll: 0,
+            total_cvgt_staked: 2_000_000_000,
+            bump: [0],
+            min_stake_duration: 0,
+        };
+        let staking_key = Pubkey::new_unique();
+
+        assert_eq!(
+            pool_state
+                .set_cvgt_staking_state(staking_key, &staking_state)
+                .unwrap_err(),
+            PoolConfigError::StakingPoolMismatch.into()
+        );
+        assert_eq!(pool_state.cvgt_staking_state, treasury);
+
+        staking_state.collateral = pool_state.collateral;
+        pool_state
+            .set_cvgt_staking_state(staking_key, &staking_state)
+            .unwrap();
+        assert_eq!(pool_state.cvgt_staking_state, staking_key);
+
+        // Borrow fees minted to the pool's vault are now credited to stakers
+        staking_state.increase_f_usv(5_000_000);
+        assert_eq!(staking_state.f_usv, 2_500_000);
+    }
+
+    #[test]
+    fn faster_decay_factor_leaves_lower_base_rate() {
+        let mut pool_state = PoolState {
+            base_rate: 50_000_000,
+            ..Default::default()
+        };
+        assert_eq!(
+            pool_state.set_minute_decay_factor(989_999_999).unwrap_err(),
+            PoolConfigError::InvalidMinuteDecayFactor.into()
+        );
+        assert_eq!(
+            pool_state.set_minute_decay_factor(999_900_001).unwrap_err(),
+            PoolConfigError::InvalidMinuteDecayFactor.into()
+        );
+
+        pool_state.set_minute_decay_factor(999_900_000).unwrap();
+        let slow = pool_state.calc_base_rate_decayed_over(60);
+        pool_state.set_minute_decay_factor(990_000_000).unwrap();
+        let fast = pool_state.calc_base_rate_decayed_over(60);
+
+        assert!(fast < slow);
+        assert!(slow < pool_state.base_rate);
+        // 0.99^60 is about 0.547, 0.9999^60 about 0.994
+        assert_eq!(fast / 1_000_000, 27);
+        assert_eq!(slow / 1_000_000, 49);
+    }
+
+    #[test]
+    fn redemptions_within_a_minute_accumulate_base_rate() {
+        let t0 = 1_000_020;
+        let mut pool_state = PoolState {
+            last_fee_operation_time: t0,
+            ..Default::default()
+        };
+        // Each redemption is 1% of the supply and adds half of it to the base rate
+        let redeem = |pool_state: &mut PoolState, timestamp: u64| {
+            pool_state
+                .update_base_fee_rate_from_redemption(
+                    10_000_000_000,
+                    DECIMAL_PRECISION,
+                    1_000_000_000_000,
+                    timestamp,
+                )
+                .unwrap()
+        };
+
+        assert_eq!(redeem(&mut pool_state, t0 + 10), 5_000_000);
+        assert_eq!(redeem(&mut pool_state, t0 + 50), 10_000_000);
+        // The decay baseline is not reset within the minute
+        assert_eq!(pool_state.last_fee_operation_time, t0);
+
+        // Decayed over a single minute, the 30 seconds left still count towards the next one
+        let base_rate = pool_state.calc_base_rate_decayed_over(1) + 5_000_000;
+        assert_eq!(redeem(&mut pool_state, t0 + 90), base_rate);
+        assert_eq!(pool_state.last_fee_operation_time, t0 + 60);
+
+        let base_rate = pool_state.calc_base_rate_decayed_over(1) + 5_000_000;
+        assert_eq!(redeem(&mut pool_state, t0 + 125), base_rate);
+        assert_eq!(pool_state.last_fee_operation_time, t0 + 120);
+    }
+
+    #[test]
+    fn redemption_capped_to_max_fraction() {
+        let mut pool_state = PoolState::default();
+        let total_usv_supply = 1_000_000_000_000;
+        assert_eq!(
+            pool_state
+                .set_max_redemption_fraction_bps(MAX_BPS + 1)
+                .unwrap_err(),
+            PoolConfigError::InvalidBps.into()
+        );
+        pool_state.set_max_redemption_fraction_bps(500).unwrap();
+
+        // 4.99% and 5% are under the cap, 5.01% is over
+        assert!(pool_state
+            .require_redemption_within_max_fraction(49_900_000_000, total_usv_supply)
+            .is_ok());
+        assert!(pool_state
+            .require_redemption_within_max_fraction(50_000_000_000, total_usv_supply)
+            .is_ok());
+        assert_eq!(
+            pool_state
+                .require_redemption_within_max_fraction(50_100_000_000, total_usv_supply)
+                .unwrap_err(),
+            BorrowerOpsError::RedemptionAboveMaxFraction.into()
+        );
+
+        // Whole supply with the default cap
+        pool_state.set_max_redemption_fraction_bps(MAX_BPS).unwrap();
+        assert!(pool_state
+            .require_redemption_within_max_fraction(total_usv_supply, total_usv_supply)
+            .is_ok());
+
+        // Just over the cap, where a rounded down fraction would still read as 5%
+        pool_state.set_max_redemption_fraction_bps(500).unwrap();
+        assert_eq!(
+            pool_state
+                .require_redemption_within_max_fraction(50_000_000_001, total_usv_supply)
+                .unwrap_err(),
+            BorrowerOpsError::RedemptionAboveMaxFraction.into()
+        );
+
+        // No supply to take a share of
+        assert_eq!(
+            pool_state
+                .require_redemption_within_max_fraction(1, 0)
+                .unwrap_err(),
+            BorrowerOpsError::RedemptionAboveMaxFraction.into()
+        );
+    }
+
+    #[test]
+    fn self_liquidation_rejected_unless_allowed() {
+        let mut pool_state = PoolState::default();
+        let borrower = Pubkey::new_unique();
+
+        assert_eq!(
+            pool_state
+                .require_not_self_liquidation(&borrower, &borrower)
+                .unwrap_err(),
+            BorrowerOpsError::SelfLiquidation.into()
+        );
+        assert!(pool_state
+            .require_not_self_liquidation(&Pubkey::new_unique(), &borrower)
+            .is_ok());
+
+        pool_state.self_liquidation_allowed = true;
+        assert!(pool_state
+            .require_not_self_liquidation(&borrower, &borrower)
+            .is_ok());
+    }
+
+    #[test]
+    fn system_debt_cap() {
+        let mut pool_state = PoolState {
+            active_debt: 9_000_000_000,
+            closed_debt: 500_000_000,
+            max_system_debt: 10_000_000_000,
+            ..Default::default()
+        };
+
+        // Borrow crossing the cap
+        assert!(pool_state
+            .require_system_debt_within_cap(500_000_000)
+            .is_ok());
+        assert_eq!(
+            pool_state
+                .require_system_debt_within_cap(500_000_001)
+                .unwrap_err(),
+            BorrowerOpsError::SystemDebtAboveMax.into()
+        );
+
+        // Repayment near the cap isn't checked
+        pool_state.decrease_active_debt(1_000_000_000);
+        assert_eq!(pool_state.get_entire_debt(), 8_500_000_000);
+        assert!(pool_state
+            .require_system_debt_within_cap(1_500_000_000)
+            .is_ok());
+
+        // Raising the cap unblocks the borrow
+        assert!(pool_state
+            .require_system_debt_within_cap(2_000_000_000)
+            .is_err());
+        pool_state.max_system_debt = 11_000_000_000;
+        assert!(pool_state
+            .require_system_debt_within_cap(2_000_000_000)
+            .is_ok());
+    }
+
+    #[test]
+    fn invariants_match_vault_balances() {
+        use crate::constants::ALL_INVARIANTS;
+
+        let pool_state = PoolState {
+            gas_compensation: 200_000_000,
+            trove_size: 2,
+            active_coll: 10_000_000_000,
+            liquidated_coll: 500_000_000,
+            total_surplus: 100_000_000,
+            active_debt: 6_000_000_000,
+            closed_debt: 1_000_000_000,
+            ..Default::default()
+        };
+        let sp_state = StabilityPoolState {
+            total_usv_deposits: 3_000_000_000,
+            total_collateral: 250_000_000,
+            ..Default::default()
+        };
+
+        let mut balances = VaultBalances {
+            collateral_vault: 10_600_000_000,
+            stablecoin_supply: 7_000_000_000,
+            gas_compensation_vault: 400_000_000,
+            sp_usv_vault: 3_000_000_000,
+            sp_coll_vault: 250_000_000,
+        };
+        assert_eq!(
+            pool_state.get_invariants(&sp_state, &balances),
+            ALL_INVARIANTS
+        );
+
+        // A gas compensation residual is backed by supply but by no debt
+        balances.gas_compensation_vault = 450_000_000;
+        balances.stablecoin_supply = 7_050_000_000;
+        assert_eq!(
+            pool_state.get_invariants(&sp_state, &balances),
+            ALL_INVARIANTS
+        );
+
+        // Desynced vaults clear only their own bit
+        balances.collateral_vault -= 1;
+        assert_eq!(
+            pool_state.get_invariants(&sp_state, &balances),
+            ALL_INVARIANTS & !INVARIANT_COLL_VAULT
+        );
+        balances.sp_usv_vault += 1;
+        balances.stablecoin_supply += 1;
+        assert_eq!(
+            pool_state.get_invariants(&sp_state, &balances),
+            INVARIANT_SP_COLL_VAULT
+        );
+    }
+}
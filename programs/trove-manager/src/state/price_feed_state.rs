@@ -1,14 +1,19 @@
 use anchor_lang::prelude::*;
 use chainlink_solana as chainlink;
-use chainlink_solana::Round;
 use pyth_solana_receiver_sdk::price_update::{Price, PriceUpdateV2};
 
 use crate::{
-    constants::{FEED_DECIMAL_PRECISION, TARGET_DECIMAL_PRECISION},
+    constants::{
+        FEED_DECIMAL_PRECISION, MAX_CONFIDENCE_RATE, MAX_JITOSOL_EPOCH_LAG,
+        MAX_JITOSOL_RATE_STALE_EPOCHS, STATUS_HISTORY_LEN, TARGET_DECIMAL_PRECISION,
+    },
+    errors::PriceFeedError,
+    events::{DevPriceChanged, PriceUsed},
     utils::{
-        both_oracles_live_unbroken_similar_price, both_oracles_similar_price, get_jitosol_rate,
-        is_chainlink_broken, is_chainlink_frozen, is_pyth_broken, is_pyth_frozen,
-        pyth_price_conf_interval_above_max,
+        both_oracles_live_unbroken_similar_price, both_oracles_similar_price,
+        get_current_timestamp_i64, get_jitosol_rate, is_pyth_broken, is_pyth_frozen,
+        pyth_price_conf_interval_above_max, require_non_zero_price, SecondaryOracleResponse,
+        SwitchboardResponse,
     },
 };
 
@@ -16,6 +21,7 @@ use crate::{
 #[derive(InitSpace)]
 pub struct PriceFeedState {
     pub creator: Pubkey,
+    // Feed of the secondary source, a Switchboard aggregator when `secondary_source` is Switchboard
     pub chainlink_feed: Pubkey,
     pub jitosol_stake_pool: Pubkey,
     pub pyth_feed_account: Pubkey,
@@ -24,10 +30,48 @@ pub struct PriceFeedState {
     pub bump: u8,
     pub _is_dev: bool,
     pub _dev_price: u64,
+
+    // Last JitoSol rate read from an up to date stake pool
+    pub jitosol_rate: u64,
+    pub jitosol_rate_epoch: u64,
+
+    pub secondary_source: SecondarySource,
+
+    // Publish time of the oracle answer behind `last_good_price`
+    pub last_good_price_time: i64,
+
+    // Ring buffer of the last `STATUS_HISTORY_LEN` status transitions, `status_transitions`
+    // counts all transitions recorded
+    pub status_history: [StatusTransition; STATUS_HISTORY_LEN],
+    pub status_transitions: u64,
+
+    // Epochs the stake pool may lag behind while its rate is still used, 0 requires an update
+    // this epoch
+    pub jitosol_max_epoch_lag: u64,
+
+    // Pyth confidence interval over price above which Pyth is not trusted alone, in
+    // FEED_DECIMAL_PRECISION. 0 on migrated accounts reads as MAX_CONFIDENCE_RATE
+    pub max_confidence_rate: u64,
+
+    // Feed id `pyth_feed_account` must carry, zeros on migrated accounts until the next fetch
+    pub pyth_feed_id: [u8; 32],
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, InitSpace, Debug)]
+/// Oracle used as the fallback of Pyth, the Chainlink statuses below refer to it
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, InitSpace, Debug, Default,
+)]
+pub enum SecondarySource {
+    #[default]
+    Chainlink,
+    Switchboard,
+}
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, InitSpace, Debug, Default,
+)]
 pub enum Status {
+    #[default]
     PythWorking,
     UsingChainlinkPythUntrusted,
     BothOraclesUntrusted,
@@ -35,46 +79,210 @@ pub enum Status {
     UsingPythChainlinkUntrusted,
 }
 
+/// Status the feed switched to and when
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, InitSpace, Debug, Default,
+)]
+pub struct StatusTransition {
+    pub status: Status,
+    pub timestamp: i64,
+}
+
 impl PriceFeedState {
+    /// Fails rather than returning a zero `last_good_price` when no oracle was ever trusted
     pub fn fetch_price<'info>(
         &mut self,
         chainlink_program: &AccountInfo<'info>,
         chainlink_feed: &AccountInfo<'info>,
         jitosol_stake_pool: &AccountInfo<'info>,
         pyth_feed_account: &PriceUpdateV2,
+    ) -> Result<u64> {
+        let price = self.read_oracles(
+            chainlink_program,
+            chainlink_feed,
+            jitosol_stake_pool,
+            pyth_feed_account,
+        )?;
+        require_non_zero_price(price)?;
+        Ok(price)
+    }
+
+    fn read_oracles<'info>(
+        &mut self,
+        chainlink_program: &AccountInfo<'info>,
+        chainlink_feed: &AccountInfo<'info>,
+        jitosol_stake_pool: &AccountInfo<'info>,
+        pyth_feed_account: &PriceUpdateV2,
     ) -> Result<u64> {
         if self._is_dev {
             return Ok(self._dev_price);
         }
 
-        let chainlink_response = chainlink::latest_round_data(
-            chainlink_program.to_account_info(),
-            chainlink_feed.to_account_info(),
-        )?;
-
         let jitosol_staking_data = &mut &jitosol_stake_pool.try_borrow_data()?[..][..];
-        let rate = get_jitosol_rate(jitosol_staking_data)?;
-
-        let jitosol_price_chainlink =
-            u64::try_from(chainlink_response.answer).unwrap() * FEED_DECIMAL_PRECISION / rate;
+        let (pool_rate, pool_epoch) = get_jitosol_rate(jitosol_staking_data)?;
+        let rate = self.resolve_jitosol_rate(pool_rate, pool_epoch, Clock::get()?.epoch)?;
 
         // Get price from pyth
+        self.check_pyth_feed_id(&pyth_feed_account.price_message.feed_id)?;
         let pyth_price_message = pyth_feed_account
-            .get_price_unchecked(&pyth_feed_account.price_message.feed_id)
+            .get_price_unchecked(&self.pyth_feed_id)
             .unwrap();
 
+        match self.secondary_source {
+            SecondarySource::Chainlink => {
+                let chainlink_response = chainlink::latest_round_data(
+                    chainlink_program.to_account_info(),
+                    chainlink_feed.to_account_info(),
+                )?;
+                self.update_with_jitosol_rate(&pyth_price_message, &chainlink_response, rate)
+            }
+            SecondarySource::Switchboard => {
+                let switchboard_response = SwitchboardResponse::from_aggregator(chainlink_feed)?;
+                self.update_with_jitosol_rate(&pyth_price_message, &switchboard_response, rate)
+            }
+        }
+    }
+
+    /// Accounts migrated from a layout without `pyth_feed_id` pin the id of their first fetch
+    pub fn check_pyth_feed_id(&mut self, feed_id: &[u8; 32]) -> Result<()> {
+        if self.pyth_feed_id == [0u8; 32] {
+            self.pyth_feed_id = *feed_id;
+        }
+        require!(
+            *feed_id == self.pyth_feed_id,
+            PriceFeedError::PythWrongFeedId
+        );
+        Ok(())
+    }
+
+    fn update_with_jitosol_rate<R: SecondaryOracleResponse>(
+        &mut self,
+        pyth_price_message: &Price,
+        secondary_response: &R,
+        rate: u64,
+    ) -> Result<u64> {
+        // The answer of a broken response may be negative, it is not used then
+        let jitosol_price_secondary = if secondary_response.is_broken() {
+            0
+        } else {
+            secondary_response.answer() * FEED_DECIMAL_PRECISION / rate
+        };
         self.update(
-            &pyth_price_message,
-            &chainlink_response,
-            jitosol_price_chainlink,
+            pyth_price_message,
+            secondary_response,
+            jitosol_price_secondary,
         )
     }
 
+    /// Uses the stake pool rate when it has been updated within `jitosol_max_epoch_lag` epochs and caches it.
+    /// Otherwise falls back on the cached rate while it is at most `MAX_JITOSOL_RATE_STALE_EPOCHS` old.
+    pub fn resolve_jitosol_rate(
+        &mut self,
+        pool_rate: u64,
+        pool_epoch: u64,
+        current_epoch: u64,
+    ) -> Result<u64> {
+        if current_epoch.saturating_sub(pool_epoch) <= self.jitosol_max_epoch_lag {
+            self.jitosol_rate = pool_rate;
+            self.jitosol_rate_epoch = pool_epoch;
+            return Ok(pool_rate);
+        }
+        require!(
+            self.jitosol_rate > 0
+                && current_epoch.saturating_sub(self.jitosol_rate_epoch)
+                    <= MAX_JITOSOL_RATE_STALE_EPOCHS,
+            PriceFeedError::PoolNotUpdated
+        );
+        Ok(self.jitosol_rate)
+    }
+
+    pub fn set_jitosol_max_epoch_lag(&mut self, jitosol_max_epoch_lag: u64) -> Result<()> {
+        require!(
+            jitosol_max_epoch_lag <= MAX_JITOSOL_EPOCH_LAG,
+            PriceFeedError::InvalidEpochLag
+        );
+        self.jitosol_max_epoch_lag = jitosol_max_epoch_lag;
+        Ok(())
+    }
+
+    pub fn get_max_confidence_rate(&self) -> u64 {
+        if self.max_confidence_rate == 0 {
+            MAX_CONFIDENCE_RATE
+        } else {
+            self.max_confidence_rate
+        }
+    }
+
+    pub fn set_max_confidence_rate(&mut self, max_confidence_rate: u64) -> Result<()> {
+        require!(
+            max_confidence_rate > 0 && max_confidence_rate <= FEED_DECIMAL_PRECISION,
+            PriceFeedError::InvalidConfidenceRate
+        );
+        self.max_confidence_rate = max_confidence_rate;
+        Ok(())
+    }
+
+    pub fn set_dev_price(&mut self, new_price: u64) -> Result<DevPriceChanged> {
+        require!(self._is_dev, PriceFeedError::OnlyDevMode);
+        require!(new_price > 0, PriceFeedError::InvalidDevPrice);
+        self._dev_price = new_price;
+        Ok(DevPriceChanged { new_price })
+    }
+
+    /// Event for the price an operation's CR checks are based on
+    pub fn price_used(&self, price: u64) -> PriceUsed {
+        PriceUsed {
+            price,
+            source: self.status,
+        }
+    }
+
+    /// Fails when `last_good_price` was published more than `max_age` seconds ago, 0 disables the check
+    pub fn require_price_age(&self, max_age: u64, current_timestamp: i64) -> Result<()> {
+        if self._is_dev || max_age == 0 {
+            return Ok(());
+        }
+        let age = current_timestamp.saturating_sub(self.last_good_price_time);
+        require!(
+            u64::try_from(age).unwrap_or(0) <= max_age,
+            PriceFeedError::PriceTooStale
+        );
+        Ok(())
+    }
+
+    /// `last_good_price` may be badly stale once neither oracle is trusted, new debt is not
+    /// priced off it. Liquidations and redemptions keep using it.
+    pub fn require_trusted_price_for_borrow(&self) -> Result<()> {
+        require!(
+            self._is_dev || self.status != Status::BothOraclesUntrusted,
+            PriceFeedError::BothOraclesUntrusted
+        );
+        Ok(())
+    }
+
     pub fn set_status(&mut self, status: Status) {
+        if status != self.status {
+            self.record_status_transition(status, get_current_timestamp_i64().unwrap());
+        }
         self.status = status;
     }
 
-    pub fn update_price(&mut self, new_price: u64) -> u64 {
+    fn record_status_transition(&mut self, status: Status, timestamp: i64) {
+        let index = self.status_transitions % STATUS_HISTORY_LEN as u64;
+        self.status_history[index as usize] = StatusTransition { status, timestamp };
+        self.status_transitions = self.status_transitions.checked_add(1).unwrap();
+    }
+
+    /// Recorded status transitions, oldest first
+    pub fn get_status_history(&self) -> Vec<StatusTransition> {
+        let len = STATUS_HISTORY_LEN as u64;
+        (self.status_transitions.saturating_sub(len)..self.status_transitions)
+            .map(|i| self.status_history[(i % len) as usize])
+            .collect()
+    }
+
+    pub fn update_price(&mut self, new_price: u64, publish_time: i64) -> u64 {
+        self.last_good_price_time = publish_time;
         self.last_good_price = u64::try_from(
             (new_price as u128)
                 .checked_mul(TARGET_DECIMAL_PRECISION as u128)
@@ -86,10 +294,10 @@ impl PriceFeedState {
         self.last_good_price
     }
 
-    pub fn update(
+    pub fn update<R: SecondaryOracleResponse>(
         &mut self,
         pyth_price_message: &Price,
-        chainlink_response: &Round,
+        chainlink_response: &R,
         price_chainlink: u64,
     ) -> Result<u64> {
         let price_pyth = u64::try_from(if pyth_price_message.price > 0 {
@@ -104,25 +312,25 @@ impl PriceFeedState {
                 // If Pyth is broken, try Chainlink
                 if is_pyth_broken(pyth_price_message) {
                     // If Chainlink is broken then both oracles are untrusted, so return the last good price
-                    if is_chainlink_broken(&chainlink_response) {
+                    if chainlink_response.is_broken() {
                         self.set_status(Status::BothOraclesUntrusted);
                         return Ok(self.last_good_price);
                     }
                     // If Chainlink is only frozen but otherwise returning valid data, return the last good price.
-                    if is_chainlink_frozen(&chainlink_response) {
+                    if chainlink_response.is_frozen() {
                         self.set_status(Status::UsingChainlinkPythUntrusted);
                         return Ok(self.last_good_price);
                     }
                     // If Pyth is broken and Chainlink is working, switch to Chainlink and return current Chainlink price
                     self.set_status(Status::UsingChainlinkPythUntrusted);
 
-                    return Ok(self.update_price(price_chainlink));
+                    return Ok(self.update_price(price_chainlink, chainlink_response.timestamp()));
                 }
 
                 // If Pyth us frozen, try Chainlink
                 if is_pyth_frozen(pyth_price_message) {
                     // If Chainlink is broken too, remember Chainlink broke, and return last good price
-                    if is_chainlink_broken(&chainlink_response) {
+                    if chainlink_response.is_broken() {
                         self.set_status(Status::UsingPythChainlinkUntrusted);
                         return Ok(self.last_good_price);
                     }
@@ -130,25 +338,28 @@ impl PriceFeedState {
                     // If Chainlink is frozen or working, remember Pyth froze, and switch to Chainlink
                     self.set_status(Status::UsingChainlinkPythFrozen);
 
-                    if is_chainlink_frozen(&chainlink_response) {
+                    if chainlink_response.is_frozen() {
                         return Ok(self.last_good_price);
                     }
 
                     // If Chainlink is working, use it
-                    return Ok(self.update_price(price_chainlink));
+                    return Ok(self.update_price(price_chainlink, chainlink_response.timestamp()));
                 }
 
                 // If Pyth price has changed by > 50% between two consecutive rounds, compare it to Chainlink's price
-                if pyth_price_conf_interval_above_max(pyth_price_message) {
+                if pyth_price_conf_interval_above_max(
+                    pyth_price_message,
+                    self.get_max_confidence_rate(),
+                ) {
                     // If Chainlink is broken, both oracles are untrusted, and return last good price
-                    if is_chainlink_broken(&chainlink_response) {
+                    if chainlink_response.is_broken() {
                         self.set_status(Status::BothOraclesUntrusted);
                         return Ok(self.last_good_price);
                     }
 
                     // If Chainlink is frozen, switch to Chainlink and return last good price
 
-                    if is_chainlink_frozen(&chainlink_response) {
+                    if chainlink_response.is_frozen() {
                         self.set_status(Status::UsingChainlinkPythUntrusted);
                         return Ok(self.last_good_price);
                     }
@@ -158,47 +369,47 @@ impl PriceFeedState {
                      * two consecutive rounds was likely a legitmate market price movement, and so continue using Pyth
                      */
                     if both_oracles_similar_price(price_pyth, price_chainlink) {
-                        return Ok(self.update_price(price_pyth));
+                        return Ok(self.update_price(price_pyth, pyth_price_message.publish_time));
                     }
 
                     // If Chainlink is live but the oracles differ too much in price, conclude that Pyth's initial price deviation was
                     // an oracle failure. Switch to Chainlink, and use Chainlink price
                     self.set_status(Status::UsingChainlinkPythUntrusted);
-                    return Ok(self.update_price(price_chainlink));
+                    return Ok(self.update_price(price_chainlink, chainlink_response.timestamp()));
                 }
 
                 // If Pyth is working and Chainlink is broken, remember Chainlink is broken
-                if is_chainlink_broken(&chainlink_response) {
+                if chainlink_response.is_broken() {
                     self.set_status(Status::UsingPythChainlinkUntrusted);
                 }
 
                 // If Pyth is working, return Pyth current price (no status change)
-                Ok(self.update_price(price_pyth))
+                Ok(self.update_price(price_pyth, pyth_price_message.publish_time))
             }
             // --- CASE 2: The system fetched last price from Chainlink ---
             Status::UsingChainlinkPythUntrusted => {
                 // If both Chainlink and Pyth are live, unbroken, and reporting similar prices, switch back to Pyth
                 if both_oracles_live_unbroken_similar_price(
                     pyth_price_message,
-                    &chainlink_response,
+                    chainlink_response,
                     price_chainlink,
                 ) {
                     self.set_status(Status::PythWorking);
-                    return Ok(self.update_price(price_pyth));
+                    return Ok(self.update_price(price_pyth, pyth_price_message.publish_time));
                 }
 
-                if is_chainlink_broken(&chainlink_response) {
+                if chainlink_response.is_broken() {
                     self.set_status(Status::BothOraclesUntrusted);
                     return Ok(self.last_good_price);
                 }
 
                 // If Chainlink is only frozen but otherwise returning valid data, just return the last good price.
-                if is_chainlink_frozen(&chainlink_response) {
+                if chainlink_response.is_frozen() {
                     return Ok(self.last_good_price);
                 }
 
                 // Otherwise, use Chainlink price
-                return Ok(self.update_price(price_chainlink));
+                Ok(self.update_price(price_chainlink, chainlink_response.timestamp()))
             }
             // --- CASE 3: Both oracles were untrusted at the last price fetch ---
             Status::BothOraclesUntrusted => {
@@ -208,11 +419,11 @@ impl PriceFeedState {
                  */
                 if both_oracles_live_unbroken_similar_price(
                     pyth_price_message,
-                    &chainlink_response,
+                    chainlink_response,
                     price_chainlink,
                 ) {
                     self.set_status(Status::PythWorking);
-                    return Ok(self.update_price(price_pyth));
+                    return Ok(self.update_price(price_pyth, pyth_price_message.publish_time));
                 }
 
                 // Otherwise, return the last good price - both oracles are still untrusted (no status change)
@@ -222,7 +433,7 @@ impl PriceFeedState {
             Status::UsingChainlinkPythFrozen => {
                 if is_pyth_broken(pyth_price_message) {
                     // If both Oracles are broken, return last good price
-                    if is_chainlink_broken(&chainlink_response) {
+                    if chainlink_response.is_broken() {
                         self.set_status(Status::BothOraclesUntrusted);
                         return Ok(self.last_good_price);
                     }
@@ -230,38 +441,38 @@ impl PriceFeedState {
                     // If Pyth is broken, remember it and switch to using Chainlink
                     self.set_status(Status::UsingChainlinkPythUntrusted);
 
-                    if is_chainlink_frozen(&chainlink_response) {
+                    if chainlink_response.is_frozen() {
                         return Ok(self.last_good_price);
                     }
 
                     // If Chainlink is working, return Chainlink current price
-                    return Ok(self.update_price(price_chainlink));
+                    return Ok(self.update_price(price_chainlink, chainlink_response.timestamp()));
                 }
 
                 if is_pyth_frozen(pyth_price_message) {
                     // if Pyth is frozen and Chainlink is broken, remember Chainlink broke, and return last good price
-                    if is_chainlink_broken(&chainlink_response) {
+                    if chainlink_response.is_broken() {
                         self.set_status(Status::UsingPythChainlinkUntrusted);
                         return Ok(self.last_good_price);
                     }
 
                     // If both are frozen, just use lastGoodPrice
-                    if is_chainlink_frozen(&chainlink_response) {
+                    if chainlink_response.is_frozen() {
                         return Ok(self.last_good_price);
                     }
 
                     // if Pyth is frozen and Chainlink is working, keep using Chainlink (no status change)
-                    return Ok(self.update_price(price_chainlink));
+                    return Ok(self.update_price(price_chainlink, chainlink_response.timestamp()));
                 }
 
                 // if Pyth is live and Chainlink is broken, remember Chainlink broke, and return Pyth price
-                if is_chainlink_broken(&chainlink_response) {
+                if chainlink_response.is_broken() {
                     self.set_status(Status::UsingPythChainlinkUntrusted);
-                    return Ok(self.update_price(price_pyth));
+                    return Ok(self.update_price(price_pyth, pyth_price_message.publish_time));
                 }
 
                 // If Pyth is live and Chainlink is frozen, just use last good price (no status change) since we have no basis for comparison
-                if is_chainlink_frozen(&chainlink_response) {
+                if chainlink_response.is_frozen() {
                     return Ok(self.last_good_price);
                 }
 
@@ -269,12 +480,12 @@ impl PriceFeedState {
                 // if prices are within 5%, and return Pyth price.
                 if both_oracles_similar_price(price_pyth, price_chainlink) {
                     self.set_status(Status::PythWorking);
-                    return Ok(self.update_price(price_pyth));
+                    return Ok(self.update_price(price_pyth, pyth_price_message.publish_time));
                 }
 
                 // Otherwise if Pyth is live but price not within 5% of Chainlink, distrust Pyth, and return Chainlink price
                 self.set_status(Status::UsingChainlinkPythUntrusted);
-                return Ok(self.update_price(price_chainlink));
+                Ok(self.update_price(price_chainlink, chainlink_response.timestamp()))
             }
             // --- CASE 5: Using Pyth, Chainlink is untrusted ---
             Status::UsingPythChainlinkUntrusted => {
@@ -292,23 +503,26 @@ impl PriceFeedState {
                 // If Pyth and Chainlink are both live, unbroken and similar price, switch back to PythWorking and return Pyth price
                 if both_oracles_live_unbroken_similar_price(
                     pyth_price_message,
-                    &chainlink_response,
+                    chainlink_response,
                     price_chainlink,
                 ) {
                     self.set_status(Status::PythWorking);
-                    return Ok(self.update_price(price_pyth));
+                    return Ok(self.update_price(price_pyth, pyth_price_message.publish_time));
                 }
 
                 // If Pyth is live but deviated >50% from it's previous price and Chainlink is still untrusted, switch
                 // to BothOraclesUntrusted and return last good price
-                if pyth_price_conf_interval_above_max(pyth_price_message) {
+                if pyth_price_conf_interval_above_max(
+                    pyth_price_message,
+                    self.get_max_confidence_rate(),
+                ) {
                     self.set_status(Status::BothOraclesUntrusted);
                     return Ok(self.last_good_price);
                 }
 
                 // Otherwise if Pyth is live and deviated <50% from it's previous price and Chainlink is still untrusted,
                 // return Pyth price (no status change)
-                Ok(self.update_price(price_pyth))
+                Ok(self.update_price(price_pyth, pyth_price_message.publish_time))
             }
         }
     }
@@ -316,9 +530,12 @@ impl PriceFeedState {
 
 #[cfg(test)]
 pub mod price_feed_info_test {
+    use chainlink_solana::Round;
+
     use super::*;
+    use crate::{constants::TIMEOUT, math::compute_cr};
 
-    fn load_price_feed_info(last_good_price: u64, status: Status) -> PriceFeedState {
+    pub fn load_price_feed_info(last_good_price: u64, status: Status) -> PriceFeedState {
         PriceFeedState {
             creator: [1u8; 32].into(),
             chainlink_feed: [1u8; 32].into(),
@@ -329,6 +546,15 @@ pub mod price_feed_info_test {
             status,
             bump: 1,
             _dev_price: 0,
+            jitosol_rate: 0,
+            jitosol_rate_epoch: 0,
+            secondary_source: SecondarySource::Chainlink,
+            last_good_price_time: 0,
+            status_history: Default::default(),
+            status_transitions: 0,
+            jitosol_max_epoch_lag: 0,
+            max_confidence_rate: MAX_CONFIDENCE_RATE,
+            pyth_feed_id: [1u8; 32],
         }
     }
 
@@ -350,10 +576,172 @@ pub mod price_feed_info_test {
         }
     }
 
+    fn load_switchboard_response(
+        timestamp: i64,
+        mantissa: i128,
+        scale: u32,
+    ) -> SwitchboardResponse {
+        SwitchboardResponse::new(mantissa, scale, 1, timestamp)
+    }
+
     fn dec(value: u64, decimals: u32) -> u64 {
         value * 10u64.pow(decimals)
     }
 
+    #[test]
+    fn dev_price_change() {
+        let mut price_feed_state = load_price_feed_info(0, Status::PythWorking);
+
+        // Only on dev feeds
+        assert_eq!(
+            price_feed_state.set_dev_price(dec(150, 9)).err().unwrap(),
+            PriceFeedError::OnlyDevMode.into()
+        );
+
+        price_feed_state._is_dev = true;
+        assert_eq!(
+            price_feed_state.set_dev_price(0).err().unwrap(),
+            PriceFeedError::InvalidDevPrice.into()
+        );
+        assert_eq!(price_feed_state._dev_price, 0);
+
+        let event = price_feed_state.set_dev_price(dec(150, 9)).unwrap();
+        assert_eq!(event.new_price, dec(150, 9));
+        assert_eq!(price_feed_state._dev_price, dec(150, 9));
+    }
+
+    #[test]
+    fn status_history_keeps_last_transitions() {
+        let mut price_feed_info = load_price_feed_info(0, Status::PythWorking);
+        assert!(price_feed_info.get_status_history().is_empty());
+
+        // Pyth froze, then flaps between Pyth and Chainlink
+        let mut expected = vec![Status::UsingChainlinkPythFrozen];
+        for _ in 0..5 {
+            expected.push(Status::PythWorking);
+            expected.push(Status::UsingChainlinkPythUntrusted);
+        }
+        for &status in &expected {
+            price_feed_info.set_status(status);
+            // Setting the same status again is not a transition
+            price_feed_info.set_status(status);
+        }
+
+        assert_eq!(price_feed_info.status_transitions, 11);
+        let history = price_feed_info.get_status_history();
+        assert_eq!(history.len(), STATUS_HISTORY_LEN);
+        for (transition, status) in history.iter().zip(&expected[11 - STATUS_HISTORY_LEN..]) {
+            assert_eq!(
+                *transition,
+                StatusTransition {
+                    status: *status,
+                    timestamp: 1_000_000
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn untrusted_oracles_without_good_price_revert() {
+        // Fresh feed that never got a trusted price
+        let mut price_feed_info = load_price_feed_info(0, Status::BothOraclesUntrusted);
+        let chainlink_response = &load_chainlink_response(1_000_000, dec(10, 8).into());
+        // Broken Pyth answer
+        let pyth_price_message = &load_price_message(0, 10, 1_000_000);
+
+        let price = price_feed_info
+            .update(pyth_price_message, chainlink_response, dec(10, 8))
+            .unwrap();
+        assert_eq!(price, 0);
+        assert_eq!(price_feed_info.status, Status::BothOraclesUntrusted);
+        assert_eq!(
+            require_non_zero_price(price).unwrap_err(),
+            PriceFeedError::ZeroPrice.into()
+        );
+        // Troves are not rated at 0%
+        assert_eq!(compute_cr(1_000, 500, price), None);
+    }
+
+    #[test]
+    fn untrusted_oracles_block_borrowing_only() {
+        let mut price_feed_info = load_price_feed_info(dec(999, 9), Status::BothOraclesUntrusted);
+        let chainlink_response = &load_chainlink_response(1_000_000, dec(10, 8).into());
+        // Broken Pyth answer
+        let pyth_price_message = &load_price_message(0, 10, 1_000_000);
+
+        // Liquidations and redemptions get the last good price
+        let price = price_feed_info
+            .update(pyth_price_message, chainlink_response, dec(10, 8))
+            .unwrap();
+        assert_eq!(price, dec(999, 9));
+        assert_eq!(price_feed_info.status, Status::BothOraclesUntrusted);
+        assert_eq!(
+            price_feed_info
+                .require_trusted_price_for_borrow()
+                .unwrap_err(),
+            PriceFeedError::BothOraclesUntrusted.into()
+        );
+
+        let price_feed_info =
+            load_price_feed_info(dec(999, 9), Status::UsingChainlinkPythUntrusted);
+        assert!(price_feed_info.require_trusted_price_for_borrow().is_ok());
+    }
+
+    #[test]
+    fn price_used_matches_last_good_price() {
+        let mut price_feed_info = load_price_feed_info(0, Status::PythWorking);
+        let price_chainlink = dec(123, 8);
+        let chainlink_response = &load_chainlink_response(1_000_000, price_chainlink.into());
+
+        let pyth_price_message = &load_price_message(dec(10, 8).try_into().unwrap(), 10, 1_000_000);
+        let price = price_feed_info
+            .update(pyth_price_message, chainlink_response, price_chainlink)
+            .unwrap();
+        let event = price_feed_info.price_used(price);
+        assert_eq!(event.price, price_feed_info.last_good_price);
+        assert_eq!(event.source, Status::PythWorking);
+
+        // Pyth broken by zero conf, the Chainlink price is used
+        let pyth_price_message = &load_price_message(dec(10, 8).try_into().unwrap(), 0, 1_000_000);
+        let price = price_feed_info
+            .update(pyth_price_message, chainlink_response, price_chainlink)
+            .unwrap();
+        let event = price_feed_info.price_used(price);
+        assert_eq!(event.price, dec(123, 9));
+        assert_eq!(event.price, price_feed_info.last_good_price);
+        assert_eq!(event.source, Status::UsingChainlinkPythUntrusted);
+    }
+
+    #[test]
+    fn redemption_price_age() {
+        let mut price_feed_info = load_price_feed_info(0, Status::PythWorking);
+        let chainlink_response = &load_chainlink_response(1_000_000, dec(10, 8).into());
+
+        // Published an hour ago, not frozen so still usable for borrowing
+        let pyth_price_message =
+            &load_price_message(dec(10, 8).try_into().unwrap(), 10, 1_000_000 - 3_600);
+        price_feed_info
+            .update(pyth_price_message, chainlink_response, dec(10, 8))
+            .unwrap();
+        assert_eq!(price_feed_info.status, Status::PythWorking);
+        assert_eq!(price_feed_info.last_good_price_time, 1_000_000 - 3_600);
+        price_feed_info.require_price_age(0, 1_000_000).unwrap();
+        assert_eq!(
+            price_feed_info
+                .require_price_age(600, 1_000_000)
+                .unwrap_err(),
+            PriceFeedError::PriceTooStale.into()
+        );
+
+        // A recent publish is within the redemption window
+        let pyth_price_message =
+            &load_price_message(dec(10, 8).try_into().unwrap(), 10, 1_000_000 - 60);
+        price_feed_info
+            .update(pyth_price_message, chainlink_response, dec(10, 8))
+            .unwrap();
+        price_feed_info.require_price_age(600, 1_000_000).unwrap();
+    }
+
     #[test]
     /// C1 Pyth working: fetchPrice should return the correct price
     fn c1_pyth_working() {
@@ -585,6 +973,54 @@ pub mod price_feed_info_test {
         assert!(price_feed_info.last_good_price == dec(123, 9));
     }
 
+    #[test]
+    fn pyth_update_for_another_feed_rejected() {
+        let mut price_feed_info = load_price_feed_info(dec(999, 9), Status::PythWorking);
+        assert!(price_feed_info.check_pyth_feed_id(&[1u8; 32]).is_ok());
+        assert_eq!(
+            price_feed_info.check_pyth_feed_id(&[2u8; 32]).unwrap_err(),
+            PriceFeedError::PythWrongFeedId.into()
+        );
+
+        // A migrated account pins the first feed id it sees
+        price_feed_info.pyth_feed_id = [0u8; 32];
+        assert!(price_feed_info.check_pyth_feed_id(&[2u8; 32]).is_ok());
+        assert_eq!(price_feed_info.pyth_feed_id, [2u8; 32]);
+        assert_eq!(
+            price_feed_info.check_pyth_feed_id(&[1u8; 32]).unwrap_err(),
+            PriceFeedError::PythWrongFeedId.into()
+        );
+    }
+
+    #[test]
+    /// C1 Pyth working: Pyth conf of 3% is only above a feed's 2% tolerance
+    fn c1_pyth_working_conf_above_feed_tolerance() {
+        let price_chainlink = dec(123, 8);
+        let chainlink_response = &load_chainlink_response(1_000_000, price_chainlink.into());
+        let pyth_price_message =
+            &load_price_message(dec(321, 8).try_into().unwrap(), 963_000_000, 1_000_000);
+
+        let mut price_feed_info = load_price_feed_info(dec(999, 9), Status::PythWorking);
+        price_feed_info
+            .update(pyth_price_message, chainlink_response, price_chainlink)
+            .unwrap();
+        assert!(price_feed_info.status == Status::PythWorking);
+        assert!(price_feed_info.last_good_price == dec(321, 9));
+
+        let mut price_feed_info = load_price_feed_info(dec(999, 9), Status::PythWorking);
+        price_feed_info.set_max_confidence_rate(2_000_000).unwrap();
+        price_feed_info
+            .update(pyth_price_message, chainlink_response, price_chainlink)
+            .unwrap();
+        assert!(price_feed_info.status == Status::UsingChainlinkPythUntrusted);
+        assert!(price_feed_info.last_good_price == dec(123, 9));
+
+        assert_eq!(
+            price_feed_info.set_max_confidence_rate(0).unwrap_err(),
+            PriceFeedError::InvalidConfidenceRate.into()
+        );
+    }
+
     #[test]
     /// C1 Pyth working: Pyth conf > 5% and chainlink price similar
     fn c1_pyth_working_conf_bigger_than_5_percent_chainlink_price_similar() {
@@ -1064,4 +1500,203 @@ pub mod price_feed_info_test {
         assert!(price_feed_info.status == Status::BothOraclesUntrusted);
         assert!(price_feed_info.last_good_price == dec(101, 9));
     }
+
+    #[test]
+    fn jitosol_rate_falls_back_on_cache_for_one_stale_epoch() {
+        let mut price_feed_info = load_price_feed_info(0, Status::PythWorking);
+
+        // No cached rate yet
+        assert!(price_feed_info
+            .resolve_jitosol_rate(90_000_000, 9, 10)
+            .is_err());
+
+        // Pool updated this epoch, rate is cached
+        assert_eq!(
+            price_feed_info
+                .resolve_jitosol_rate(90_000_000, 10, 10)
+                .unwrap(),
+            90_000_000
+        );
+        assert_eq!(price_feed_info.jitosol_rate_epoch, 10);
+
+        // Pool one epoch stale, cached rate is used
+        assert_eq!(
+            price_feed_info
+                .resolve_jitosol_rate(89_000_000, 10, 11)
+                .unwrap(),
+            90_000_000
+        );
+
+        // Cache too old
+        assert!(price_feed_info
+            .resolve_jitosol_rate(89_000_000, 10, 12)
+            .is_err());
+    }
+
+    #[test]
+    fn jitosol_pool_rate_used_within_epoch_lag() {
+        let mut price_feed_info = load_price_feed_info(0, Status::PythWorking);
+
+        // Strict by default, a pool one epoch stale reverts
+        assert_eq!(
+            price_feed_info
+                .resolve_jitosol_rate(90_000_000, 9, 10)
+                .unwrap_err(),
+            PriceFeedError::PoolNotUpdated.into()
+        );
+
+        assert_eq!(
+            price_feed_info
+                .set_jitosol_max_epoch_lag(MAX_JITOSOL_EPOCH_LAG + 1)
+                .unwrap_err(),
+            PriceFeedError::InvalidEpochLag.into()
+        );
+        price_feed_info.set_jitosol_max_epoch_lag(1).unwrap();
+        assert_eq!(
+            price_feed_info
+                .resolve_jitosol_rate(90_000_000, 9, 10)
+                .unwrap(),
+            90_000_000
+        );
+        assert_eq!(price_feed_info.jitosol_rate_epoch, 9);
+
+        // Two epochs behind only the cached rate can be used
+        assert_eq!(
+            price_feed_info
+                .resolve_jitosol_rate(89_000_000, 9, 11)
+                .unwrap_err(),
+            PriceFeedError::PoolNotUpdated.into()
+        );
+    }
+
+    #[test]
+    /// Switchboard decimals are scaled to the Chainlink feed decimals
+    fn switchboard_answer_uses_feed_decimals() {
+        assert_eq!(
+            load_switchboard_response(1_000_000, 123_456, 3).answer(),
+            12_345_600_000
+        );
+        assert_eq!(
+            load_switchboard_response(1_000_000, 123_456_789_012_345_678, 15).answer(),
+            12_345_678_901
+        );
+        assert!(load_switchboard_response(1_000_000, -1, 0).is_broken());
+        assert!(load_switchboard_response(0, dec(10, 8).into(), 8).is_broken());
+        assert!(
+            load_switchboard_response(1_000_000 - TIMEOUT - 1, dec(10, 8).into(), 8).is_frozen()
+        );
+    }
+
+    #[test]
+    /// C1 Pyth working, negative Switchboard answer: Switchboard is broken, keep using Pyth
+    fn c1_pyth_working_switchboard_negative() {
+        let mut price_feed_info = load_price_feed_info(dec(999, 9), Status::PythWorking);
+        price_feed_info.secondary_source = SecondarySource::Switchboard;
+
+        let pyth_price_message = &load_price_message(dec(321, 8).try_into().unwrap(), 1, 1_000_000);
+        let switchboard_response = &load_switchboard_response(1_000_000, -123, 0);
+        assert!(switchboard_response.is_broken());
+
+        price_feed_info
+            .update_with_jitosol_rate(pyth_price_message, switchboard_response, dec(1, 9))
+            .unwrap();
+        assert!(price_feed_info.last_good_price == dec(321, 9));
+    }
+
+    #[test]
+    /// C1 Pyth breaks, Switchboard working: switch to Switchboard and use its price
+    fn c1_pyth_breaks_switchboard_working() {
+        let mut price_feed_info = load_price_feed_info(0, Status::PythWorking);
+        price_feed_info.secondary_source = SecondarySource::Switchboard;
+
+        let pyth_price_message = &load_price_message(-5000, 1, 1_000_000);
+        // 123 with 18 decimals
+        let switchboard_response = &load_switchboard_response(1_000_000, 123 * 10i128.pow(18), 18);
+        let price_switchboard = switchboard_response.answer();
+
+        price_feed_info
+            .update(pyth_price_message, switchboard_response, price_switchboard)
+            .unwrap();
+        assert!(price_feed_info.status == Status::UsingChainlinkPythUntrusted);
+        assert!(price_feed_info.last_good_price == dec(123, 9));
+    }
+
+    #[test]
+    /// C1 Pyth frozen, Switchboard broken: remember it and return the last good price
+    fn c1_pyth_frozen_switchboard_broken() {
+        let mut price_feed_info = load_price_feed_info(dec(999, 9), Status::PythWorking);
+
+        let pyth_price_message = &load_price_message(dec(100, 8).try_into().unwrap(), 1, 1);
+        let switchboard_response = &load_switchboard_response(1_000_000, 0, 8);
+
+        price_feed_info
+            .update(pyth_price_message, switchboard_response, 0)
+            .unwrap();
+        assert!(price_feed_info.status == Status::UsingPythChainlinkUntrusted);
+        assert!(price_feed_info.last_good_price == dec(999, 9));
+    }
+
+    #[test]
+    /// C2 Using Switchboard, it freezes: keep the status and return the last good price
+    fn c2_using_switchboard_pyth_untrusted_switchboard_frozen() {
+        let mut price_feed_info =
+            load_price_feed_info(dec(999, 9), Status::UsingChainlinkPythUntrusted);
+
+        let pyth_price_message = &load_price_message(-5000, 1, 1_000_000);
+        let switchboard_response =
+            &load_switchboard_response(1_000_000 - TIMEOUT - 1, dec(123, 8).into(), 8);
+
+        price_feed_info
+            .update(pyth_price_message, switchboard_response, dec(123, 8))
+            .unwrap();
+        assert!(price_feed_info.status == Status::UsingChainlinkPythUntrusted);
+        assert!(price_feed_info.last_good_price == dec(999, 9));
+    }
+
+    #[test]
+    /// C3 Both untrusted, both go live with similar prices: switch back to Pyth
+    fn c3_both_oracles_suspect_switchboard_live_and_price_similar() {
+        let mut price_feed_info = load_price_feed_info(dec(50, 9), Status::BothOraclesUntrusted);
+
+        let pyth_price_message = &load_price_message(dec(100, 8).try_into().unwrap(), 1, 1_000_000);
+        let switchboard_response = &load_switchboard_response(1_000_000, dec(101, 8).into(), 8);
+
+        price_feed_info
+            .update(pyth_price_message, switchboard_response, dec(101, 8))
+            .unwrap();
+        assert!(price_feed_info.status == Status::PythWorking);
+        assert!(price_feed_info.last_good_price == dec(100, 9));
+    }
+
+    #[test]
+    /// C4 Using Switchboard with Pyth frozen, Pyth live with a different price: distrust Pyth
+    fn c4_using_switchboard_pyth_frozen_both_live_price_different() {
+        let mut price_feed_info =
+            load_price_feed_info(dec(50, 9), Status::UsingChainlinkPythFrozen);
+
+        let pyth_price_message = &load_price_message(dec(100, 8).try_into().unwrap(), 1, 1_000_000);
+        let switchboard_response = &load_switchboard_response(1_000_000, dec(120, 8).into(), 8);
+
+        price_feed_info
+            .update(pyth_price_message, switchboard_response, dec(120, 8))
+            .unwrap();
+        assert!(price_feed_info.status == Status::UsingChainlinkPythUntrusted);
+        assert!(price_feed_info.last_good_price == dec(120, 9));
+    }
+
+    #[test]
+    /// C5 Using Pyth with Switchboard untrusted, Switchboard recovers with a similar price: back to PythWorking
+    fn c5_using_pyth_switchboard_untrusted_price_similar() {
+        let mut price_feed_info =
+            load_price_feed_info(dec(50, 9), Status::UsingPythChainlinkUntrusted);
+
+        let pyth_price_message = &load_price_message(dec(100, 8).try_into().unwrap(), 1, 1_000_000);
+        let switchboard_response = &load_switchboard_response(1_000_000, dec(102, 8).into(), 8);
+
+        price_feed_info
+            .update(pyth_price_message, switchboard_response, dec(102, 8))
+            .unwrap();
+        assert!(price_feed_info.status == Status::PythWorking);
+        assert!(price_feed_info.last_good_price == dec(100, 9));
+    }
 }
@@ -3,11 +3,19 @@ use anchor_lang::prelude::*;
 use crate::{
     constants::{DECIMAL_PRECISION, SCALE_FACTOR},
     errors::StabilityPoolError,
-    events::DepositSnapshotUpdated,
+    events::{DepositSnapshotUpdated, SpClaimed},
 };
 
 use super::{EpochScale, StabilityPoolState};
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SPDepositPreview {
+    pub compounded_usv: u64,
+    pub coll_gain: u64,
+    pub cvgt_gain: u64,
+    pub usv_loss: u64,
+}
+
 #[account]
 #[derive(InitSpace, Default)]
 pub struct StabilityPoolDeposit {
@@ -36,7 +44,7 @@ impl StabilityPoolDeposit {
         let second_portion = second_epoch_scale.sum.checked_div(SCALE_FACTOR.into())?;
 
         let coll_gain = (self.initial_value as u128)
-            .checked_mul(first_portion.checked_add(second_portion)?.into())?
+            .checked_mul(first_portion.checked_add(second_portion)?)?
             .checked_div(self.snapshots_p)?
             .checked_div(DECIMAL_PRECISION.into())?;
         Some(u64::try_from(coll_gain).unwrap())
@@ -63,12 +71,12 @@ impl StabilityPoolDeposit {
         // Consider to change the snapshots to be u128 instead of u64
         let compounded_stake = u64::try_from(if scale_diff == 0 {
             (self.initial_value as u128)
-                .checked_mul(sp_state.p.into())?
-                .checked_div(self.snapshots_p.into())?
+                .checked_mul(sp_state.p)?
+                .checked_div(self.snapshots_p)?
         } else if scale_diff == 1 {
             (self.initial_value as u128)
-                .checked_mul(sp_state.p.into())?
-                .checked_div(self.snapshots_p.into())?
+                .checked_mul(sp_state.p)?
+                .checked_div(self.snapshots_p)?
                 .checked_div(SCALE_FACTOR.into())?
         } else {
             0u128
@@ -116,11 +124,128 @@ impl StabilityPoolDeposit {
         Some(u64::try_from(cvgt_gain).unwrap())
     }
 
+    /// Collateral and CVGT gains accumulated over an ordered run of epoch-scales starting at the
+    /// deposit's snapshot. Every scale boundary crossed shrinks the deposit by `SCALE_FACTOR`, so the
+    /// k-th epoch-scale after the snapshot contributes its sums divided by `SCALE_FACTOR^k`.
+    pub fn get_gains_from_epoch_scales(&self, epoch_scales: &[EpochScale]) -> Option<(u64, u64)> {
+        if self.initial_value == 0 || epoch_scales.is_empty() {
+            return Some((0, 0));
+        }
+
+        let mut coll_portion = epoch_scales[0].sum.checked_sub(self.snapshots_s)?;
+        let mut cvgt_portion = epoch_scales[0].g.checked_sub(self.snapshots_g)?;
+        let mut divisor: u128 = 1;
+        for epoch_scale in epoch_scales.iter().skip(1) {
+            divisor = match divisor.checked_mul(SCALE_FACTOR.into()) {
+                Some(divisor) => divisor,
+                // Contributions this far out are already rounded away
+                None => break,
+            };
+            coll_portion = coll_portion.checked_add(epoch_scale.sum.checked_div(divisor)?)?;
+            cvgt_portion = cvgt_portion.checked_add(epoch_scale.g.checked_div(divisor)?)?;
+        }
+
+        let coll_gain = (self.initial_value as u128)
+            .checked_mul(coll_portion)?
+            .checked_div(self.snapshots_p)?
+            .checked_div(DECIMAL_PRECISION.into())?;
+        let cvgt_gain = (self.initial_value as u128)
+            .checked_mul(cvgt_portion)?
+            .checked_div(self.snapshots_p)?
+            .checked_div(DECIMAL_PRECISION.into())?;
+
+        Some((
+            u64::try_from(coll_gain).ok()?,
+            u64::try_from(cvgt_gain).ok()?,
+        ))
+    }
+
+    /// What a withdrawal of zero would leave claimable right now, gains realized earlier included
+    pub fn preview(
+        &self,
+        sp_state: &StabilityPoolState,
+        first_epoch_scale: &EpochScale,
+        second_epoch_scale: &EpochScale,
+    ) -> Option<SPDepositPreview> {
+        let compounded_usv = self.get_compounded_usv_deposit(sp_state)?;
+        Some(SPDepositPreview {
+            compounded_usv,
+            coll_gain: self
+                .get_depositor_coll_gain(first_epoch_scale, second_epoch_scale)?
+                .checked_add(self.claimable_coll)?,
+            cvgt_gain: self
+                .get_cvgt_gain(first_epoch_scale, second_epoch_scale)?
+                .checked_add(self.claimable_cvgt)?,
+            usv_loss: self.initial_value.checked_sub(compounded_usv)?,
+        })
+    }
+
     pub fn require_user_has_deposit(&self) -> Result<()> {
         require!(self.initial_value > 0, StabilityPoolError::ZeroDeposit);
         Ok(())
     }
 
+    pub fn require_empty(&self) -> Result<()> {
+        require!(
+            self.initial_value == 0 && self.claimable_coll == 0 && self.claimable_cvgt == 0,
+            StabilityPoolError::DepositNotEmpty
+        );
+        Ok(())
+    }
+
+    /// Zeroes the claimable gains, the deposit itself stays in the pool
+    pub fn claim(&mut self, depositor: Pubkey) -> SpClaimed {
+        SpClaimed {
+            depositor,
+            coll_claimed: std::mem::take(&mut self.claimable_coll),
+            cvgt_claimed: std::mem::take(&mut self.claimable_cvgt),
+            remaining_deposit: self.initial_value,
+        }
+    }
+
+    /// Moves the whole position, snapshots and unclaimed gains included, into an empty deposit
+    pub fn transfer_to(&mut self, destination: &mut StabilityPoolDeposit) -> Result<()> {
+        destination.require_empty()?;
+        *destination = std::mem::take(self);
+        Ok(())
+    }
+
+    /// Realizes gains over `epoch_scales` into the claimable balances and snapshots the compounded deposit,
+    /// as a withdrawal of zero does. Returns the coll gain, CVGT gain and compounded deposit.
+    pub fn realize_gains(
+        &mut self,
+        sp_state: &StabilityPoolState,
+        epoch_scales: &[EpochScale],
+        current_epoch_scale: &EpochScale,
+        depositor: Pubkey,
+    ) -> Option<(u64, u64, u64)> {
+        self.provide(sp_state, epoch_scales, current_epoch_scale, depositor, 0)
+    }
+
+    /// Same as `realize_gains`, with `usv_amt` added on top of the compounded deposit.
+    /// The returned compounded deposit excludes `usv_amt`.
+    pub fn provide(
+        &mut self,
+        sp_state: &StabilityPoolState,
+        epoch_scales: &[EpochScale],
+        current_epoch_scale: &EpochScale,
+        depositor: Pubkey,
+        usv_amt: u64,
+    ) -> Option<(u64, u64, u64)> {
+        let (coll_gain, cvgt_gain) = self.get_gains_from_epoch_scales(epoch_scales)?;
+        let compounded_usv_deposit = self.get_compounded_usv_deposit(sp_state)?;
+
+        self.update_deposit_and_snapshot(
+            sp_state,
+            current_epoch_scale,
+            depositor,
+            compounded_usv_deposit.checked_add(usv_amt)?,
+        );
+        self.claimable_coll = self.claimable_coll.checked_add(coll_gain)?;
+        self.claimable_cvgt = self.claimable_cvgt.checked_add(cvgt_gain)?;
+        Some((coll_gain, cvgt_gain, compounded_usv_deposit))
+    }
+
     pub fn update_deposit_and_snapshot(
         &mut self,
         sp_state: &StabilityPoolState,
@@ -156,3 +281,414 @@ impl StabilityPoolDeposit {
         }
     }
 }
+
+#[cfg(test)]
+pub mod stability_pool_deposit_test {
+    use super::*;
+    use crate::state::{
+        stability_pool_state_test::{self, DEPOSIT},
+        LiquidationTotals,
+    };
+
+    fn load_deposit(initial_value: u64) -> StabilityPoolDeposit {
+        StabilityPoolDeposit {
+            initial_value,
+            snapshots_p: DECIMAL_PRECISION.into(),
+            ..Default::default()
+        }
+    }
+
+    fn load_epoch_scale(sum: u128, g: u128) -> EpochScale {
+        EpochScale { sum, g }
+    }
+
+    /// Pool that hasn't absorbed any debt yet
+    pub fn load_unit_sp_state() -> StabilityPoolState {
+        StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Half a coll and a fifth of a CVGT gained per deposited unit
+    pub fn load_current_epoch_scale() -> EpochScale {
+        load_epoch_scale(500_000_000_000_000_000, 200_000_000_000_000_000)
+    }
+
+    #[test]
+    fn gains_from_two_epoch_scales_match_snapshot_gains() {
+        let deposit = load_deposit(1_000_000_000);
+        let first = load_epoch_scale(2_000_000_000_000_000_000, 7_000_000_000_000_000_000);
+        let second = load_epoch_scale(
+            300_000_000_000_000_000_000_000,
+            1_000_000_000_000_000_000_000,
+        );
+
+        let (coll_gain, cvgt_gain) = deposit
+            .get_gains_from_epoch_scales(&[first.clone(), second.clone()])
+            .unwrap();
+        assert_eq!(
+            coll_gain,
+            deposit.get_depositor_coll_gain(&first, &second).unwrap()
+        );
+        assert_eq!(cvgt_gain, deposit.get_cvgt_gain(&first, &second).unwrap());
+    }
+
+    #[test]
+    fn gains_accumulate_across_every_epoch_scale() {
+        let deposit = load_deposit(1_000_000_000);
+        let epoch_scales = [
+            load_epoch_scale(2_000_000_000_000_000_000, 1_000_000_000_000_000_000),
+            load_epoch_scale(
+                300_000_000_000_000_000_000_000,
+                100_000_000_000_000_000_000_000,
+            ),
+            load_epoch_scale(
+                40_000_000_000_000_000_000_000_000_000,
+                10_000_000_000_000_000_000_000_000_000,
+            ),
+        ];
+
+        let (coll_gain, cvgt_gain) = deposit.get_gains_from_epoch_scales(&epoch_scales).unwrap();
+        // 2 + 3 + 4 from each boundary
+        assert_eq!(coll_gain, 9_000_000_000);
+        // 1 + 1 + 1 from each boundary
+        assert_eq!(cvgt_gain, 3_000_000_000);
+
+        let (first_only, _) = deposit
+            .get_gains_from_epoch_scales(&epoch_scales[..1])
+            .unwrap();
+        assert_eq!(first_only, 2_000_000_000);
+    }
+
+    #[test]
+    fn deposit_spanning_two_epoch_resets() {
+        let mut sp_state = stability_pool_state_test::load_sp_state();
+        let deposit = stability_pool_state_test::load_deposit(&sp_state);
+        let offset = |sp_state: &mut StabilityPoolState,
+                      epoch_scale: &mut EpochScale,
+                      debt: u64,
+                      coll: u64| {
+            let totals = LiquidationTotals {
+                total_debt_to_offset: debt,
+                total_coll_to_send_to_sp: coll,
+                ..Default::default()
+            };
+            sp_state
+                .offset(epoch_scale, &totals, 1_000_000_000)
+                .unwrap();
+            sp_state.decrease_usv(debt);
+        };
+
+        // Epoch 0, the deposit is the whole pool: half of it absorbed, then the rest
+        let mut first_epoch = EpochScale::default();
+        offset(&mut sp_state, &mut first_epoch, DEPOSIT / 2, 1_000_000_000);
+        let first_gains = deposit.get_gains_from_epoch_scales(&[first_epoch.clone()]);
+        offset(&mut sp_state, &mut first_epoch, DEPOSIT / 2, 2_000_000_000);
+        assert_eq!(sp_state.current_epoch, 1);
+
+        // Epoch 1 belongs to a later depositor
+        sp_state.increase_usv(DEPOSIT);
+        let later_deposit = stability_pool_state_test::load_deposit(&sp_state);
+        let mut second_epoch = EpochScale::default();
+        offset(&mut sp_state, &mut second_epoch, DEPOSIT, 3_000_000_000);
+        assert_eq!(sp_state.current_epoch, 2);
+
+        // Only the snapshot epoch's scales are passed, the later epoch adds nothing to the deposit
+        let (coll_gain, cvgt_gain) = deposit
+            .get_gains_from_epoch_scales(&[first_epoch, EpochScale::default()])
+            .unwrap();
+        assert_eq!(first_gains, Some((1_000_000_000, 1_000_000_000)));
+        // Rounding of the first offset's USV loss favors the pool
+        let (coll_total, cvgt_total) =
+            (1_000_000_000 + 2_000_000_000, 1_000_000_000 + 1_000_000_000);
+        assert!(coll_gain <= coll_total && coll_total - coll_gain <= 10);
+        assert!(cvgt_gain <= cvgt_total && cvgt_total - cvgt_gain <= 10);
+        assert_eq!(deposit.get_compounded_usv_deposit(&sp_state), Some(0));
+        assert_eq!(
+            later_deposit.get_gains_from_epoch_scales(&[second_epoch, EpochScale::default()]),
+            Some((3_000_000_000, 1_000_000_000))
+        );
+        assert_eq!(later_deposit.get_compounded_usv_deposit(&sp_state), Some(0));
+    }
+
+    #[test]
+    fn transfer_keeps_pending_gains() {
+        let mut source = StabilityPoolDeposit {
+            initial_value: 1_000_000_000,
+            snapshots_s: 1_000_000_000_000_000_000,
+            snapshots_p: DECIMAL_PRECISION.into(),
+            snapshots_g: 5,
+            snapshots_scale: 1,
+            snapshots_epoch: 2,
+            claimable_coll: 3,
+            claimable_cvgt: 4,
+        };
+        let epoch_scales = [load_epoch_scale(3_000_000_000_000_000_000, 5)];
+        let gains = source.get_gains_from_epoch_scales(&epoch_scales);
+
+        let mut destination = StabilityPoolDeposit::default();
+        source.transfer_to(&mut destination).unwrap();
+
+        assert_eq!(
+            destination.get_gains_from_epoch_scales(&epoch_scales),
+            gains
+        );
+        assert_eq!(destination.initial_value, 1_000_000_000);
+        assert_eq!(destination.snapshots_scale, 1);
+        assert_eq!(destination.snapshots_epoch, 2);
+        assert_eq!(destination.claimable_coll, 3);
+        assert_eq!(destination.claimable_cvgt, 4);
+        assert!(source.require_empty().is_ok());
+    }
+
+    #[test]
+    fn claim_keeps_active_deposit() {
+        let depositor = Pubkey::new_unique();
+        let mut deposit = StabilityPoolDeposit {
+            claimable_coll: 3,
+            claimable_cvgt: 4,
+            ..load_deposit(1_000_000_000)
+        };
+
+        let claimed = deposit.claim(depositor);
+        assert_eq!(claimed.depositor, depositor);
+        assert_eq!(claimed.coll_claimed, 3);
+        assert_eq!(claimed.cvgt_claimed, 4);
+        assert_eq!(claimed.remaining_deposit, 1_000_000_000);
+
+        assert_eq!(deposit.claimable_coll, 0);
+        assert_eq!(deposit.claimable_cvgt, 0);
+        assert_eq!(deposit.initial_value, 1_000_000_000);
+
+        let claimed = deposit.claim(depositor);
+        assert_eq!(claimed.coll_claimed, 0);
+        assert_eq!(claimed.cvgt_claimed, 0);
+    }
+
+    #[test]
+    fn provide_then_claim_in_one_go() {
+        let depositor = Pubkey::new_unique();
+        let sp_state = load_unit_sp_state();
+        let current = load_current_epoch_scale();
+        let mut deposit = StabilityPoolDeposit {
+            claimable_coll: 7,
+            claimable_cvgt: 3,
+            ..load_deposit(1_000_000_000)
+        };
+
+        let (coll_gain, cvgt_gain, compounded) = deposit
+            .provide(
+                &sp_state,
+                std::slice::from_ref(&current),
+                &current,
+                depositor,
+                250_000_000,
+            )
+            .unwrap();
+        assert_eq!(coll_gain, 500_000_000);
+        assert_eq!(cvgt_gain, 200_000_000);
+        assert_eq!(compounded, 1_000_000_000);
+        assert_eq!(deposit.initial_value, 1_250_000_000);
+        assert_eq!(deposit.snapshots_s, current.sum);
+        assert_eq!(deposit.snapshots_g, current.g);
+
+        // Realized and earlier unclaimed gains are all delivered
+        let claimed = deposit.claim(depositor);
+        assert_eq!(claimed.coll_claimed, 500_000_007);
+        assert_eq!(claimed.cvgt_claimed, 200_000_003);
+        assert_eq!(claimed.remaining_deposit, 1_250_000_000);
+        assert_eq!(deposit.claimable_coll, 0);
+        assert_eq!(deposit.claimable_cvgt, 0);
+    }
+
+    #[test]
+    fn realize_gains_keeps_deposit() {
+        let sp_state = load_unit_sp_state();
+        let current = load_current_epoch_scale();
+        let mut deposit = StabilityPoolDeposit {
+            claimable_coll: 7,
+            claimable_cvgt: 3,
+            ..load_deposit(1_000_000_000)
+        };
+
+        // Realized by the admin for a third party depositor
+        let (coll_gain, cvgt_gain, compounded) = deposit
+            .realize_gains(
+                &sp_state,
+                std::slice::from_ref(&current),
+                &current,
+                Pubkey::new_unique(),
+            )
+            .unwrap();
+        assert_eq!(coll_gain, 500_000_000);
+        assert_eq!(cvgt_gain, 200_000_000);
+        assert_eq!(compounded, 1_000_000_000);
+        assert_eq!(deposit.claimable_coll, 500_000_007);
+        assert_eq!(deposit.claimable_cvgt, 200_000_003);
+        assert_eq!(deposit.initial_value, 1_000_000_000);
+        assert_eq!(deposit.snapshots_s, current.sum);
+        assert_eq!(deposit.snapshots_g, current.g);
+
+        // Nothing left to realize
+        assert_eq!(
+            deposit
+                .realize_gains(
+                    &sp_state,
+                    std::slice::from_ref(&current),
+                    &current,
+                    Pubkey::default()
+                )
+                .unwrap(),
+            (0, 0, 1_000_000_000)
+        );
+    }
+
+    #[test]
+    fn realized_gains_claimed_without_withdrawal() {
+        let sp_state = load_unit_sp_state();
+        let current = load_current_epoch_scale();
+        let mut deposit = load_deposit(1_000_000_000);
+        let depositor = Pubkey::new_unique();
+
+        // `claim_sp_gains` realizes, then `claim_from_sp` pays out, with the deposit untouched
+        deposit
+            .realize_gains(
+                &sp_state,
+                std::slice::from_ref(&current),
+                &current,
+                depositor,
+            )
+            .unwrap();
+        let claimed = deposit.claim(depositor);
+        assert_eq!(claimed.coll_claimed, 500_000_000);
+        assert_eq!(claimed.cvgt_claimed, 200_000_000);
+        assert_eq!(claimed.remaining_deposit, 1_000_000_000);
+        assert_eq!(deposit.claimable_coll, 0);
+        assert_eq!(deposit.claimable_cvgt, 0);
+    }
+
+    #[test]
+    fn empty_after_full_withdrawal_and_claim() {
+        let sp_state = load_unit_sp_state();
+        let mut deposit = StabilityPoolDeposit {
+            claimable_coll: 3,
+            claimable_cvgt: 4,
+            ..load_deposit(1_000_000_000)
+        };
+        assert!(deposit.require_empty().is_err());
+
+        // withdraw_from_sp of the whole deposit
+        deposit.update_deposit_and_snapshot(
+            &sp_state,
+            &EpochScale::default(),
+            Pubkey::default(),
+            0,
+        );
+        assert_eq!(
+            deposit.require_empty().unwrap_err(),
+            StabilityPoolError::DepositNotEmpty.into()
+        );
+
+        deposit.claim(Pubkey::default());
+        assert!(deposit.require_empty().is_ok());
+    }
+
+    #[test]
+    fn not_empty_with_claimable_gains() {
+        let coll_left = StabilityPoolDeposit {
+            claimable_coll: 1,
+            ..Default::default()
+        };
+        let cvgt_left = StabilityPoolDeposit {
+            claimable_cvgt: 1,
+            ..Default::default()
+        };
+        assert!(coll_left.require_empty().is_err());
+        assert!(cvgt_left.require_empty().is_err());
+        assert!(StabilityPoolDeposit::default().require_empty().is_ok());
+    }
+
+    #[test]
+    fn transfer_rejects_non_empty_destination() {
+        let mut source = load_deposit(1_000);
+        let mut destination = load_deposit(1);
+        assert!(source.transfer_to(&mut destination).is_err());
+        assert_eq!(source.initial_value, 1_000);
+    }
+
+    #[test]
+    fn gains_are_zero_without_deposit() {
+        let deposit = load_deposit(0);
+        let epoch_scales = [load_epoch_scale(1, 1)];
+        assert_eq!(
+            deposit.get_gains_from_epoch_scales(&epoch_scales),
+            Some((0, 0))
+        );
+    }
+
+    #[test]
+    fn preview_matches_realized_gains_after_offset() {
+        let mut sp_state = StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            total_usv_deposits: 2_000_000_000,
+            ..Default::default()
+        };
+        let mut epoch_scale = EpochScale::default();
+        let mut deposit = load_deposit(1_000_000_000);
+
+        let totals = LiquidationTotals {
+            total_debt_to_offset: 500_000_000,
+            total_coll_to_send_to_sp: 300_000_000,
+            ..Default::default()
+        };
+        sp_state
+            .offset(&mut epoch_scale, &totals, 1_000_000)
+            .unwrap();
+
+        let next_epoch_scale = EpochScale::default();
+        let preview = deposit
+            .preview(&sp_state, &epoch_scale, &next_epoch_scale)
+            .unwrap();
+        // Rounding favors the pool
+        assert_eq!(preview.compounded_usv, 749_999_999);
+        assert_eq!(preview.usv_loss, 250_000_001);
+        assert_eq!(preview.coll_gain, 150_000_000);
+        assert_eq!(preview.cvgt_gain, 500_000);
+
+        // withdraw_from_sp(0) realizes the same amounts
+        let coll_gain = deposit
+            .get_depositor_coll_gain(&epoch_scale, &next_epoch_scale)
+            .unwrap();
+        let cvgt_gain = deposit
+            .get_cvgt_gain(&epoch_scale, &next_epoch_scale)
+            .unwrap();
+        let compounded_usv = deposit.get_compounded_usv_deposit(&sp_state).unwrap();
+        deposit.update_deposit_and_snapshot(
+            &sp_state,
+            &epoch_scale,
+            Pubkey::default(),
+            compounded_usv,
+        );
+        deposit.claimable_coll += coll_gain;
+        deposit.claimable_cvgt += cvgt_gain;
+        assert_eq!(coll_gain, preview.coll_gain);
+        assert_eq!(cvgt_gain, preview.cvgt_gain);
+        assert_eq!(deposit.initial_value, preview.compounded_usv);
+
+        // Nothing left to realize, the realized gains are still to claim
+        let realized = deposit
+            .preview(&sp_state, &epoch_scale, &next_epoch_scale)
+            .unwrap();
+        assert_eq!(realized.coll_gain, preview.coll_gain);
+        assert_eq!(realized.cvgt_gain, preview.cvgt_gain);
+        assert_eq!(realized.usv_loss, 0);
+
+        deposit.claim(Pubkey::default());
+        let claimed = deposit
+            .preview(&sp_state, &epoch_scale, &next_epoch_scale)
+            .unwrap();
+        assert_eq!(claimed.coll_gain, 0);
+        assert_eq!(claimed.cvgt_gain, 0);
+    }
+}
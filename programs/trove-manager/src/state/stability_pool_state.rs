@@ -1,14 +1,15 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    constants::{DECIMAL_PRECISION, SCALE_FACTOR},
+    constants::{DECIMAL_PRECISION, MAX_BPS, SCALE_FACTOR},
+    errors::{PoolConfigError, StabilityPoolError},
     events::{EpochUpdated, PUpdated, SUpdated, ScaleUpdated, StabilityPoolUSVBalanceUpdated},
 };
 
-use super::{EpochScale, LiquidationTotals};
+use super::{calc_bps_share, EpochScale, LiquidationTotals};
 
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct StabilityPoolState {
     // Addresses
     pub cvgt: Pubkey,
@@ -22,6 +23,11 @@ pub struct StabilityPoolState {
     pub last_cvgt_error: u128,
     pub last_coll_error_offset: u128,
     pub last_usv_error_offset: u128,
+
+    // Share of each deposit sent to the `deposit_fee_reserve` USV ATA as an insurance reserve,
+    // the depositor is credited the rest. 0 disables it
+    pub deposit_fee_bps: u64,
+    pub deposit_fee_reserve: Pubkey,
 }
 
 impl StabilityPoolState {
@@ -33,16 +39,46 @@ impl StabilityPoolState {
         self.current_scale = 0;
         self.current_epoch = 0;
         self.last_cvgt_error = 0;
+        self.deposit_fee_bps = 0;
+        self.deposit_fee_reserve = Pubkey::default();
+    }
+
+    pub fn get_deposit_fee(&self, usv_amt: u64) -> u64 {
+        calc_bps_share(usv_amt, self.deposit_fee_bps)
+    }
+
+    pub fn set_deposit_fee(&mut self, bps: u64, reserve: Pubkey) -> Result<()> {
+        require!(bps <= MAX_BPS, PoolConfigError::InvalidBps);
+        require!(
+            bps == 0 || reserve != Pubkey::default(),
+            StabilityPoolError::MissingFeeReserve
+        );
+        self.deposit_fee_bps = bps;
+        self.deposit_fee_reserve = reserve;
+        Ok(())
     }
 
     pub fn compute_cvgt_per_unit_staked(&mut self, cvgt_issuance: u64) -> Option<u128> {
+        let (cvgt_per_unit_staked, last_cvgt_error) =
+            self.get_cvgt_per_unit_staked(cvgt_issuance)?;
+        self.last_cvgt_error = last_cvgt_error;
+        Some(cvgt_per_unit_staked)
+    }
+
+    /// Returns the CVGT per unit staked with the error carried to the next issuance
+    pub fn get_cvgt_per_unit_staked(&self, cvgt_issuance: u64) -> Option<(u128, u128)> {
         let cvgt_numerator = (cvgt_issuance as u128)
             .checked_mul(DECIMAL_PRECISION.into())?
             .checked_add(self.last_cvgt_error)?;
         let cvgt_per_unit_staked = cvgt_numerator.checked_div(self.total_usv_deposits.into())?;
-        self.last_cvgt_error = cvgt_numerator
+        let last_cvgt_error = cvgt_numerator
             .checked_sub(cvgt_per_unit_staked.checked_mul(self.total_usv_deposits.into())?)?;
-        Some(cvgt_per_unit_staked)
+        Some((cvgt_per_unit_staked, last_cvgt_error))
+    }
+
+    /// Whether `(epoch, scale)` is strictly behind `(current_epoch, current_scale)`
+    pub fn is_epoch_scale_superseded(&self, epoch: u128, scale: u128) -> bool {
+        (epoch, scale) < (self.current_epoch, self.current_scale)
     }
 
     pub fn increase_usv(&mut self, amount: u64) {
@@ -78,12 +114,13 @@ impl StabilityPoolState {
         coll_to_add: u64,
         debt_to_offset: u64,
     ) -> Option<(u64, u64)> {
+        let total_usv_deposits = self.total_usv_deposits;
+        if debt_to_offset > total_usv_deposits {
+            return None;
+        }
         let coll_numerator = (coll_to_add as u128)
             .checked_mul(DECIMAL_PRECISION.into())?
             .checked_add(self.last_coll_error_offset)?;
-        let total_usv_deposits = self.total_usv_deposits;
-
-        assert!(debt_to_offset <= total_usv_deposits);
 
         let usv_loss_per_unit_staked = if debt_to_offset == total_usv_deposits {
             self.last_coll_error_offset = 0;
@@ -113,18 +150,25 @@ impl StabilityPoolState {
         Some((coll_gain_per_unit_staked, usv_loss_per_unit_staked))
     }
 
+    /// P starts at DECIMAL_PRECISION and only decreases until it would drop below SCALE_FACTOR, when a scale
+    /// increment multiplies it by SCALE_FACTOR instead. This keeps P within `[SCALE_FACTOR, SCALE_FACTOR^2)`,
+    /// unless a single offset leaves less than one unit of P. As SCALE_FACTOR^2 > DECIMAL_PRECISION, an increment
+    /// can lift P above DECIMAL_PRECISION. `coll_gain_per_unit_staked * p` stays far below `u128::MAX` either way.
     pub fn update_reward_sum_and_product(
         &mut self,
         current_epoch_scale: &mut EpochScale,
         coll_gain_per_unit_staked: u64,
         usv_loss_per_unit_staked: u64,
-    ) -> Option<()> {
-        assert!(usv_loss_per_unit_staked <= DECIMAL_PRECISION);
+    ) -> Result<()> {
+        require!(
+            usv_loss_per_unit_staked <= DECIMAL_PRECISION,
+            StabilityPoolError::RewardMathOverflow
+        );
         /*
          * The newProductFactor is the factor by which to change all deposits, due to the depletion of Stability Pool USV in the liquidation.
          * We make the product factor 0 if there was a pool-emptying. Otherwise, it is (1 - USVLossPerUnitStaked)
          */
-        let new_product_factor = DECIMAL_PRECISION.checked_sub(usv_loss_per_unit_staked)?;
+        let new_product_factor = DECIMAL_PRECISION - usv_loss_per_unit_staked;
 
         /*
          * Calculate the new S first, before we update P.
@@ -133,8 +177,20 @@ impl StabilityPoolState {
          *
          * Since S corresponds to Coll gain, and P to deposit loss, we update S first.
          */
-        let marginal_coll_gain = (coll_gain_per_unit_staked as u128).checked_mul(self.p)?;
-        let new_s = current_epoch_scale.sum.checked_add(marginal_coll_gain)?;
+        let marginal_coll_gain = (coll_gain_per_unit_staked as u128)
+            .checked_mul(self.p)
+            .ok_or(StabilityPoolError::RewardMathOverflow)?;
+        let new_s = current_epoch_scale
+            .sum
+            .checked_add(marginal_coll_gain)
+            .ok_or(StabilityPoolError::RewardMathOverflow)?;
+
+        // Computed before any state changes so an overflow leaves the pool untouched
+        let scaled_p = self
+            .p
+            .checked_mul(new_product_factor.into())
+            .ok_or(StabilityPoolError::RewardMathOverflow)?;
+
         current_epoch_scale.sum = new_s;
         emit!(SUpdated {
             s: new_s,
@@ -144,7 +200,7 @@ impl StabilityPoolState {
 
         // If the Stability Pool was emptied, increment the epoch, and reset the scale and product P
         let new_p = if new_product_factor == 0 {
-            self.current_epoch = self.current_epoch.checked_add(1)?;
+            self.current_epoch = self.current_epoch.checked_add(1).unwrap();
             emit!(EpochUpdated {
                 current_epoch: self.current_epoch
             });
@@ -154,31 +210,22 @@ impl StabilityPoolState {
             });
             DECIMAL_PRECISION.into()
         // If multiplying P by a non-zero product factor would reduce P below the scale boundary, increment the scale
-        } else if self
-            .p
-            .checked_mul(new_product_factor.into())?
-            .checked_div(DECIMAL_PRECISION.into())?
-            < SCALE_FACTOR.into()
-        {
-            self.current_scale = self.current_scale.checked_add(1)?;
+        } else if scaled_p / u128::from(DECIMAL_PRECISION) < SCALE_FACTOR.into() {
+            self.current_scale = self.current_scale.checked_add(1).unwrap();
             emit!(ScaleUpdated {
                 current_scale: self.current_scale
             });
-            self.p
-                .checked_mul(new_product_factor.into())?
-                .checked_mul(SCALE_FACTOR.into())?
-                .checked_div(DECIMAL_PRECISION.into())?
+            // scaled_p < SCALE_FACTOR * DECIMAL_PRECISION here, this can't overflow
+            scaled_p * u128::from(SCALE_FACTOR) / u128::from(DECIMAL_PRECISION)
         } else {
-            self.p
-                .checked_mul(new_product_factor.into())?
-                .checked_div(DECIMAL_PRECISION.into())?
+            scaled_p / u128::from(DECIMAL_PRECISION)
         };
 
-        assert!(new_p > 0);
+        require!(new_p > 0, StabilityPoolError::RewardMathOverflow);
         self.p = new_p;
 
         emit!(PUpdated { p: self.p });
-        Some(())
+        Ok(())
     }
 
     pub fn offset(
@@ -186,18 +233,162 @@ impl StabilityPoolState {
         current_epoch_scale: &mut EpochScale,
         totals: &LiquidationTotals,
         cvgt_issuance: u64,
-    ) {
+    ) -> Result<()> {
+        // Debt above the deposits must have been left for redistribution by `offset_and_redistribute`
+        require!(
+            totals.total_debt_to_offset <= self.total_usv_deposits,
+            StabilityPoolError::OffsetAboveDeposits
+        );
         current_epoch_scale.update_g(self, cvgt_issuance);
         let (coll_gain_per_unit_staked, usv_loss_per_unit_staked) = self
             .compute_rewards_per_unit_staked(
                 totals.total_coll_to_send_to_sp,
-                totals.total_debt_to_offset,
+                totals.get_sp_usv_loss(),
             )
             .unwrap();
         self.update_reward_sum_and_product(
             current_epoch_scale,
             coll_gain_per_unit_staked,
             usv_loss_per_unit_staked,
+        )
+    }
+}
+
+#[cfg(test)]
+pub mod stability_pool_state_test {
+    use super::*;
+    use crate::state::StabilityPoolDeposit;
+
+    pub const DEPOSIT: u64 = 1_000_000_000_000;
+
+    pub fn load_sp_state() -> StabilityPoolState {
+        StabilityPoolState {
+            p: DECIMAL_PRECISION.into(),
+            total_usv_deposits: DEPOSIT,
+            ..Default::default()
+        }
+    }
+
+    pub fn load_deposit(sp_state: &StabilityPoolState) -> StabilityPoolDeposit {
+        StabilityPoolDeposit {
+            initial_value: sp_state.total_usv_deposits,
+            snapshots_p: sp_state.p,
+            snapshots_scale: sp_state.current_scale,
+            snapshots_epoch: sp_state.current_epoch,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn near_full_offsets_compound_across_scales() {
+        let mut sp_state = load_sp_state();
+        let mut epoch_scale = EpochScale::default();
+        let mut deposit = load_deposit(&sp_state);
+
+        for _ in 0..30 {
+            let scale = sp_state.current_scale;
+            // Leave a thousandth of the pool after each offset
+            let totals = LiquidationTotals {
+                total_debt_to_offset: DEPOSIT - DEPOSIT / 1_000,
+                total_coll_to_send_to_sp: 1_000_000_000,
+                ..Default::default()
+            };
+            sp_state.offset(&mut epoch_scale, &totals, 0).unwrap();
+            sp_state.decrease_usv(totals.total_debt_to_offset);
+            if sp_state.current_scale != scale {
+                epoch_scale = EpochScale::default();
+            }
+
+            assert!(sp_state.p >= SCALE_FACTOR.into());
+            // A scale increment can lift P above DECIMAL_PRECISION
+            assert!(sp_state.p < u128::from(SCALE_FACTOR) * u128::from(SCALE_FACTOR));
+            // Rounding favors the pool, by at most a hundred-thousandth
+            let compounded = deposit.get_compounded_usv_deposit(&sp_state).unwrap();
+            let remaining = sp_state.total_usv_deposits;
+            assert!(compounded <= remaining);
+            assert!(remaining - compounded <= remaining / 100_000);
+
+            // The depositor tops the pool back up, taking a new snapshot
+            let top_up = DEPOSIT - remaining;
+            sp_state.increase_usv(top_up);
+            deposit.update_deposit_and_snapshot(
+                &sp_state,
+                &epoch_scale,
+                Pubkey::default(),
+                compounded + top_up,
+            );
+            assert_eq!(deposit.snapshots_p, sp_state.p);
+            assert_eq!(deposit.snapshots_scale, sp_state.current_scale);
+        }
+        assert!(sp_state.current_scale >= 10);
+        assert_eq!(sp_state.current_epoch, 0);
+    }
+
+    #[test]
+    fn reward_sum_overflow_is_rejected() {
+        let mut sp_state = load_sp_state();
+        let mut epoch_scale = EpochScale {
+            sum: u128::MAX - 1,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sp_state
+                .update_reward_sum_and_product(&mut epoch_scale, 1, 500_000_000)
+                .unwrap_err(),
+            StabilityPoolError::RewardMathOverflow.into()
         );
+        assert_eq!(sp_state.p, u128::from(DECIMAL_PRECISION));
+        assert_eq!(epoch_scale.sum, u128::MAX - 1);
+    }
+
+    #[test]
+    fn epoch_scale_superseded_after_two_epochs() {
+        let mut sp_state = load_sp_state();
+        let mut epoch_scale = EpochScale::default();
+        assert!(!sp_state.is_epoch_scale_superseded(0, 0));
+
+        // Two pool-emptying offsets
+        for epoch in 1..=2 {
+            let totals = LiquidationTotals {
+                total_debt_to_offset: DEPOSIT,
+                total_coll_to_send_to_sp: 1_000_000_000,
+                ..Default::default()
+            };
+            sp_state.offset(&mut epoch_scale, &totals, 0).unwrap();
+            sp_state.decrease_usv(DEPOSIT);
+            sp_state.increase_usv(DEPOSIT);
+            epoch_scale = EpochScale::default();
+            assert_eq!(sp_state.current_epoch, epoch);
+        }
+
+        assert!(sp_state.is_epoch_scale_superseded(0, 0));
+        assert!(sp_state.is_epoch_scale_superseded(1, 5));
+        assert!(!sp_state.is_epoch_scale_superseded(2, 0));
+        assert!(!sp_state.is_epoch_scale_superseded(3, 0));
+    }
+
+    #[test]
+    fn deposit_fee_needs_reserve() {
+        let mut sp_state = load_sp_state();
+        let reserve = Pubkey::new_unique();
+        assert_eq!(sp_state.get_deposit_fee(10_000_000_000), 0);
+
+        assert_eq!(
+            sp_state.set_deposit_fee(MAX_BPS + 1, reserve).unwrap_err(),
+            PoolConfigError::InvalidBps.into()
+        );
+        assert_eq!(
+            sp_state.set_deposit_fee(50, Pubkey::default()).unwrap_err(),
+            StabilityPoolError::MissingFeeReserve.into()
+        );
+
+        sp_state.set_deposit_fee(50, reserve).unwrap();
+        assert_eq!(sp_state.get_deposit_fee(10_000_000_000), 50_000_000);
+        assert_eq!(sp_state.deposit_fee_reserve, reserve);
+
+        // Disabling the fee doesn't need a reserve
+        sp_state.set_deposit_fee(0, Pubkey::default()).unwrap();
+        assert_eq!(sp_state.get_deposit_fee(10_000_000_000), 0);
     }
 }
@@ -1,11 +1,11 @@
 use anchor_lang::prelude::*;
 
 use crate::{
-    constants::DECIMAL_PRECISION,
+    constants::{DECIMAL_PRECISION, ONE_HUNDERED_PERCENT},
     errors::BorrowerOpsError,
     events::{
-        NodeAdded, NodeRemoved, SurplusPoolCollBalanceUpdated, SurplusPoolCollSent,
-        TotalStakesUpdated, TroveSnapshotsUpdated,
+        NodeAdded, NodeRemoved, Operation, SurplusPoolCollBalanceUpdated, SurplusPoolCollSent,
+        TotalStakesUpdated, TroveICRUpdated, TroveSnapshotsUpdated,
     },
     math::{compute_cr, compute_nominal_cr},
     ID,
@@ -14,7 +14,7 @@ use crate::{
 use super::PoolState;
 
 #[account]
-#[derive(InitSpace)]
+#[derive(InitSpace, Default)]
 pub struct Trove {
     pub pool_state: Pubkey,
     pub creator: Pubkey,
@@ -27,6 +27,17 @@ pub struct Trove {
     pub status: TroveStatus,
     pub prev: Pubkey,
     pub next: Pubkey,
+    // Gas compensation was minted to the borrower when the trove was opened
+    pub gas_comp_held_by_borrower: bool,
+    // Collateral without debt nor gas compensation, not staked for redistributions
+    pub coll_only: bool,
+    // Last time the debt was opened or increased, for `debt_increase_cooldown`
+    pub last_debt_increase_time: u64,
+    // Opening time, for `liquidation_grace_period`
+    pub created_at: u64,
+    // Borrower-held gas compensation left unbacked by a redemption close, burned before the
+    // surplus is claimed
+    pub gas_comp_owed: u64,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, PartialEq, Eq, InitSpace, Default)]
@@ -40,6 +51,8 @@ pub enum TroveStatus {
 }
 
 impl Trove {
+    /// Also used to reopen a closed trove. Links left from the previous trove are cleared
+    /// before insertion, while its surplus stays claimable through `claim_coll_surplus`.
     pub fn init(&mut self, pool_state: Pubkey, creator: Pubkey, coll_amt: u64, debt_amt: u64) {
         self.pool_state = pool_state;
         self.creator = creator;
@@ -49,6 +62,68 @@ impl Trove {
         self.stake = 0;
         self.snapshot_debt_reward = 0;
         self.snapshot_coll_reward = 0;
+        self.prev = Pubkey::default();
+        self.next = Pubkey::default();
+    }
+
+    pub fn require_debt_increase_cooldown_elapsed(
+        &self,
+        pool_state: &PoolState,
+        current_timestamp: u64,
+    ) -> Result<()> {
+        let elapsed = current_timestamp.saturating_sub(self.last_debt_increase_time);
+        require!(
+            elapsed >= pool_state.debt_increase_cooldown,
+            BorrowerOpsError::DebtIncreaseCooldownNotElapsed
+        );
+        Ok(())
+    }
+
+    /// Normal mode liquidations wait for the grace period unless the trove is under 100% ICR
+    pub fn is_in_liquidation_grace_period(
+        &self,
+        pool_state: &PoolState,
+        icr: u64,
+        current_timestamp: u64,
+    ) -> bool {
+        icr >= ONE_HUNDERED_PERCENT
+            && current_timestamp.saturating_sub(self.created_at)
+                < pool_state.liquidation_grace_period
+    }
+
+    /// USV to burn on close as (from borrower, from gas compensation vault)
+    pub fn get_close_burn_amounts(&self, debt: u64, gas_compensation: u64) -> (u64, u64) {
+        if self.gas_comp_held_by_borrower || self.coll_only {
+            (debt, 0)
+        } else {
+            (
+                debt.checked_sub(gas_compensation).unwrap(),
+                gas_compensation,
+            )
+        }
+    }
+
+    /// USV gas compensation minted to the liquidator. A borrower-held reserve never reached the
+    /// vault, the liquidated debt it backed is covered by the SP and redistribution instead.
+    pub fn get_liquidation_usv_gas_compensation(&self, gas_compensation: u64) -> u64 {
+        if self.gas_comp_held_by_borrower {
+            0
+        } else {
+            gas_compensation
+        }
+    }
+
+    /// Gas compensation added to the debt of a collateral-only trove taking its first debt
+    pub fn get_first_debt_gas_compensation(
+        &self,
+        is_debt_increase: bool,
+        gas_compensation: u64,
+    ) -> u64 {
+        if self.coll_only && is_debt_increase {
+            gas_compensation
+        } else {
+            0
+        }
     }
 
     pub fn get_new_trove_amounts(
@@ -109,19 +184,38 @@ impl Trove {
         compute_cr(self.coll, self.debt, price).unwrap()
     }
 
+    /// ICR the trove lands at after `operation`, at the price the operation used
+    pub fn icr_updated(
+        &self,
+        pool_state: &PoolState,
+        price: u64,
+        operation: Operation,
+    ) -> TroveICRUpdated {
+        let icr = if self.status == TroveStatus::Active {
+            self.get_current_icr(pool_state, price)
+        } else {
+            0
+        };
+        TroveICRUpdated {
+            borrower: self.creator,
+            icr,
+            operation,
+        }
+    }
+
     pub fn compute_new_stake(&self, pool_state: &PoolState, coll: u64) -> u64 {
         if pool_state.total_coll_snapshot == 0 {
-            return coll;
+            coll
         } else {
             assert!(pool_state.total_stakes_snapshot > 0);
-            return u64::try_from(
+            u64::try_from(
                 u128::from(coll)
                     .checked_mul(pool_state.total_stakes_snapshot.into())
                     .unwrap()
                     .checked_div(pool_state.total_coll_snapshot.into())
                     .unwrap(),
             )
-            .unwrap();
+            .unwrap()
         }
     }
 
@@ -142,37 +236,39 @@ impl Trove {
         } else if prev.is_none() {
             // `(null, next)` is a valid insert position if `next` is the head of the list
             let next_account = next.as_ref().unwrap();
+            let next_nicr = next_account.get_nominal_icr(pool_state);
             if pool_state.trove_head == *id {
                 require!(
                     self.next == next_account.key()
-                        && nicr >= next_account.get_nominal_icr(pool_state),
+                        && sorts_before(nicr, id, next_nicr, &next_account.key()),
                     BorrowerOpsError::InvalidTroveNeighbor
                 );
             } else {
                 require!(
                     pool_state.trove_head == next_account.key()
-                        && nicr >= next_account.get_nominal_icr(pool_state),
+                        && sorts_before(nicr, id, next_nicr, &next_account.key()),
                     BorrowerOpsError::InvalidTroveNeighbor
                 );
             }
         } else if next.is_none() {
             // `(prev, null)` is a valid insert position if `prev` is the tail of the list
             let prev_account = prev.as_ref().unwrap();
+            let prev_nicr = prev_account.get_nominal_icr(pool_state);
             if pool_state.trove_tail == *id {
                 require!(
                     self.prev == prev_account.key()
-                        && nicr <= prev_account.get_nominal_icr(pool_state),
+                        && sorts_before(prev_nicr, &prev_account.key(), nicr, id),
                     BorrowerOpsError::InvalidTroveNeighbor
                 );
             } else {
                 require!(
                     pool_state.trove_tail == prev_account.key()
-                        && nicr <= prev_account.get_nominal_icr(pool_state),
+                        && sorts_before(prev_nicr, &prev_account.key(), nicr, id),
                     BorrowerOpsError::InvalidTroveNeighbor
                 );
             }
         } else {
-            // `(prev, next)` is a valid insert position if they are adjacent nodes and `NICR` falls between the two nodes' NICRs
+            // `(prev, next)` is a valid insert position if they are adjacent nodes and the trove sorts between them
             let next_account = next.as_ref().unwrap();
             let prev_account = prev.as_ref().unwrap();
             if prev_account.next != next_account.key() {
@@ -188,8 +284,17 @@ impl Trove {
                 );
             }
             require!(
-                prev_account.get_nominal_icr(pool_state) >= nicr
-                    && nicr >= next_account.get_nominal_icr(pool_state),
+                sorts_before(
+                    prev_account.get_nominal_icr(pool_state),
+                    &prev_account.key(),
+                    nicr,
+                    id
+                ) && sorts_before(
+                    nicr,
+                    id,
+                    next_account.get_nominal_icr(pool_state),
+                    &next_account.key()
+                ),
                 BorrowerOpsError::InvalidTroveNeighbor
             );
         }
@@ -204,6 +309,34 @@ impl Trove {
         Ok(())
     }
 
+    /// A closed trove's account can be closed once its surplus is claimed and it is unlinked
+    pub fn require_account_closable(&self) -> Result<()> {
+        require!(
+            self.status != TroveStatus::NonExistent,
+            BorrowerOpsError::TroveAccountNotClosable
+        );
+        self.require_trove_not_active()?;
+        require!(
+            self.coll == 0
+                && self.debt == 0
+                && self.surplus_balance == 0
+                && self.gas_comp_owed == 0
+                && self.prev == Pubkey::default()
+                && self.next == Pubkey::default(),
+            BorrowerOpsError::TroveAccountNotClosable
+        );
+        Ok(())
+    }
+
+    /// A trove can't reopen while gas compensation from its redemption close is unpaid
+    pub fn require_no_gas_comp_owed(&self) -> Result<()> {
+        require!(
+            self.gas_comp_owed == 0,
+            BorrowerOpsError::GasCompensationOwed
+        );
+        Ok(())
+    }
+
     pub fn require_trove_active(&self) -> Result<()> {
         require!(
             self.status == TroveStatus::Active,
@@ -228,7 +361,7 @@ impl Trove {
         let stake = self.stake;
         u64::try_from(
             u128::from(stake)
-                .checked_mul(reward_per_unit_staked.into())
+                .checked_mul(reward_per_unit_staked)
                 .unwrap()
                 .checked_div(DECIMAL_PRECISION.into())
                 .unwrap(),
@@ -245,7 +378,7 @@ impl Trove {
         let stake = self.stake;
         u64::try_from(
             u128::from(stake)
-                .checked_mul(reward_per_unit_staked.into())
+                .checked_mul(reward_per_unit_staked)
                 .unwrap()
                 .checked_div(DECIMAL_PRECISION.into())
                 .unwrap(),
@@ -327,7 +460,11 @@ impl Trove {
     }
 
     pub fn update_stake_and_total_stakes(&mut self, pool_state: &mut PoolState) -> u64 {
-        let new_stake = self.compute_new_stake(pool_state, self.coll);
+        let new_stake = if self.coll_only {
+            0
+        } else {
+            self.compute_new_stake(pool_state, self.coll)
+        };
         let old_stake = self.stake;
         self.stake = new_stake;
         pool_state.total_stakes = pool_state
@@ -359,6 +496,12 @@ impl Trove {
         });
     }
 
+    pub fn clear_gas_comp_owed(&mut self) -> u64 {
+        let amount = self.gas_comp_owed;
+        self.gas_comp_owed = 0;
+        amount
+    }
+
     pub fn clear_surplus(&mut self) -> u64 {
         let amount = self.surplus_balance;
         self.surplus_balance = 0;
@@ -367,7 +510,7 @@ impl Trove {
             amount,
             to: self.creator
         });
-        return amount;
+        amount
     }
 
     pub fn insert_sorted(
@@ -378,6 +521,7 @@ impl Trove {
         next: &mut Option<Box<Account<'_, Self>>>,
         pool_state: &mut PoolState,
     ) -> Result<()> {
+        require!(nicr > 0, BorrowerOpsError::NICRZero);
         self.validate_head_tail(&id, nicr, prev, next, pool_state)?;
         if prev.is_none() && next.is_none() {
             // Insert trove as head and tail
@@ -567,11 +711,710 @@ impl Trove {
     }
 }
 
-pub fn find_trove_index(remaining_accounts: &[AccountInfo<'_>], id: Pubkey) -> Option<usize> {
-    for i in 0..remaining_accounts.len() {
-        if remaining_accounts[i].key() == id {
-            return Some(i);
+/// Position data of a trove in a segment of the sorted list
+#[derive(Clone)]
+pub struct NeighborNode {
+    pub key: Pubkey,
+    pub prev: Pubkey,
+    pub next: Pubkey,
+    pub nicr: u64,
+}
+
+/// Entry of a sorted list page, NICR includes pending rewards
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TrovePageEntry {
+    pub trove: Pubkey,
+    pub nicr: u64,
+}
+
+/// Checks `nodes` follow each other in the sorted list from `start`, and that a page shorter
+/// than `limit` ends at the tail
+pub fn get_trove_page(
+    start: Pubkey,
+    limit: usize,
+    nodes: &[NeighborNode],
+) -> Result<Vec<TrovePageEntry>> {
+    require!(nodes.len() <= limit, BorrowerOpsError::TooManyTroves);
+    require!(
+        nodes.first().map(|node| node.key) == Some(start),
+        BorrowerOpsError::InvalidTroveNeighbor
+    );
+    for pair in nodes.windows(2) {
+        require!(
+            pair[0].next == pair[1].key && pair[1].prev == pair[0].key,
+            BorrowerOpsError::InvalidTroveNeighbor
+        );
+    }
+    if nodes.len() < limit {
+        require!(
+            nodes[nodes.len() - 1].next == Pubkey::default(),
+            BorrowerOpsError::InvalidTroveNeighbor
+        );
+    }
+    Ok(nodes
+        .iter()
+        .map(|node| TrovePageEntry {
+            trove: node.key,
+            nicr: node.nicr,
+        })
+        .collect())
+}
+
+/// Whether a trove belongs closer to the head than another one. Higher NICRs come first and equal
+/// NICRs are ordered by pubkey, so every trove has a single valid position
+pub fn sorts_before(nicr: u64, key: &Pubkey, other_nicr: u64, other_key: &Pubkey) -> bool {
+    nicr > other_nicr || (nicr == other_nicr && key < other_key)
+}
+
+/// Finds where a trove with `nicr` belongs in `nodes`, a contiguous segment of the sorted list
+/// ordered from head to tail that does not contain the trove itself.
+/// Returns the indexes of the new `(prev, next)` neighbors, `None` standing for the head or tail end.
+pub fn find_insert_position(
+    id: &Pubkey,
+    nicr: u64,
+    nodes: &[NeighborNode],
+    head: Pubkey,
+    tail: Pubkey,
+) -> Option<(Option<usize>, Option<usize>)> {
+    let first = nodes.first()?;
+    let last = nodes.last()?;
+    if first.key == head && sorts_before(nicr, id, first.nicr, &first.key) {
+        return Some((None, Some(0)));
+    }
+    if last.key == tail && sorts_before(last.nicr, &last.key, nicr, id) {
+        return Some((Some(nodes.len() - 1), None));
+    }
+    for (i, pair) in nodes.windows(2).enumerate() {
+        let (prev, next) = (&pair[0], &pair[1]);
+        // The trove itself may still sit between the two nodes
+        let adjacent = (prev.next == next.key && next.prev == prev.key)
+            || (prev.next == *id && next.prev == *id);
+        if adjacent
+            && sorts_before(prev.nicr, &prev.key, nicr, id)
+            && sorts_before(nicr, id, next.nicr, &next.key)
+        {
+            return Some((Some(i), Some(i + 1)));
         }
     }
     None
 }
+
+pub fn find_trove_index(remaining_accounts: &[AccountInfo<'_>], id: Pubkey) -> Option<usize> {
+    remaining_accounts
+        .iter()
+        .position(|account| account.key() == id)
+}
+
+#[cfg(test)]
+pub mod trove_test {
+    use super::*;
+    use crate::constants::NICR_PRECISION;
+
+    /// Program-owned account holding `trove`. Its key, lamports and data are leaked so that
+    /// the account can be handed around like the ones of an instruction
+    pub fn load_trove_account<'a>(key: Pubkey, trove: &Trove) -> AccountInfo<'a> {
+        let mut data = vec![0u8; 8 + Trove::INIT_SPACE];
+        trove.try_serialize(&mut data.as_mut_slice()).unwrap();
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(0u64)),
+            Box::leak(data.into_boxed_slice()),
+            &ID,
+            false,
+            0,
+        )
+    }
+
+    pub fn read_trove_account(info: &AccountInfo) -> Trove {
+        Trove::try_deserialize(&mut &info.try_borrow_data().unwrap()[..]).unwrap()
+    }
+
+    #[test]
+    fn close_burns_gas_comp_from_vault() {
+        let trove = Trove::default();
+        assert_eq!(trove.get_close_burn_amounts(2_200, 200), (2_000, 200));
+    }
+
+    #[test]
+    fn icr_updated_after_add_coll() {
+        let pool_state = PoolState::default();
+        let price = 100_000_000_000;
+        let mut trove = Trove {
+            coll: 10_000_000_000,
+            debt: 500_000_000_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+
+        trove.update_from_adjustment(5_000_000_000, true, 0, false);
+        let event = trove.icr_updated(&pool_state, price, Operation::AdjustTrove);
+        // 15 coll at 100 against 500 debt
+        assert_eq!(event.icr, 3_000_000_000);
+        assert_eq!(
+            event.icr,
+            compute_cr(15_000_000_000, 500_000_000_000, price).unwrap()
+        );
+
+        trove.status = TroveStatus::ClosedByLiquidation;
+        let event = trove.icr_updated(&pool_state, price, Operation::LiquidateInNormalMode);
+        assert_eq!(event.icr, 0);
+    }
+
+    #[test]
+    fn liquidation_grace_period() {
+        let mut pool_state = PoolState {
+            mcr: 1_100_000_000,
+            ..Default::default()
+        };
+        let trove = Trove {
+            status: TroveStatus::Active,
+            created_at: 1_000_000,
+            ..Default::default()
+        };
+        let just_under_mcr = 1_090_000_000;
+
+        // Disabled by default
+        assert!(!trove.is_in_liquidation_grace_period(&pool_state, just_under_mcr, 1_000_000));
+
+        pool_state.liquidation_grace_period = 300;
+        assert!(trove.is_in_liquidation_grace_period(&pool_state, just_under_mcr, 1_000_000));
+        assert!(trove.is_in_liquidation_grace_period(&pool_state, just_under_mcr, 1_000_000 + 299));
+        assert!(!trove.is_in_liquidation_grace_period(
+            &pool_state,
+            just_under_mcr,
+            1_000_000 + 300
+        ));
+
+        // Under 100% the protocol has to act regardless
+        assert!(!trove.is_in_liquidation_grace_period(
+            &pool_state,
+            ONE_HUNDERED_PERCENT - 1,
+            1_000_000
+        ));
+    }
+
+    #[test]
+    fn close_burns_gas_comp_from_borrower() {
+        let trove = Trove {
+            gas_comp_held_by_borrower: true,
+            ..Default::default()
+        };
+        assert_eq!(trove.get_close_burn_amounts(2_200, 200), (2_200, 0));
+    }
+
+    #[test]
+    fn reopened_trove_starts_unlinked_and_keeps_surplus() {
+        let pool_state_key = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let mut pool_state = PoolState {
+            trove_size: 2,
+            ..Default::default()
+        };
+        let mut trove = Trove {
+            coll: 1_200_000_000,
+            debt: 1_000_000_000,
+            status: TroveStatus::Active,
+            prev: Pubkey::new_unique(),
+            next: Pubkey::new_unique(),
+            ..Default::default()
+        };
+
+        // Liquidated in recovery mode with coll left over, links not cleared
+        trove.account_surplus(100_000_000);
+        trove
+            .close_trove(&mut pool_state, TroveStatus::ClosedByLiquidation)
+            .unwrap();
+
+        trove.init(pool_state_key, creator, 2_000_000_000, 1_200_000_000);
+        assert!(trove.status == TroveStatus::Active);
+        assert_eq!(trove.prev, Pubkey::default());
+        assert_eq!(trove.next, Pubkey::default());
+        assert_eq!(trove.snapshot_coll_reward, 0);
+        assert_eq!(trove.surplus_balance, 100_000_000);
+
+        // The surplus is claimed apart from the new trove's coll
+        assert_eq!(trove.clear_surplus(), 100_000_000);
+        assert_eq!(trove.coll, 2_000_000_000);
+    }
+
+    #[test]
+    fn liquidated_trove_account_closable_after_surplus_claim() {
+        let mut pool_state = PoolState {
+            trove_size: 2,
+            ..Default::default()
+        };
+        let mut trove = Trove {
+            coll: 1_200_000_000,
+            debt: 1_000_000_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+        assert_eq!(
+            trove.require_account_closable().unwrap_err(),
+            BorrowerOpsError::TroveIsActive.into()
+        );
+
+        trove.account_surplus(100_000_000);
+        trove
+            .close_trove(&mut pool_state, TroveStatus::ClosedByLiquidation)
+            .unwrap();
+        assert_eq!(
+            trove.require_account_closable().unwrap_err(),
+            BorrowerOpsError::TroveAccountNotClosable.into()
+        );
+
+        assert_eq!(trove.clear_surplus(), 100_000_000);
+        trove.require_account_closable().unwrap();
+
+        // Still linked in the sorted list
+        trove.next = Pubkey::new_unique();
+        assert_eq!(
+            trove.require_account_closable().unwrap_err(),
+            BorrowerOpsError::TroveAccountNotClosable.into()
+        );
+    }
+
+    #[test]
+    fn debt_increase_cooldown() {
+        let mut pool_state = PoolState::default();
+        let mut trove = Trove {
+            last_debt_increase_time: 1_000_000,
+            ..Default::default()
+        };
+
+        // Disabled by default
+        assert!(trove
+            .require_debt_increase_cooldown_elapsed(&pool_state, 1_000_000)
+            .is_ok());
+
+        // Second increase within the window
+        pool_state.debt_increase_cooldown = 600;
+        assert_eq!(
+            trove
+                .require_debt_increase_cooldown_elapsed(&pool_state, 1_000_000 + 599)
+                .unwrap_err(),
+            BorrowerOpsError::DebtIncreaseCooldownNotElapsed.into()
+        );
+
+        // Increase after the window, which restarts it
+        assert!(trove
+            .require_debt_increase_cooldown_elapsed(&pool_state, 1_000_000 + 600)
+            .is_ok());
+        trove.last_debt_increase_time = 1_000_000 + 600;
+        assert!(trove
+            .require_debt_increase_cooldown_elapsed(&pool_state, 1_000_000 + 900)
+            .is_err());
+    }
+
+    #[test]
+    fn coll_only_trove_unstaked_until_first_debt() {
+        let mut pool_state = PoolState {
+            gas_compensation: 200,
+            ..Default::default()
+        };
+        let mut trove = Trove {
+            coll: 5_000,
+            coll_only: true,
+            ..Default::default()
+        };
+
+        assert_eq!(trove.update_stake_and_total_stakes(&mut pool_state), 0);
+        assert_eq!(pool_state.total_stakes, 0);
+        assert_eq!(trove.get_nominal_icr(&pool_state), u64::MAX);
+        assert_eq!(trove.get_close_burn_amounts(0, 200), (0, 0));
+        assert_eq!(trove.get_first_debt_gas_compensation(false, 200), 0);
+        assert_eq!(trove.get_first_debt_gas_compensation(true, 200), 200);
+
+        // Adjusted into a trove with debt, the gas compensation is held by the borrower
+        trove.update_from_adjustment(0, true, 2_200, true);
+        trove.coll_only = false;
+        trove.gas_comp_held_by_borrower = true;
+        assert_eq!(trove.update_stake_and_total_stakes(&mut pool_state), 5_000);
+        assert_eq!(pool_state.total_stakes, 5_000);
+        assert_eq!(trove.get_first_debt_gas_compensation(true, 200), 0);
+        assert_eq!(trove.get_close_burn_amounts(2_200, 200), (2_200, 0));
+    }
+
+    #[test]
+    fn coll_only_trove_inserted_at_head() {
+        let id = Pubkey::new_unique();
+        let nodes = load_segment(id, &[u64::MAX, 500, 400]);
+        let head = nodes[0].key;
+
+        // Next to the other collateral-only trove
+        assert_eq!(
+            find_insert_position(&id, u64::MAX, &nodes, head, Pubkey::default()),
+            Some((None, Some(0)))
+        );
+        // Troves with debt stay behind collateral-only troves
+        assert_eq!(
+            find_insert_position(&id, 600, &nodes, head, Pubkey::default()),
+            Some((Some(0), Some(1)))
+        );
+    }
+
+    #[test]
+    fn insert_sorted_rejects_zero_nicr() {
+        let id = Pubkey::new_unique();
+        let mut pool_state = PoolState::default();
+        let mut trove = Trove::default();
+
+        assert_eq!(
+            trove
+                .insert_sorted(id, 0, &mut None, &mut None, &mut pool_state)
+                .unwrap_err(),
+            BorrowerOpsError::NICRZero.into()
+        );
+        assert_eq!(pool_state.trove_head, Pubkey::default());
+
+        trove
+            .insert_sorted(id, 1, &mut None, &mut None, &mut pool_state)
+            .unwrap();
+        assert_eq!(pool_state.trove_head, id);
+        assert_eq!(pool_state.trove_tail, id);
+    }
+
+    #[test]
+    fn trove_count_capped() {
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let mut pool_state = PoolState {
+            max_troves: 1,
+            ..Default::default()
+        };
+        let mut trove = Trove::default();
+
+        pool_state.require_trove_count_below_cap().unwrap();
+        trove
+            .insert_sorted(keys[0], 100, &mut None, &mut None, &mut pool_state)
+            .unwrap();
+        assert_eq!(
+            pool_state.require_trove_count_below_cap().unwrap_err(),
+            BorrowerOpsError::TroveCountAtMax.into()
+        );
+
+        // Closing the trove frees its slot
+        trove
+            .remove_sorted(keys[0], &mut None, &mut None, &mut pool_state)
+            .unwrap();
+        pool_state.require_trove_count_below_cap().unwrap();
+        trove
+            .insert_sorted(keys[1], 100, &mut None, &mut None, &mut pool_state)
+            .unwrap();
+        assert_eq!(pool_state.trove_head, keys[1]);
+
+        // Unlimited when 0
+        pool_state.max_troves = 0;
+        pool_state.require_trove_count_below_cap().unwrap();
+    }
+
+    #[test]
+    fn surplus_is_cleared_once() {
+        let mut trove = Trove::default();
+        assert_eq!(trove.clear_surplus(), 0);
+
+        trove.account_surplus(300);
+        trove.account_surplus(200);
+        assert_eq!(trove.clear_surplus(), 500);
+        assert_eq!(trove.clear_surplus(), 0);
+    }
+
+    fn load_segment(id: Pubkey, nicrs: &[u64]) -> Vec<NeighborNode> {
+        let keys: Vec<Pubkey> = nicrs.iter().map(|_| Pubkey::new_unique()).collect();
+        nicrs
+            .iter()
+            .enumerate()
+            .map(|(i, &nicr)| NeighborNode {
+                key: keys[i],
+                prev: if i == 0 {
+                    Pubkey::default()
+                } else {
+                    keys[i - 1]
+                },
+                next: keys.get(i + 1).copied().unwrap_or(id),
+                nicr,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn insert_position_found_in_segment() {
+        let id = Pubkey::new_unique();
+        let nodes = load_segment(id, &[500, 400, 300, 200]);
+        let (head, tail) = (nodes[0].key, nodes[3].key);
+
+        assert_eq!(
+            find_insert_position(&id, 250, &nodes, head, Pubkey::default()),
+            Some((Some(2), Some(3)))
+        );
+        assert_eq!(
+            find_insert_position(&id, 600, &nodes, head, Pubkey::default()),
+            Some((None, Some(0)))
+        );
+        assert_eq!(
+            find_insert_position(&id, 100, &nodes, Pubkey::default(), tail),
+            Some((Some(3), None))
+        );
+        // Outside the segment
+        assert_eq!(
+            find_insert_position(&id, 100, &nodes, head, Pubkey::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn trove_page_from_head() {
+        let nodes = load_segment(Pubkey::default(), &[500, 400, 300, 200]);
+
+        assert_eq!(
+            get_trove_page(nodes[0].key, 2, &nodes[..2]).unwrap(),
+            vec![
+                TrovePageEntry {
+                    trove: nodes[0].key,
+                    nicr: 500
+                },
+                TrovePageEntry {
+                    trove: nodes[1].key,
+                    nicr: 400
+                },
+            ]
+        );
+        // Short page ending at the tail
+        assert_eq!(
+            get_trove_page(nodes[2].key, 3, &nodes[2..]).unwrap().len(),
+            2
+        );
+
+        // Not starting at `start`, skipping a trove, or short of the tail
+        assert_eq!(
+            get_trove_page(nodes[1].key, 2, &nodes[..2]).unwrap_err(),
+            BorrowerOpsError::InvalidTroveNeighbor.into()
+        );
+        assert_eq!(
+            get_trove_page(nodes[0].key, 2, &[nodes[0].clone(), nodes[2].clone()]).unwrap_err(),
+            BorrowerOpsError::InvalidTroveNeighbor.into()
+        );
+        assert_eq!(
+            get_trove_page(nodes[0].key, 3, &nodes[..2]).unwrap_err(),
+            BorrowerOpsError::InvalidTroveNeighbor.into()
+        );
+        assert_eq!(
+            get_trove_page(nodes[0].key, 1, &nodes[..2]).unwrap_err(),
+            BorrowerOpsError::TooManyTroves.into()
+        );
+    }
+
+    #[test]
+    fn equal_nicr_troves_ordered_by_pubkey() {
+        let mut keys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        keys.sort();
+        let nicr = NICR_PRECISION * 2;
+
+        for order in [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ] {
+            let mut list: Vec<Pubkey> = Vec::new();
+            for i in order {
+                let id = keys[i];
+                let nodes: Vec<NeighborNode> = list
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &key)| NeighborNode {
+                        key,
+                        prev: if j == 0 {
+                            Pubkey::default()
+                        } else {
+                            list[j - 1]
+                        },
+                        next: list.get(j + 1).copied().unwrap_or_default(),
+                        nicr,
+                    })
+                    .collect();
+                let index = match (list.first(), list.last()) {
+                    (Some(&head), Some(&tail)) => {
+                        match find_insert_position(&id, nicr, &nodes, head, tail).unwrap() {
+                            (_, Some(next)) => next,
+                            (Some(prev), None) => prev + 1,
+                            (None, None) => unreachable!(),
+                        }
+                    }
+                    _ => 0,
+                };
+                list.insert(index, id);
+            }
+            assert_eq!(list, keys);
+        }
+    }
+
+    #[test]
+    fn equal_nicr_neighbor_validated_by_pubkey() {
+        const UNIT: u64 = 1_000_000_000;
+        let mut keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        keys.sort();
+        let pool_state = PoolState {
+            trove_size: 1,
+            trove_head: keys[0],
+            trove_tail: keys[0],
+            ..Default::default()
+        };
+        let listed = Trove {
+            coll: 200 * UNIT,
+            debt: 100 * UNIT,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+        let info = load_trove_account(keys[0], &listed);
+        let neighbor = Some(Box::new(Account::<Trove>::try_from(&info).unwrap()));
+
+        // Same NICR as the listed trove, the higher pubkey goes after it
+        let nicr = listed.get_nominal_icr(&pool_state);
+        let trove = Trove::default();
+        assert_eq!(
+            trove
+                .validate_head_tail(&keys[1], nicr, &None, &neighbor, &pool_state)
+                .unwrap_err(),
+            BorrowerOpsError::InvalidTroveNeighbor.into()
+        );
+        trove
+            .validate_head_tail(&keys[1], nicr, &neighbor, &None, &pool_state)
+            .unwrap();
+    }
+
+    #[test]
+    fn insert_position_next_to_itself() {
+        let id = Pubkey::new_unique();
+        let mut nodes = load_segment(id, &[500, 300]);
+        // The trove currently sits between both nodes
+        nodes[0].next = id;
+        nodes[1].prev = id;
+
+        assert_eq!(
+            find_insert_position(&id, 400, &nodes, Pubkey::default(), Pubkey::default()),
+            Some((Some(0), Some(1)))
+        );
+    }
+
+    #[test]
+    fn insert_position_moved_by_pending_rewards() {
+        let id = Pubkey::new_unique();
+        let nodes = load_segment(
+            id,
+            &[
+                NICR_PRECISION * 3,
+                NICR_PRECISION * 3 / 2,
+                NICR_PRECISION,
+                NICR_PRECISION / 2,
+            ],
+        );
+        let trove = Trove {
+            coll: 1_000,
+            debt: 500,
+            stake: 1_000,
+            status: TroveStatus::Active,
+            ..Default::default()
+        };
+        // Redistribution of 100 coll and 500 debt over 1_000 stake
+        let pool_state = PoolState {
+            l_coll: u128::from(DECIMAL_PRECISION / 10),
+            l_usv_debt: u128::from(DECIMAL_PRECISION / 2),
+            ..Default::default()
+        };
+        let (head, tail) = (Pubkey::default(), Pubkey::default());
+
+        let stale_nicr = compute_nominal_cr(trove.coll, trove.debt).unwrap();
+        assert_eq!(
+            find_insert_position(&id, stale_nicr, &nodes, head, tail),
+            Some((Some(0), Some(1)))
+        );
+        let nicr = trove.get_nominal_icr(&pool_state);
+        assert_eq!(
+            find_insert_position(&id, nicr, &nodes, head, tail),
+            Some((Some(1), Some(2)))
+        );
+    }
+
+    #[test]
+    fn redemption_reinsert_rejects_wrong_neighbors() {
+        const UNIT: u64 = 1_000_000_000;
+        // head (300%) -> mid (200%) -> tail, the tail is redeemed up to a NICR of 250%
+        let keys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let new_nicr = 250 * NICR_PRECISION / 100;
+
+        for (prev, next, valid) in [
+            // Claims to be the new tail although its NICR is above the tail's
+            (Some(1), None, false),
+            // Claims to be the new head although its NICR is below the head's
+            (None, Some(0), false),
+            (Some(0), Some(1), true),
+        ] {
+            let mut pool_state = PoolState {
+                trove_size: 3,
+                trove_head: keys[0],
+                trove_tail: keys[2],
+                ..Default::default()
+            };
+            let mut redeemed = Trove {
+                coll: 250 * UNIT,
+                debt: 100 * UNIT,
+                status: TroveStatus::Active,
+                prev: keys[1],
+                ..Default::default()
+            };
+            let accounts: Vec<AccountInfo> = [
+                (300 * UNIT, Pubkey::default(), keys[1]),
+                (200 * UNIT, keys[0], keys[2]),
+            ]
+            .iter()
+            .zip(keys)
+            .map(|(&(coll, prev, next), key)| {
+                let trove = Trove {
+                    coll,
+                    debt: 100 * UNIT,
+                    status: TroveStatus::Active,
+                    prev,
+                    next,
+                    ..Default::default()
+                };
+                load_trove_account(key, &trove)
+            })
+            .collect();
+            let mut new_prev =
+                prev.map(|i| Box::new(Account::<Trove>::try_from(&accounts[i]).unwrap()));
+            let mut new_next =
+                next.map(|i| Box::new(Account::<Trove>::try_from(&accounts[i]).unwrap()));
+
+            let result = redeemed.re_insert_redemption(
+                &accounts,
+                keys[2],
+                new_nicr,
+                &mut new_prev,
+                &mut new_next,
+                &mut pool_state,
+            );
+            if valid {
+                result.unwrap();
+                assert_eq!((redeemed.prev, redeemed.next), (keys[0], keys[1]));
+                assert_eq!(pool_state.trove_tail, keys[1]);
+            } else {
+                assert_eq!(
+                    result.unwrap_err(),
+                    BorrowerOpsError::InvalidTroveNeighbor.into()
+                );
+            }
+        }
+    }
+}
@@ -1,17 +1,21 @@
 use crate::{
     constants::{
-        BORROWING_FEE_FLOOR, DECIMAL_PRECISION, FEED_DECIMAL_PRECISION, MAX_CONFIDENCE_RATE,
-        MAX_PRICE_DIFFERENCE_BETWEEN_ORACLES, REDEMPTION_FEE_FLOOR, TIMEOUT,
+        BORROWING_FEE_FLOOR, COLLATERAL_DECIMALS, DECIMAL_PRECISION, FEED_DECIMAL_PRECISION,
+        MAX_PRICE_DIFFERENCE_BETWEEN_ORACLES, REDEMPTION_FEE_FLOOR, STABLECOIN_DECIMALS, TIMEOUT,
     },
-    errors::{BorrowerOpsError, PriceFeedError},
+    errors::{BorrowerOpsError, PoolConfigError, PriceFeedError, StabilityPoolError},
     state::CommunityIssuanceConfig,
 };
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
 use spl_stake_pool::state::StakePool;
 
 use chainlink_solana as chainlink;
 use pyth_solana_receiver_sdk::price_update::Price;
 use std::cmp;
+use switchboard_solana::AggregatorAccountData;
 
 pub fn get_current_timestamp() -> u64 {
     Clock::get().unwrap().unix_timestamp.try_into().unwrap()
@@ -35,8 +39,12 @@ pub fn require_valid_borrow_max_fee_percentage(
         );
     } else {
         require!(
-            max_fee_percentage >= BORROWING_FEE_FLOOR && max_fee_percentage <= DECIMAL_PRECISION,
-            BorrowerOpsError::InvalidMaxFee
+            max_fee_percentage >= BORROWING_FEE_FLOOR,
+            BorrowerOpsError::MaxFeeBelowFloor
+        );
+        require!(
+            max_fee_percentage <= DECIMAL_PRECISION,
+            BorrowerOpsError::MaxFeeAboveMax
         );
     }
     Ok(())
@@ -44,7 +52,7 @@ pub fn require_valid_borrow_max_fee_percentage(
 
 pub fn require_valid_redeem_max_fee_percentage(max_fee_percentage: u64) -> Result<()> {
     require!(
-        max_fee_percentage >= REDEMPTION_FEE_FLOOR && max_fee_percentage <= DECIMAL_PRECISION,
+        (REDEMPTION_FEE_FLOOR..=DECIMAL_PRECISION).contains(&max_fee_percentage),
         BorrowerOpsError::InvalidRedeemMaxFee
     );
     Ok(())
@@ -55,6 +63,11 @@ pub fn require_non_zero_redeem_amount(amount: u64) -> Result<()> {
     Ok(())
 }
 
+pub fn require_non_zero_price(price: u64) -> Result<()> {
+    require!(price > 0, PriceFeedError::ZeroPrice);
+    Ok(())
+}
+
 pub fn require_non_zero_debt_change(usv_change: u64) -> Result<()> {
     require!(usv_change > 0, BorrowerOpsError::ZeroDebtChange);
     Ok(())
@@ -101,24 +114,136 @@ pub fn require_sufficient_usv_balance(balance: u64, payment: u64) -> Result<()>
     Ok(())
 }
 
-// Price feed utilities
-pub fn get_jitosol_rate(acc_data: &mut &[u8]) -> Result<u64> {
-    let pool_state = StakePool::deserialize(acc_data).unwrap();
+/// Rejects neighbor hints whose in-memory copies would overwrite each other on exit.
+/// A current neighbor may still equal a new neighbor: `re_insert` writes the current
+/// neighbors back before the new ones are reloaded, and the new ones are written last.
+pub fn require_distinct_trove_neighbors(
+    trove: Pubkey,
+    cur_prev: Option<Pubkey>,
+    cur_next: Option<Pubkey>,
+    new_prev: Option<Pubkey>,
+    new_next: Option<Pubkey>,
+) -> Result<()> {
+    let neighbors = [cur_prev, cur_next, new_prev, new_next];
+    require!(
+        !neighbors.contains(&Some(trove)),
+        BorrowerOpsError::DuplicateTroveNeighbor
+    );
+    require!(
+        cur_prev.is_none() || cur_prev != cur_next,
+        BorrowerOpsError::DuplicateTroveNeighbor
+    );
     require!(
-        pool_state.last_update_epoch == Clock::get()?.epoch,
-        PriceFeedError::PoolNotUpdated
+        new_prev.is_none() || new_prev != new_next,
+        BorrowerOpsError::DuplicateTroveNeighbor
     );
+    Ok(())
+}
+
+/// Redemption rewrites `remaining_accounts` troves through their raw data, so the re-insert
+/// neighbors held as Anchor accounts must not be one of them, or either copy would overwrite the other
+pub fn require_neighbors_outside_segment(
+    segment: &[Pubkey],
+    prev: Option<Pubkey>,
+    next: Option<Pubkey>,
+) -> Result<()> {
+    require!(
+        [prev, next]
+            .iter()
+            .flatten()
+            .all(|neighbor| !segment.contains(neighbor)),
+        BorrowerOpsError::InvalidAccount
+    );
+    Ok(())
+}
+
+/// The lowest trove must be the list tail, and must be omitted only when the list is empty
+pub fn require_valid_lowest_trove(lowest_trove: Option<Pubkey>, trove_tail: Pubkey) -> Result<()> {
+    match lowest_trove {
+        Some(lowest_trove) => require!(
+            trove_tail != Pubkey::default() && lowest_trove == trove_tail,
+            StabilityPoolError::InvalidLowestTrove
+        ),
+        None => require!(
+            trove_tail == Pubkey::default(),
+            StabilityPoolError::InvalidLowestTrove
+        ),
+    }
+    Ok(())
+}
+
+/// ICR math assumes collateral and stablecoin amounts share `TARGET_DECIMAL_PRECISION`
+pub fn require_valid_mint_decimals(collateral_decimals: u8, stablecoin_decimals: u8) -> Result<()> {
+    require!(
+        collateral_decimals == COLLATERAL_DECIMALS,
+        PoolConfigError::CollateralDecimalsMismatch
+    );
+    require!(
+        stablecoin_decimals == STABLECOIN_DECIMALS,
+        PoolConfigError::StablecoinDecimalsMismatch
+    );
+    Ok(())
+}
+
+pub fn require_min_coll_out(coll_out: u64, min_coll_out: u64) -> Result<()> {
+    require!(coll_out >= min_coll_out, BorrowerOpsError::CollOutBelowMin);
+    Ok(())
+}
+
+/// Grows a program account to `new_len`, zero-filling the new bytes and topping up rent from `payer`.
+/// Accounts already at least `new_len` long are left untouched.
+pub fn realloc_zeroed<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    new_len: usize,
+) -> Result<()> {
+    if account.data_len() >= new_len {
+        return Ok(());
+    }
+
+    let rent_exempt = Rent::get()?.minimum_balance(new_len);
+    let top_up = rent_exempt.saturating_sub(account.lamports());
+    if top_up > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    account.realloc(new_len, true)?;
+    Ok(())
+}
+
+/// Stake pool accounts are optional for borrowers depositing jitoSOL directly
+pub fn require_stake_account<'a, 'info>(
+    account: &'a Option<UncheckedAccount<'info>>,
+) -> Result<&'a UncheckedAccount<'info>> {
+    account
+        .as_ref()
+        .ok_or_else(|| BorrowerOpsError::MissingStakeAccount.into())
+}
+
+// Price feed utilities
+/// Returns the JitoSol rate with the epoch the stake pool was last updated in
+pub fn get_jitosol_rate(acc_data: &mut &[u8]) -> Result<(u64, u64)> {
+    let pool_state = StakePool::deserialize(acc_data).unwrap();
     let rate = pool_state
         .calc_pool_tokens_for_deposit(100_000_000)
         .unwrap();
-    Ok(rate)
+    Ok((rate, pool_state.last_update_epoch))
 }
 
 pub fn get_current_timestamp_i64() -> Result<i64> {
     if cfg!(test) {
         return Ok(1_000_000);
     }
-    Ok(Clock::get()?.unix_timestamp.try_into().unwrap())
+    Ok(Clock::get()?.unix_timestamp)
 }
 
 pub fn is_pyth_broken(msg: &Price) -> bool {
@@ -134,7 +259,13 @@ pub fn is_pyth_broken(msg: &Price) -> bool {
 }
 
 pub fn is_chainlink_broken(round: &chainlink::Round) -> bool {
-    if round.answer == 0 || round.round_id == 0 || round.slot == 0 || round.timestamp == 0 {
+    let current_timestamp = get_current_timestamp_i64().unwrap();
+    if round.answer <= 0
+        || round.round_id == 0
+        || round.slot == 0
+        || round.timestamp == 0
+        || current_timestamp < round.timestamp.into()
+    {
         return true;
     }
     false
@@ -148,15 +279,112 @@ pub fn is_pyth_frozen(msg: &Price) -> bool {
     elapsed > TIMEOUT
 }
 
+/// A round from the future is not frozen, `is_chainlink_broken` reports it
 pub fn is_chainlink_frozen(round: &chainlink::Round) -> bool {
     let current_timestamp = get_current_timestamp_i64().unwrap();
-    let elapsed = i64::from(current_timestamp)
-        .checked_sub(round.timestamp.into())
-        .expect("underflow");
+    let elapsed = current_timestamp.saturating_sub(round.timestamp.into());
     elapsed > TIMEOUT
 }
 
-pub fn pyth_price_conf_interval_above_max(msg: &Price) -> bool {
+/// Response of the fallback oracle checked against Pyth
+pub trait SecondaryOracleResponse {
+    fn is_broken(&self) -> bool;
+    fn is_frozen(&self) -> bool;
+    /// Answer with `FEED_DECIMAL_PRECISION` decimals
+    fn answer(&self) -> u64;
+    fn timestamp(&self) -> i64;
+}
+
+impl SecondaryOracleResponse for chainlink::Round {
+    fn is_broken(&self) -> bool {
+        is_chainlink_broken(self)
+    }
+
+    fn is_frozen(&self) -> bool {
+        is_chainlink_frozen(self)
+    }
+
+    fn answer(&self) -> u64 {
+        u64::try_from(self.answer).unwrap()
+    }
+
+    fn timestamp(&self) -> i64 {
+        self.timestamp.into()
+    }
+}
+
+/// Latest confirmed round of a Switchboard aggregator
+#[derive(Clone, Copy, Debug)]
+pub struct SwitchboardResponse {
+    /// Result with `FEED_DECIMAL_PRECISION` decimals
+    pub value: i128,
+    pub slot: u64,
+    pub timestamp: i64,
+}
+
+impl SwitchboardResponse {
+    pub fn new(mantissa: i128, scale: u32, slot: u64, timestamp: i64) -> Self {
+        let feed_scale = FEED_DECIMAL_PRECISION.ilog10();
+        // Switchboard decimals have a scale of at most 28
+        let value = if scale > feed_scale {
+            mantissa / 10i128.pow(scale - feed_scale)
+        } else {
+            mantissa.saturating_mul(10i128.pow(feed_scale - scale))
+        };
+        Self {
+            value,
+            slot,
+            timestamp,
+        }
+    }
+
+    /// A feed without a valid result reads as a broken response, so that Pyth can still be used
+    pub fn from_aggregator(aggregator: &AccountInfo) -> Result<Self> {
+        require!(
+            *aggregator.owner == *switchboard_solana::SWITCHBOARD_PROGRAM_ID,
+            PriceFeedError::SwitchboardWrongFeed
+        );
+        let data = aggregator.try_borrow_data()?;
+        let aggregator = AggregatorAccountData::new_from_bytes(&data)?;
+        let result = match aggregator.get_result() {
+            Ok(result) => result,
+            Err(_) => return Ok(Self::new(0, 0, 0, 0)),
+        };
+        let round = &aggregator.latest_confirmed_round;
+        Ok(Self::new(
+            result.mantissa,
+            result.scale,
+            round.round_open_slot,
+            round.round_open_timestamp,
+        ))
+    }
+}
+
+impl SecondaryOracleResponse for SwitchboardResponse {
+    fn is_broken(&self) -> bool {
+        self.value <= 0
+            || self.slot == 0
+            || self.timestamp == 0
+            || get_current_timestamp_i64().unwrap() < self.timestamp
+    }
+
+    fn is_frozen(&self) -> bool {
+        let current_timestamp = get_current_timestamp_i64().unwrap();
+        let elapsed = current_timestamp.saturating_sub(self.timestamp);
+        elapsed > TIMEOUT
+    }
+
+    fn answer(&self) -> u64 {
+        u64::try_from(self.value).unwrap()
+    }
+
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+/// `max_confidence_rate` in `FEED_DECIMAL_PRECISION`
+pub fn pyth_price_conf_interval_above_max(msg: &Price, max_confidence_rate: u64) -> bool {
     let conf = msg.conf;
     let price = u64::try_from(msg.price).unwrap();
 
@@ -168,22 +396,22 @@ pub fn pyth_price_conf_interval_above_max(msg: &Price) -> bool {
             .unwrap(),
     )
     .unwrap();
-    conf_rate > MAX_CONFIDENCE_RATE
+    conf_rate > max_confidence_rate
 }
 
-pub fn both_oracles_live_unbroken_similar_price(
+pub fn both_oracles_live_unbroken_similar_price<R: SecondaryOracleResponse>(
     pyth_res: &Price,
-    chainlink_res: &chainlink::Round,
-    chainlink_price: u64,
+    secondary_res: &R,
+    secondary_price: u64,
 ) -> bool {
-    if is_chainlink_broken(chainlink_res)
-        || is_chainlink_frozen(chainlink_res)
+    if secondary_res.is_broken()
+        || secondary_res.is_frozen()
         || is_pyth_broken(pyth_res)
         || is_pyth_frozen(pyth_res)
     {
         return false;
     }
-    both_oracles_similar_price(pyth_res.price.try_into().unwrap(), chainlink_price)
+    both_oracles_similar_price(pyth_res.price.try_into().unwrap(), secondary_price)
 }
 
 pub fn both_oracles_similar_price(pyth_price: u64, chainlink_price: u64) -> bool {
@@ -202,10 +430,191 @@ pub fn both_oracles_similar_price(pyth_price: u64, chainlink_price: u64) -> bool
 
 #[cfg(test)]
 pub mod utils_test {
+    use anchor_lang::{
+        solana_program::{
+            entrypoint::{MAX_PERMITTED_DATA_INCREASE, SUCCESS},
+            instruction::Instruction,
+            program_option::COption,
+            program_pack::Pack,
+            program_stubs::{set_syscall_stubs, SyscallStubs},
+            program_utils::limited_deserialize,
+            system_instruction::SystemInstruction,
+        },
+        Discriminator,
+    };
+    use anchor_spl::token::spl_token::{
+        self,
+        state::{Account as SplTokenAccount, AccountState, Mint as SplMint},
+    };
     use chainlink::Round;
 
     use super::*;
 
+    /// Runs the token and system program CPIs of a handler against the accounts passed in, and
+    /// provides the default clock and rent. The default stubs only log CPIs and have no sysvars.
+    struct ProgramTestStubs;
+
+    impl SyscallStubs for ProgramTestStubs {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Clock) = Clock::default() };
+            SUCCESS
+        }
+
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Rent) = Rent::default() };
+            SUCCESS
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> std::result::Result<(), ProgramError> {
+            // The runtime checks the signer seeds, the metas are trusted here
+            let accounts: Vec<AccountInfo> = instruction
+                .accounts
+                .iter()
+                .map(|meta| {
+                    let mut info = account_infos
+                        .iter()
+                        .find(|info| info.key == &meta.pubkey)
+                        .unwrap()
+                        .clone();
+                    info.is_signer = meta.is_signer;
+                    info
+                })
+                .collect();
+
+            if instruction.program_id == spl_token::ID {
+                spl_token::processor::Processor::process(
+                    &instruction.program_id,
+                    &accounts,
+                    &instruction.data,
+                )
+            } else if instruction.program_id == System::id() {
+                match limited_deserialize(&instruction.data, 1_024).unwrap() {
+                    SystemInstruction::Transfer { lamports } => {
+                        **accounts[0].try_borrow_mut_lamports()? -= lamports;
+                        **accounts[1].try_borrow_mut_lamports()? += lamports;
+                        Ok(())
+                    }
+                    _ => unimplemented!(),
+                }
+            } else {
+                unimplemented!()
+            }
+        }
+    }
+
+    pub fn set_program_test_stubs() {
+        set_syscall_stubs(Box::new(ProgramTestStubs));
+    }
+
+    pub fn load_account(key: Pubkey, owner: Pubkey, data: Vec<u8>) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(0u64)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(owner)),
+            false,
+            0,
+        )
+    }
+
+    pub fn load_anchor_account<T: AccountSerialize>(
+        key: Pubkey,
+        account: &T,
+    ) -> AccountInfo<'static> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        load_account(key, crate::ID, data)
+    }
+
+    /// Program account laid out like in the runtime's input buffer: the original data length
+    /// precedes the key and the current one the data, which can grow by `MAX_PERMITTED_DATA_INCREASE`
+    pub fn load_reallocable_account(
+        key: Pubkey,
+        lamports: u64,
+        data: &[u8],
+    ) -> AccountInfo<'static> {
+        #[repr(C)]
+        struct SerializedKey {
+            original_data_len: u32,
+            key: Pubkey,
+        }
+        let serialized_key = Box::leak(Box::new(SerializedKey {
+            original_data_len: data.len() as u32,
+            key,
+        }));
+        let words = Box::leak(
+            vec![0u64; 1 + (data.len() + MAX_PERMITTED_DATA_INCREASE).div_ceil(8)]
+                .into_boxed_slice(),
+        );
+        words[0] = data.len() as u64;
+        let buffer = unsafe {
+            std::slice::from_raw_parts_mut(words.as_mut_ptr().add(1) as *mut u8, data.len())
+        };
+        buffer.copy_from_slice(data);
+        AccountInfo::new(
+            &serialized_key.key,
+            false,
+            true,
+            Box::leak(Box::new(lamports)),
+            buffer,
+            Box::leak(Box::new(crate::ID)),
+            false,
+            0,
+        )
+    }
+
+    pub fn load_signer(key: Pubkey, lamports: u64) -> AccountInfo<'static> {
+        let mut info = load_account(key, System::id(), vec![]);
+        info.is_signer = true;
+        **info.lamports.borrow_mut() = lamports;
+        info
+    }
+
+    pub fn load_program(key: Pubkey) -> AccountInfo<'static> {
+        let mut info = load_account(key, Pubkey::default(), vec![]);
+        info.is_writable = false;
+        info.executable = true;
+        info
+    }
+
+    pub fn load_mint(mint_authority: Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; SplMint::LEN];
+        SplMint {
+            mint_authority: COption::Some(mint_authority),
+            decimals: 9,
+            is_initialized: true,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    pub fn load_token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Vec<u8> {
+        let mut data = vec![0u8; SplTokenAccount::LEN];
+        SplTokenAccount {
+            mint,
+            owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        data
+    }
+
+    pub fn read_token_amount(info: &AccountInfo) -> u64 {
+        SplTokenAccount::unpack(&info.try_borrow_data().unwrap())
+            .unwrap()
+            .amount
+    }
+
     fn load_price_message(price: i64, conf: u64, publish_time: i64) -> Price {
         Price {
             price,
@@ -224,6 +633,23 @@ pub mod utils_test {
         }
     }
 
+    #[test]
+    fn borrow_max_fee_bounds() {
+        assert_eq!(
+            require_valid_borrow_max_fee_percentage(0, false).unwrap_err(),
+            BorrowerOpsError::MaxFeeBelowFloor.into()
+        );
+        assert_eq!(
+            require_valid_borrow_max_fee_percentage(2 * DECIMAL_PRECISION, false).unwrap_err(),
+            BorrowerOpsError::MaxFeeAboveMax.into()
+        );
+        assert!(require_valid_borrow_max_fee_percentage(BORROWING_FEE_FLOOR, false).is_ok());
+        assert!(require_valid_borrow_max_fee_percentage(DECIMAL_PRECISION, false).is_ok());
+
+        // Recovery mode has no floor
+        assert!(require_valid_borrow_max_fee_percentage(0, true).is_ok());
+    }
+
     #[test]
     fn is_pyth_broken_test() {
         let price_zero = load_price_message(0, 1, 1);
@@ -235,7 +661,7 @@ pub mod utils_test {
         assert!(is_pyth_broken(&conf_zero));
         assert!(is_pyth_broken(&time_zero));
         assert!(is_pyth_broken(&from_future));
-        assert!(is_pyth_broken(&non_zero) == false);
+        assert!(!is_pyth_broken(&non_zero));
     }
 
     #[test]
@@ -243,7 +669,7 @@ pub mod utils_test {
         let outdated_msg = load_price_message(1, 1, 1_000_000 - TIMEOUT - 1);
         let valid_msg = load_price_message(1, 1, 1_000_000);
         assert!(is_pyth_frozen(&outdated_msg));
-        assert!(is_pyth_frozen(&valid_msg) == false);
+        assert!(!is_pyth_frozen(&valid_msg));
     }
 
     #[test]
@@ -258,7 +684,7 @@ pub mod utils_test {
         assert!(is_chainlink_broken(&round_id_zero));
         assert!(is_chainlink_broken(&timestamp_zero));
         assert!(is_chainlink_broken(&slot_zero));
-        assert!(is_chainlink_broken(&valid_msg) == false);
+        assert!(!is_chainlink_broken(&valid_msg));
     }
 
     #[test]
@@ -267,6 +693,164 @@ pub mod utils_test {
             load_chainlink_response(1, 1, 1_000_000 - u32::try_from(TIMEOUT).unwrap() - 1, 1);
         let valid_msg = load_chainlink_response(1, 1, 1_000_000, 1);
         assert!(is_chainlink_frozen(&outdated_msg));
-        assert!(is_chainlink_frozen(&valid_msg) == false);
+        assert!(!is_chainlink_frozen(&valid_msg));
+    }
+
+    #[test]
+    fn chainlink_round_from_future_is_broken() {
+        let from_future = load_chainlink_response(1, 1, 1_000_001, 1);
+        assert!(is_chainlink_broken(&from_future));
+        assert!(!is_chainlink_frozen(&from_future));
+
+        let from_far_future = load_chainlink_response(1, 1, u32::MAX, 1);
+        assert!(is_chainlink_broken(&from_far_future));
+        assert!(!is_chainlink_frozen(&from_far_future));
+    }
+
+    #[test]
+    fn switchboard_feed_without_result_is_broken() {
+        let aggregator = AggregatorAccountData {
+            min_oracle_results: 1,
+            ..Default::default()
+        };
+        let mut data = AggregatorAccountData::discriminator().to_vec();
+        data.extend_from_slice(anchor_lang::__private::bytemuck::bytes_of(&aggregator));
+        let key = Pubkey::new_unique();
+        let owner = *switchboard_solana::SWITCHBOARD_PROGRAM_ID;
+        let mut lamports = 0u64;
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+
+        // Not enough oracle results in the latest round
+        assert!(
+            AggregatorAccountData::new_from_bytes(&info.try_borrow_data().unwrap())
+                .unwrap()
+                .get_result()
+                .is_err()
+        );
+        assert!(SwitchboardResponse::from_aggregator(&info)
+            .unwrap()
+            .is_broken());
+    }
+
+    #[test]
+    fn chainlink_negative_answer_is_broken() {
+        assert!(is_chainlink_broken(&load_chainlink_response(
+            1, 1, 1_000_000, -1
+        )));
+    }
+
+    #[test]
+    fn require_valid_mint_decimals_test() {
+        assert!(require_valid_mint_decimals(9, 9).is_ok());
+        assert_eq!(
+            require_valid_mint_decimals(6, 9).unwrap_err(),
+            PoolConfigError::CollateralDecimalsMismatch.into()
+        );
+        assert_eq!(
+            require_valid_mint_decimals(9, 6).unwrap_err(),
+            PoolConfigError::StablecoinDecimalsMismatch.into()
+        );
+    }
+
+    #[test]
+    fn require_distinct_trove_neighbors_test() {
+        let trove = Pubkey::new_unique();
+        let a = Some(Pubkey::new_unique());
+        let b = Some(Pubkey::new_unique());
+
+        assert!(require_distinct_trove_neighbors(trove, None, None, None, None).is_ok());
+        assert!(require_distinct_trove_neighbors(trove, a, b, a, b).is_ok());
+        // Moving past the current next neighbor
+        assert!(require_distinct_trove_neighbors(trove, None, a, a, b).is_ok());
+
+        let duplicate = BorrowerOpsError::DuplicateTroveNeighbor.into();
+        assert_eq!(
+            require_distinct_trove_neighbors(trove, a, a, None, None).unwrap_err(),
+            duplicate
+        );
+        assert_eq!(
+            require_distinct_trove_neighbors(trove, None, None, b, b).unwrap_err(),
+            duplicate
+        );
+        assert_eq!(
+            require_distinct_trove_neighbors(trove, a, b, Some(trove), None).unwrap_err(),
+            duplicate
+        );
+    }
+
+    #[test]
+    fn redemption_neighbors_outside_segment() {
+        let segment = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let outside = Some(Pubkey::new_unique());
+
+        assert!(require_neighbors_outside_segment(&segment, None, None).is_ok());
+        assert!(require_neighbors_outside_segment(&segment, outside, None).is_ok());
+
+        // A neighbor also passed in the remaining accounts
+        assert_eq!(
+            require_neighbors_outside_segment(&segment, outside, Some(segment[2])).unwrap_err(),
+            BorrowerOpsError::InvalidAccount.into()
+        );
+        assert_eq!(
+            require_neighbors_outside_segment(&segment, Some(segment[0]), None).unwrap_err(),
+            BorrowerOpsError::InvalidAccount.into()
+        );
+    }
+
+    #[test]
+    fn require_valid_lowest_trove_test() {
+        let tail = Pubkey::new_unique();
+        let invalid = StabilityPoolError::InvalidLowestTrove.into();
+
+        // Empty list
+        assert!(require_valid_lowest_trove(None, Pubkey::default()).is_ok());
+        assert_eq!(
+            require_valid_lowest_trove(Some(Pubkey::default()), Pubkey::default()).unwrap_err(),
+            invalid
+        );
+        assert_eq!(
+            require_valid_lowest_trove(Some(tail), Pubkey::default()).unwrap_err(),
+            invalid
+        );
+
+        // Populated list
+        assert!(require_valid_lowest_trove(Some(tail), tail).is_ok());
+        assert_eq!(require_valid_lowest_trove(None, tail).unwrap_err(), invalid);
+        assert_eq!(
+            require_valid_lowest_trove(Some(Pubkey::new_unique()), tail).unwrap_err(),
+            invalid
+        );
+    }
+
+    #[test]
+    fn require_stake_account_test() {
+        assert_eq!(
+            require_stake_account(&None).unwrap_err(),
+            BorrowerOpsError::MissingStakeAccount.into()
+        );
+    }
+
+    #[test]
+    fn require_min_coll_out_test() {
+        // Stake pool minted less jitoSOL than expected
+        assert_eq!(
+            require_min_coll_out(89_000_000, 90_000_000).unwrap_err(),
+            BorrowerOpsError::CollOutBelowMin.into()
+        );
+        assert!(require_min_coll_out(90_000_000, 90_000_000).is_ok());
+        assert!(require_min_coll_out(91_000_000, 90_000_000).is_ok());
     }
 }